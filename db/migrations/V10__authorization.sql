@@ -0,0 +1,39 @@
+-- ##########################################
+-- Authorization: permissions used by server functions
+-- ##########################################
+
+-- Roles referenced by the application
+INSERT INTO roles (name, description) VALUES
+('judge', 'Federal judge'),
+('attorney', 'Practicing attorney'),
+('clerk', 'Court clerk'),
+('admin', 'System administrator')
+ON CONFLICT (name) DO NOTHING;
+
+-- One row per (resource_type, action_type) checked by `functions::authz`
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('case:create', 'Open new cases', 'CASE', 'CREATE'),
+('case:read', 'View cases', 'CASE', 'READ'),
+('case:update', 'Change case details and status', 'CASE', 'UPDATE'),
+('party:create', 'Add parties to a case', 'PARTY', 'CREATE'),
+('party:read', 'View case parties', 'PARTY', 'READ'),
+('court:read', 'View courts and judicial officers', 'COURT', 'READ'),
+('user:create', 'Create user accounts', 'USER', 'CREATE'),
+('user:read', 'View user accounts', 'USER', 'READ')
+ON CONFLICT (name) DO NOTHING;
+
+-- Role grants
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON (
+    r.name = 'admin'
+    OR (r.name = 'clerk' AND p.name IN (
+        'case:create', 'case:read', 'case:update',
+        'party:create', 'party:read', 'court:read', 'user:read'))
+    OR (r.name = 'judge' AND p.name IN (
+        'case:read', 'case:update', 'party:read', 'court:read'))
+    OR (r.name = 'attorney' AND p.name IN (
+        'case:create', 'case:read', 'party:read', 'court:read'))
+)
+ON CONFLICT (role_id, permission_id) DO NOTHING;
@@ -0,0 +1,19 @@
+-- ##########################################
+-- Two-factor authentication administration
+-- ##########################################
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('user:update', 'Change user accounts, including resetting two-factor authentication', 'USER', 'UPDATE')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'user:update'
+WHERE r.name = 'admin'
+ON CONFLICT (role_id, permission_id) DO NOTHING;
+
+-- The time step of the last authenticator code accepted for each user. A code
+-- stays valid for its whole window, so codes from that step or earlier are
+-- refused and an observed code cannot be replayed.
+ALTER TABLE users ADD COLUMN IF NOT EXISTS two_factor_last_step BIGINT;
@@ -0,0 +1,19 @@
+-- ##########################################
+-- Authentication audit view and account lockout
+-- ##########################################
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('audit:read', 'View authentication and activity logs', 'AUDIT', 'READ')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'audit:read'
+WHERE r.name = 'admin'
+ON CONFLICT (role_id, permission_id) DO NOTHING;
+
+-- Locked account lookups for the admin view
+CREATE INDEX IF NOT EXISTS idx_users_account_locked_until
+    ON users(account_locked_until)
+    WHERE account_locked_until IS NOT NULL;
@@ -0,0 +1,35 @@
+-- ##########################################
+-- Password reset, email verification and outbound mail
+-- ##########################################
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS email_verified_at TIMESTAMP WITH TIME ZONE;
+
+-- Single-use tokens sent by email. Only the SHA-256 of the token is stored.
+CREATE TABLE IF NOT EXISTS user_tokens (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    purpose TEXT NOT NULL CHECK (purpose IN ('PASSWORD_RESET', 'EMAIL_VERIFICATION')),
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    used_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Rate limiting counts recent tokens per user and purpose
+CREATE INDEX IF NOT EXISTS idx_user_tokens_user_purpose
+    ON user_tokens(user_id, purpose, created_at);
+
+-- Every message the application sends. With the default `outbox` transport this
+-- is the only delivery; with `smtp` it is the record of what was handed to the server.
+CREATE TABLE IF NOT EXISTS mail_outbox (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    recipient TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    transport TEXT NOT NULL, -- 'outbox' or 'smtp'
+    sent_at TIMESTAMP WITH TIME ZONE,
+    error TEXT,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_mail_outbox_created_at ON mail_outbox(created_at);
@@ -0,0 +1,15 @@
+-- ##########################################
+-- Password reuse history
+-- ##########################################
+
+-- Previous password hashes, newest first by created_at. Pruned to the
+-- configured history length whenever a password changes.
+CREATE TABLE IF NOT EXISTS password_history (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    password_hash TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_password_history_user
+    ON password_history(user_id, created_at DESC);
@@ -0,0 +1,18 @@
+-- ##########################################
+-- External identities for OpenID Connect single sign-on
+-- ##########################################
+
+-- Links an identity provider's subject to a Lexodus user. `sub` is only unique
+-- per issuer, so the pair is the key.
+CREATE TABLE IF NOT EXISTS user_identities (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    user_id BIGINT NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    issuer TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    email TEXT,
+    last_login_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (issuer, subject)
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_identities_user ON user_identities(user_id);
@@ -0,0 +1,56 @@
+-- ##########################################
+-- Invitation-only signup and attorney registration requests
+-- ##########################################
+
+-- Staff join through a mailed invitation that fixes their email and role.
+-- Only the SHA-256 of the invitation token is stored.
+CREATE TABLE IF NOT EXISTS user_invitations (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    email TEXT NOT NULL,
+    role_id BIGINT NOT NULL REFERENCES roles(id),
+    invited_by BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    accepted_at TIMESTAMP WITH TIME ZONE,
+    accepted_user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    revoked_at TIMESTAMP WITH TIME ZONE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_invitations_email ON user_invitations(lower(email));
+
+-- Attorneys ask for an account; a clerk approves or denies the request. The
+-- password hash is held only until the request is decided. The address a
+-- request came from lets one client be kept from flooding the review queue.
+CREATE TABLE IF NOT EXISTS attorney_registrations (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    username TEXT NOT NULL,
+    email TEXT NOT NULL,
+    full_name TEXT NOT NULL,
+    password_hash TEXT,
+    bar_number TEXT NOT NULL,
+    admitting_jurisdiction TEXT NOT NULL,
+    firm_name TEXT,
+    status TEXT NOT NULL DEFAULT 'PENDING' CHECK (status IN ('PENDING', 'APPROVED', 'DENIED')),
+    reviewed_by BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    reviewed_at TIMESTAMP WITH TIME ZONE,
+    review_note TEXT,
+    user_id BIGINT REFERENCES users(id) ON DELETE SET NULL,
+    ip_address INET,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_attorney_registrations_status ON attorney_registrations(status, created_at);
+CREATE INDEX IF NOT EXISTS idx_attorney_registrations_ip ON attorney_registrations(ip_address, created_at);
+CREATE INDEX IF NOT EXISTS idx_attorney_registrations_email ON attorney_registrations(lower(email), created_at);
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('registration:review', 'Approve or deny attorney registration requests', 'REGISTRATION', 'REVIEW')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'registration:review'
+WHERE r.name IN ('admin', 'clerk')
+ON CONFLICT (role_id, permission_id) DO NOTHING;
@@ -0,0 +1,19 @@
+-- ##########################################
+-- Acting as another user
+-- ##########################################
+
+-- Administrators may act as another user to see what they see. Grant this to a
+-- help-desk role rather than widening the admin role.
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('user:impersonate', 'Act as another user, read-only, with every request audited', 'USER', 'IMPERSONATE')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'user:impersonate'
+WHERE r.name = 'admin'
+ON CONFLICT (role_id, permission_id) DO NOTHING;
+
+-- Impersonation entries are looked up by type on the security audit page
+CREATE INDEX IF NOT EXISTS idx_activity_logs_action_type ON activity_logs(action_type, created_at);
@@ -0,0 +1,16 @@
+-- ##########################################
+-- Case visibility
+-- ##########################################
+
+-- Cases and documents name the classification they need in security_level.
+-- Seed every level the case form offers so none is unreachable; a level with
+-- no classification row is visible only to the people working the case.
+INSERT INTO security_classifications (name, description, access_level, requires_clearance) VALUES
+('PUBLIC', 'Public accessible records', 1, false),
+('RESTRICTED', 'Records limited to court staff with clearance', 2, true),
+('SEALED', 'Sealed court records', 3, true)
+ON CONFLICT (name) DO NOTHING;
+
+-- Clearance checks look up a user's unrevoked clearances on every case read
+CREATE INDEX IF NOT EXISTS idx_user_security_clearances_active
+    ON user_security_clearances(user_id) WHERE revoked_at IS NULL;
@@ -0,0 +1,30 @@
+-- ##########################################
+-- Case search
+-- ##########################################
+
+-- Trigram matching for fuzzy name and title search
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Case numbers match as written; titles and nature of suit are stemmed
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', case_number), 'A') ||
+        setweight(to_tsvector('english', title), 'B') ||
+        setweight(to_tsvector('english', COALESCE(nature_of_suit, '')), 'C')
+    ) STORED;
+
+-- The statute the case is brought under, as a title:section cause code such
+-- as 28:1332. Searched by prefix, so "28:" finds every title 28 cause.
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS cause_of_action TEXT;
+
+-- Names are not stemmed, so "Rogers" does not match "Roger"
+ALTER TABLE case_parties ADD COLUMN IF NOT EXISTS name_search tsvector
+    GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_cases_search_vector ON cases USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_cases_title_trgm ON cases USING GIN (title gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_cases_case_number_pattern ON cases (lower(case_number) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_cases_cause_of_action_pattern ON cases (lower(cause_of_action) text_pattern_ops);
+CREATE INDEX IF NOT EXISTS idx_case_parties_name_search ON case_parties USING GIN (name_search);
+CREATE INDEX IF NOT EXISTS idx_case_parties_name_trgm ON case_parties USING GIN (name gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_users_full_name_trgm ON users USING GIN (full_name gin_trgm_ops);
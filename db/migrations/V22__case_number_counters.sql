@@ -0,0 +1,204 @@
+-- ##########################################
+-- Case number allocation
+-- ##########################################
+
+-- Each court numbers its cases from a template. Tokens:
+--   {court}         the court's id
+--   {office}        the court's office code
+--   {yyyy} / {yy}   the filing year
+--   {type}/{TYPE}   the case type code (cv, cr, bk, ad)
+--   {seq}/{seq:05}  the sequence, optionally zero padded
+--   {judge}         the assigned judge's initials, XX while unassigned
+-- Many federal courts use '{office}:{yy}-{type}-{seq:05}', giving 1:24-cv-00042.
+-- Sequences restart each year in each court, so a template must show the year
+-- and the court or its office as well as the sequence, or two cases could be
+-- given the same number.
+ALTER TABLE courts ADD COLUMN IF NOT EXISTS case_number_template TEXT NOT NULL
+    DEFAULT '{court}-{yy}-{seq:04}-{judge}'
+    CONSTRAINT case_number_template_identifies_case CHECK (
+        case_number_template ~ '\{seq(:0[1-9])?\}'
+        AND case_number_template ~ '\{(yyyy|yy)\}'
+        AND case_number_template ~ '\{(court|office)\}'
+    );
+ALTER TABLE courts ADD COLUMN IF NOT EXISTS case_number_office TEXT NOT NULL DEFAULT '1';
+
+-- Numbers typed into search or a /cases/:number URL are parsed without
+-- knowing the court, by splitting them into runs of digits and letters. That
+-- reads two shapes:
+--   {office} {yy|yyyy} {type|TYPE} {seq} [{judge}]
+--   {court}  {yy|yyyy} {seq} [{judge}]
+-- with any punctuation between the parts, where it is needed to keep two runs
+-- of digits apart, and an office code of one digit. Refuse any other template,
+-- since cases numbered from it could not be found by their numbers.
+CREATE OR REPLACE FUNCTION case_number_template_readable(p_template TEXT, p_office TEXT)
+RETURNS BOOLEAN AS $$
+    SELECT
+        -- Letters or digits outside the tokens would read as parts of the number
+        regexp_replace(p_template, '\{(court|office|yyyy|yy|type|TYPE|seq(:0[1-9])?|judge)\}', '', 'g') !~ '[A-Za-z0-9{}]'
+        AND regexp_replace(
+                regexp_replace(regexp_replace(regexp_replace(regexp_replace(regexp_replace(regexp_replace(
+                    p_template,
+                    '\{court\}', '<C>', 'g'),
+                    '\{office\}', '<O>', 'g'),
+                    '\{yyyy\}|\{yy\}', '<Y>', 'g'),
+                    '\{type\}|\{TYPE\}', '<T>', 'g'),
+                    '\{seq(:0[1-9])?\}', '<S>', 'g'),
+                    '\{judge\}', '<J>', 'g'),
+                -- Collapse each run of punctuation to one separator
+                '>[^<]+<', '>-<', 'g'
+            ) ~ '^[^<]*(<O>-<Y>-?<T>-?<S>|<C>-<Y>-<S>)(-?<J>)?[^>]*$'
+        AND (position('{office}' IN p_template) = 0 OR p_office ~ '^[1-9]$');
+$$ LANGUAGE sql IMMUTABLE;
+
+ALTER TABLE courts ADD CONSTRAINT case_number_template_readable
+    CHECK (case_number_template_readable(case_number_template, case_number_office));
+
+-- Sequences run per court and filing year, and per case type when the template
+-- shows the type. Otherwise case_type is '*' and all types share one sequence,
+-- so two numbers can never render the same.
+CREATE TABLE IF NOT EXISTS case_number_counters (
+    court_id BIGINT NOT NULL REFERENCES courts(id),
+    year INTEGER NOT NULL,
+    case_type TEXT NOT NULL,
+    last_sequence INTEGER NOT NULL,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (court_id, year, case_type)
+);
+
+-- What each number was drawn from, so it can be redrawn when the judge changes
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS case_sequence INTEGER;
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS case_number_template TEXT;
+
+-- Existing numbers follow the old format, one sequence per court and year
+UPDATE cases
+SET case_sequence = substring(case_number FROM '^\d+-\d+-(\d+)-')::integer,
+    case_number_template = '{court}-{yy}-{seq:04}-{judge}'
+WHERE case_sequence IS NULL AND case_number ~ '^\d+-\d+-\d+-';
+
+INSERT INTO case_number_counters (court_id, year, case_type, last_sequence)
+SELECT court_id, EXTRACT(YEAR FROM filed_date)::integer, '*', MAX(case_sequence)
+FROM cases
+WHERE case_sequence IS NOT NULL
+GROUP BY court_id, EXTRACT(YEAR FROM filed_date)::integer
+ON CONFLICT (court_id, year, case_type)
+DO UPDATE SET last_sequence = GREATEST(case_number_counters.last_sequence, EXCLUDED.last_sequence);
+
+CREATE OR REPLACE FUNCTION case_type_code(TEXT)
+RETURNS TEXT AS $$
+    SELECT CASE upper($1)
+        WHEN 'CIVIL' THEN 'cv'
+        WHEN 'CRIMINAL' THEN 'cr'
+        WHEN 'BANKRUPTCY' THEN 'bk'
+        WHEN 'ADMINISTRATIVE' THEN 'ad'
+        ELSE lower(left($1, 2))
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Initials of the first two words of a judge's name, padded with X
+CREATE OR REPLACE FUNCTION judge_initials(BIGINT)
+RETURNS TEXT AS $$
+    SELECT COALESCE(
+        (SELECT rpad(upper(string_agg(left(w.word, 1), '' ORDER BY w.n)), 2, 'X')
+         FROM judicial_officers jo
+         JOIN users u ON u.id = jo.user_id,
+         LATERAL regexp_split_to_table(trim(u.full_name), '\s+') WITH ORDINALITY AS w(word, n)
+         WHERE jo.id = $1 AND w.n <= 2),
+        'XX');
+$$ LANGUAGE sql STABLE;
+
+CREATE OR REPLACE FUNCTION render_case_number(
+    p_template TEXT,
+    p_court_id BIGINT,
+    p_office TEXT,
+    p_year INTEGER,
+    p_case_type TEXT,
+    p_sequence INTEGER,
+    p_judge_id BIGINT
+)
+RETURNS TEXT AS $$
+DECLARE
+    width INTEGER := COALESCE(substring(p_template FROM '\{seq:0([1-9])\}')::integer, 1);
+    result TEXT := p_template;
+BEGIN
+    result := regexp_replace(result, '\{seq(:0[1-9])?\}',
+        lpad(p_sequence::text, GREATEST(width, length(p_sequence::text)), '0'), 'g');
+    result := replace(result, '{court}', p_court_id::text);
+    result := replace(result, '{office}', p_office);
+    result := replace(result, '{yyyy}', p_year::text);
+    result := replace(result, '{yy}', lpad((p_year % 100)::text, 2, '0'));
+    result := replace(result, '{type}', case_type_code(p_case_type));
+    result := replace(result, '{TYPE}', upper(case_type_code(p_case_type)));
+    IF position('{judge}' IN result) > 0 THEN
+        result := replace(result, '{judge}', judge_initials(p_judge_id));
+    END IF;
+    RETURN result;
+END;
+$$ LANGUAGE plpgsql STABLE;
+
+-- Draw the next number for a new case. Called from the INSERT that creates the
+-- case, so concurrent filings queue on the counter row and a failed insert
+-- gives its sequence back.
+CREATE OR REPLACE FUNCTION allocate_case_number(
+    p_court_id BIGINT,
+    p_case_type TEXT,
+    p_filed_date DATE,
+    p_judge_id BIGINT
+)
+RETURNS TABLE (number TEXT, sequence INTEGER, template TEXT) AS $$
+DECLARE
+    v_template TEXT;
+    v_office TEXT;
+    v_year INTEGER := EXTRACT(YEAR FROM p_filed_date)::integer;
+    v_sequence INTEGER;
+BEGIN
+    SELECT co.case_number_template, co.case_number_office INTO v_template, v_office
+    FROM courts co
+    WHERE co.id = p_court_id;
+    IF NOT FOUND THEN
+        RAISE EXCEPTION 'Court % does not exist', p_court_id;
+    END IF;
+
+    INSERT INTO case_number_counters AS n (court_id, year, case_type, last_sequence)
+    VALUES (
+        p_court_id,
+        v_year,
+        CASE WHEN v_template ~* '\{type\}' THEN upper(p_case_type) ELSE '*' END,
+        1
+    )
+    ON CONFLICT (court_id, year, case_type)
+    DO UPDATE SET last_sequence = n.last_sequence + 1, updated_at = CURRENT_TIMESTAMP
+    RETURNING n.last_sequence INTO v_sequence;
+
+    RETURN QUERY SELECT
+        render_case_number(v_template, p_court_id, v_office, v_year, p_case_type, v_sequence, p_judge_id),
+        v_sequence,
+        v_template;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Keep the judge's initials in the number current when a case is reassigned
+CREATE OR REPLACE FUNCTION redraw_case_number_judge()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.assigned_judge_id IS DISTINCT FROM OLD.assigned_judge_id
+       AND NEW.case_sequence IS NOT NULL
+       AND position('{judge}' IN NEW.case_number_template) > 0 THEN
+        NEW.case_number := render_case_number(
+            NEW.case_number_template,
+            NEW.court_id,
+            (SELECT case_number_office FROM courts WHERE id = NEW.court_id),
+            EXTRACT(YEAR FROM NEW.filed_date)::integer,
+            NEW.case_type,
+            NEW.case_sequence,
+            NEW.assigned_judge_id
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS redraw_case_number_on_reassignment ON cases;
+CREATE TRIGGER redraw_case_number_on_reassignment
+    BEFORE UPDATE OF assigned_judge_id ON cases
+    FOR EACH ROW
+    EXECUTE FUNCTION redraw_case_number_judge();
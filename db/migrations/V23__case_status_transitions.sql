@@ -0,0 +1,47 @@
+-- ##########################################
+-- Case status lifecycle
+-- ##########################################
+
+-- Status changes are made through the application's lifecycle rules, which
+-- record when the change took effect and why (in notes)
+ALTER TABLE case_status_history ADD COLUMN IF NOT EXISTS effective_date DATE;
+
+CREATE INDEX IF NOT EXISTS idx_case_status_history_case
+    ON case_status_history (case_id, case_filed_date, change_date DESC);
+
+-- Not validated against existing rows, which may hold older statuses
+ALTER TABLE cases ADD CONSTRAINT cases_status_known
+    CHECK (status IN ('OPEN', 'PENDING', 'STAYED', 'CLOSED', 'REOPENED')) NOT VALID;
+
+-- The application writes its own history row, with the reason, in the same
+-- statement as the update. That statement names the case and status in a
+-- transaction-local setting, and the trigger skips exactly that change; only
+-- changes made some other way are logged here.
+CREATE OR REPLACE FUNCTION mark_status_change_recorded(p_case_id BIGINT, p_filed_date DATE, p_status TEXT)
+RETURNS BOOLEAN AS $$
+    SELECT set_config('lexodus.status_change_recorded', p_case_id || '/' || p_filed_date || '/' || p_status, true) IS NOT NULL;
+$$ LANGUAGE sql VOLATILE;
+
+CREATE OR REPLACE FUNCTION log_case_status_change()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.status <> OLD.status
+       AND current_setting('lexodus.status_change_recorded', true) IS DISTINCT FROM
+           NEW.id || '/' || NEW.filed_date || '/' || NEW.status THEN
+        INSERT INTO case_status_history (
+            case_id,
+            case_filed_date,
+            old_status,
+            new_status,
+            changed_by
+        ) VALUES (
+            NEW.id,
+            NEW.filed_date,
+            OLD.status,
+            NEW.status,
+            NEW.updated_by
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
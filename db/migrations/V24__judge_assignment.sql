@@ -0,0 +1,69 @@
+-- ##########################################
+-- Judge assignment wheel
+-- ##########################################
+
+-- Judges on each court's wheel. A member with no division or case type is in
+-- the draw for all of them. Weights below 1 take a reduced share, as senior
+-- judges and chief judges usually do. A court with no members draws from all
+-- of its active judges equally.
+CREATE TABLE IF NOT EXISTS judge_wheel_members (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    court_id BIGINT NOT NULL REFERENCES courts(id),
+    division_id BIGINT REFERENCES court_divisions(id),
+    case_type TEXT,
+    judge_id BIGINT NOT NULL REFERENCES judicial_officers(id),
+    weight NUMERIC(5,2) NOT NULL DEFAULT 1 CHECK (weight > 0),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_judge_wheel_members_unique
+    ON judge_wheel_members (court_id, COALESCE(division_id, 0), COALESCE(case_type, ''), judge_id);
+
+-- Every assignment of a judge to a case. Random draws keep their seed and the
+-- eligible pool (judge, weight, load, share), so a draw can be replayed.
+CREATE TABLE IF NOT EXISTS judge_assignments (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    case_id BIGINT NOT NULL,
+    case_filed_date DATE NOT NULL,
+    judge_id BIGINT NOT NULL REFERENCES judicial_officers(id),
+    previous_judge_id BIGINT REFERENCES judicial_officers(id),
+    method TEXT NOT NULL CHECK (method IN ('RANDOM', 'RELATED', 'MANUAL')),
+    seed BIGINT,
+    pool JSONB,
+    related_case_id BIGINT,
+    reason TEXT,
+    assigned_by BIGINT NOT NULL REFERENCES users(id),
+    assigned_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (case_id, case_filed_date) REFERENCES cases (id, filed_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_judge_assignments_case
+    ON judge_assignments (case_id, case_filed_date, assigned_at DESC);
+
+-- Count every case that is not closed as active, not only OPEN ones
+CREATE OR REPLACE VIEW vw_judicial_workload AS
+SELECT
+    jo.id as judge_id,
+    u.full_name as judge_name,
+    COUNT(DISTINCT c.id) as total_cases,
+    COUNT(DISTINCT CASE WHEN c.status <> 'CLOSED' THEN c.id END) as active_cases,
+    COUNT(DISTINCT ce.id) as total_hearings,
+    COUNT(DISTINCT d.id) as total_decisions
+FROM judicial_officers jo
+JOIN users u ON jo.user_id = u.id
+LEFT JOIN cases c ON c.assigned_judge_id = jo.id
+LEFT JOIN calendar_events ce ON ce.case_id = c.id AND ce.event_type = 'HEARING'
+LEFT JOIN documents d ON d.case_id = c.id
+LEFT JOIN document_types dt ON d.document_type_id = dt.id AND dt.category = 'DECISION'
+GROUP BY jo.id, u.full_name;
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('case:assign', 'Reassign cases to another judge', 'CASE', 'ASSIGN')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'case:assign'
+WHERE r.name IN ('admin', 'clerk')
+ON CONFLICT (role_id, permission_id) DO NOTHING;
@@ -0,0 +1,186 @@
+-- ##########################################
+-- Judicial recusal lists and conflict screening
+-- ##########################################
+
+-- Organizations, individuals and law firms a judge must not sit on a case
+-- with, maintained by chambers
+CREATE TABLE IF NOT EXISTS judge_recusals (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    judge_id BIGINT NOT NULL REFERENCES judicial_officers(id),
+    entity_type TEXT NOT NULL CHECK (entity_type IN ('ORGANIZATION', 'INDIVIDUAL', 'LAW_FIRM')),
+    name TEXT NOT NULL CHECK (btrim(name) <> ''),
+    reason TEXT,
+    active BOOLEAN NOT NULL DEFAULT true,
+    created_by BIGINT NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_judge_recusals_judge ON judge_recusals (judge_id) WHERE active;
+
+-- The part of a name that identifies who it is: lower case, punctuation and
+-- corporate suffixes dropped, so "Acme, Inc." and "ACME Corporation" compare equal
+CREATE OR REPLACE FUNCTION recusal_name_key(p_name TEXT) RETURNS TEXT AS $$
+    SELECT btrim(regexp_replace(
+        regexp_replace(
+            regexp_replace(lower(COALESCE(p_name, '')), '[^a-z0-9 ]+', ' ', 'g'),
+            '\m(the|inc|incorporated|llc|llp|lp|ltd|limited|corp|corporation|co|company|plc|pc|pllc)\M', ' ', 'g'
+        ),
+        '\s+', ' ', 'g'
+    ))
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Every name on a case a judge could be conflicted with: parties, their
+-- active attorneys, and those attorneys' firms
+CREATE OR REPLACE VIEW vw_case_screened_names AS
+SELECT p.case_id, p.case_filed_date, 'PARTY' AS source, p.name
+FROM case_parties p
+UNION
+SELECT p.case_id, p.case_filed_date, 'ATTORNEY', u.full_name
+FROM party_attorney_representations par
+JOIN case_parties p ON p.id = par.case_party_id
+JOIN users u ON u.id = par.attorney_id
+WHERE par.status = 'ACTIVE'
+UNION
+SELECT p.case_id, p.case_filed_date, 'FIRM', ar.firm_name
+FROM party_attorney_representations par
+JOIN case_parties p ON p.id = par.case_party_id
+JOIN attorney_registrations ar ON ar.user_id = par.attorney_id AND ar.status = 'APPROVED'
+WHERE par.status = 'ACTIVE' AND ar.firm_name IS NOT NULL;
+
+-- A judge's recusal entries matching a name. Attorneys are screened against
+-- individuals and firms against law firms; parties against every entry. The
+-- same name, or a close spelling of it, blocks the judge; a weaker likeness,
+-- or the entry appearing within a longer name, is flagged for review.
+CREATE OR REPLACE FUNCTION recusal_matches(p_judge_id BIGINT, p_name TEXT, p_source TEXT)
+RETURNS TABLE (recusal_id BIGINT, score REAL, severity TEXT) AS $$
+    SELECT r.id,
+           GREATEST(s.similarity, s.word_similarity),
+           CASE WHEN s.same OR s.similarity >= 0.8 THEN 'BLOCK' ELSE 'FLAG' END
+    FROM judge_recusals r
+    CROSS JOIN LATERAL (
+        SELECT recusal_name_key(r.name) = recusal_name_key(p_name) AS same,
+               similarity(recusal_name_key(r.name), recusal_name_key(p_name)) AS similarity,
+               word_similarity(recusal_name_key(r.name), recusal_name_key(p_name)) AS word_similarity
+    ) s
+    WHERE r.judge_id = p_judge_id
+      AND r.active
+      AND recusal_name_key(p_name) <> ''
+      AND (p_source <> 'ATTORNEY' OR r.entity_type = 'INDIVIDUAL')
+      AND (p_source <> 'FIRM' OR r.entity_type = 'LAW_FIRM')
+      AND (s.same OR s.similarity >= 0.5 OR s.word_similarity >= 0.8)
+$$ LANGUAGE sql STABLE;
+
+-- Conflicts found between a case's assigned judge and a name on the case.
+-- Open ones are resolved by redrawing the judge or by clearing them as a
+-- false match; a cleared match is not raised again.
+CREATE TABLE IF NOT EXISTS recusal_conflicts (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    case_id BIGINT NOT NULL,
+    case_filed_date DATE NOT NULL,
+    judge_id BIGINT NOT NULL REFERENCES judicial_officers(id),
+    recusal_id BIGINT NOT NULL REFERENCES judge_recusals(id),
+    source TEXT NOT NULL CHECK (source IN ('PARTY', 'ATTORNEY', 'FIRM')),
+    matched_name TEXT NOT NULL,
+    score REAL NOT NULL,
+    severity TEXT NOT NULL CHECK (severity IN ('BLOCK', 'FLAG')),
+    status TEXT NOT NULL DEFAULT 'OPEN' CHECK (status IN ('OPEN', 'CLEARED', 'REDRAWN')),
+    detected_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    resolved_by BIGINT REFERENCES users(id),
+    resolved_at TIMESTAMP WITH TIME ZONE,
+    resolution_note TEXT,
+    FOREIGN KEY (case_id, case_filed_date) REFERENCES cases (id, filed_date),
+    CONSTRAINT unique_recusal_conflict UNIQUE (case_id, case_filed_date, judge_id, recusal_id, source, matched_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_recusal_conflicts_open
+    ON recusal_conflicts (detected_at DESC) WHERE status = 'OPEN';
+CREATE INDEX IF NOT EXISTS idx_recusal_conflicts_case
+    ON recusal_conflicts (case_id, case_filed_date);
+
+-- Screen a case's assigned judge against every name on it, recording new conflicts
+CREATE OR REPLACE FUNCTION screen_case_conflicts(p_case_id BIGINT, p_filed_date DATE) RETURNS INTEGER AS $$
+DECLARE
+    detected INTEGER;
+BEGIN
+    INSERT INTO recusal_conflicts (case_id, case_filed_date, judge_id, recusal_id, source, matched_name, score, severity)
+    SELECT c.id, c.filed_date, c.assigned_judge_id, m.recusal_id, n.source, n.name, m.score, m.severity
+    FROM cases c
+    JOIN vw_case_screened_names n ON n.case_id = c.id AND n.case_filed_date = c.filed_date
+    CROSS JOIN LATERAL recusal_matches(c.assigned_judge_id, n.name, n.source) m
+    WHERE c.id = p_case_id AND c.filed_date = p_filed_date AND c.assigned_judge_id IS NOT NULL
+    ON CONFLICT ON CONSTRAINT unique_recusal_conflict DO NOTHING;
+    GET DIAGNOSTICS detected = ROW_COUNT;
+    RETURN detected;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Screen whenever a judge is assigned, whenever a party or attorney is added,
+-- and whenever chambers add to a recusal list, whichever path makes the change
+CREATE OR REPLACE FUNCTION screen_case_on_assignment() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.assigned_judge_id IS NOT NULL
+       AND (TG_OP = 'INSERT' OR NEW.assigned_judge_id IS DISTINCT FROM OLD.assigned_judge_id) THEN
+        PERFORM screen_case_conflicts(NEW.id, NEW.filed_date);
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS screen_case_on_assignment ON cases;
+CREATE TRIGGER screen_case_on_assignment
+    AFTER INSERT OR UPDATE OF assigned_judge_id ON cases
+    FOR EACH ROW EXECUTE FUNCTION screen_case_on_assignment();
+
+CREATE OR REPLACE FUNCTION screen_case_on_party() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM screen_case_conflicts(NEW.case_id, NEW.case_filed_date);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS screen_case_on_party ON case_parties;
+CREATE TRIGGER screen_case_on_party
+    AFTER INSERT OR UPDATE OF name ON case_parties
+    FOR EACH ROW EXECUTE FUNCTION screen_case_on_party();
+
+CREATE OR REPLACE FUNCTION screen_case_on_representation() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM screen_case_conflicts(p.case_id, p.case_filed_date)
+    FROM case_parties p
+    WHERE p.id = NEW.case_party_id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS screen_case_on_representation ON party_attorney_representations;
+CREATE TRIGGER screen_case_on_representation
+    AFTER INSERT OR UPDATE OF status, attorney_id ON party_attorney_representations
+    FOR EACH ROW EXECUTE FUNCTION screen_case_on_representation();
+
+CREATE OR REPLACE FUNCTION screen_cases_on_recusal() RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.active THEN
+        PERFORM screen_case_conflicts(c.id, c.filed_date)
+        FROM cases c
+        WHERE c.assigned_judge_id = NEW.judge_id AND c.status <> 'CLOSED';
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS screen_cases_on_recusal ON judge_recusals;
+CREATE TRIGGER screen_cases_on_recusal
+    AFTER INSERT OR UPDATE OF name, entity_type, active ON judge_recusals
+    FOR EACH ROW EXECUTE FUNCTION screen_cases_on_recusal();
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('recusal:manage', 'Maintain judges'' recusal lists', 'RECUSAL', 'MANAGE')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'recusal:manage'
+WHERE r.name IN ('admin', 'clerk', 'judge')
+ON CONFLICT (role_id, permission_id) DO NOTHING;
@@ -0,0 +1,98 @@
+-- ##########################################
+-- Related, consolidated, MDL and appeal links between cases
+-- ##########################################
+
+-- A link reads "case_id <relationship_type> related_case_id":
+--   RELATED       the two cases are related (either way round)
+--   CONSOLIDATED  case_id is a member consolidated into the lead related_case_id
+--   MDL           case_id is a member of the MDL led by related_case_id
+--   APPEAL_OF     case_id is an appeal of related_case_id
+--   REFILED_AS    case_id was refiled as related_case_id
+ALTER TABLE related_cases
+    ADD COLUMN IF NOT EXISTS mirror_docket BOOLEAN NOT NULL DEFAULT false,
+    ADD COLUMN IF NOT EXISTS notes TEXT,
+    ADD COLUMN IF NOT EXISTS created_by BIGINT REFERENCES users(id);
+
+ALTER TABLE related_cases DROP CONSTRAINT IF EXISTS related_cases_type_known;
+ALTER TABLE related_cases ADD CONSTRAINT related_cases_type_known
+    CHECK (relationship_type IN ('RELATED', 'CONSOLIDATED', 'MDL', 'APPEAL_OF', 'REFILED_AS')) NOT VALID;
+
+ALTER TABLE related_cases DROP CONSTRAINT IF EXISTS related_cases_not_self;
+ALTER TABLE related_cases ADD CONSTRAINT related_cases_not_self
+    CHECK (case_id <> related_case_id) NOT VALID;
+
+-- Only a consolidated member takes copies of its lead's docket
+ALTER TABLE related_cases DROP CONSTRAINT IF EXISTS related_cases_mirror_consolidated;
+ALTER TABLE related_cases ADD CONSTRAINT related_cases_mirror_consolidated
+    CHECK (NOT mirror_docket OR relationship_type = 'CONSOLIDATED');
+
+-- A member has one lead case, and belongs to at most one MDL
+CREATE UNIQUE INDEX IF NOT EXISTS idx_related_cases_one_lead
+    ON related_cases (case_id, case_filed_date) WHERE relationship_type = 'CONSOLIDATED';
+CREATE UNIQUE INDEX IF NOT EXISTS idx_related_cases_one_mdl
+    ON related_cases (case_id, case_filed_date) WHERE relationship_type = 'MDL';
+
+CREATE INDEX IF NOT EXISTS idx_related_cases_case
+    ON related_cases (case_id, case_filed_date);
+CREATE INDEX IF NOT EXISTS idx_related_cases_related
+    ON related_cases (related_case_id, related_case_filed_date);
+
+-- Docket entries copied from a consolidated lead case onto its members
+ALTER TABLE case_events
+    ADD COLUMN IF NOT EXISTS mirrored_from_event_id BIGINT REFERENCES case_events(id);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_case_events_mirror
+    ON case_events (case_id, case_filed_date, mirrored_from_event_id)
+    WHERE mirrored_from_event_id IS NOT NULL;
+
+-- Copy each entry filed in a lead case onto the members that mirror its
+-- docket. Copies are not copied again, so links cannot loop. A sealed lead
+-- case's entries stay on its own docket, since a copy would show their titles
+-- and text to anyone who can see a member; entries filed while it was sealed
+-- are not copied when it is unsealed.
+CREATE OR REPLACE FUNCTION mirror_lead_docket_entry() RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO case_events (
+        case_id, case_filed_date, event_type_id, event_date, title, description,
+        public_entry, document_number, page_count, filed_by, entered_by, event_status,
+        mirrored_from_event_id
+    )
+    SELECT r.case_id, r.case_filed_date, NEW.event_type_id, NEW.event_date, NEW.title, NEW.description,
+           NEW.public_entry, NEW.document_number, NEW.page_count, NEW.filed_by, NEW.entered_by, NEW.event_status,
+           NEW.id
+    FROM related_cases r
+    JOIN cases lead ON lead.id = r.related_case_id AND lead.filed_date = r.related_case_filed_date
+    WHERE r.relationship_type = 'CONSOLIDATED'
+      AND r.mirror_docket
+      AND r.related_case_id = NEW.case_id
+      AND r.related_case_filed_date = NEW.case_filed_date
+      AND NOT COALESCE(lead.sealed, false)
+      AND lead.security_level <> 'SEALED'
+    ON CONFLICT DO NOTHING;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS mirror_lead_docket_entry ON case_events;
+CREATE TRIGGER mirror_lead_docket_entry
+    AFTER INSERT ON case_events
+    FOR EACH ROW
+    WHEN (NEW.mirrored_from_event_id IS NULL)
+    EXECUTE FUNCTION mirror_lead_docket_entry();
+
+-- An entry struck or restored in the lead is struck or restored in its copies
+CREATE OR REPLACE FUNCTION mirror_lead_docket_status() RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE case_events
+    SET event_status = NEW.event_status, updated_at = CURRENT_TIMESTAMP
+    WHERE mirrored_from_event_id = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS mirror_lead_docket_status ON case_events;
+CREATE TRIGGER mirror_lead_docket_status
+    AFTER UPDATE OF event_status ON case_events
+    FOR EACH ROW
+    WHEN (NEW.mirrored_from_event_id IS NULL AND OLD.event_status IS DISTINCT FROM NEW.event_status)
+    EXECUTE FUNCTION mirror_lead_docket_status();
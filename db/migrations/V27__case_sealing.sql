@@ -0,0 +1,43 @@
+-- ##########################################
+-- Sealing and unsealing cases by court order
+-- ##########################################
+
+-- Every change to whether a case is sealed, or to its security level, cites
+-- the judicial order that made it. The case's own columns hold the current
+-- state; this table holds how it got there.
+CREATE TABLE IF NOT EXISTS case_sealing_orders (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    case_id BIGINT NOT NULL,
+    case_filed_date DATE NOT NULL,
+    old_sealed BOOLEAN NOT NULL,
+    old_security_level TEXT NOT NULL,
+    sealed BOOLEAN NOT NULL,
+    security_level TEXT NOT NULL CHECK (security_level IN ('PUBLIC', 'RESTRICTED', 'SEALED')),
+    order_reference TEXT NOT NULL CHECK (btrim(order_reference) <> ''),
+    order_date DATE NOT NULL,
+    reason TEXT NOT NULL CHECK (btrim(reason) <> ''),
+    entered_by BIGINT NOT NULL REFERENCES users(id),
+    entered_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (case_id, case_filed_date) REFERENCES cases (id, filed_date),
+    CONSTRAINT sealing_order_changes_something
+        CHECK (old_sealed <> sealed OR old_security_level <> security_level)
+);
+
+CREATE INDEX IF NOT EXISTS idx_case_sealing_orders_case
+    ON case_sealing_orders (case_id, case_filed_date, entered_at DESC);
+
+-- Not validated against existing rows, which may hold other levels
+ALTER TABLE cases DROP CONSTRAINT IF EXISTS cases_security_level_known;
+ALTER TABLE cases ADD CONSTRAINT cases_security_level_known
+    CHECK (security_level IN ('PUBLIC', 'RESTRICTED', 'SEALED')) NOT VALID;
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('case:seal', 'Seal and unseal cases under a court order', 'CASE', 'SEAL')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'case:seal'
+WHERE r.name IN ('admin', 'clerk', 'judge')
+ON CONFLICT (role_id, permission_id) DO NOTHING;
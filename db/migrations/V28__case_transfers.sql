@@ -0,0 +1,91 @@
+-- ##########################################
+-- Transfers and remands between courts
+-- ##########################################
+
+-- A transferred case takes a number from the receiving court's counter for
+-- the year it arrives, which can differ from the year it was filed
+ALTER TABLE cases ADD COLUMN IF NOT EXISTS case_number_year INTEGER;
+
+-- Every move of a case to another court, under the order that directed it.
+--   TRANSFER  to another court of the same type
+--   REMAND    back to the court it came from, or down to a court below
+CREATE TABLE IF NOT EXISTS case_transfers (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    case_id BIGINT NOT NULL,
+    case_filed_date DATE NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('TRANSFER', 'REMAND')),
+    from_court_id BIGINT NOT NULL REFERENCES courts(id),
+    to_court_id BIGINT NOT NULL REFERENCES courts(id),
+    old_case_number TEXT NOT NULL,
+    new_case_number TEXT NOT NULL,
+    previous_judge_id BIGINT REFERENCES judicial_officers(id),
+    order_reference TEXT NOT NULL CHECK (btrim(order_reference) <> ''),
+    order_date DATE NOT NULL,
+    effective_date DATE NOT NULL,
+    reason TEXT,
+    transferred_by BIGINT NOT NULL REFERENCES users(id),
+    transferred_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (case_id, case_filed_date) REFERENCES cases (id, filed_date),
+    CONSTRAINT case_transfer_changes_court CHECK (from_court_id <> to_court_id),
+    CONSTRAINT case_transfer_after_order CHECK (effective_date >= order_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_case_transfers_case
+    ON case_transfers (case_id, case_filed_date, transferred_at);
+
+-- Numbers a case was known by in courts it has left. Each keeps the parts it
+-- was drawn from, so it is found however it is typed, like a current number.
+CREATE TABLE IF NOT EXISTS case_number_aliases (
+    id BIGINT PRIMARY KEY GENERATED ALWAYS AS IDENTITY,
+    case_id BIGINT NOT NULL,
+    case_filed_date DATE NOT NULL,
+    case_number TEXT NOT NULL,
+    court_id BIGINT NOT NULL REFERENCES courts(id),
+    case_sequence INTEGER,
+    number_year INTEGER NOT NULL,
+    transfer_id BIGINT REFERENCES case_transfers(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (case_id, case_filed_date) REFERENCES cases (id, filed_date)
+);
+
+CREATE INDEX IF NOT EXISTS idx_case_number_aliases_case
+    ON case_number_aliases (case_id, case_filed_date);
+CREATE INDEX IF NOT EXISTS idx_case_number_aliases_number
+    ON case_number_aliases (lower(case_number));
+
+-- Redraw judge initials in the year the number was issued, not the filing year
+CREATE OR REPLACE FUNCTION redraw_case_number_judge()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF NEW.assigned_judge_id IS DISTINCT FROM OLD.assigned_judge_id
+       AND NEW.case_sequence IS NOT NULL
+       AND position('{judge}' IN NEW.case_number_template) > 0 THEN
+        NEW.case_number := render_case_number(
+            NEW.case_number_template,
+            NEW.court_id,
+            (SELECT case_number_office FROM courts WHERE id = NEW.court_id),
+            COALESCE(NEW.case_number_year, EXTRACT(YEAR FROM NEW.filed_date)::integer),
+            NEW.case_type,
+            NEW.case_sequence,
+            NEW.assigned_judge_id
+        );
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- The order moving a case is entered on its docket
+INSERT INTO event_types (name, category, description, requires_document, requires_judicial_review, auto_notification) VALUES
+('TRANSFER', 'JUDICIAL', 'Case transferred or remanded to another court', false, false, true)
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO permissions (name, description, resource_type, action_type) VALUES
+('case:transfer', 'Transfer and remand cases to other courts', 'CASE', 'TRANSFER')
+ON CONFLICT (name) DO NOTHING;
+
+INSERT INTO role_permissions (role_id, permission_id)
+SELECT r.id, p.id
+FROM roles r
+JOIN permissions p ON p.name = 'case:transfer'
+WHERE r.name IN ('admin', 'clerk')
+ON CONFLICT (role_id, permission_id) DO NOTHING;
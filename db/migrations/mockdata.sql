@@ -374,8 +374,9 @@ INSERT INTO security_classifications (
     requires_clearance
 ) VALUES
 ('PUBLIC', 'Public accessible records', 1, false),
-('SEALED', 'Sealed court records', 2, true),
-('CLASSIFIED', 'Classified national security information', 3, true)
+('RESTRICTED', 'Records limited to court staff with clearance', 2, true),
+('SEALED', 'Sealed court records', 3, true),
+('CLASSIFIED', 'Classified national security information', 4, true)
 ON CONFLICT DO NOTHING;
 
 -- Grant clearances to users
@@ -0,0 +1,132 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+use crate::domain::models::db::{int, pretty, text};
+    }}
+
+/// An `activity_logs` entry written while an administrator acted as another user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpersonationActivity {
+    pub id: i64,
+    /// The administrator who was really making the request
+    pub actor: Option<String>,
+    /// The user they were acting as
+    pub acting_as: Option<String>,
+    pub action_type: String,
+    pub description: String,
+    pub status: String,
+    pub ip_address: Option<String>,
+    pub created_at: i64,
+    pub created_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, ParameterValue};
+
+pub mod action_type {
+    pub const IMPERSONATION_START: &str = "IMPERSONATION_START";
+    pub const IMPERSONATION_STOP: &str = "IMPERSONATION_STOP";
+    /// A page or server function requested while acting as another user
+    pub const IMPERSONATED_REQUEST: &str = "IMPERSONATED_REQUEST";
+    /// A change refused because the caller was acting as another user
+    pub const IMPERSONATION_BLOCKED: &str = "IMPERSONATION_BLOCKED";
+    /// Case details edited; the metadata holds the change set
+    pub const CASE_UPDATE: &str = "CASE_UPDATE";
+}
+
+pub mod status {
+    pub const SUCCESS: &str = "SUCCESS";
+    pub const DENIED: &str = "DENIED";
+}
+
+/// Fields written for an `activity_logs` entry. `user_id` is always the person
+/// really making the request, never the user they are acting as.
+#[derive(Clone, Debug)]
+pub struct NewActivity {
+    pub user_id: i64,
+    pub action_type: &'static str,
+    pub action_category: &'static str,
+    pub action_description: String,
+    pub entity_type: &'static str,
+    pub entity_id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub session_id: Option<String>,
+    pub status: &'static str,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Writes to the general `activity_logs` audit table
+pub struct ActivityLog;
+
+impl ActivityLog {
+    /// Append an entry, stamping the actor's current role. The IP must already be a valid address.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn record(activity: NewActivity, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        con.execute(
+            "INSERT INTO activity_logs
+                (user_id, role_id, action_type, action_category, action_description,
+                 entity_type, entity_id, entity_name, ip_address, user_agent, session_id, status, metadata)
+             VALUES ($1, (SELECT role_id FROM users WHERE id = $1), $2, $3, $4,
+                     $5, $6, (SELECT username FROM users WHERE id::text = $6 AND $5 = 'USER'),
+                     $7::inet, $8, $9, $10, $11::jsonb)",
+            &[
+                ParameterValue::Int64(activity.user_id),
+                ParameterValue::Str(activity.action_type.to_string()),
+                ParameterValue::Str(activity.action_category.to_string()),
+                ParameterValue::Str(activity.action_description),
+                ParameterValue::Str(activity.entity_type.to_string()),
+                ParameterValue::Str(activity.entity_id),
+                activity.ip_address.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                activity.user_agent.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                activity.session_id.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                ParameterValue::Str(activity.status.to_string()),
+                activity.metadata.map_or(ParameterValue::DbNull, |m| ParameterValue::Str(m.to_string())),
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+impl ImpersonationActivity {
+    /// Most recent impersonation entries first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn recent(limit: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT l.id, u.username, l.entity_name, l.action_type, l.action_description, l.status,
+                    host(l.ip_address), EXTRACT(EPOCH FROM l.created_at)::bigint
+             FROM activity_logs l
+             LEFT JOIN users u ON u.id = l.user_id
+             WHERE l.action_type IN ('IMPERSONATION_START', 'IMPERSONATION_STOP',
+                                     'IMPERSONATED_REQUEST', 'IMPERSONATION_BLOCKED')
+             ORDER BY l.created_at DESC, l.id DESC
+             LIMIT $1",
+            &[ParameterValue::Int64(limit)],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let created_at = int(&row[7]).unwrap_or_default();
+                ImpersonationActivity {
+                    id: int(&row[0]).unwrap_or(-1),
+                    actor: text(&row[1]),
+                    acting_as: text(&row[2]),
+                    action_type: text(&row[3]).unwrap_or_default(),
+                    description: text(&row[4]).unwrap_or_default(),
+                    status: text(&row[5]).unwrap_or_default(),
+                    ip_address: text(&row[6]),
+                    created_at,
+                    created_at_pretty: pretty(created_at),
+                }
+            })
+            .collect())
+    }
+}
+}
+}
@@ -0,0 +1,141 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+use crate::domain::models::db::{int, pretty, text};
+use rand_core::{OsRng, RngCore};
+use crate::domain::models::user_token::hash_token;
+    }}
+
+/// A personal API token as listed on the profile page. The token itself is never
+/// shown again after creation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApiToken {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    /// The first characters of the token, enough to tell tokens apart
+    pub token_prefix: String,
+    /// Permission names such as `CASE:READ`
+    pub scopes: Vec<String>,
+    pub expires_at: i64,
+    pub expires_at_pretty: String,
+    pub last_used_at: Option<i64>,
+    pub last_used_at_pretty: Option<String>,
+    pub revoked: bool,
+    pub expired: bool,
+    pub created_at: i64,
+    pub created_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+const API_TOKEN_COLUMNS: &str = "id, user_id, name, token_prefix, array_to_string(scopes, ','),
+    EXTRACT(EPOCH FROM expires_at)::bigint, EXTRACT(EPOCH FROM last_used_at)::bigint,
+    revoked_at IS NOT NULL, EXTRACT(EPOCH FROM created_at)::bigint,
+    expires_at <= CURRENT_TIMESTAMP";
+
+/// Marks a string as a Lexodus API token, e.g. for secret scanners
+pub const TOKEN_PREFIX: &str = "lxd_";
+/// Characters of the plain token kept to identify it in listings
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+impl ApiToken {
+    fn from_row(row: &[DbValue]) -> Self {
+        let expires_at = int(&row[5]).unwrap_or_default();
+        let last_used_at = int(&row[6]);
+        let created_at = int(&row[8]).unwrap_or_default();
+        ApiToken {
+            id: int(&row[0]).unwrap_or(-1),
+            user_id: int(&row[1]).unwrap_or(-1),
+            name: text(&row[2]).unwrap_or_default(),
+            token_prefix: text(&row[3]).unwrap_or_default(),
+            scopes: text(&row[4])
+                .unwrap_or_default()
+                .split(',')
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+                .collect(),
+            expires_at,
+            expires_at_pretty: pretty(expires_at),
+            last_used_at,
+            last_used_at_pretty: last_used_at.map(pretty),
+            revoked: matches!(&row[7], DbValue::Boolean(true)),
+            expired: matches!(&row[9], DbValue::Boolean(true)),
+            created_at,
+            created_at_pretty: pretty(created_at),
+        }
+    }
+
+    /// Create a token for `user_id` and return its id with the plain token, which
+    /// is not stored and cannot be shown again
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn issue(user_id: i64, name: &str, scopes: &[String], ttl_secs: i64, con: &Arc<Connection>) -> Result<(i64, String), LexodusAppError> {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+        let token = format!("{TOKEN_PREFIX}{hex}");
+
+        let rowset = con.query(
+            "INSERT INTO api_tokens (user_id, name, token_prefix, token_hash, scopes, expires_at)
+             VALUES ($1, $2, $3, $4, string_to_array($5, ','),
+                     CURRENT_TIMESTAMP + make_interval(secs => $6::bigint))
+             RETURNING id",
+            &[
+                ParameterValue::Int64(user_id),
+                ParameterValue::Str(name.to_string()),
+                ParameterValue::Str(token[..DISPLAY_PREFIX_LEN].to_string()),
+                ParameterValue::Str(hash_token(&token)),
+                ParameterValue::Str(scopes.join(",")),
+                ParameterValue::Int64(ttl_secs),
+            ],
+        )?;
+        match rowset.rows.first().map(|row| &row[0]) {
+            Some(DbValue::Int64(id)) => Ok((*id, token)),
+            _ => Err(LexodusAppError::DBError("Insert did not return a token id".to_string())),
+        }
+    }
+
+    /// The user's tokens, newest first, including revoked and expired ones
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list_for_user(user_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("SELECT {API_TOKEN_COLUMNS} FROM api_tokens WHERE user_id = $1 ORDER BY created_at DESC, id DESC"),
+            &[ParameterValue::Int64(user_id)],
+        )?;
+        Ok(rowset.rows.iter().map(|row| Self::from_row(row)).collect())
+    }
+
+    /// Revoke one of the user's tokens. Returns false if no such live token exists.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn revoke(id: i64, user_id: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let affected = con.execute(
+            "UPDATE api_tokens SET revoked_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL",
+            &[ParameterValue::Int64(id), ParameterValue::Int64(user_id)],
+        )?;
+        Ok(affected > 0)
+    }
+
+    /// Find a live token and stamp its last use in the same statement
+    #[tracing::instrument(level = "info", fields(error), skip(token, con))]
+    pub async fn authenticate(token: &str, ip_address: Option<String>, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("UPDATE api_tokens SET last_used_at = CURRENT_TIMESTAMP, last_used_ip = $2::inet
+                      WHERE token_hash = $1 AND revoked_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+                      RETURNING {API_TOKEN_COLUMNS}"),
+            &[
+                ParameterValue::Str(hash_token(token)),
+                ip_address.map_or(ParameterValue::DbNull, ParameterValue::Str),
+            ],
+        )?;
+        Ok(rowset.rows.first().map(|row| Self::from_row(row)))
+    }
+}
+}
+}
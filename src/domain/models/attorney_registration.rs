@@ -0,0 +1,241 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+use crate::domain::models::db::pretty;
+    }}
+
+pub mod registration_status {
+    pub const PENDING: &str = "PENDING";
+    pub const APPROVED: &str = "APPROVED";
+    pub const DENIED: &str = "DENIED";
+}
+
+/// An attorney's request for an account, as shown in the clerk review queue
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttorneyRegistration {
+    pub id: i64,
+    pub username: String,
+    pub email: String,
+    pub full_name: String,
+    pub bar_number: String,
+    pub admitting_jurisdiction: String,
+    pub firm_name: Option<String>,
+    pub status: String,
+    pub reviewed_by: Option<String>,
+    pub reviewed_at_pretty: Option<String>,
+    pub review_note: Option<String>,
+    pub created_at: i64,
+    pub created_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+const REGISTRATION_COLUMNS: &str = "a.id, a.username, a.email, a.full_name, a.bar_number,
+    a.admitting_jurisdiction, a.firm_name, a.status, u.username,
+    EXTRACT(EPOCH FROM a.reviewed_at)::bigint, a.review_note, EXTRACT(EPOCH FROM a.created_at)::bigint";
+
+/// A registration request as submitted, before review
+pub struct NewAttorneyRegistration {
+    pub username: String,
+    pub email: String,
+    pub full_name: String,
+    pub password_hash: String,
+    pub bar_number: String,
+    pub admitting_jurisdiction: String,
+    pub firm_name: Option<String>,
+    /// Address the request was submitted from
+    pub ip_address: Option<String>,
+}
+
+impl AttorneyRegistration {
+    fn from_row(row: &[DbValue]) -> Self {
+        let text = |i: usize| match &row[i] {
+            DbValue::Str(s) => Some(s.clone()),
+            _ => None,
+        };
+        let int = |i: usize| match &row[i] {
+            DbValue::Int64(n) => Some(*n),
+            DbValue::Int32(n) => Some(*n as i64),
+            _ => None,
+        };
+        let created_at = int(11).unwrap_or_default();
+        AttorneyRegistration {
+            id: int(0).unwrap_or(-1),
+            username: text(1).unwrap_or_default(),
+            email: text(2).unwrap_or_default(),
+            full_name: text(3).unwrap_or_default(),
+            bar_number: text(4).unwrap_or_default(),
+            admitting_jurisdiction: text(5).unwrap_or_default(),
+            firm_name: text(6),
+            status: text(7).unwrap_or_default(),
+            reviewed_by: text(8),
+            reviewed_at_pretty: int(9).map(pretty),
+            review_note: text(10),
+            created_at,
+            created_at_pretty: pretty(created_at),
+        }
+    }
+
+    /// Queue a request for review and return its id
+    #[tracing::instrument(level = "info", fields(error), skip(new_registration, con))]
+    pub async fn create(new_registration: NewAttorneyRegistration, con: &Arc<Connection>) -> Result<i64, LexodusAppError> {
+        let rowset = con.query(
+            "INSERT INTO attorney_registrations
+                 (username, email, full_name, password_hash, bar_number, admitting_jurisdiction, firm_name, ip_address)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8::inet)
+             RETURNING id",
+            &[
+                ParameterValue::Str(new_registration.username),
+                ParameterValue::Str(new_registration.email),
+                ParameterValue::Str(new_registration.full_name),
+                ParameterValue::Str(new_registration.password_hash),
+                ParameterValue::Str(new_registration.bar_number),
+                ParameterValue::Str(new_registration.admitting_jurisdiction),
+                new_registration.firm_name.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                new_registration.ip_address.map_or(ParameterValue::DbNull, ParameterValue::Str),
+            ],
+        )?;
+        match rowset.rows.first().map(|row| &row[0]) {
+            Some(DbValue::Int64(id)) => Ok(*id),
+            _ => Err(LexodusAppError::DBError("Insert did not return a registration id".to_string())),
+        }
+    }
+
+    /// Requests submitted within the last `window_secs` from the address and for
+    /// the email, as `(from ip, for email)`, for rate limiting
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn recent_counts(ip_address: Option<&str>, email: &str, window_secs: i64, con: &Arc<Connection>) -> Result<(i64, i64), LexodusAppError> {
+        let rowset = con.query(
+            "SELECT COUNT(*) FILTER (WHERE ip_address = $1::inet),
+                    COUNT(*) FILTER (WHERE lower(email) = lower($2))
+             FROM attorney_registrations
+             WHERE created_at > CURRENT_TIMESTAMP - make_interval(secs => $3::bigint)",
+            &[
+                ip_address.map_or(ParameterValue::DbNull, |ip| ParameterValue::Str(ip.to_string())),
+                ParameterValue::Str(email.to_string()),
+                ParameterValue::Int64(window_secs),
+            ],
+        )?;
+        let count = |i: usize| {
+            rowset.rows.first().map_or(0, |row| match &row[i] {
+                DbValue::Int64(n) => *n,
+                _ => 0,
+            })
+        };
+        Ok((count(0), count(1)))
+    }
+
+    /// Whether a pending request already claims the username, email or bar number
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn pending_conflict(username: &str, email: &str, bar_number: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT 1 FROM attorney_registrations
+             WHERE status = 'PENDING'
+               AND (lower(username) = lower($1) OR lower(email) = lower($2) OR bar_number = $3)
+             LIMIT 1",
+            &[
+                ParameterValue::Str(username.to_string()),
+                ParameterValue::Str(email.to_string()),
+                ParameterValue::Str(bar_number.to_string()),
+            ],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+
+    /// Pending requests oldest first, followed by the most recent decisions
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(limit: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("SELECT {REGISTRATION_COLUMNS}
+                      FROM attorney_registrations a
+                      LEFT JOIN users u ON u.id = a.reviewed_by
+                      ORDER BY a.status <> 'PENDING',
+                               CASE WHEN a.status = 'PENDING' THEN a.created_at END ASC,
+                               a.reviewed_at DESC
+                      LIMIT $1"),
+            &[ParameterValue::Int64(limit)],
+        )?;
+        Ok(rowset.rows.iter().map(|row| Self::from_row(row)).collect())
+    }
+
+    /// A request that is still waiting for review
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn get_pending(id: i64, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("SELECT {REGISTRATION_COLUMNS}
+                      FROM attorney_registrations a
+                      LEFT JOIN users u ON u.id = a.reviewed_by
+                      WHERE a.id = $1 AND a.status = 'PENDING'"),
+            &[ParameterValue::Int64(id)],
+        )?;
+        Ok(rowset.rows.first().map(|row| Self::from_row(row)))
+    }
+
+    /// Create the attorney's account from a pending request and mark it approved.
+    /// The account, the decision and dropping the stored password hash happen in
+    /// one statement, so a request cannot be both approved and denied or yield
+    /// two accounts. Returns the new user's id, or None if it was no longer pending.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn approve(
+        id: i64,
+        role_id: i64,
+        reviewed_by: i64,
+        review_note: Option<&str>,
+        con: &Arc<Connection>,
+    ) -> Result<Option<i64>, LexodusAppError> {
+        let rowset = con.query(
+            "WITH claimed AS (
+                 SELECT username, email, password_hash, full_name, bar_number
+                 FROM attorney_registrations
+                 WHERE id = $1 AND status = 'PENDING' AND password_hash IS NOT NULL
+                 FOR UPDATE
+             ), created AS (
+                 INSERT INTO users (username, email, password_hash, full_name, role_id, bar_number)
+                 SELECT username, email, password_hash, full_name, $2, bar_number FROM claimed
+                 RETURNING id
+             )
+             UPDATE attorney_registrations a
+             SET status = 'APPROVED', reviewed_by = $3, reviewed_at = CURRENT_TIMESTAMP,
+                 review_note = $4, user_id = created.id, password_hash = NULL
+             FROM created
+             WHERE a.id = $1
+             RETURNING created.id",
+            &[
+                ParameterValue::Int64(id),
+                ParameterValue::Int64(role_id),
+                ParameterValue::Int64(reviewed_by),
+                review_note.map_or(ParameterValue::DbNull, |n| ParameterValue::Str(n.to_string())),
+            ],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| match &row[0] {
+            DbValue::Int64(id) => Some(*id),
+            _ => None,
+        }))
+    }
+
+    /// Deny a pending request and drop its password hash. Returns false if the
+    /// request was no longer pending, e.g. another clerk decided it first.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn deny(id: i64, reviewed_by: i64, review_note: Option<&str>, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let affected = con.execute(
+            "UPDATE attorney_registrations
+             SET status = 'DENIED', reviewed_by = $2, reviewed_at = CURRENT_TIMESTAMP,
+                 review_note = $3, password_hash = NULL
+             WHERE id = $1 AND status = 'PENDING'",
+            &[
+                ParameterValue::Int64(id),
+                ParameterValue::Int64(reviewed_by),
+                review_note.map_or(ParameterValue::DbNull, |n| ParameterValue::Str(n.to_string())),
+            ],
+        )?;
+        Ok(affected > 0)
+    }
+}
+}
+}
@@ -0,0 +1,157 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+use crate::domain::models::db::{int, pretty, text};
+    }}
+
+/// A row from `authentication_logs`, joined with the username when the user is known
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuthEvent {
+    pub id: i64,
+    pub user_id: Option<i64>,
+    pub username: Option<String>,
+    pub event_type: String,
+    pub status: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub failure_reason: Option<String>,
+    pub created_at: i64,
+    pub created_at_pretty: String,
+}
+
+/// An account whose `account_locked_until` is still in the future
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockedAccount {
+    pub user_id: i64,
+    pub username: String,
+    pub failed_login_attempts: i64,
+    pub locked_until: i64,
+    pub locked_until_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, ParameterValue};
+
+pub mod event_type {
+    pub const LOGIN: &str = "LOGIN";
+    pub const LOGOUT: &str = "LOGOUT";
+    pub const TWO_FACTOR: &str = "2FA_ATTEMPT";
+    pub const UNLOCK: &str = "UNLOCK";
+    pub const PASSWORD_RESET: &str = "PASSWORD_RESET";
+    pub const API_TOKEN_CREATE: &str = "API_TOKEN_CREATE";
+    pub const API_TOKEN_REVOKE: &str = "API_TOKEN_REVOKE";
+    pub const API_TOKEN_AUTH: &str = "API_TOKEN_AUTH";
+    pub const SSO_LOGIN: &str = "SSO_LOGIN";
+}
+
+pub mod status {
+    pub const SUCCESS: &str = "SUCCESS";
+    pub const FAILURE: &str = "FAILURE";
+}
+
+/// Fields written for every authentication attempt
+#[derive(Clone, Debug)]
+pub struct NewAuthEvent {
+    pub user_id: Option<i64>,
+    pub event_type: &'static str,
+    pub status: &'static str,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub failure_reason: Option<String>,
+    /// Stored in `additional_details`, e.g. the username typed for an unknown account
+    pub attempted_username: Option<String>,
+}
+
+impl AuthEvent {
+    /// Append an entry to `authentication_logs`. The IP must already be a valid address.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn record(event: NewAuthEvent, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let details = event
+            .attempted_username
+            .map(|name| serde_json::json!({ "attempted_username": name }).to_string());
+        con.execute(
+            "INSERT INTO authentication_logs
+                (user_id, event_type, status, ip_address, user_agent, failure_reason, additional_details)
+             VALUES ($1, $2, $3, $4::inet, $5, $6, $7::jsonb)",
+            &[
+                event.user_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ParameterValue::Str(event.event_type.to_string()),
+                ParameterValue::Str(event.status.to_string()),
+                event.ip_address.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                event.user_agent.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                event.failure_reason.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                details.map_or(ParameterValue::DbNull, ParameterValue::Str),
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Most recent events first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn recent(limit: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT l.id, l.user_id, COALESCE(u.username, l.additional_details->>'attempted_username'),
+                    l.event_type, l.status, host(l.ip_address), l.user_agent, l.failure_reason,
+                    EXTRACT(EPOCH FROM l.created_at)::bigint
+             FROM authentication_logs l
+             LEFT JOIN users u ON u.id = l.user_id
+             ORDER BY l.created_at DESC, l.id DESC
+             LIMIT $1",
+            &[ParameterValue::Int64(limit)],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let created_at = int(&row[8]).unwrap_or_default();
+                AuthEvent {
+                    id: int(&row[0]).unwrap_or(-1),
+                    user_id: int(&row[1]),
+                    username: text(&row[2]),
+                    event_type: text(&row[3]).unwrap_or_default(),
+                    status: text(&row[4]).unwrap_or_default(),
+                    ip_address: text(&row[5]),
+                    user_agent: text(&row[6]),
+                    failure_reason: text(&row[7]),
+                    created_at,
+                    created_at_pretty: pretty(created_at),
+                }
+            })
+            .collect())
+    }
+}
+
+impl LockedAccount {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT id, username, COALESCE(failed_login_attempts, 0),
+                    EXTRACT(EPOCH FROM account_locked_until)::bigint
+             FROM users
+             WHERE account_locked_until > CURRENT_TIMESTAMP AND deleted_at IS NULL
+             ORDER BY account_locked_until DESC",
+            &[],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let locked_until = int(&row[3]).unwrap_or_default();
+                LockedAccount {
+                    user_id: int(&row[0]).unwrap_or(-1),
+                    username: text(&row[1]).unwrap_or_default(),
+                    failed_login_attempts: int(&row[2]).unwrap_or_default(),
+                    locked_until,
+                    locked_until_pretty: pretty(locked_until),
+                }
+            })
+            .collect())
+    }
+}
+}
+}
@@ -1,34 +1,814 @@
+use cfg_if::cfg_if;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Case {
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// The heading of the case detail page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseHeader {
     pub id: i64,
     pub case_number: String,
     pub title: String,
     pub case_type: String,
-    pub nature_of_suit: String,
+    pub nature_of_suit: Option<String>,
     pub filing_type: String,
     pub status: String,
     pub filed_date: String,
     pub closed_date: Option<String>,
     pub reopened_date: Option<String>,
-    pub court_id: i64,
-    pub division_id: Option<i64>,
+    pub disposition_method: Option<String>,
+    pub disposition_date: Option<String>,
+    pub court_name: String,
+    pub division_name: Option<String>,
     pub assigned_judge_id: Option<i64>,
+    pub assigned_judge: Option<String>,
+    pub magistrate_judge: Option<String>,
+    pub jury_demand: Option<String>,
+    pub demand_amount: Option<String>,
+    pub jurisdictional_basis: Option<String>,
     pub security_level: String,
-    pub created_by: i64,
-    pub updated_by: Option<i64>
+    pub sealed: bool,
+}
+
+/// An attorney of record for a party
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartyAttorney {
+    pub name: String,
+    pub email: Option<String>,
+    pub representation_type: String,
+    pub status: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CaseCreate {
+/// A party to a case with its attorneys, lead parties first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseParty {
+    pub id: i64,
+    pub name: String,
+    pub party_type: String,
+    pub is_lead: bool,
+    pub is_pro_se: bool,
+    pub attorneys: Vec<PartyAttorney>,
+}
+
+/// A docket entry. Only the public text is sent, never the internal notes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocketEntry {
+    pub id: i64,
+    pub event_type: String,
     pub title: String,
-    pub case_type: String,
-    pub nature_of_suit: String,
-    pub filing_type: String,
-    pub court_id: i64,
+    pub entry: Option<String>,
+    pub document_number: Option<String>,
+    pub page_count: Option<i64>,
+    pub event_date: i64,
+    pub event_date_pretty: String,
+    /// The lead case's number, for entries copied from a consolidated lead
+    pub mirrored_from: Option<String>,
+}
+
+/// A deadline that has not been completed yet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpenDeadline {
+    pub id: i64,
+    pub title: String,
+    pub deadline_type: String,
+    pub priority: String,
+    pub status: String,
+    pub overdue: bool,
+    pub due_date: i64,
+    pub due_date_pretty: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseMotion {
+    pub id: i64,
+    pub title: String,
+    pub motion_type: String,
+    pub filed_by: Option<String>,
+    pub status: String,
+    pub response_deadline: Option<String>,
+    pub hearing_date_pretty: Option<String>,
+    pub filed_date: i64,
+    pub filed_date_pretty: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseDocument {
+    pub id: i64,
+    pub document_number: Option<String>,
+    pub title: String,
+    pub document_type: Option<String>,
+    pub filed_by: Option<String>,
+    pub page_count: Option<i64>,
+    pub filed_date: i64,
+    pub filed_date_pretty: String,
+}
+
+/// The fields of a case that can be changed after filing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseEdit {
+    pub title: String,
+    pub nature_of_suit: Option<String>,
+    /// Cause code, e.g. `28:1332`
+    pub cause_of_action: Option<String>,
+    pub jury_demand: Option<String>,
+    pub demand_amount: Option<String>,
+    pub jurisdictional_basis: Option<String>,
     pub division_id: Option<i64>,
-    pub assigned_judge_id: Option<i64>,
-    pub security_level: String,
-    pub created_by: i64
+    pub magistrate_judge_id: Option<i64>,
+    /// `updated_at` in microseconds. A save must name the version it was
+    /// edited from, so a change made in the meantime is never overwritten.
+    pub version: i64,
+}
+
+/// Divisions and magistrate judges a case can be moved to, as `(id, name)`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseEditOptions {
+    pub divisions: Vec<(i64, String)>,
+    pub magistrates: Vec<(i64, String)>,
+}
+
+/// One field that differs between two versions of a case
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// Result of saving an edit
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseSaveOutcome {
+    Saved,
+    /// Someone else saved the case after the editor was loaded. Nothing was
+    /// written; `changes` lists where `mine` and `current` disagree.
+    Conflict {
+        mine: Box<CaseEdit>,
+        current: Box<CaseEdit>,
+        changes: Vec<FieldChange>,
+    },
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+use crate::domain::models::db::{int, pretty, text};
+
+impl CaseEditOptions {
+    fn name(options: &[(i64, String)], id: Option<i64>) -> Option<String> {
+        let id = id?;
+        Some(
+            options
+                .iter()
+                .find(|(option, _)| *option == id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_else(|| format!("#{id}")),
+        )
+    }
+}
+
+impl CaseEdit {
+    /// The fields that differ going from `self` to `other`, with divisions and
+    /// judges shown by name. The version is not compared.
+    pub fn changes(&self, other: &CaseEdit, options: &CaseEditOptions) -> Vec<FieldChange> {
+        let division = |e: &CaseEdit| CaseEditOptions::name(&options.divisions, e.division_id);
+        let magistrate = |e: &CaseEdit| CaseEditOptions::name(&options.magistrates, e.magistrate_judge_id);
+        [
+            ("Title", Some(self.title.clone()), Some(other.title.clone())),
+            ("Nature of Suit", self.nature_of_suit.clone(), other.nature_of_suit.clone()),
+            ("Cause of Action", self.cause_of_action.clone(), other.cause_of_action.clone()),
+            ("Jury Demand", self.jury_demand.clone(), other.jury_demand.clone()),
+            ("Demand Amount", self.demand_amount.clone(), other.demand_amount.clone()),
+            ("Jurisdictional Basis", self.jurisdictional_basis.clone(), other.jurisdictional_basis.clone()),
+            ("Division", division(self), division(other)),
+            ("Magistrate Judge", magistrate(self), magistrate(other)),
+        ]
+        .into_iter()
+        .filter(|(_, before, after)| before != after)
+        .map(|(field, before, after)| FieldChange {
+            field: field.to_string(),
+            before,
+            after,
+        })
+        .collect()
+    }
+}
+
+/// The classification a record needs: its security level, raised to SEALED
+/// while it is sealed at a lower one. `level` and `sealed` are its columns.
+fn sealed_classification(level: &str, sealed: &str) -> String {
+    format!(
+        "CASE WHEN COALESCE({sealed}, false)
+                   AND COALESCE((SELECT access_level FROM security_classifications WHERE name = {level}), 0)
+                       < (SELECT access_level FROM security_classifications WHERE name = 'SEALED')
+              THEN 'SEALED' ELSE {level} END"
+    )
+}
+
+/// SQL that is true when user `$param` holds an active clearance at or above
+/// the classification named by `level`. An unknown classification matches nobody.
+pub fn cleared_for(level: &str, param: usize) -> String {
+    format!(
+        "EXISTS (SELECT 1 FROM user_security_clearances usc
+                 JOIN security_classifications sc ON sc.id = usc.classification_id
+                 WHERE usc.user_id = ${param} AND usc.revoked_at IS NULL
+                   AND (usc.expires_at IS NULL OR usc.expires_at > CURRENT_TIMESTAMP)
+                   AND sc.access_level >= (SELECT access_level FROM security_classifications WHERE name = {level}))"
+    )
+}
+
+/// SQL that is true when user `$param` may see case `c`: the case is public
+/// and unsealed, or the user filed it and it is unsealed, is a judge on it, is
+/// an active attorney of record in it, or holds clearance for its security
+/// level. Sealing a case takes it away from its filer like anyone else.
+pub fn case_visible_to(param: usize) -> String {
+    format!(
+        "((c.security_level = 'PUBLIC' AND NOT COALESCE(c.sealed, false))
+          OR (c.created_by = ${param} AND NOT COALESCE(c.sealed, false))
+          OR EXISTS (SELECT 1 FROM judicial_officers jo
+                     WHERE jo.user_id = ${param} AND jo.id IN (c.assigned_judge_id, c.magistrate_judge_id))
+          OR EXISTS (SELECT 1 FROM case_parties cp
+                     JOIN party_attorney_representations par ON par.case_party_id = cp.id
+                     WHERE cp.case_id = c.id AND cp.case_filed_date = c.filed_date
+                       AND par.attorney_id = ${param} AND par.status = 'ACTIVE')
+          OR {})",
+        cleared_for(&sealed_classification("c.security_level", "c.sealed"), param)
+    )
+}
+
+/// Decides which cases a user may see
+pub struct CaseAccess;
+
+impl CaseAccess {
+    /// The partition key (`filed_date`) of a case the viewer may see. None when
+    /// the case does not exist or is hidden from them, so sealed cases cannot
+    /// be told apart from missing ones.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn visible_filed_date(case_id: i64, viewer_id: i64, con: &Arc<Connection>) -> Result<Option<String>, LexodusAppError> {
+        let sql = format!(
+            "SELECT c.filed_date::text FROM cases c WHERE c.id = $1 AND {}",
+            case_visible_to(2)
+        );
+        let rowset = con.query(&sql, &[ParameterValue::Int64(case_id), ParameterValue::Int64(viewer_id)])?;
+        Ok(rowset.rows.first().and_then(|row| text(&row[0])))
+    }
+}
+
+impl CaseHeader {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn get(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT c.id, c.case_number, c.title, c.case_type, c.nature_of_suit, c.filing_type, c.status,
+                    c.filed_date::text, c.closed_date::text, c.reopened_date::text,
+                    co.name, d.name, aj.full_name, mj.full_name,
+                    c.jury_demand, c.demand_amount::text, c.jurisdictional_basis,
+                    c.security_level, COALESCE(c.sealed, false),
+                    c.disposition_method, c.disposition_date::text, c.assigned_judge_id
+             FROM cases c
+             JOIN courts co ON co.id = c.court_id
+             LEFT JOIN court_divisions d ON d.id = c.division_id
+             LEFT JOIN judicial_officers ajo ON ajo.id = c.assigned_judge_id
+             LEFT JOIN users aj ON aj.id = ajo.user_id
+             LEFT JOIN judicial_officers mjo ON mjo.id = c.magistrate_judge_id
+             LEFT JOIN users mj ON mj.id = mjo.user_id
+             WHERE c.id = $1 AND c.filed_date = $2::date",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        // Free-text columns are often saved as empty strings by the create form
+        let optional = |v: &DbValue| text(v).filter(|s| !s.is_empty());
+        Ok(rowset.rows.first().map(|row| CaseHeader {
+            id: int(&row[0]).unwrap_or(-1),
+            case_number: text(&row[1]).unwrap_or_default(),
+            title: text(&row[2]).unwrap_or_default(),
+            case_type: text(&row[3]).unwrap_or_default(),
+            nature_of_suit: optional(&row[4]),
+            filing_type: text(&row[5]).unwrap_or_default(),
+            status: text(&row[6]).unwrap_or_default(),
+            filed_date: text(&row[7]).unwrap_or_default(),
+            closed_date: text(&row[8]),
+            reopened_date: text(&row[9]),
+            disposition_method: optional(&row[19]),
+            disposition_date: text(&row[20]),
+            assigned_judge_id: int(&row[21]),
+            court_name: text(&row[10]).unwrap_or_default(),
+            division_name: text(&row[11]),
+            assigned_judge: text(&row[12]),
+            magistrate_judge: text(&row[13]),
+            jury_demand: optional(&row[14]),
+            demand_amount: text(&row[15]),
+            jurisdictional_basis: optional(&row[16]),
+            security_level: text(&row[17]).unwrap_or_default(),
+            sealed: matches!(&row[18], DbValue::Boolean(true)),
+        }))
+    }
+}
+
+impl CaseParty {
+    /// Parties with their current and past attorneys, lead parties first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT p.id, p.name, pt.name, COALESCE(p.is_lead, false), COALESCE(p.is_pro_se, false),
+                    u.full_name, u.email, par.representation_type, par.status
+             FROM case_parties p
+             JOIN party_types pt ON pt.id = p.party_type_id
+             LEFT JOIN party_attorney_representations par ON par.case_party_id = p.id
+             LEFT JOIN users u ON u.id = par.attorney_id
+             WHERE p.case_id = $1 AND p.case_filed_date = $2::date
+             ORDER BY p.is_lead DESC NULLS LAST, pt.name, p.name, p.id,
+                      par.status = 'ACTIVE' DESC, par.representation_start_date",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        let mut parties: Vec<CaseParty> = Vec::new();
+        for row in &rowset.rows {
+            let id = int(&row[0]).unwrap_or(-1);
+            if parties.last().map(|p| p.id) != Some(id) {
+                parties.push(CaseParty {
+                    id,
+                    name: text(&row[1]).unwrap_or_default(),
+                    party_type: text(&row[2]).unwrap_or_default(),
+                    is_lead: matches!(&row[3], DbValue::Boolean(true)),
+                    is_pro_se: matches!(&row[4], DbValue::Boolean(true)),
+                    attorneys: Vec::new(),
+                });
+            }
+            if let (Some(party), Some(name)) = (parties.last_mut(), text(&row[5])) {
+                party.attorneys.push(PartyAttorney {
+                    name,
+                    email: text(&row[6]),
+                    representation_type: text(&row[7]).unwrap_or_default(),
+                    status: text(&row[8]).unwrap_or_default(),
+                });
+            }
+        }
+        Ok(parties)
+    }
+
+    /// Adds a party of the named type (`PLAINTIFF`, `Expert witness`, ...) to the
+    /// case, with its attorney as lead counsel when one is given. `None` when
+    /// there is no such party type.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn add(
+        case_id: i64,
+        filed_date: &str,
+        name: &str,
+        party_type: &str,
+        attorney_id: Option<i64>,
+        added_by: i64,
+        con: &Arc<Connection>,
+    ) -> Result<Option<i64>, LexodusAppError> {
+        let rowset = con.query(
+            "WITH party AS (
+                 INSERT INTO case_parties (case_id, case_filed_date, party_type_id, name)
+                 SELECT $1, $2::date, pt.id, $3
+                 FROM party_types pt
+                 WHERE pt.name = upper(replace(btrim($4), ' ', '_'))
+                 RETURNING id
+             ), counsel AS (
+                 INSERT INTO party_attorney_representations
+                     (case_party_id, attorney_id, representation_type, representation_start_date, created_by)
+                 SELECT party.id, $5, 'LEAD', CURRENT_DATE, $6
+                 FROM party
+                 WHERE $5::bigint IS NOT NULL
+             )
+             SELECT id FROM party",
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Str(name.to_string()),
+                ParameterValue::Str(party_type.to_string()),
+                attorney_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ParameterValue::Int64(added_by),
+            ],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| int(&row[0])))
+    }
+}
+
+impl DocketEntry {
+    /// Active docket entries, oldest first as on a printed docket sheet.
+    /// Entries copied from a lead case the viewer may not see are left out,
+    /// so a sealed lead's filings do not show on its members' dockets.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, viewer_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let sql = format!(
+            "SELECT e.id, et.name, e.title, e.public_entry, e.document_number, e.page_count,
+                    EXTRACT(EPOCH FROM e.event_date)::bigint, c.case_number
+             FROM case_events e
+             JOIN event_types et ON et.id = e.event_type_id
+             LEFT JOIN case_events source ON source.id = e.mirrored_from_event_id
+             LEFT JOIN cases c ON c.id = source.case_id AND c.filed_date = source.case_filed_date AND {}
+             WHERE e.case_id = $1 AND e.case_filed_date = $2::date AND e.event_status = 'ACTIVE'
+               AND (e.mirrored_from_event_id IS NULL OR c.id IS NOT NULL)
+             ORDER BY e.event_date, e.id",
+            case_visible_to(3)
+        );
+        let rowset = con.query(
+            &sql,
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(viewer_id),
+            ],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let event_date = int(&row[6]).unwrap_or_default();
+                DocketEntry {
+                    id: int(&row[0]).unwrap_or(-1),
+                    event_type: text(&row[1]).unwrap_or_default(),
+                    title: text(&row[2]).unwrap_or_default(),
+                    entry: text(&row[3]),
+                    document_number: text(&row[4]),
+                    page_count: int(&row[5]),
+                    event_date,
+                    event_date_pretty: pretty(event_date),
+                    mirrored_from: text(&row[7]),
+                }
+            })
+            .collect())
+    }
+}
+
+impl OpenDeadline {
+    /// Deadlines without a completion date, soonest first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT d.id, d.title, d.deadline_type, d.priority, d.status,
+                    d.due_date < CURRENT_TIMESTAMP, EXTRACT(EPOCH FROM d.due_date)::bigint
+             FROM case_deadlines d
+             WHERE d.case_id = $1 AND d.case_filed_date = $2::date AND d.completion_date IS NULL
+             ORDER BY d.due_date, d.id",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let due_date = int(&row[6]).unwrap_or_default();
+                OpenDeadline {
+                    id: int(&row[0]).unwrap_or(-1),
+                    title: text(&row[1]).unwrap_or_default(),
+                    deadline_type: text(&row[2]).unwrap_or_default(),
+                    priority: text(&row[3]).unwrap_or_default(),
+                    status: text(&row[4]).unwrap_or_default(),
+                    overdue: matches!(&row[5], DbValue::Boolean(true)),
+                    due_date,
+                    due_date_pretty: pretty(due_date),
+                }
+            })
+            .collect())
+    }
+}
+
+impl CaseMotion {
+    /// Motions newest first. Motions under seal are left out unless the viewer
+    /// filed them or is cleared for sealed records.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, viewer_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let sql = format!(
+            "SELECT m.id, m.title, mt.name, u.full_name, m.status, m.response_deadline::text,
+                    EXTRACT(EPOCH FROM m.hearing_date)::bigint, EXTRACT(EPOCH FROM m.filed_date)::bigint
+             FROM motions m
+             JOIN motion_types mt ON mt.id = m.motion_type_id
+             LEFT JOIN users u ON u.id = m.filed_by
+             WHERE m.case_id = $1 AND m.case_filed_date = $2::date
+               AND (NOT COALESCE(m.under_seal, false) OR m.filed_by = $3 OR {})
+             ORDER BY m.filed_date DESC, m.id DESC",
+            cleared_for("'SEALED'", 3)
+        );
+        let rowset = con.query(
+            &sql,
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(viewer_id),
+            ],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let filed_date = int(&row[7]).unwrap_or_default();
+                CaseMotion {
+                    id: int(&row[0]).unwrap_or(-1),
+                    title: text(&row[1]).unwrap_or_default(),
+                    motion_type: text(&row[2]).unwrap_or_default(),
+                    filed_by: text(&row[3]),
+                    status: text(&row[4]).unwrap_or_default(),
+                    response_deadline: text(&row[5]),
+                    hearing_date_pretty: int(&row[6]).map(pretty),
+                    filed_date,
+                    filed_date_pretty: pretty(filed_date),
+                }
+            })
+            .collect())
+    }
+}
+
+impl CaseDocument {
+    /// Documents newest first. Sealed or classified documents are left out
+    /// unless the viewer filed them or is cleared for their level.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, viewer_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let sql = format!(
+            "SELECT d.id, d.document_number, d.title, dt.name, u.full_name, d.page_count,
+                    EXTRACT(EPOCH FROM d.filed_date)::bigint
+             FROM documents d
+             LEFT JOIN document_types dt ON dt.id = d.document_type_id
+             LEFT JOIN users u ON u.id = d.filed_by
+             WHERE d.case_id = $1 AND d.case_filed_date = $2::date
+               AND ((d.security_level = 'PUBLIC' AND NOT COALESCE(d.is_sealed, false))
+                    OR d.filed_by = $3
+                    OR {})
+             ORDER BY d.filed_date DESC, d.id DESC",
+            cleared_for(&sealed_classification("d.security_level", "d.is_sealed"), 3)
+        );
+        let rowset = con.query(
+            &sql,
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(viewer_id),
+            ],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let filed_date = int(&row[6]).unwrap_or_default();
+                CaseDocument {
+                    id: int(&row[0]).unwrap_or(-1),
+                    document_number: text(&row[1]),
+                    title: text(&row[2]).unwrap_or_default(),
+                    document_type: text(&row[3]),
+                    filed_by: text(&row[4]),
+                    page_count: int(&row[5]),
+                    filed_date,
+                    filed_date_pretty: pretty(filed_date),
+                }
+            })
+            .collect())
+    }
+}
+
+/// `updated_at` in microseconds; see `CaseEdit::version`
+const CASE_VERSION: &str = "(EXTRACT(EPOCH FROM COALESCE(c.updated_at, c.created_at)) * 1000000)::bigint";
+
+impl CaseEdit {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn get(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+        let sql = format!(
+            "SELECT c.title, c.nature_of_suit, c.jury_demand, c.demand_amount::text, c.jurisdictional_basis,
+                    c.division_id, c.magistrate_judge_id, {CASE_VERSION}, c.cause_of_action
+             FROM cases c
+             WHERE c.id = $1 AND c.filed_date = $2::date"
+        );
+        let rowset = con.query(&sql, &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())])?;
+        let optional = |v: &DbValue| text(v).filter(|s| !s.is_empty());
+        Ok(rowset.rows.first().map(|row| CaseEdit {
+            title: text(&row[0]).unwrap_or_default(),
+            nature_of_suit: optional(&row[1]),
+            cause_of_action: optional(&row[8]),
+            jury_demand: optional(&row[2]),
+            demand_amount: text(&row[3]),
+            jurisdictional_basis: optional(&row[4]),
+            division_id: int(&row[5]),
+            magistrate_judge_id: int(&row[6]),
+            version: int(&row[7]).unwrap_or_default(),
+        }))
+    }
+
+    /// Write the edit if the case is still at `self.version`. Returns the new
+    /// version, or None when someone else saved first and nothing was written.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn save(&self, case_id: i64, filed_date: &str, updated_by: i64, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        let optional = |v: &Option<String>| v.clone().map_or(ParameterValue::DbNull, ParameterValue::Str);
+        let id = |v: Option<i64>| v.map_or(ParameterValue::DbNull, ParameterValue::Int64);
+        let sql = format!(
+            "UPDATE cases c
+             SET title = $3, nature_of_suit = $4, jury_demand = $5, demand_amount = $6::numeric,
+                 jurisdictional_basis = $7, division_id = $8, magistrate_judge_id = $9,
+                 cause_of_action = $12, updated_by = $10, updated_at = CURRENT_TIMESTAMP
+             WHERE c.id = $1 AND c.filed_date = $2::date AND {CASE_VERSION} = $11
+             RETURNING {CASE_VERSION}"
+        );
+        let rowset = con.query(
+            &sql,
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Str(self.title.clone()),
+                optional(&self.nature_of_suit),
+                optional(&self.jury_demand),
+                optional(&self.demand_amount),
+                optional(&self.jurisdictional_basis),
+                id(self.division_id),
+                id(self.magistrate_judge_id),
+                ParameterValue::Int64(updated_by),
+                ParameterValue::Int64(self.version),
+                optional(&self.cause_of_action),
+            ],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| int(&row[0])))
+    }
+}
+
+impl CaseEditOptions {
+    /// Divisions of the case's court and its active judicial officers. The
+    /// case's current choices are always included, even if since retired.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn load(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Self, LexodusAppError> {
+        let params = [ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())];
+        let pairs = |rows: &[Vec<DbValue>]| {
+            rows.iter()
+                .filter_map(|row| Some((int(&row[0])?, text(&row[1])?)))
+                .collect::<Vec<_>>()
+        };
+        let divisions = con.query(
+            "SELECT d.id, d.name
+             FROM cases c
+             JOIN court_divisions d ON d.court_id = c.court_id
+             WHERE c.id = $1 AND c.filed_date = $2::date
+             ORDER BY d.name",
+            &params,
+        )?;
+        let magistrates = con.query(
+            "SELECT jo.id, u.full_name || ' (' || jo.title || ')'
+             FROM cases c
+             JOIN judicial_officers jo ON jo.court_id = c.court_id
+             JOIN users u ON u.id = jo.user_id
+             WHERE c.id = $1 AND c.filed_date = $2::date
+               AND (jo.status = 'ACTIVE' OR jo.id = c.magistrate_judge_id)
+             ORDER BY u.full_name",
+            &params,
+        )?;
+        Ok(CaseEditOptions {
+            divisions: pairs(&divisions.rows),
+            magistrates: pairs(&magistrates.rows),
+        })
+    }
+}
+}
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn options() -> CaseEditOptions {
+        CaseEditOptions {
+            divisions: vec![(1, "Northern".to_string()), (2, "Southern".to_string())],
+            magistrates: vec![(7, "Hon. Ada Reyes".to_string())],
+        }
+    }
+
+    fn edit() -> CaseEdit {
+        CaseEdit {
+            title: "Smith v. Jones".to_string(),
+            nature_of_suit: Some("190".to_string()),
+            cause_of_action: Some("28:1332".to_string()),
+            jury_demand: Some("YES".to_string()),
+            demand_amount: Some("1500.00".to_string()),
+            jurisdictional_basis: Some("DIVERSITY".to_string()),
+            division_id: Some(1),
+            magistrate_judge_id: None,
+            version: 100,
+        }
+    }
+
+    #[test]
+    fn identical_edits_have_no_changes() {
+        assert!(edit().changes(&edit(), &options()).is_empty());
+    }
+
+    #[test]
+    fn version_alone_is_not_a_change() {
+        let newer = CaseEdit { version: 200, ..edit() };
+        assert!(edit().changes(&newer, &options()).is_empty());
+    }
+
+    #[test]
+    fn changed_fields_are_listed_in_form_order() {
+        let other = CaseEdit {
+            title: "Smith v. Jones Corp.".to_string(),
+            jury_demand: None,
+            ..edit()
+        };
+        assert_eq!(
+            edit().changes(&other, &options()),
+            vec![
+                FieldChange {
+                    field: "Title".to_string(),
+                    before: Some("Smith v. Jones".to_string()),
+                    after: Some("Smith v. Jones Corp.".to_string()),
+                },
+                FieldChange {
+                    field: "Jury Demand".to_string(),
+                    before: Some("YES".to_string()),
+                    after: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn divisions_and_judges_are_shown_by_name() {
+        let other = CaseEdit {
+            division_id: Some(2),
+            magistrate_judge_id: Some(7),
+            ..edit()
+        };
+        let changes = edit().changes(&other, &options());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field, "Division");
+        assert_eq!(changes[0].before.as_deref(), Some("Northern"));
+        assert_eq!(changes[0].after.as_deref(), Some("Southern"));
+        assert_eq!(changes[1].field, "Magistrate Judge");
+        assert_eq!(changes[1].before, None);
+        assert_eq!(changes[1].after.as_deref(), Some("Hon. Ada Reyes"));
+    }
+
+    #[test]
+    fn option_no_longer_offered_is_shown_by_id() {
+        let other = CaseEdit { division_id: Some(9), ..edit() };
+        let changes = edit().changes(&other, &options());
+        assert_eq!(changes[0].after.as_deref(), Some("#9"));
+    }
+
+    /// Whether user `$1` may see a case at `security_level`, sealed or not,
+    /// filed by user `$4` and that they have no other part in
+    fn visible_sql() -> String {
+        format!(
+            "SELECT {} FROM (SELECT 0::bigint AS id, DATE '2024-01-05' AS filed_date, $2::text AS security_level,
+                                    $3::boolean AS sealed, $4::bigint AS created_by,
+                                    NULL::bigint AS assigned_judge_id, NULL::bigint AS magistrate_judge_id) c",
+            case_visible_to(1)
+        )
+    }
+
+    /// A user with no clearance, inside a transaction the caller rolls back
+    fn test_user(con: &Connection) -> i64 {
+        con.execute("BEGIN", &[]).unwrap();
+        let user = con
+            .query(
+                "INSERT INTO users (username, email, password_hash, full_name, role_id)
+                 SELECT 'clearance-test', 'clearance-test@example.com', '-', 'Clearance Test', MIN(id) FROM roles
+                 RETURNING id",
+                &[],
+            )
+            .unwrap();
+        int(&user.rows[0][0]).unwrap()
+    }
+
+    fn visible(con: &Connection, user_id: i64, level: &str, sealed: bool, filed_by: Option<i64>) -> bool {
+        let rowset = con
+            .query(
+                &visible_sql(),
+                &[
+                    ParameterValue::Int64(user_id),
+                    ParameterValue::Str(level.to_string()),
+                    ParameterValue::Boolean(sealed),
+                    filed_by.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ],
+            )
+            .unwrap();
+        matches!(rowset.rows[0][0], DbValue::Boolean(true))
+    }
+
+    #[test]
+    #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+    fn sealed_restricted_case_is_hidden_from_restricted_clearance() {
+        let url = std::env::var("LEXODUS_TEST_DB_URL").unwrap();
+        let con = Connection::open(&url).unwrap();
+        let user_id = test_user(&con);
+        con.execute(
+            "INSERT INTO user_security_clearances (user_id, classification_id, granted_by)
+             SELECT $1, id, $1 FROM security_classifications WHERE name = 'RESTRICTED'",
+            &[ParameterValue::Int64(user_id)],
+        )
+        .unwrap();
+        let visible = |level: &str, sealed: bool| visible(&con, user_id, level, sealed, None);
+        let seen = [visible("RESTRICTED", false), visible("RESTRICTED", true), visible("PUBLIC", true), visible("SEALED", false)];
+        con.execute("ROLLBACK", &[]).unwrap();
+        assert_eq!(seen, [true, false, false, false]);
+    }
+
+    #[test]
+    #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+    fn sealed_case_is_hidden_from_its_filer() {
+        let url = std::env::var("LEXODUS_TEST_DB_URL").unwrap();
+        let con = Connection::open(&url).unwrap();
+        let user_id = test_user(&con);
+        let filed = |level: &str, sealed: bool| visible(&con, user_id, level, sealed, Some(user_id));
+        let seen = [filed("PUBLIC", false), filed("RESTRICTED", false), filed("PUBLIC", true), filed("RESTRICTED", true)];
+        con.execute("ROLLBACK", &[]).unwrap();
+        assert_eq!(seen, [true, true, false, false]);
+    }
 }
@@ -0,0 +1,450 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::case::case_visible_to;
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// How two linked cases relate. A link reads "case <kind> other case", so
+/// each kind has a label for either end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseLinkKind {
+    Related,
+    /// A member consolidated into its lead case
+    Consolidated,
+    /// A member of the multidistrict litigation led by the other case
+    Mdl,
+    /// An appeal of the other case
+    AppealOf,
+    /// Refiled as the other case
+    RefiledAs,
+}
+
+/// The choices for linking from a case, as `KIND:OUT` when the case is the
+/// link's subject and `KIND:IN` when it is the object
+pub const CASE_LINK_CHOICES: [(&str, &str); 9] = [
+    ("RELATED:OUT", "Related to"),
+    ("CONSOLIDATED:OUT", "Consolidated into lead case"),
+    ("CONSOLIDATED:IN", "Lead case of member"),
+    ("MDL:OUT", "Member of MDL led by"),
+    ("MDL:IN", "MDL lead case of"),
+    ("APPEAL_OF:OUT", "Appeal of"),
+    ("APPEAL_OF:IN", "Appealed in"),
+    ("REFILED_AS:OUT", "Refiled as"),
+    ("REFILED_AS:IN", "Refiled from"),
+];
+
+impl CaseLinkKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "RELATED" => Some(Self::Related),
+            "CONSOLIDATED" => Some(Self::Consolidated),
+            "MDL" => Some(Self::Mdl),
+            "APPEAL_OF" => Some(Self::AppealOf),
+            "REFILED_AS" => Some(Self::RefiledAs),
+            _ => None,
+        }
+    }
+
+    /// How the case at one end describes the other: `outgoing` when it is the
+    /// link's subject
+    pub fn label(self, outgoing: bool) -> &'static str {
+        match (self, outgoing) {
+            (Self::Related, _) => "Related to",
+            (Self::Consolidated, true) => "Consolidated into",
+            (Self::Consolidated, false) => "Lead case of",
+            (Self::Mdl, true) => "MDL member of",
+            (Self::Mdl, false) => "MDL lead of",
+            (Self::AppealOf, true) => "Appeal of",
+            (Self::AppealOf, false) => "Appealed in",
+            (Self::RefiledAs, true) => "Refiled as",
+            (Self::RefiledAs, false) => "Refiled from",
+        }
+    }
+}
+
+/// A case in a link graph, `depth` links away from the case being viewed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseLinkNode {
+    pub id: i64,
+    pub case_number: String,
+    pub title: String,
+    pub status: String,
+    pub depth: i64,
+}
+
+/// A link between two cases in a graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseLink {
+    pub id: i64,
+    pub from_id: i64,
+    pub to_id: i64,
+    pub kind: String,
+    pub mirror_docket: bool,
+    pub notes: Option<String>,
+    pub created_by: Option<String>,
+    pub created_at_pretty: String,
+}
+
+impl CaseLink {
+    /// How `case_id`, one end of this link, describes the other end
+    pub fn label_for(&self, case_id: i64) -> &'static str {
+        CaseLinkKind::parse(&self.kind).map_or("Linked to", |kind| kind.label(self.from_id == case_id))
+    }
+
+    /// The end of this link that is not `case_id`
+    pub fn other(&self, case_id: i64) -> i64 {
+        if self.from_id == case_id {
+            self.to_id
+        } else {
+            self.from_id
+        }
+    }
+}
+
+/// The cases linked to a case, directly or through others, that the viewer
+/// can see, and the links among them
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct CaseLinkGraph {
+    pub nodes: Vec<CaseLinkNode>,
+    pub links: Vec<CaseLink>,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+impl CaseLinkKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Related => "RELATED",
+            Self::Consolidated => "CONSOLIDATED",
+            Self::Mdl => "MDL",
+            Self::AppealOf => "APPEAL_OF",
+            Self::RefiledAs => "REFILED_AS",
+        }
+    }
+
+    /// A `KIND:OUT` or `KIND:IN` choice from the link form
+    pub fn parse_choice(choice: &str) -> Option<(Self, bool)> {
+        let (kind, direction) = choice.split_once(':')?;
+        let outgoing = match direction {
+            "OUT" => true,
+            "IN" => false,
+            _ => return None,
+        };
+        Some((Self::parse(kind)?, outgoing))
+    }
+}
+
+/// How many links away from the viewed case the graph reaches
+const GRAPH_DEPTH: i64 = 3;
+
+/// A link about to be made, from the subject case to the object case
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewCaseLink {
+    pub from: (i64, String),
+    pub to: (i64, String),
+    pub kind: CaseLinkKind,
+    pub mirror_docket: bool,
+    pub notes: Option<String>,
+}
+
+/// Cases reachable from $1/$2 through links, walking only through cases the
+/// viewer ($3) can see
+fn walk_sql() -> String {
+    format!(
+        "WITH RECURSIVE walk (case_id, filed_date, depth) AS (
+             SELECT $1::bigint, $2::date, 0
+             UNION
+             SELECT next.case_id, next.filed_date, w.depth + 1
+             FROM walk w
+             JOIN related_cases r
+               ON (r.case_id = w.case_id AND r.case_filed_date = w.filed_date)
+               OR (r.related_case_id = w.case_id AND r.related_case_filed_date = w.filed_date)
+             CROSS JOIN LATERAL (
+                 SELECT CASE WHEN r.case_id = w.case_id THEN r.related_case_id ELSE r.case_id END AS case_id,
+                        CASE WHEN r.case_id = w.case_id THEN r.related_case_filed_date ELSE r.case_filed_date END AS filed_date
+             ) next
+             JOIN cases c ON c.id = next.case_id AND c.filed_date = next.filed_date
+             WHERE w.depth < {GRAPH_DEPTH} AND {}
+         ),
+         reached AS (
+             SELECT case_id, filed_date, MIN(depth) AS depth FROM walk GROUP BY case_id, filed_date
+         )",
+        case_visible_to(3)
+    )
+}
+
+impl CaseLinkGraph {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn load(case_id: i64, filed_date: &str, viewer_id: i64, con: &Arc<Connection>) -> Result<Self, LexodusAppError> {
+        let params = [
+            ParameterValue::Int64(case_id),
+            ParameterValue::Str(filed_date.to_string()),
+            ParameterValue::Int64(viewer_id),
+        ];
+        let nodes = con.query(
+            &format!(
+                "{}
+                 SELECT c.id, c.case_number, c.title, c.status, reached.depth::bigint
+                 FROM reached
+                 JOIN cases c ON c.id = reached.case_id AND c.filed_date = reached.filed_date
+                 ORDER BY reached.depth, c.filed_date, c.id",
+                walk_sql()
+            ),
+            &params,
+        )?;
+        let links = con.query(
+            &format!(
+                "{}
+                 SELECT r.id, r.case_id, r.related_case_id, r.relationship_type, r.mirror_docket, r.notes,
+                        u.full_name, EXTRACT(EPOCH FROM r.created_at)::bigint
+                 FROM related_cases r
+                 JOIN reached a ON a.case_id = r.case_id AND a.filed_date = r.case_filed_date
+                 JOIN reached b ON b.case_id = r.related_case_id AND b.filed_date = r.related_case_filed_date
+                 LEFT JOIN users u ON u.id = r.created_by
+                 ORDER BY r.id",
+                walk_sql()
+            ),
+            &params,
+        )?;
+        Ok(CaseLinkGraph {
+            nodes: nodes
+                .rows
+                .iter()
+                .map(|row| CaseLinkNode {
+                    id: int(&row[0]).unwrap_or(-1),
+                    case_number: text(&row[1]).unwrap_or_default(),
+                    title: text(&row[2]).unwrap_or_default(),
+                    status: text(&row[3]).unwrap_or_default(),
+                    depth: int(&row[4]).unwrap_or_default(),
+                })
+                .collect(),
+            links: links
+                .rows
+                .iter()
+                .map(|row| CaseLink {
+                    id: int(&row[0]).unwrap_or(-1),
+                    from_id: int(&row[1]).unwrap_or(-1),
+                    to_id: int(&row[2]).unwrap_or(-1),
+                    kind: text(&row[3]).unwrap_or_default(),
+                    mirror_docket: matches!(row[4], DbValue::Boolean(true)),
+                    notes: text(&row[5]),
+                    created_by: text(&row[6]),
+                    created_at_pretty: pretty(int(&row[7]).unwrap_or_default()),
+                })
+                .collect(),
+        })
+    }
+}
+
+impl NewCaseLink {
+    /// Check what the link asks for on its own: no linking a case to itself,
+    /// and only consolidated members mirror their lead's docket
+    pub fn check(&self) -> Result<(), LexodusAppError> {
+        if self.from == self.to {
+            return Err(LexodusAppError::BadRequest("A case cannot be linked to itself.".to_string()));
+        }
+        if self.mirror_docket && self.kind != CaseLinkKind::Consolidated {
+            return Err(LexodusAppError::BadRequest("Only consolidated members can mirror their lead's docket.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Check the link against those the two cases already have: no linking a
+    /// case to itself or twice, one lead per consolidated member and one MDL
+    /// per member, and no lead that is itself consolidated into another case.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn validate(&self, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        self.check()?;
+        let rowset = con.query(
+            "SELECT
+                 EXISTS (SELECT 1 FROM related_cases
+                         WHERE (case_id = $1 AND case_filed_date = $2::date AND related_case_id = $3 AND related_case_filed_date = $4::date)
+                            OR (case_id = $3 AND case_filed_date = $4::date AND related_case_id = $1 AND related_case_filed_date = $2::date)),
+                 EXISTS (SELECT 1 FROM related_cases
+                         WHERE case_id = $1 AND case_filed_date = $2::date AND relationship_type = $5),
+                 EXISTS (SELECT 1 FROM related_cases
+                         WHERE case_id = $3 AND case_filed_date = $4::date AND relationship_type = $5),
+                 EXISTS (SELECT 1 FROM related_cases
+                         WHERE related_case_id = $1 AND related_case_filed_date = $2::date AND relationship_type = $5)",
+            &[
+                ParameterValue::Int64(self.from.0),
+                ParameterValue::Str(self.from.1.clone()),
+                ParameterValue::Int64(self.to.0),
+                ParameterValue::Str(self.to.1.clone()),
+                ParameterValue::Str(self.kind.as_str().to_string()),
+            ],
+        )?;
+        let flag = |i: usize| rowset.rows.first().is_some_and(|row| matches!(row[i], DbValue::Boolean(true)));
+        if flag(0) {
+            return Err(LexodusAppError::BadRequest("These cases are already linked.".to_string()));
+        }
+        if matches!(self.kind, CaseLinkKind::Consolidated | CaseLinkKind::Mdl) {
+            let what = if self.kind == CaseLinkKind::Mdl { "an MDL" } else { "a lead case" };
+            if flag(1) {
+                return Err(LexodusAppError::BadRequest(format!("The member case already belongs to {what}.")));
+            }
+            if flag(2) {
+                return Err(LexodusAppError::BadRequest(format!("The lead case is itself a member of {what}.")));
+            }
+            if flag(3) {
+                return Err(LexodusAppError::BadRequest(format!("The member case already leads {what}.")));
+            }
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn insert(&self, created_by: i64, con: &Arc<Connection>) -> Result<i64, LexodusAppError> {
+        let rowset = con.query(
+            "INSERT INTO related_cases (
+                 case_id, case_filed_date, related_case_id, related_case_filed_date,
+                 relationship_type, mirror_docket, notes, created_by
+             )
+             VALUES ($1, $2::date, $3, $4::date, $5, $6, $7, $8)
+             RETURNING id",
+            &[
+                ParameterValue::Int64(self.from.0),
+                ParameterValue::Str(self.from.1.clone()),
+                ParameterValue::Int64(self.to.0),
+                ParameterValue::Str(self.to.1.clone()),
+                ParameterValue::Str(self.kind.as_str().to_string()),
+                ParameterValue::Boolean(self.mirror_docket),
+                self.notes.clone().map_or(ParameterValue::DbNull, ParameterValue::Str),
+                ParameterValue::Int64(created_by),
+            ],
+        )?;
+        rowset
+            .rows
+            .first()
+            .and_then(|row| int(&row[0]))
+            .ok_or_else(|| LexodusAppError::DBError("Case link was not saved".to_string()))
+    }
+}
+
+impl CaseLink {
+    /// Remove a link touching the case. Entries already mirrored stay on the
+    /// member's docket. Returns false when there was no such link.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn remove(id: i64, case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "DELETE FROM related_cases
+             WHERE id = $1
+               AND ((case_id = $2 AND case_filed_date = $3::date)
+                    OR (related_case_id = $2 AND related_case_filed_date = $3::date))
+             RETURNING id",
+            &[ParameterValue::Int64(id), ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: [CaseLinkKind; 5] =
+        [CaseLinkKind::Related, CaseLinkKind::Consolidated, CaseLinkKind::Mdl, CaseLinkKind::AppealOf, CaseLinkKind::RefiledAs];
+
+    fn link(from_id: i64, to_id: i64, kind: &str) -> CaseLink {
+        CaseLink {
+            id: 1,
+            from_id,
+            to_id,
+            kind: kind.to_string(),
+            mirror_docket: false,
+            notes: None,
+            created_by: None,
+            created_at_pretty: String::new(),
+        }
+    }
+
+    #[test]
+    fn unknown_kind_does_not_parse() {
+        assert_eq!(CaseLinkKind::parse("related"), None);
+        assert_eq!(CaseLinkKind::parse("MEMBER"), None);
+    }
+
+    #[test]
+    fn related_reads_the_same_from_either_end() {
+        assert_eq!(CaseLinkKind::Related.label(true), CaseLinkKind::Related.label(false));
+        for kind in KINDS.into_iter().filter(|k| *k != CaseLinkKind::Related) {
+            assert_ne!(kind.label(true), kind.label(false), "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn link_reads_from_the_end_viewing_it() {
+        let member = link(10, 20, "CONSOLIDATED");
+        assert_eq!(member.label_for(10), "Consolidated into");
+        assert_eq!(member.label_for(20), "Lead case of");
+        assert_eq!(member.other(10), 20);
+        assert_eq!(member.other(20), 10);
+    }
+
+    #[test]
+    fn link_of_unknown_kind_still_reads() {
+        assert_eq!(link(10, 20, "COMPANION").label_for(10), "Linked to");
+    }
+
+    #[cfg(feature = "ssr")]
+    mod ssr {
+        use super::super::*;
+        use super::KINDS;
+
+        fn new_link(to: i64, kind: CaseLinkKind, mirror_docket: bool) -> NewCaseLink {
+            NewCaseLink {
+                from: (10, "2024-01-05".to_string()),
+                to: (to, "2024-01-05".to_string()),
+                kind,
+                mirror_docket,
+                notes: None,
+            }
+        }
+
+        #[test]
+        fn kinds_round_trip_through_their_names() {
+            for kind in KINDS {
+                assert_eq!(CaseLinkKind::parse(kind.as_str()), Some(kind));
+            }
+        }
+
+        #[test]
+        fn every_offered_choice_parses() {
+            for (choice, _) in CASE_LINK_CHOICES {
+                let (kind, outgoing) = CaseLinkKind::parse_choice(choice).unwrap();
+                assert_eq!(choice, format!("{}:{}", kind.as_str(), if outgoing { "OUT" } else { "IN" }));
+            }
+        }
+
+        #[test]
+        fn malformed_choice_is_refused() {
+            assert_eq!(CaseLinkKind::parse_choice("RELATED"), None);
+            assert_eq!(CaseLinkKind::parse_choice("RELATED:BOTH"), None);
+            assert_eq!(CaseLinkKind::parse_choice("SIBLING:OUT"), None);
+        }
+
+        #[test]
+        fn case_cannot_be_linked_to_itself() {
+            assert!(new_link(10, CaseLinkKind::Related, false).check().is_err());
+            assert!(new_link(11, CaseLinkKind::Related, false).check().is_ok());
+        }
+
+        #[test]
+        fn only_consolidated_members_mirror_the_docket() {
+            assert!(new_link(11, CaseLinkKind::Consolidated, true).check().is_ok());
+            for kind in KINDS.into_iter().filter(|k| *k != CaseLinkKind::Consolidated) {
+                assert!(new_link(11, kind, true).check().is_err(), "{kind:?}");
+                assert!(new_link(11, kind, false).check().is_ok(), "{kind:?}");
+            }
+        }
+    }
+}
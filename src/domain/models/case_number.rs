@@ -0,0 +1,540 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::case::case_visible_to;
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// Case type codes a federal style number may carry
+pub const CASE_TYPE_CODES: [&str; 8] = ["cv", "cr", "bk", "ad", "mj", "mc", "po", "ap"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CaseNumberError {
+    #[error("Enter a case number like 1:24-cv-00042 or 3-24-0042-JS")]
+    Unrecognized,
+    #[error("Unknown case type \"{0}\" in case number")]
+    UnknownType(String),
+    #[error("The year in a case number must be two or four digits")]
+    Year,
+    #[error("The sequence in a case number must be between 1 and 999999")]
+    Sequence,
+    #[error("Judge initials in a case number must be two or three letters")]
+    Judge,
+}
+
+impl From<CaseNumberError> for crate::errors::LexodusAppError {
+    fn from(e: CaseNumberError) -> Self {
+        crate::errors::LexodusAppError::BadRequest(e.to_string())
+    }
+}
+
+/// The two shapes of case number a court can issue. Court templates are held
+/// to these shapes by `case_number_template_readable`, so whatever separators,
+/// year width or type case a court chooses, its numbers parse back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaseNumberStyle {
+    /// `{office}:{yy}-{type}-{seq:05}`, as most federal courts write them
+    Federal { office: u8, case_type: String },
+    /// `{court}-{yy}-{seq:04}`, the Lexodus default
+    Local { court_id: i64 },
+}
+
+/// A validated case number. Parsing accepts what people type ("1:24cv42",
+/// "1 24 CV 42", "3-2024-42-js") and Display gives the canonical form
+/// ("1:24-cv-00042", "3-24-0042-JS"). Judge initials are kept when given
+/// but never needed to find a case, since they change on reassignment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseNumber {
+    pub style: CaseNumberStyle,
+    /// Two digit filing year
+    pub year: u8,
+    pub sequence: u32,
+    pub judge: Option<String>,
+}
+
+impl CaseNumber {
+    /// The canonical number without judge initials
+    pub fn stem(&self) -> String {
+        match &self.style {
+            CaseNumberStyle::Federal { office, case_type } => {
+                format!("{office}:{:02}-{case_type}-{:05}", self.year, self.sequence)
+            }
+            CaseNumberStyle::Local { court_id } => format!("{court_id}-{:02}-{:04}", self.year, self.sequence),
+        }
+    }
+}
+
+/// Runs of digits or letters; anything else separates them
+fn tokens(input: &str) -> Vec<String> {
+    let mut tokens: Vec<String> = Vec::new();
+    let mut last: Option<bool> = None;
+    for c in input.chars() {
+        if !c.is_ascii_alphanumeric() {
+            last = None;
+            continue;
+        }
+        let digit = c.is_ascii_digit();
+        match tokens.last_mut() {
+            Some(token) if last == Some(digit) => token.push(c),
+            _ => tokens.push(c.to_string()),
+        }
+        last = Some(digit);
+    }
+    tokens
+}
+
+fn is_digits(token: &str) -> bool {
+    token.chars().all(|c| c.is_ascii_digit())
+}
+
+fn year(token: &str) -> Result<u8, CaseNumberError> {
+    match token.len() {
+        2 | 4 => token.parse::<u16>().map(|y| (y % 100) as u8).map_err(|_| CaseNumberError::Year),
+        _ => Err(CaseNumberError::Year),
+    }
+}
+
+fn sequence(token: &str) -> Result<u32, CaseNumberError> {
+    match token.parse::<u32>() {
+        Ok(n) if (1..=999_999).contains(&n) => Ok(n),
+        _ => Err(CaseNumberError::Sequence),
+    }
+}
+
+fn judge(token: Option<&String>) -> Result<Option<String>, CaseNumberError> {
+    match token {
+        None => Ok(None),
+        Some(t) if (2..=3).contains(&t.len()) && !is_digits(t) => Ok(Some(t.to_ascii_uppercase())),
+        Some(_) => Err(CaseNumberError::Judge),
+    }
+}
+
+impl FromStr for CaseNumber {
+    type Err = CaseNumberError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let tokens = tokens(input);
+        if !(3..=5).contains(&tokens.len()) || !is_digits(&tokens[0]) || !is_digits(&tokens[1]) {
+            return Err(CaseNumberError::Unrecognized);
+        }
+        let (style, sequence_token) = if is_digits(&tokens[2]) {
+            let court_id = tokens[0].parse::<i64>().map_err(|_| CaseNumberError::Unrecognized)?;
+            if tokens.len() > 4 {
+                return Err(CaseNumberError::Unrecognized);
+            }
+            (CaseNumberStyle::Local { court_id }, &tokens[2])
+        } else {
+            let office = match tokens[0].parse::<u8>() {
+                Ok(n) if (1..=9).contains(&n) => n,
+                _ => return Err(CaseNumberError::Unrecognized),
+            };
+            let case_type = tokens[2].to_ascii_lowercase();
+            if !CASE_TYPE_CODES.contains(&case_type.as_str()) {
+                return Err(CaseNumberError::UnknownType(tokens[2].clone()));
+            }
+            if tokens.len() < 4 || !is_digits(&tokens[3]) {
+                return Err(CaseNumberError::Unrecognized);
+            }
+            (CaseNumberStyle::Federal { office, case_type }, &tokens[3])
+        };
+        let judge_token = match style {
+            CaseNumberStyle::Local { .. } => tokens.get(3),
+            CaseNumberStyle::Federal { .. } => tokens.get(4),
+        };
+        Ok(CaseNumber {
+            style,
+            year: year(&tokens[1])?,
+            sequence: sequence(sequence_token)?,
+            judge: judge(judge_token)?,
+        })
+    }
+}
+
+impl fmt::Display for CaseNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.judge {
+            Some(judge) => write!(f, "{}-{judge}", self.stem()),
+            None => f.write_str(&self.stem()),
+        }
+    }
+}
+
+impl Serialize for CaseNumber {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CaseNumber {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// How a route names a case: by id (`/cases/42`) or by number (`/cases/1:24-cv-00042`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaseRef {
+    Id(i64),
+    Number(CaseNumber),
+}
+
+impl FromStr for CaseRef {
+    type Err = CaseNumberError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.parse::<i64>() {
+            Ok(id) => Ok(CaseRef::Id(id)),
+            Err(_) => input.parse().map(CaseRef::Number),
+        }
+    }
+}
+
+impl fmt::Display for CaseRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CaseRef::Id(id) => write!(f, "{id}"),
+            CaseRef::Number(number) => write!(f, "{number}"),
+        }
+    }
+}
+
+impl Serialize for CaseRef {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for CaseRef {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        value.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+impl CaseNumber {
+    /// SQL matching the case (aliased `c`, its court `co`) this number names,
+    /// binding parameters from `$first`. Numbers drawn from a court's counter
+    /// are matched by their parts, so padding and judge initials do not
+    /// matter; older numbers are matched as written. A case also answers to
+    /// the numbers it had in courts it was transferred out of.
+    pub fn predicate(&self, first: usize) -> (String, Vec<ParameterValue>) {
+        let (stem, sequence, year, scope) = (first, first + 1, first + 2, first + 3);
+        let mut params = vec![
+            ParameterValue::Str(self.stem().to_lowercase()),
+            ParameterValue::Int32(self.sequence as i32),
+            ParameterValue::Int32(self.year as i32),
+        ];
+        match &self.style {
+            CaseNumberStyle::Federal { office, case_type } => {
+                params.push(ParameterValue::Str(office.to_string()));
+                params.push(ParameterValue::Str(case_type.clone()));
+            }
+            CaseNumberStyle::Local { court_id } => params.push(ParameterValue::Int64(*court_id)),
+        }
+        let matches = |number: &str, number_sequence: &str, number_year: &str, court_id: &str, office: &str| {
+            let scope = match &self.style {
+                CaseNumberStyle::Federal { .. } => {
+                    format!("{office} = ${scope} AND case_type_code(c.case_type) = ${}", scope + 1)
+                }
+                CaseNumberStyle::Local { .. } => format!("{court_id} = ${scope}"),
+            };
+            format!(
+                "(({number_sequence} = ${sequence}
+                   AND {number_year} % 100 = ${year}
+                   AND {scope})
+                  OR lower({number}) = ${stem}
+                  OR lower({number}) LIKE ${stem} || '-%')"
+            )
+        };
+        let sql = format!(
+            "({} OR EXISTS (
+                 SELECT 1 FROM case_number_aliases a
+                 JOIN courts aco ON aco.id = a.court_id
+                 WHERE a.case_id = c.id AND a.case_filed_date = c.filed_date AND {}
+             ))",
+            matches(
+                "c.case_number",
+                "c.case_sequence",
+                "COALESCE(c.case_number_year, EXTRACT(YEAR FROM c.filed_date)::integer)",
+                "c.court_id",
+                "co.case_number_office"
+            ),
+            matches("a.case_number", "a.case_sequence", "a.number_year", "a.court_id", "aco.case_number_office")
+        );
+        (sql, params)
+    }
+
+    /// The id of the case this number names, if `viewer_id` may see it. Several
+    /// courts can share an office code, so a number matching more than one
+    /// visible case is refused rather than guessed.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn find(&self, viewer_id: i64, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        let (matches, params) = self.predicate(2);
+        let sql = format!(
+            "SELECT c.id
+             FROM cases c
+             JOIN courts co ON co.id = c.court_id
+             WHERE {} AND {matches}
+             LIMIT 2",
+            case_visible_to(1)
+        );
+        let params = [vec![ParameterValue::Int64(viewer_id)], params].concat();
+        let rowset = con.query(&sql, &params)?;
+        match rowset.rows.as_slice() {
+            [] => Ok(None),
+            [row] => match &row[0] {
+                DbValue::Int64(id) => Ok(Some(*id)),
+                _ => Ok(None),
+            },
+            _ => Err(LexodusAppError::BadRequest(format!(
+                "{self} matches cases in more than one court; open the case from search instead."
+            ))),
+        }
+    }
+}
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(input: &str) -> String {
+        input.parse::<CaseNumber>().unwrap().to_string()
+    }
+
+    #[test]
+    fn federal_numbers_normalize() {
+        assert_eq!(canonical("1:24cv42"), "1:24-cv-00042");
+        assert_eq!(canonical("1 24 CV 42"), "1:24-cv-00042");
+        assert_eq!(canonical("1:2024-cv-00042"), "1:24-cv-00042");
+        assert_eq!(canonical("2:24-cr-00107-js"), "2:24-cr-00107-JS");
+    }
+
+    #[test]
+    fn local_numbers_normalize() {
+        assert_eq!(canonical("3-2024-42-js"), "3-24-0042-JS");
+        assert_eq!(canonical("3-24-0042"), "3-24-0042");
+        assert_eq!(canonical("12/2024/7"), "12-24-0007");
+    }
+
+    #[test]
+    fn parts_are_kept() {
+        let number: CaseNumber = "1:24cv42-abc".parse().unwrap();
+        assert_eq!(
+            number.style,
+            CaseNumberStyle::Federal {
+                office: 1,
+                case_type: "cv".to_string()
+            }
+        );
+        assert_eq!((number.year, number.sequence), (24, 42));
+        assert_eq!(number.judge.as_deref(), Some("ABC"));
+        assert_eq!(number.stem(), "1:24-cv-00042");
+    }
+
+    #[test]
+    fn malformed_numbers_are_refused() {
+        let error = |input: &str| input.parse::<CaseNumber>().unwrap_err();
+        assert_eq!(error("smith"), CaseNumberError::Unrecognized);
+        assert_eq!(error("1:24"), CaseNumberError::Unrecognized);
+        assert_eq!(error("12:24-cv-42"), CaseNumberError::Unrecognized);
+        assert_eq!(error("1:24-zz-42"), CaseNumberError::UnknownType("zz".to_string()));
+        assert_eq!(error("1:024-cv-42"), CaseNumberError::Year);
+        assert_eq!(error("3-24-0"), CaseNumberError::Sequence);
+        assert_eq!(error("3-24-1000000"), CaseNumberError::Sequence);
+        assert_eq!(error("3-24-42-j"), CaseNumberError::Judge);
+        assert_eq!(error("3-24-42-jdoe"), CaseNumberError::Judge);
+    }
+
+    /// `render_case_number` from V22, for the tokens the templates may use
+    fn render(template: &str, court: i64, office: &str, year: u16, case_type: &str, sequence: u32, judge: &str) -> String {
+        let mut number = template.to_string();
+        for width in 1..=9 {
+            number = number.replace(&format!("{{seq:0{width}}}"), &format!("{sequence:0width$}"));
+        }
+        number
+            .replace("{seq}", &sequence.to_string())
+            .replace("{court}", &court.to_string())
+            .replace("{office}", office)
+            .replace("{yyyy}", &year.to_string())
+            .replace("{yy}", &format!("{:02}", year % 100))
+            .replace("{type}", case_type)
+            .replace("{TYPE}", &case_type.to_ascii_uppercase())
+            .replace("{judge}", judge)
+    }
+
+    #[test]
+    fn numbers_from_readable_templates_parse_back() {
+        let federal = CaseNumberStyle::Federal {
+            office: 4,
+            case_type: "bk".to_string(),
+        };
+        let local = CaseNumberStyle::Local { court_id: 17 };
+        for (template, style) in [
+            ("{court}-{yy}-{seq:04}-{judge}", &local),
+            ("{court}/{yyyy}/{seq}", &local),
+            ("{office}:{yy}-{type}-{seq:05}", &federal),
+            ("{office}:{yyyy}-{TYPE}-{seq:05}-{judge}", &federal),
+            ("{office}.{yy}{type}{seq:06}", &federal),
+            ("{office} {yy} {TYPE} {seq}{judge}", &federal),
+        ] {
+            let rendered = render(template, 17, "4", 2024, "bk", 305, "RW");
+            let number: CaseNumber = rendered.parse().unwrap_or_else(|e| panic!("{rendered} from {template}: {e}"));
+            assert_eq!(&number.style, style, "{rendered} from {template}");
+            assert_eq!((number.year, number.sequence), (24, 305), "{rendered} from {template}");
+            assert_eq!(number.judge.is_some(), template.contains("{judge}"), "{rendered} from {template}");
+        }
+    }
+
+    #[test]
+    fn case_refs_are_ids_or_numbers() {
+        assert_eq!("42".parse::<CaseRef>().unwrap(), CaseRef::Id(42));
+        assert_eq!("1:24cv42".parse::<CaseRef>().unwrap().to_string(), "1:24-cv-00042");
+        assert!("nonsense".parse::<CaseRef>().is_err());
+    }
+
+    #[test]
+    fn serde_uses_the_canonical_form() {
+        let number: CaseNumber = serde_json::from_str("\"1:24cv42\"").unwrap();
+        assert_eq!(serde_json::to_string(&number).unwrap(), "\"1:24-cv-00042\"");
+        assert!(serde_json::from_str::<CaseNumber>("\"42\"").is_err());
+    }
+
+    #[cfg(feature = "ssr")]
+    mod ssr {
+        use super::super::*;
+        use crate::domain::models::db::{int, text};
+
+        /// A connection with an open transaction and a court numbering from `template`
+        fn court(template: &str) -> (Connection, i64) {
+            let url = std::env::var("LEXODUS_TEST_DB_URL").unwrap();
+            let con = Connection::open(&url).unwrap();
+            con.execute("BEGIN", &[]).unwrap();
+            let court = con
+                .query(
+                    "INSERT INTO courts (name, court_type, district, circuit, case_number_template)
+                     VALUES ('Numbering Test Court', 'DISTRICT', 'Test', 'Test', $1)
+                     RETURNING id",
+                    &[ParameterValue::Str(template.to_string())],
+                )
+                .unwrap();
+            let court_id = int(&court.rows[0][0]).unwrap();
+            (con, court_id)
+        }
+
+        fn allocate(con: &Connection, court_id: i64, case_type: &str, filed_date: &str) -> (String, i64) {
+            let rowset = con
+                .query(
+                    "SELECT number, sequence FROM allocate_case_number($1, $2, $3::date, NULL)",
+                    &[
+                        ParameterValue::Int64(court_id),
+                        ParameterValue::Str(case_type.to_string()),
+                        ParameterValue::Str(filed_date.to_string()),
+                    ],
+                )
+                .unwrap();
+            (text(&rowset.rows[0][0]).unwrap(), int(&rowset.rows[0][1]).unwrap())
+        }
+
+        #[test]
+        #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+        fn templates_render_padding_and_judge_initials() {
+            let (con, court_id) = court("{court}-{yy}-{seq:04}-{judge}");
+            let judge = con
+                .query(
+                    "WITH u AS (
+                         INSERT INTO users (username, email, password_hash, full_name, role_id)
+                         SELECT 'numbering-test', 'numbering-test@example.com', '-', 'ruth bader ginsburg', MIN(id) FROM roles
+                         RETURNING id
+                     )
+                     INSERT INTO judicial_officers (user_id, court_id, title, status, appointment_date)
+                     SELECT u.id, $1, 'Judge', 'ACTIVE', DATE '2020-01-01' FROM u
+                     RETURNING id",
+                    &[ParameterValue::Int64(court_id)],
+                )
+                .unwrap();
+            let judge_id = int(&judge.rows[0][0]).unwrap();
+            let render = |template: &str, case_type: &str, sequence: i32, judge: Option<i64>| {
+                let rowset = con
+                    .query(
+                        "SELECT render_case_number($1, 17, '4', 2024, $2, $3, $4)",
+                        &[
+                            ParameterValue::Str(template.to_string()),
+                            ParameterValue::Str(case_type.to_string()),
+                            ParameterValue::Int32(sequence),
+                            judge.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                        ],
+                    )
+                    .unwrap();
+                text(&rowset.rows[0][0]).unwrap()
+            };
+            let rendered = [
+                render("{office}:{yy}-{type}-{seq:05}", "CIVIL", 42, None),
+                render("{office}:{yyyy}-{TYPE}-{seq:05}-{judge}", "CRIMINAL", 42, Some(judge_id)),
+                render("{court}-{yy}-{seq:04}-{judge}", "CIVIL", 123456, None),
+                render("{court}/{yyyy}/{seq}", "CIVIL", 7, None),
+            ];
+            con.execute("ROLLBACK", &[]).unwrap();
+            assert_eq!(rendered, ["4:24-cv-00042", "4:2024-CR-00042-RB", "17-24-123456-XX", "17/2024/7"]);
+        }
+
+        #[test]
+        #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+        fn counters_restart_each_year() {
+            let (con, court_id) = court("{court}-{yy}-{seq:04}");
+            let drawn = [
+                allocate(&con, court_id, "CIVIL", "2024-12-31").1,
+                allocate(&con, court_id, "CRIMINAL", "2024-12-31").1,
+                allocate(&con, court_id, "CIVIL", "2025-01-02").1,
+                allocate(&con, court_id, "CIVIL", "2024-12-30").1,
+            ];
+            let (number, _) = allocate(&con, court_id, "CIVIL", "2025-01-03");
+            con.execute("ROLLBACK", &[]).unwrap();
+            assert_eq!(drawn, [1, 2, 1, 3]);
+            assert_eq!(number, format!("{court_id}-25-0002"));
+        }
+
+        #[test]
+        #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+        fn typed_templates_count_each_type_separately() {
+            let (con, court_id) = court("{office}:{yy}-{type}-{seq:05}");
+            let drawn = [
+                allocate(&con, court_id, "CIVIL", "2024-03-01"),
+                allocate(&con, court_id, "CRIMINAL", "2024-03-01"),
+                allocate(&con, court_id, "CIVIL", "2024-03-02"),
+            ];
+            con.execute("ROLLBACK", &[]).unwrap();
+            assert_eq!(
+                drawn.map(|(number, _)| number),
+                ["1:24-cv-00001".to_string(), "1:24-cr-00001".to_string(), "1:24-cv-00002".to_string()]
+            );
+        }
+
+        #[test]
+        #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+        fn templates_without_year_or_court_are_refused() {
+            let url = std::env::var("LEXODUS_TEST_DB_URL").unwrap();
+            let con = Connection::open(&url).unwrap();
+            for template in ["{court}-{seq:04}", "{yy}-{seq:04}", "{office}:{yy}-{type}"] {
+                let inserted = con.execute(
+                    "INSERT INTO courts (name, court_type, district, circuit, case_number_template)
+                     VALUES ('Numbering Test Court', 'DISTRICT', 'Test', 'Test', $1)",
+                    &[ParameterValue::Str(template.to_string())],
+                );
+                assert!(inserted.is_err(), "{template}");
+            }
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// The security levels a case can be filed at or moved to, as `(value, label)`
+pub const SECURITY_LEVELS: [(&str, &str); 3] = [("PUBLIC", "Public"), ("RESTRICTED", "Restricted"), ("SEALED", "Sealed")];
+
+/// How a case's seal and security level read on the page, e.g. "Restricted, sealed"
+pub fn security_label(sealed: bool, security_level: &str) -> String {
+    let level = SECURITY_LEVELS
+        .iter()
+        .find(|(value, _)| *value == security_level)
+        .map_or(security_level, |(_, label)| label);
+    if sealed && security_level != "SEALED" {
+        format!("{level}, sealed")
+    } else {
+        level.to_string()
+    }
+}
+
+/// An order that sealed, unsealed or reclassified a case, newest first on the case page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealingOrder {
+    pub id: i64,
+    pub old_sealed: bool,
+    pub old_security_level: String,
+    pub sealed: bool,
+    pub security_level: String,
+    pub order_reference: String,
+    pub order_date: String,
+    pub reason: String,
+    pub entered_by: Option<String>,
+    pub entered_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use chrono::{NaiveDate, Utc};
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+/// Whether a case is sealed and the classification it is held at
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaseSecurity {
+    pub sealed: bool,
+    pub security_level: String,
+}
+
+/// A validated change to a case's seal or security level under a court order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SealingChange {
+    pub from: CaseSecurity,
+    pub to: CaseSecurity,
+    pub order_reference: String,
+    pub order_date: NaiveDate,
+    pub reason: String,
+}
+
+impl SealingChange {
+    /// Check a change against the order that makes it. The order may not be
+    /// dated after today or before the case was filed.
+    pub fn new(
+        from: CaseSecurity,
+        to: CaseSecurity,
+        order_reference: &str,
+        order_date: NaiveDate,
+        reason: &str,
+        filed_date: NaiveDate,
+    ) -> Result<Self, LexodusAppError> {
+        if !SECURITY_LEVELS.iter().any(|(value, _)| *value == to.security_level) {
+            return Err(LexodusAppError::BadRequest(format!("Unknown security level {}.", to.security_level)));
+        }
+        if from == to {
+            return Err(LexodusAppError::BadRequest("The case is already sealed and classified that way.".to_string()));
+        }
+        if !to.sealed && to.security_level == "SEALED" {
+            return Err(LexodusAppError::BadRequest(
+                "An unsealed case cannot stay at the sealed level; choose public or restricted.".to_string(),
+            ));
+        }
+        let order_reference = order_reference.trim();
+        if order_reference.is_empty() {
+            return Err(LexodusAppError::BadRequest("Cite the order that seals or unseals the case.".to_string()));
+        }
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(LexodusAppError::BadRequest("Give the reason stated in the order.".to_string()));
+        }
+        if order_date > Utc::now().date_naive() {
+            return Err(LexodusAppError::BadRequest("The order cannot be dated in the future.".to_string()));
+        }
+        if order_date < filed_date {
+            return Err(LexodusAppError::BadRequest("The order cannot predate the case.".to_string()));
+        }
+        Ok(SealingChange {
+            from,
+            to,
+            order_reference: order_reference.to_string(),
+            order_date,
+            reason: reason.to_string(),
+        })
+    }
+
+    /// Apply the change and record its order in one statement. Sealing sets
+    /// the sealed date and sealer, which stay put while a sealed case is only
+    /// reclassified; unsealing clears them. Returns false when the case was no
+    /// longer as `from` describes, in which case nothing was written.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn apply(&self, case_id: i64, filed_date: &str, entered_by: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "WITH changed AS (
+                 UPDATE cases
+                 SET sealed = $5::boolean, security_level = $6,
+                     sealed_date = CASE WHEN NOT $5::boolean THEN NULL WHEN $3::boolean THEN sealed_date ELSE $8::date END,
+                     sealed_by = CASE WHEN NOT $5::boolean THEN NULL WHEN $3::boolean THEN sealed_by ELSE $10 END,
+                     updated_by = $10, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1 AND filed_date = $2::date
+                   AND COALESCE(sealed, false) = $3::boolean AND security_level = $4
+                 RETURNING id, filed_date
+             )
+             INSERT INTO case_sealing_orders (
+                 case_id, case_filed_date, old_sealed, old_security_level, sealed, security_level,
+                 order_reference, order_date, reason, entered_by
+             )
+             SELECT id, filed_date, $3::boolean, $4, $5::boolean, $6, $7, $8::date, $9, $10 FROM changed
+             RETURNING id",
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Boolean(self.from.sealed),
+                ParameterValue::Str(self.from.security_level.clone()),
+                ParameterValue::Boolean(self.to.sealed),
+                ParameterValue::Str(self.to.security_level.clone()),
+                ParameterValue::Str(self.order_reference.clone()),
+                ParameterValue::Str(self.order_date.to_string()),
+                ParameterValue::Str(self.reason.clone()),
+                ParameterValue::Int64(entered_by),
+            ],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+
+impl SealingOrder {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT o.id, o.old_sealed, o.old_security_level, o.sealed, o.security_level,
+                    o.order_reference, o.order_date::text, o.reason, u.full_name,
+                    EXTRACT(EPOCH FROM o.entered_at)::bigint
+             FROM case_sealing_orders o
+             LEFT JOIN users u ON u.id = o.entered_by
+             WHERE o.case_id = $1 AND o.case_filed_date = $2::date
+             ORDER BY o.entered_at DESC, o.id DESC",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| SealingOrder {
+                id: int(&row[0]).unwrap_or(-1),
+                old_sealed: matches!(&row[1], DbValue::Boolean(true)),
+                old_security_level: text(&row[2]).unwrap_or_default(),
+                sealed: matches!(&row[3], DbValue::Boolean(true)),
+                security_level: text(&row[4]).unwrap_or_default(),
+                order_reference: text(&row[5]).unwrap_or_default(),
+                order_date: text(&row[6]).unwrap_or_default(),
+                reason: text(&row[7]).unwrap_or_default(),
+                entered_by: text(&row[8]),
+                entered_at_pretty: pretty(int(&row[9]).unwrap_or_default()),
+            })
+            .collect())
+    }
+}
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_names_the_level_and_any_seal() {
+        assert_eq!(security_label(false, "PUBLIC"), "Public");
+        assert_eq!(security_label(true, "RESTRICTED"), "Restricted, sealed");
+        assert_eq!(security_label(true, "SEALED"), "Sealed");
+    }
+
+    #[test]
+    fn unknown_level_is_shown_as_stored() {
+        assert_eq!(security_label(false, "CLASSIFIED"), "CLASSIFIED");
+        assert_eq!(security_label(true, "CLASSIFIED"), "CLASSIFIED, sealed");
+    }
+
+    #[cfg(feature = "ssr")]
+    mod change {
+        use super::super::*;
+        use chrono::Days;
+
+        fn security(sealed: bool, security_level: &str) -> CaseSecurity {
+            CaseSecurity {
+                sealed,
+                security_level: security_level.to_string(),
+            }
+        }
+
+        fn day(value: &str) -> NaiveDate {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+        }
+
+        fn change(to: CaseSecurity, order_date: NaiveDate) -> Result<SealingChange, LexodusAppError> {
+            SealingChange::new(security(false, "PUBLIC"), to, "ECF No. 12", order_date, "Protects a minor", day("2024-01-05"))
+        }
+
+        fn message(result: Result<SealingChange, LexodusAppError>) -> String {
+            match result {
+                Err(LexodusAppError::BadRequest(message)) => message,
+                other => panic!("expected a bad request, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn sealing_under_an_order_is_accepted() {
+            let sealed = SealingChange::new(
+                security(false, "PUBLIC"),
+                security(true, "SEALED"),
+                " ECF No. 12 ",
+                day("2024-03-01"),
+                " Protects a minor ",
+                day("2024-01-05"),
+            )
+            .unwrap();
+            assert_eq!(sealed.to, security(true, "SEALED"));
+            assert_eq!(sealed.order_reference, "ECF No. 12");
+            assert_eq!(sealed.reason, "Protects a minor");
+        }
+
+        #[test]
+        fn unknown_level_is_refused() {
+            assert_eq!(message(change(security(true, "CLASSIFIED"), day("2024-03-01"))), "Unknown security level CLASSIFIED.");
+        }
+
+        #[test]
+        fn change_must_change_something() {
+            assert_eq!(
+                message(change(security(false, "PUBLIC"), day("2024-03-01"))),
+                "The case is already sealed and classified that way."
+            );
+        }
+
+        #[test]
+        fn unsealed_case_cannot_stay_at_the_sealed_level() {
+            assert!(change(security(false, "SEALED"), day("2024-03-01")).is_err());
+            assert!(change(security(false, "RESTRICTED"), day("2024-03-01")).is_ok());
+        }
+
+        #[test]
+        fn order_and_reason_are_required() {
+            let to = || security(true, "RESTRICTED");
+            let filed = day("2024-01-05");
+            let no_order = SealingChange::new(security(false, "PUBLIC"), to(), " ", day("2024-03-01"), "Reason", filed);
+            assert_eq!(message(no_order), "Cite the order that seals or unseals the case.");
+            let no_reason = SealingChange::new(security(false, "PUBLIC"), to(), "ECF No. 12", day("2024-03-01"), "", filed);
+            assert_eq!(message(no_reason), "Give the reason stated in the order.");
+        }
+
+        #[test]
+        fn order_is_dated_between_filing_and_today() {
+            let tomorrow = Utc::now().date_naive().checked_add_days(Days::new(1)).unwrap();
+            assert_eq!(message(change(security(true, "SEALED"), tomorrow)), "The order cannot be dated in the future.");
+            assert_eq!(message(change(security(true, "SEALED"), day("2024-01-04"))), "The order cannot predate the case.");
+            assert!(change(security(true, "SEALED"), day("2024-01-05")).is_ok());
+            assert!(change(security(true, "SEALED"), Utc::now().date_naive()).is_ok());
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::case::case_visible_to;
+use crate::domain::models::case_number::CaseNumber;
+use crate::domain::models::db::{int, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// A case found by a search, best match first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseSearchHit {
+    pub id: i64,
+    pub case_number: String,
+    pub title: String,
+    pub status: String,
+    pub filed_date: String,
+    pub court_name: String,
+    /// The party or attorney whose name matched, for name searches
+    pub matched_name: Option<String>,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, ParameterValue};
+
+/// How text criteria are compared
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// Every word must begin a word in the record ("smi" finds "Smith")
+    #[default]
+    Prefix,
+    /// Close spellings are accepted, ranked by trigram similarity
+    Fuzzy,
+    /// Every word must appear as a whole word; case numbers must match exactly
+    Exact,
+}
+
+impl MatchMode {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "prefix" => Some(Self::Prefix),
+            "fuzzy" => Some(Self::Fuzzy),
+            "exact" => Some(Self::Exact),
+            _ => None,
+        }
+    }
+}
+
+/// Whose names a name search looks at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NameScope {
+    #[default]
+    Party,
+    Attorney,
+}
+
+/// Validated search criteria; empty fields are not filtered on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CaseSearch {
+    /// The case number as typed, for partial matches
+    pub case_number: Option<String>,
+    /// The same number when it is a complete one, matched by its parts
+    pub parsed_number: Option<CaseNumber>,
+    pub keywords: Option<String>,
+    /// `Some(true)` for cases not closed, `Some(false)` for closed ones
+    pub open: Option<bool>,
+    pub filed_from: Option<String>,
+    pub filed_to: Option<String>,
+    pub nature_of_suit: Option<String>,
+    /// Cause code prefix, e.g. `28:1332`
+    pub cause_of_action: Option<String>,
+    pub name: Option<String>,
+    pub name_scope: NameScope,
+    pub mode: MatchMode,
+}
+
+/// Searches never return more than this many cases
+pub const SEARCH_LIMIT: i64 = 100;
+
+/// Bind a parameter and return its `$n` placeholder
+fn bind(params: &mut Vec<ParameterValue>, value: ParameterValue) -> String {
+    params.push(value);
+    format!("${}", params.len())
+}
+
+/// A `to_tsquery` expression requiring every word as a prefix. Words are
+/// reduced to letters and digits so user input can never be tsquery syntax.
+fn prefix_query(text: &str) -> Option<String> {
+    let words: Vec<String> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| format!("{}:*", w.to_lowercase()))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" & "))
+}
+
+/// Escape `%`, `_` and `\` for use in a LIKE pattern
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Match `text` against a tsvector column (or trigram text for fuzzy mode).
+/// Returns the predicate and a relevance score expression.
+fn text_match(
+    mode: MatchMode,
+    config: &str,
+    vector: &str,
+    plain: &str,
+    text: &str,
+    params: &mut Vec<ParameterValue>,
+) -> Option<(String, String)> {
+    match mode {
+        MatchMode::Exact => {
+            let p = bind(params, ParameterValue::Str(text.to_string()));
+            let query = format!("plainto_tsquery('{config}', {p})");
+            Some((format!("{vector} @@ {query}"), format!("ts_rank({vector}, {query})")))
+        }
+        MatchMode::Prefix => {
+            let p = bind(params, ParameterValue::Str(prefix_query(text)?));
+            let query = format!("to_tsquery('{config}', {p})");
+            Some((format!("{vector} @@ {query}"), format!("ts_rank({vector}, {query})")))
+        }
+        MatchMode::Fuzzy => {
+            let p = bind(params, ParameterValue::Str(text.to_string()));
+            Some((format!("{p} <% {plain}"), format!("word_similarity({p}, {plain})")))
+        }
+    }
+}
+
+impl CaseSearch {
+    /// Run the search as `viewer_id`. Cases the viewer may not see are
+    /// filtered inside the query, so they are never matched, ranked or counted.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn run(&self, viewer_id: i64, con: &Arc<Connection>) -> Result<Vec<CaseSearchHit>, LexodusAppError> {
+        let mut params = vec![ParameterValue::Int64(viewer_id)];
+        let mut filters = vec![case_visible_to(1)];
+        let mut scores = vec!["0".to_string()];
+
+        if let Some(number) = &self.case_number {
+            let parsed = self.parsed_number.as_ref().map(|parsed| {
+                let (predicate, values) = parsed.predicate(params.len() + 1);
+                params.extend(values);
+                predicate
+            });
+            match (self.mode, parsed) {
+                (MatchMode::Exact, Some(parsed)) => filters.push(parsed),
+                (MatchMode::Exact, None) => {
+                    let p = bind(&mut params, ParameterValue::Str(number.clone()));
+                    filters.push(format!("lower(c.case_number) = lower({p})"));
+                }
+                (_, parsed) => {
+                    let p = bind(&mut params, ParameterValue::Str(like_escape(number)));
+                    let partial = format!("c.case_number ILIKE {p} || '%'");
+                    let exact = match parsed {
+                        Some(parsed) => {
+                            filters.push(format!("({partial} OR {parsed})"));
+                            parsed
+                        }
+                        None => {
+                            filters.push(partial);
+                            let exact = bind(&mut params, ParameterValue::Str(number.clone()));
+                            format!("lower(c.case_number) = lower({exact})")
+                        }
+                    };
+                    scores.push(format!("CASE WHEN {exact} THEN 1 ELSE 0 END"));
+                }
+            }
+        }
+        if let Some(keywords) = &self.keywords {
+            let Some((filter, score)) = text_match(self.mode, "english", "c.search_vector", "c.title", keywords, &mut params) else {
+                return Ok(Vec::new());
+            };
+            filters.push(filter);
+            scores.push(score);
+        }
+        if let Some(open) = self.open {
+            filters.push(if open { "c.status <> 'CLOSED'" } else { "c.status = 'CLOSED'" }.to_string());
+        }
+        if let Some(from) = &self.filed_from {
+            let p = bind(&mut params, ParameterValue::Str(from.clone()));
+            filters.push(format!("c.filed_date >= {p}::date"));
+        }
+        if let Some(to) = &self.filed_to {
+            let p = bind(&mut params, ParameterValue::Str(to.clone()));
+            filters.push(format!("c.filed_date <= {p}::date"));
+        }
+        if let Some(nature) = &self.nature_of_suit {
+            let p = bind(&mut params, ParameterValue::Str(like_escape(nature)));
+            filters.push(format!("c.nature_of_suit ILIKE {p} || '%'"));
+        }
+        if let Some(cause) = &self.cause_of_action {
+            let p = bind(&mut params, ParameterValue::Str(like_escape(cause)));
+            filters.push(format!("c.cause_of_action ILIKE {p} || '%'"));
+        }
+
+        // The best matching party or attorney of each case, if searching by name
+        let mut name_join = String::new();
+        if let Some(name) = &self.name {
+            let (from, vector, plain) = match self.name_scope {
+                NameScope::Party => ("case_parties p", "p.name_search", "p.name"),
+                NameScope::Attorney => (
+                    "case_parties p
+                     JOIN party_attorney_representations par ON par.case_party_id = p.id
+                     JOIN users u ON u.id = par.attorney_id",
+                    "to_tsvector('simple', u.full_name)",
+                    "u.full_name",
+                ),
+            };
+            let Some((filter, score)) = text_match(self.mode, "simple", vector, plain, name, &mut params) else {
+                return Ok(Vec::new());
+            };
+            name_join = format!(
+                "JOIN LATERAL (
+                    SELECT {plain} AS name, {score} AS score
+                    FROM {from}
+                    WHERE p.case_id = c.id AND p.case_filed_date = c.filed_date AND {filter}
+                    ORDER BY 2 DESC
+                    LIMIT 1
+                 ) m ON true"
+            );
+            scores.push("m.score".to_string());
+        }
+
+        let matched_name = if name_join.is_empty() { "NULL::text" } else { "m.name" };
+        let limit = bind(&mut params, ParameterValue::Int64(SEARCH_LIMIT));
+        let sql = format!(
+            "SELECT c.id, c.case_number, c.title, c.status, c.filed_date::text, co.name, {matched_name}
+             FROM cases c
+             JOIN courts co ON co.id = c.court_id
+             {name_join}
+             WHERE {}
+             ORDER BY {} DESC, c.filed_date DESC, c.id DESC
+             LIMIT {limit}",
+            filters.join(" AND "),
+            scores.join(" + "),
+        );
+        let rowset = con.query(&sql, &params)?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| CaseSearchHit {
+                id: int(&row[0]).unwrap_or(-1),
+                case_number: text(&row[1]).unwrap_or_default(),
+                title: text(&row[2]).unwrap_or_default(),
+                status: text(&row[3]).unwrap_or_default(),
+                filed_date: text(&row[4]).unwrap_or_default(),
+                court_name: text(&row[5]).unwrap_or_default(),
+                matched_name: text(&row[6]),
+            })
+            .collect())
+    }
+}
+}
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefix_query_requires_every_word_as_a_prefix() {
+        assert_eq!(prefix_query("Smith Jones").as_deref(), Some("smith:* & jones:*"));
+        assert_eq!(prefix_query("o'brien & co").as_deref(), Some("o:* & brien:* & co:*"));
+    }
+
+    #[test]
+    fn prefix_query_never_passes_tsquery_syntax_through() {
+        assert_eq!(prefix_query("!(a | b):*").as_deref(), Some("a:* & b:*"));
+        assert_eq!(prefix_query(" & | ! "), None);
+        assert_eq!(prefix_query(""), None);
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(like_escape("28:1332"), "28:1332");
+        assert_eq!(like_escape("50%_off\\"), "50\\%\\_off\\\\");
+    }
+
+    #[test]
+    fn match_modes_parse_from_the_form_values() {
+        assert_eq!(MatchMode::parse("prefix"), Some(MatchMode::Prefix));
+        assert_eq!(MatchMode::parse("fuzzy"), Some(MatchMode::Fuzzy));
+        assert_eq!(MatchMode::parse("exact"), Some(MatchMode::Exact));
+        assert_eq!(MatchMode::parse("Exact"), None);
+    }
+
+    #[test]
+    fn placeholders_follow_the_bound_parameters() {
+        let mut params = vec![ParameterValue::Int64(1)];
+        assert_eq!(bind(&mut params, ParameterValue::Str("28:".to_string())), "$2");
+        assert_eq!(bind(&mut params, ParameterValue::Str("110".to_string())), "$3");
+    }
+}
@@ -0,0 +1,399 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// Where a case is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CaseStatus {
+    Open,
+    Pending,
+    Stayed,
+    Closed,
+    Reopened,
+}
+
+/// How a case was disposed of when it closed
+pub const DISPOSITION_METHODS: [&str; 7] =
+    ["JUDGMENT", "SETTLEMENT", "VOLUNTARY_DISMISSAL", "INVOLUNTARY_DISMISSAL", "TRANSFER", "CONSOLIDATION", "OTHER"];
+
+impl CaseStatus {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "OPEN" => Some(Self::Open),
+            "PENDING" => Some(Self::Pending),
+            "STAYED" => Some(Self::Stayed),
+            "CLOSED" => Some(Self::Closed),
+            "REOPENED" => Some(Self::Reopened),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Open => "OPEN",
+            Self::Pending => "PENDING",
+            Self::Stayed => "STAYED",
+            Self::Closed => "CLOSED",
+            Self::Reopened => "REOPENED",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Open => "Open",
+            Self::Pending => "Pending",
+            Self::Stayed => "Stayed",
+            Self::Closed => "Closed",
+            Self::Reopened => "Reopened",
+        }
+    }
+
+    /// Statuses a case may be filed with; it cannot start out stayed or closed
+    pub fn initial() -> &'static [CaseStatus] {
+        &[Self::Open, Self::Pending]
+    }
+
+    /// Can a case in this status move to another court. Only a case still
+    /// heading toward closure moves; a closed case must be reopened first.
+    pub fn can_transfer(self) -> bool {
+        self.next().contains(&Self::Closed)
+    }
+
+    /// The statuses this one may move to. A closed case can only be reopened,
+    /// and a reopened case proceeds like an open one.
+    pub fn next(self) -> &'static [CaseStatus] {
+        match self {
+            Self::Open | Self::Reopened => &[Self::Pending, Self::Stayed, Self::Closed],
+            Self::Pending => &[Self::Open, Self::Stayed, Self::Closed],
+            Self::Stayed => &[Self::Open, Self::Pending, Self::Closed],
+            Self::Closed => &[Self::Reopened],
+        }
+    }
+}
+
+impl fmt::Display for CaseStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// One recorded status change, newest first on the case page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusChange {
+    pub id: i64,
+    pub old_status: String,
+    pub new_status: String,
+    pub changed_by: Option<String>,
+    pub change_date: i64,
+    pub change_date_pretty: String,
+    /// When the change took effect, which may be earlier than when it was recorded
+    pub effective_date: Option<String>,
+    pub reason: Option<String>,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use chrono::{NaiveDate, Utc};
+use spin_sdk::pg::{Connection, ParameterValue};
+
+/// A validated request to move a case from one status to another
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusTransition {
+    pub from: CaseStatus,
+    pub to: CaseStatus,
+    pub reason: String,
+    pub effective_date: NaiveDate,
+    /// Required when closing, ignored otherwise
+    pub disposition_method: Option<String>,
+}
+
+impl StatusTransition {
+    /// Check a transition against the lifecycle. `filed_date` and
+    /// `closed_date` bound how far back it may take effect.
+    pub fn new(
+        from: CaseStatus,
+        to: CaseStatus,
+        reason: &str,
+        effective_date: NaiveDate,
+        disposition_method: Option<String>,
+        filed_date: NaiveDate,
+        closed_date: Option<NaiveDate>,
+    ) -> Result<Self, LexodusAppError> {
+        if !from.next().contains(&to) {
+            return Err(LexodusAppError::BadRequest(format!(
+                "A {} case cannot become {}.",
+                from.label().to_lowercase(),
+                to.label().to_lowercase()
+            )));
+        }
+        let reason = reason.trim();
+        if reason.is_empty() {
+            return Err(LexodusAppError::BadRequest("Give a reason for the status change.".to_string()));
+        }
+        if effective_date > Utc::now().date_naive() {
+            return Err(LexodusAppError::BadRequest("A status change cannot take effect in the future.".to_string()));
+        }
+        if effective_date < filed_date {
+            return Err(LexodusAppError::BadRequest("A status change cannot take effect before the case was filed.".to_string()));
+        }
+        if to == CaseStatus::Reopened && closed_date.is_some_and(|closed| effective_date < closed) {
+            return Err(LexodusAppError::BadRequest("A case cannot be reopened before it was closed.".to_string()));
+        }
+        let disposition_method = match to {
+            CaseStatus::Closed => match disposition_method.as_deref().map(str::trim) {
+                Some(method) if DISPOSITION_METHODS.contains(&method) => Some(method.to_string()),
+                Some(method) if !method.is_empty() => {
+                    return Err(LexodusAppError::BadRequest(format!("Unknown disposition method {method}.")))
+                }
+                _ => return Err(LexodusAppError::BadRequest("Choose how the case was disposed of.".to_string())),
+            },
+            _ => None,
+        };
+        Ok(StatusTransition {
+            from,
+            to,
+            reason: reason.to_string(),
+            effective_date,
+            disposition_method,
+        })
+    }
+
+    /// Apply the transition and record it in the status history in one
+    /// statement, marked so the status trigger does not log it again. Closing sets the closed and disposition dates; reopening
+    /// sets the reopened date and clears them. Returns false when the case was
+    /// no longer in `from`, in which case nothing was written.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn apply(&self, case_id: i64, filed_date: &str, changed_by: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let dates = match self.to {
+            CaseStatus::Closed => {
+                "closed_date = $6::date, statistical_close_date = $6::date,
+                 disposition_method = $8, disposition_date = $6::date,"
+            }
+            CaseStatus::Reopened => {
+                "reopened_date = $6::date, closed_date = NULL, statistical_close_date = NULL,
+                 disposition_method = NULL, disposition_date = NULL,"
+            }
+            _ => "",
+        };
+        let sql = format!(
+            "WITH changed AS (
+                 UPDATE cases
+                 SET status = $4, {dates}
+                     updated_by = $5, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1 AND filed_date = $2::date AND status = $3
+                 RETURNING id, filed_date
+             )
+             INSERT INTO case_status_history (
+                 case_id, case_filed_date, old_status, new_status, changed_by, effective_date, notes
+             )
+             SELECT id, filed_date, $3, $4, $5, $6::date, $7 FROM changed
+             WHERE mark_status_change_recorded(id, filed_date, $4)
+             RETURNING id"
+        );
+        let mut params = vec![
+            ParameterValue::Int64(case_id),
+            ParameterValue::Str(filed_date.to_string()),
+            ParameterValue::Str(self.from.as_str().to_string()),
+            ParameterValue::Str(self.to.as_str().to_string()),
+            ParameterValue::Int64(changed_by),
+            ParameterValue::Str(self.effective_date.to_string()),
+            ParameterValue::Str(self.reason.clone()),
+        ];
+        if let Some(method) = &self.disposition_method {
+            params.push(ParameterValue::Str(method.clone()));
+        }
+        let rowset = con.query(&sql, &params)?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+
+impl StatusChange {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT h.id, h.old_status, h.new_status, u.full_name,
+                    EXTRACT(EPOCH FROM h.change_date)::bigint, h.effective_date::text, h.notes
+             FROM case_status_history h
+             LEFT JOIN users u ON u.id = h.changed_by
+             WHERE h.case_id = $1 AND h.case_filed_date = $2::date
+             ORDER BY h.change_date DESC, h.id DESC",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let change_date = int(&row[4]).unwrap_or_default();
+                StatusChange {
+                    id: int(&row[0]).unwrap_or(-1),
+                    old_status: text(&row[1]).unwrap_or_default(),
+                    new_status: text(&row[2]).unwrap_or_default(),
+                    changed_by: text(&row[3]),
+                    change_date,
+                    change_date_pretty: pretty(change_date),
+                    effective_date: text(&row[5]),
+                    reason: text(&row[6]),
+                }
+            })
+            .collect())
+    }
+}
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [CaseStatus; 5] = [CaseStatus::Open, CaseStatus::Pending, CaseStatus::Stayed, CaseStatus::Closed, CaseStatus::Reopened];
+
+    #[test]
+    fn statuses_round_trip_through_their_names() {
+        for status in ALL {
+            assert_eq!(CaseStatus::parse(status.as_str()), Some(status));
+        }
+        assert_eq!(CaseStatus::parse("open"), None);
+        assert_eq!(CaseStatus::parse("DISMISSED"), None);
+    }
+
+    #[test]
+    fn closed_case_can_only_be_reopened() {
+        assert_eq!(CaseStatus::Closed.next(), &[CaseStatus::Reopened]);
+    }
+
+    #[test]
+    fn reopened_case_proceeds_like_an_open_one() {
+        assert_eq!(CaseStatus::Reopened.next(), CaseStatus::Open.next());
+    }
+
+    #[test]
+    fn no_status_moves_to_itself_and_only_closed_cases_reopen() {
+        for status in ALL {
+            assert!(!status.next().contains(&status), "{status} may move to itself");
+            assert_eq!(status.next().contains(&CaseStatus::Reopened), status == CaseStatus::Closed, "{status}");
+        }
+    }
+
+    #[test]
+    fn only_active_cases_can_transfer() {
+        for status in [CaseStatus::Open, CaseStatus::Pending, CaseStatus::Stayed, CaseStatus::Reopened] {
+            assert!(status.can_transfer(), "{status} case refused");
+        }
+        assert!(!CaseStatus::Closed.can_transfer());
+    }
+
+    #[test]
+    fn cases_start_open_or_pending() {
+        assert_eq!(CaseStatus::initial(), &[CaseStatus::Open, CaseStatus::Pending]);
+    }
+
+    #[cfg(feature = "ssr")]
+    mod transition {
+        use super::super::*;
+        use chrono::Days;
+
+        fn day(value: &str) -> NaiveDate {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+        }
+
+        fn filed() -> NaiveDate {
+            day("2024-01-05")
+        }
+
+        fn transition(from: CaseStatus, to: CaseStatus, effective: &str, disposition: Option<&str>) -> Result<StatusTransition, LexodusAppError> {
+            StatusTransition::new(from, to, "Order of the court", day(effective), disposition.map(str::to_string), filed(), None)
+        }
+
+        fn message(result: Result<StatusTransition, LexodusAppError>) -> String {
+            match result {
+                Err(LexodusAppError::BadRequest(message)) => message,
+                other => panic!("expected a bad request, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn allowed_transition_is_accepted() {
+            let stay = transition(CaseStatus::Open, CaseStatus::Stayed, "2024-03-01", None).unwrap();
+            assert_eq!(stay.reason, "Order of the court");
+            assert_eq!(stay.effective_date, day("2024-03-01"));
+            assert_eq!(stay.disposition_method, None);
+        }
+
+        #[test]
+        fn transition_outside_the_lifecycle_is_refused() {
+            assert_eq!(message(transition(CaseStatus::Closed, CaseStatus::Open, "2024-03-01", None)), "A closed case cannot become open.");
+            assert!(transition(CaseStatus::Open, CaseStatus::Reopened, "2024-03-01", None).is_err());
+            assert!(transition(CaseStatus::Stayed, CaseStatus::Stayed, "2024-03-01", None).is_err());
+        }
+
+        #[test]
+        fn reason_is_required() {
+            let blank = StatusTransition::new(CaseStatus::Open, CaseStatus::Stayed, "  ", day("2024-03-01"), None, filed(), None);
+            assert_eq!(message(blank), "Give a reason for the status change.");
+        }
+
+        #[test]
+        fn reason_is_trimmed() {
+            let stay = StatusTransition::new(CaseStatus::Open, CaseStatus::Stayed, " Bankruptcy stay ", day("2024-03-01"), None, filed(), None);
+            assert_eq!(stay.unwrap().reason, "Bankruptcy stay");
+        }
+
+        #[test]
+        fn change_cannot_take_effect_in_the_future() {
+            let tomorrow = Utc::now().date_naive().checked_add_days(Days::new(1)).unwrap();
+            let result = StatusTransition::new(CaseStatus::Open, CaseStatus::Stayed, "Order", tomorrow, None, filed(), None);
+            assert_eq!(message(result), "A status change cannot take effect in the future.");
+            let today = Utc::now().date_naive();
+            assert!(StatusTransition::new(CaseStatus::Open, CaseStatus::Stayed, "Order", today, None, filed(), None).is_ok());
+        }
+
+        #[test]
+        fn change_cannot_take_effect_before_filing() {
+            assert_eq!(
+                message(transition(CaseStatus::Open, CaseStatus::Stayed, "2024-01-04", None)),
+                "A status change cannot take effect before the case was filed."
+            );
+            assert!(transition(CaseStatus::Open, CaseStatus::Stayed, "2024-01-05", None).is_ok());
+        }
+
+        #[test]
+        fn case_cannot_be_reopened_before_it_closed() {
+            let closed = Some(day("2024-06-01"));
+            let early = StatusTransition::new(CaseStatus::Closed, CaseStatus::Reopened, "Remand", day("2024-05-31"), None, filed(), closed);
+            assert_eq!(message(early), "A case cannot be reopened before it was closed.");
+            let reopen = StatusTransition::new(CaseStatus::Closed, CaseStatus::Reopened, "Remand", day("2024-06-01"), None, filed(), closed);
+            assert!(reopen.is_ok());
+        }
+
+        #[test]
+        fn closing_requires_a_known_disposition() {
+            assert_eq!(
+                message(transition(CaseStatus::Open, CaseStatus::Closed, "2024-03-01", None)),
+                "Choose how the case was disposed of."
+            );
+            assert_eq!(
+                message(transition(CaseStatus::Open, CaseStatus::Closed, "2024-03-01", Some(" "))),
+                "Choose how the case was disposed of."
+            );
+            assert_eq!(
+                message(transition(CaseStatus::Open, CaseStatus::Closed, "2024-03-01", Some("MISTRIAL"))),
+                "Unknown disposition method MISTRIAL."
+            );
+            let closed = transition(CaseStatus::Open, CaseStatus::Closed, "2024-03-01", Some(" SETTLEMENT ")).unwrap();
+            assert_eq!(closed.disposition_method.as_deref(), Some("SETTLEMENT"));
+        }
+
+        #[test]
+        fn disposition_is_ignored_unless_closing() {
+            let stay = transition(CaseStatus::Open, CaseStatus::Stayed, "2024-03-01", Some("SETTLEMENT")).unwrap();
+            assert_eq!(stay.disposition_method, None);
+        }
+    }
+}
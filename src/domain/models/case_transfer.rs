@@ -0,0 +1,457 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// How a case leaves its court
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TransferKind {
+    /// To another court of the same type
+    Transfer,
+    /// Back to the court it was transferred from, or down to a court below
+    Remand,
+}
+
+impl TransferKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "TRANSFER" => Some(Self::Transfer),
+            "REMAND" => Some(Self::Remand),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Transfer => "TRANSFER",
+            Self::Remand => "REMAND",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Transfer => "Transfer",
+            Self::Remand => "Remand",
+        }
+    }
+
+    /// "transferred" or "remanded", for docket entries and notices
+    pub fn past(self) -> &'static str {
+        match self {
+            Self::Transfer => "transferred",
+            Self::Remand => "remanded",
+        }
+    }
+}
+
+/// A court a case may be sent to, and whether going there is a transfer or a remand
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransferDestination {
+    pub court_id: i64,
+    pub court_name: String,
+    pub kind: TransferKind,
+}
+
+/// A recorded move of a case between courts, oldest first on the case page
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseTransfer {
+    pub id: i64,
+    pub kind: String,
+    pub from_court: String,
+    pub to_court: String,
+    pub old_case_number: String,
+    pub new_case_number: String,
+    pub order_reference: String,
+    pub order_date: String,
+    pub effective_date: String,
+    pub reason: Option<String>,
+    pub transferred_by: Option<String>,
+    pub transferred_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use crate::domain::models::case_status::CaseStatus;
+use crate::domain::models::judge_assignment::Draw;
+use chrono::{NaiveDate, Utc};
+use spin_sdk::pg::{Connection, ParameterValue};
+
+impl TransferDestination {
+    /// Where the case in `court_id` may go: other active courts of the same
+    /// type by transfer; by remand, active courts directly below it and the
+    /// court it was last transferred in from
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn for_case(case_id: i64, filed_date: &str, court_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "WITH here AS (SELECT id, court_type FROM courts WHERE id = $3),
+                  came_from AS (
+                      SELECT t.from_court_id FROM case_transfers t
+                      WHERE t.case_id = $1 AND t.case_filed_date = $2::date
+                        AND t.to_court_id = $3 AND t.kind = 'TRANSFER'
+                      ORDER BY t.transferred_at DESC, t.id DESC
+                      LIMIT 1
+                  )
+             SELECT co.id, co.name,
+                    CASE WHEN co.parent_court_id = here.id OR co.id IN (SELECT from_court_id FROM came_from)
+                         THEN 'REMAND' ELSE 'TRANSFER' END
+             FROM courts co, here
+             WHERE COALESCE(co.is_active, true) AND co.id <> here.id
+               AND (co.court_type = here.court_type
+                    OR co.parent_court_id = here.id
+                    OR co.id IN (SELECT from_court_id FROM came_from))
+             ORDER BY 3, co.name",
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(court_id),
+            ],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .filter_map(|row| {
+                Some(TransferDestination {
+                    court_id: int(&row[0])?,
+                    court_name: text(&row[1]).unwrap_or_default(),
+                    kind: TransferKind::parse(&text(&row[2])?)?,
+                })
+            })
+            .collect())
+    }
+}
+
+/// A move about to be made, from the case's court, number and status as last read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NewTransfer {
+    pub case_id: i64,
+    pub filed_date: String,
+    pub from_court_id: i64,
+    pub case_number: String,
+    pub status: CaseStatus,
+    pub destination: TransferDestination,
+    pub order_reference: String,
+    pub order_date: NaiveDate,
+    pub effective_date: NaiveDate,
+    pub reason: Option<String>,
+}
+
+impl NewTransfer {
+    /// The case's status must allow it to move, the order must be cited and
+    /// dated, and the move take effect on or after the order, no later than
+    /// today and not before the case was filed
+    pub fn validate(&self, case_filed: NaiveDate) -> Result<(), LexodusAppError> {
+        if !self.status.can_transfer() {
+            return Err(LexodusAppError::BadRequest(format!(
+                "A {} case cannot be {}; reopen it first.",
+                self.status.label().to_lowercase(),
+                self.destination.kind.past()
+            )));
+        }
+        if self.order_reference.trim().is_empty() {
+            return Err(LexodusAppError::BadRequest(format!(
+                "Cite the order directing the {}.",
+                self.destination.kind.label().to_lowercase()
+            )));
+        }
+        if self.order_date < case_filed {
+            return Err(LexodusAppError::BadRequest("The order cannot predate the case.".to_string()));
+        }
+        if self.effective_date < self.order_date {
+            return Err(LexodusAppError::BadRequest("The move cannot take effect before the order.".to_string()));
+        }
+        if self.effective_date > Utc::now().date_naive() {
+            return Err(LexodusAppError::BadRequest("Record the move once it has taken effect.".to_string()));
+        }
+        Ok(())
+    }
+
+    /// Move the case in one statement: draw a number from the receiving
+    /// court's counter for the year the move takes effect, keep the old number
+    /// as an alias, hand the case to the drawn judge (closing open recusal
+    /// conflicts with the judge it leaves), record the move and enter its
+    /// order on the docket. Division and magistrate judge are cleared, as they
+    /// belong to the court being left. Returns the new number, or None when the
+    /// case was no longer in `from_court_id` under `case_number` and `status`.
+    #[tracing::instrument(level = "info", fields(error), skip(con, draw))]
+    pub async fn apply(&self, draw: &Draw, transferred_by: i64, con: &Arc<Connection>) -> Result<Option<String>, LexodusAppError> {
+        let kind = self.destination.kind;
+        let rowset = con.query(
+            "WITH current AS (
+                 SELECT c.id, c.filed_date, c.court_id, c.case_number, c.case_sequence, c.case_type, c.assigned_judge_id,
+                        COALESCE(c.case_number_year, EXTRACT(YEAR FROM c.filed_date)::integer) AS number_year
+                 FROM cases c
+                 WHERE c.id = $1 AND c.filed_date = $2::date AND c.court_id = $3 AND c.case_number = $4
+                   AND c.status = $18
+                 FOR UPDATE
+             ),
+             number AS (
+                 SELECT n.* FROM current, LATERAL allocate_case_number($5, current.case_type, $8::date, $6) n
+             ),
+             moved AS (
+                 UPDATE cases c
+                 SET court_id = $5, division_id = NULL, magistrate_judge_id = NULL, assigned_judge_id = $6,
+                     case_number = number.number, case_sequence = number.sequence,
+                     case_number_template = number.template,
+                     case_number_year = EXTRACT(YEAR FROM $8::date)::integer,
+                     updated_by = $12, updated_at = CURRENT_TIMESTAMP
+                 FROM current, number
+                 WHERE c.id = current.id AND c.filed_date = current.filed_date
+                 RETURNING c.id, c.filed_date, c.case_number
+             ),
+             transfer AS (
+                 INSERT INTO case_transfers (
+                     case_id, case_filed_date, kind, from_court_id, to_court_id, old_case_number, new_case_number,
+                     previous_judge_id, order_reference, order_date, effective_date, reason, transferred_by
+                 )
+                 SELECT moved.id, moved.filed_date, $9, current.court_id, $5, current.case_number, moved.case_number,
+                        current.assigned_judge_id, $10, $7::date, $8::date, $11, $12
+                 FROM moved, current
+                 RETURNING id, case_id, case_filed_date, old_case_number, new_case_number
+             ),
+             alias AS (
+                 INSERT INTO case_number_aliases (case_id, case_filed_date, case_number, court_id, case_sequence, number_year, transfer_id)
+                 SELECT current.id, current.filed_date, current.case_number, current.court_id, current.case_sequence,
+                        current.number_year, transfer.id
+                 FROM current, transfer
+             ),
+             redrawn AS (
+                 UPDATE recusal_conflicts rc
+                 SET status = 'REDRAWN', resolved_by = $12, resolved_at = CURRENT_TIMESTAMP, resolution_note = $16
+                 FROM current, transfer
+                 WHERE rc.case_id = current.id AND rc.case_filed_date = current.filed_date
+                   AND rc.judge_id = current.assigned_judge_id AND rc.status = 'OPEN'
+             ),
+             assignment AS (
+                 INSERT INTO judge_assignments (
+                     case_id, case_filed_date, judge_id, previous_judge_id, method, seed, pool, reason, assigned_by
+                 )
+                 SELECT transfer.case_id, transfer.case_filed_date, $6, current.assigned_judge_id, $13, $14, $15::jsonb, $16, $12
+                 FROM transfer, current
+             ),
+             docket AS (
+                 INSERT INTO case_events (case_id, case_filed_date, event_type_id, event_date, title, public_entry, entered_by)
+                 SELECT transfer.case_id, transfer.case_filed_date, et.id, $8::date, $17,
+                        format('%s from %s (%s) to %s (%s) under order %s dated %s.',
+                               $17::text, fco.name, transfer.old_case_number, tco.name, transfer.new_case_number, $10::text, $7::text),
+                        $12
+                 FROM transfer
+                 JOIN event_types et ON et.name = 'TRANSFER'
+                 JOIN courts fco ON fco.id = $3
+                 JOIN courts tco ON tco.id = $5
+             )
+             SELECT new_case_number FROM transfer",
+            &[
+                ParameterValue::Int64(self.case_id),
+                ParameterValue::Str(self.filed_date.clone()),
+                ParameterValue::Int64(self.from_court_id),
+                ParameterValue::Str(self.case_number.clone()),
+                ParameterValue::Int64(self.destination.court_id),
+                ParameterValue::Int64(draw.judge_id),
+                ParameterValue::Str(self.order_date.to_string()),
+                ParameterValue::Str(self.effective_date.to_string()),
+                ParameterValue::Str(kind.as_str().to_string()),
+                ParameterValue::Str(self.order_reference.trim().to_string()),
+                self.reason.clone().map_or(ParameterValue::DbNull, ParameterValue::Str),
+                ParameterValue::Int64(transferred_by),
+                ParameterValue::Str(draw.method.as_str().to_string()),
+                draw.seed.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ParameterValue::Str(draw.pool_json()),
+                ParameterValue::Str(format!("Case {} to {}", kind.past(), self.destination.court_name)),
+                ParameterValue::Str(format!("Case {}", kind.past())),
+                ParameterValue::Str(self.status.as_str().to_string()),
+            ],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| text(&row[0])))
+    }
+
+    /// Tell the clerks' offices of both courts, in the app and by email. Case
+    /// titles are left out, since either court may hold the case sealed.
+    /// Returns the offices' email addresses with the notice to send each.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn notify_clerks(&self, new_case_number: &str, con: &Arc<Connection>) -> Result<Vec<(String, String, String)>, LexodusAppError> {
+        let kind = self.destination.kind;
+        let title = format!("Case {} {}", self.case_number, kind.past());
+        let content = format!(
+            "Case {} was {} to {} as {} under order {}, effective {}.",
+            self.case_number,
+            kind.past(),
+            self.destination.court_name,
+            new_case_number,
+            self.order_reference.trim(),
+            self.effective_date
+        );
+        con.execute(
+            "INSERT INTO notifications (user_id, title, content, notification_type, priority, case_id, case_filed_date)
+             SELECT DISTINCT cs.user_id, $4, $5, 'CASE', 'HIGH', $1, $2::date
+             FROM court_staff cs
+             WHERE cs.court_id IN ($3, $6)
+               AND COALESCE(cs.is_active, true)
+               AND (cs.end_date IS NULL OR cs.end_date >= CURRENT_DATE)
+               AND upper(cs.role_type) LIKE '%CLERK%'",
+            &[
+                ParameterValue::Int64(self.case_id),
+                ParameterValue::Str(self.filed_date.clone()),
+                ParameterValue::Int64(self.from_court_id),
+                ParameterValue::Str(title.clone()),
+                ParameterValue::Str(content.clone()),
+                ParameterValue::Int64(self.destination.court_id),
+            ],
+        )?;
+        let rowset = con.query(
+            "SELECT email FROM courts WHERE id IN ($1, $2) AND COALESCE(email, '') <> ''",
+            &[ParameterValue::Int64(self.from_court_id), ParameterValue::Int64(self.destination.court_id)],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .filter_map(|row| text(&row[0]))
+            .map(|email| (email, title.clone(), content.clone()))
+            .collect())
+    }
+}
+
+impl CaseTransfer {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT t.id, t.kind, fco.name, tco.name, t.old_case_number, t.new_case_number,
+                    t.order_reference, t.order_date::text, t.effective_date::text, t.reason, u.full_name,
+                    EXTRACT(EPOCH FROM t.transferred_at)::bigint
+             FROM case_transfers t
+             JOIN courts fco ON fco.id = t.from_court_id
+             JOIN courts tco ON tco.id = t.to_court_id
+             LEFT JOIN users u ON u.id = t.transferred_by
+             WHERE t.case_id = $1 AND t.case_filed_date = $2::date
+             ORDER BY t.transferred_at, t.id",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| CaseTransfer {
+                id: int(&row[0]).unwrap_or(-1),
+                kind: text(&row[1]).unwrap_or_default(),
+                from_court: text(&row[2]).unwrap_or_default(),
+                to_court: text(&row[3]).unwrap_or_default(),
+                old_case_number: text(&row[4]).unwrap_or_default(),
+                new_case_number: text(&row[5]).unwrap_or_default(),
+                order_reference: text(&row[6]).unwrap_or_default(),
+                order_date: text(&row[7]).unwrap_or_default(),
+                effective_date: text(&row[8]).unwrap_or_default(),
+                reason: text(&row[9]),
+                transferred_by: text(&row[10]),
+                transferred_at_pretty: pretty(int(&row[11]).unwrap_or_default()),
+            })
+            .collect())
+    }
+}
+}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kinds_round_trip_through_their_names() {
+        for kind in [TransferKind::Transfer, TransferKind::Remand] {
+            assert_eq!(TransferKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(TransferKind::parse("transfer"), None);
+        assert_eq!(TransferKind::parse("APPEAL"), None);
+    }
+
+    #[test]
+    fn kinds_read_as_words() {
+        assert_eq!(TransferKind::Remand.label(), "Remand");
+        assert_eq!(TransferKind::Transfer.past(), "transferred");
+        assert_eq!(TransferKind::Remand.past(), "remanded");
+    }
+
+    #[cfg(feature = "ssr")]
+    mod new_transfer {
+        use super::super::*;
+        use chrono::Days;
+
+        fn day(value: &str) -> NaiveDate {
+            NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+        }
+
+        fn transfer(kind: TransferKind, order_reference: &str, order_date: NaiveDate, effective_date: NaiveDate) -> NewTransfer {
+            NewTransfer {
+                case_id: 1,
+                filed_date: "2024-01-05".to_string(),
+                from_court_id: 1,
+                case_number: "1:24-cv-00042".to_string(),
+                status: CaseStatus::Open,
+                destination: TransferDestination {
+                    court_id: 2,
+                    court_name: "Southern District".to_string(),
+                    kind,
+                },
+                order_reference: order_reference.to_string(),
+                order_date,
+                effective_date,
+                reason: None,
+            }
+        }
+
+        fn message(result: Result<(), LexodusAppError>) -> String {
+            match result {
+                Err(LexodusAppError::BadRequest(message)) => message,
+                other => panic!("expected a bad request, got {other:?}"),
+            }
+        }
+
+        #[test]
+        fn move_under_a_dated_order_is_accepted() {
+            let moved = transfer(TransferKind::Transfer, "ECF No. 57", day("2024-03-01"), day("2024-03-15"));
+            assert!(moved.validate(day("2024-01-05")).is_ok());
+        }
+
+        #[test]
+        fn closed_case_is_not_moved() {
+            let closed = NewTransfer {
+                status: CaseStatus::Closed,
+                ..transfer(TransferKind::Remand, "ECF No. 57", day("2024-03-01"), day("2024-03-15"))
+            };
+            assert_eq!(message(closed.validate(day("2024-01-05"))), "A closed case cannot be remanded; reopen it first.");
+            let stayed = NewTransfer { status: CaseStatus::Stayed, ..closed };
+            assert!(stayed.validate(day("2024-01-05")).is_ok());
+        }
+
+        #[test]
+        fn order_must_be_cited() {
+            let remand = transfer(TransferKind::Remand, "  ", day("2024-03-01"), day("2024-03-01"));
+            assert_eq!(message(remand.validate(day("2024-01-05"))), "Cite the order directing the remand.");
+        }
+
+        #[test]
+        fn order_cannot_predate_the_case() {
+            let moved = transfer(TransferKind::Transfer, "ECF No. 57", day("2024-01-04"), day("2024-03-01"));
+            assert_eq!(message(moved.validate(day("2024-01-05"))), "The order cannot predate the case.");
+        }
+
+        #[test]
+        fn move_takes_effect_on_or_after_the_order() {
+            let early = transfer(TransferKind::Transfer, "ECF No. 57", day("2024-03-01"), day("2024-02-29"));
+            assert_eq!(message(early.validate(day("2024-01-05"))), "The move cannot take effect before the order.");
+            let same_day = transfer(TransferKind::Transfer, "ECF No. 57", day("2024-03-01"), day("2024-03-01"));
+            assert!(same_day.validate(day("2024-01-05")).is_ok());
+        }
+
+        #[test]
+        fn move_is_recorded_once_it_has_taken_effect() {
+            let today = Utc::now().date_naive();
+            let tomorrow = today.checked_add_days(Days::new(1)).unwrap();
+            let pending = transfer(TransferKind::Transfer, "ECF No. 57", today, tomorrow);
+            assert_eq!(message(pending.validate(day("2024-01-05"))), "Record the move once it has taken effect.");
+            assert!(transfer(TransferKind::Transfer, "ECF No. 57", today, today).validate(day("2024-01-05")).is_ok());
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//! Reading columns out of Postgres rows
+
+use chrono::DateTime;
+use spin_sdk::pg::DbValue;
+
+/// A text column, None when it is NULL or not text
+pub(crate) fn text(v: &DbValue) -> Option<String> {
+    match v {
+        DbValue::Str(s) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// An integer column of either width, None when it is NULL
+pub(crate) fn int(v: &DbValue) -> Option<i64> {
+    match v {
+        DbValue::Int64(n) => Some(*n),
+        DbValue::Int32(n) => Some(*n as i64),
+        _ => None,
+    }
+}
+
+/// A Unix timestamp as shown on pages
+pub(crate) fn pretty(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0).unwrap_or_default().to_string()
+}
@@ -0,0 +1,184 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+use crate::domain::models::db::pretty;
+use rand_core::{OsRng, RngCore};
+use crate::domain::models::user_token::hash_token;
+    }}
+
+/// A staff invitation as listed on the user management page
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Invitation {
+    pub id: i64,
+    pub email: String,
+    pub role: String,
+    pub invited_by: Option<String>,
+    pub expires_at: i64,
+    pub expires_at_pretty: String,
+    pub accepted: bool,
+    pub revoked: bool,
+    pub expired: bool,
+    pub created_at: i64,
+    pub created_at_pretty: String,
+}
+
+/// What an unused invitation grants, shown on the signup page before it is redeemed
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InvitationGrant {
+    pub id: i64,
+    pub email: String,
+    pub role_id: i64,
+    pub role: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+/// An invitation that is neither used, revoked nor expired
+const OPEN: &str = "i.accepted_at IS NULL AND i.revoked_at IS NULL AND i.expires_at > CURRENT_TIMESTAMP";
+
+impl InvitationGrant {
+    fn from_row(row: &[DbValue]) -> Self {
+        let text = |i: usize| match &row[i] {
+            DbValue::Str(s) => s.clone(),
+            _ => String::new(),
+        };
+        let int = |i: usize| match &row[i] {
+            DbValue::Int64(n) => *n,
+            _ => -1,
+        };
+        InvitationGrant {
+            id: int(0),
+            email: text(1),
+            role_id: int(2),
+            role: text(3),
+        }
+    }
+}
+
+impl Invitation {
+    fn from_row(row: &[DbValue]) -> Self {
+        let text = |i: usize| match &row[i] {
+            DbValue::Str(s) => Some(s.clone()),
+            _ => None,
+        };
+        let int = |i: usize| match &row[i] {
+            DbValue::Int64(n) => *n,
+            DbValue::Int32(n) => *n as i64,
+            _ => 0,
+        };
+        let expires_at = int(4);
+        let created_at = int(8);
+        Invitation {
+            id: int(0),
+            email: text(1).unwrap_or_default(),
+            role: text(2).unwrap_or_default(),
+            invited_by: text(3),
+            expires_at,
+            expires_at_pretty: pretty(expires_at),
+            accepted: matches!(&row[5], DbValue::Boolean(true)),
+            revoked: matches!(&row[6], DbValue::Boolean(true)),
+            expired: matches!(&row[7], DbValue::Boolean(true)),
+            created_at,
+            created_at_pretty: pretty(created_at),
+        }
+    }
+
+    /// Invite `email` to join with `role_id` and return the plain token to put in
+    /// the email. Earlier open invitations for the address are revoked.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn issue(email: &str, role_id: i64, invited_by: i64, ttl_secs: i64, con: &Arc<Connection>) -> Result<String, LexodusAppError> {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+        con.execute(
+            "UPDATE user_invitations SET revoked_at = CURRENT_TIMESTAMP
+             WHERE lower(email) = lower($1) AND accepted_at IS NULL AND revoked_at IS NULL",
+            &[ParameterValue::Str(email.to_string())],
+        )?;
+        con.execute(
+            "INSERT INTO user_invitations (email, role_id, invited_by, token_hash, expires_at)
+             VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP + make_interval(secs => $5::bigint))",
+            &[
+                ParameterValue::Str(email.to_string()),
+                ParameterValue::Int64(role_id),
+                ParameterValue::Int64(invited_by),
+                ParameterValue::Str(hash_token(&token)),
+                ParameterValue::Int64(ttl_secs),
+            ],
+        )?;
+        Ok(token)
+    }
+
+    /// What an open invitation grants, without redeeming it
+    #[tracing::instrument(level = "info", fields(error), skip(token, con))]
+    pub async fn peek(token: &str, con: &Arc<Connection>) -> Result<Option<InvitationGrant>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("SELECT i.id, i.email, i.role_id, r.name
+                      FROM user_invitations i JOIN roles r ON r.id = i.role_id
+                      WHERE i.token_hash = $1 AND {OPEN}"),
+            &[ParameterValue::Str(hash_token(token))],
+        )?;
+        Ok(rowset.rows.first().map(|row| InvitationGrant::from_row(row)))
+    }
+
+    /// Mark an open invitation as used. The check and the update are one
+    /// statement, so an invitation cannot be redeemed twice.
+    #[tracing::instrument(level = "info", fields(error), skip(token, con))]
+    pub async fn accept(token: &str, con: &Arc<Connection>) -> Result<Option<InvitationGrant>, LexodusAppError> {
+        let rowset = con.query(
+            &format!("UPDATE user_invitations i SET accepted_at = CURRENT_TIMESTAMP
+                      FROM roles r
+                      WHERE r.id = i.role_id AND i.token_hash = $1 AND {OPEN}
+                      RETURNING i.id, i.email, i.role_id, r.name"),
+            &[ParameterValue::Str(hash_token(token))],
+        )?;
+        Ok(rowset.rows.first().map(|row| InvitationGrant::from_row(row)))
+    }
+
+    /// Record the account created from an accepted invitation
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn set_accepted_user(id: i64, user_id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        con.execute(
+            "UPDATE user_invitations SET accepted_user_id = $2 WHERE id = $1",
+            &[ParameterValue::Int64(id), ParameterValue::Int64(user_id)],
+        )?;
+        Ok(())
+    }
+
+    /// Recent invitations, newest first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(limit: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT i.id, i.email, r.name, u.username, EXTRACT(EPOCH FROM i.expires_at)::bigint,
+                    i.accepted_at IS NOT NULL, i.revoked_at IS NOT NULL,
+                    i.expires_at <= CURRENT_TIMESTAMP, EXTRACT(EPOCH FROM i.created_at)::bigint
+             FROM user_invitations i
+             JOIN roles r ON r.id = i.role_id
+             LEFT JOIN users u ON u.id = i.invited_by
+             ORDER BY i.created_at DESC, i.id DESC
+             LIMIT $1",
+            &[ParameterValue::Int64(limit)],
+        )?;
+        Ok(rowset.rows.iter().map(|row| Self::from_row(row)).collect())
+    }
+
+    /// Revoke an invitation that has not been used. Returns false if there was none.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn revoke(id: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let affected = con.execute(
+            "UPDATE user_invitations SET revoked_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND accepted_at IS NULL AND revoked_at IS NULL",
+            &[ParameterValue::Int64(id)],
+        )?;
+        Ok(affected > 0)
+    }
+}
+}
+}
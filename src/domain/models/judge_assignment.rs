@@ -0,0 +1,448 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::case::case_visible_to;
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// A judge eligible for a draw, with what their chance was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolEntry {
+    pub judge_id: i64,
+    pub judge_name: String,
+    /// Wheel weight; senior and chief judges usually draw less than 1
+    pub weight: f64,
+    /// Cases not yet closed, from `vw_judicial_workload`
+    pub active_cases: i64,
+    /// Probability of being drawn, `weight / (1 + active_cases)` normalized
+    pub share: f64,
+}
+
+/// One judge assignment on a case, newest first on the case page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JudgeAssignment {
+    pub id: i64,
+    pub judge: String,
+    pub previous_judge: Option<String>,
+    pub method: String,
+    pub seed: Option<i64>,
+    pub pool: Vec<PoolEntry>,
+    /// The related case drawn from, when the viewer may see it
+    pub related_case_number: Option<String>,
+    pub reason: Option<String>,
+    pub assigned_by: Option<String>,
+    pub assigned_at: i64,
+    pub assigned_at_pretty: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use chrono::Utc;
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+/// How a judge came to be assigned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignmentMethod {
+    Random,
+    Related,
+    Manual,
+}
+
+impl AssignmentMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Random => "RANDOM",
+            Self::Related => "RELATED",
+            Self::Manual => "MANUAL",
+        }
+    }
+}
+
+/// The draw a case takes part in: its court, division and type, on a date
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Wheel {
+    pub court_id: i64,
+    pub division_id: Option<i64>,
+    pub case_type: String,
+    /// Judges must be serving on this date
+    pub on: String,
+    /// A filed case's id and filed date; judges its parties, attorneys or
+    /// firms block by recusal are left out of the draw
+    pub case: Option<(i64, String)>,
+}
+
+impl Wheel {
+    /// The wheel a filed case draws from today, and the judge it has now
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn for_case(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Option<(Self, Option<i64>)>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT court_id, division_id, case_type, assigned_judge_id
+             FROM cases WHERE id = $1 AND filed_date = $2::date",
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset.rows.first().map(|row| {
+            let wheel = Wheel {
+                court_id: int(&row[0]).unwrap_or_default(),
+                division_id: int(&row[1]),
+                case_type: text(&row[2]).unwrap_or_default(),
+                on: Utc::now().date_naive().to_string(),
+                case: Some((case_id, filed_date.to_string())),
+            };
+            (wheel, int(&row[3]))
+        }))
+    }
+
+    /// Active judges serving on `on` who are on the wheel for this court,
+    /// division and case type, ordered by id so a seed always picks the same
+    /// judge. A court with no wheel configured draws from all its judges at
+    /// weight 1. `exclude` leaves out the judge being reassigned away from.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn pool(&self, exclude: Option<i64>, con: &Arc<Connection>) -> Result<Vec<PoolEntry>, LexodusAppError> {
+        let mut params = vec![
+            ParameterValue::Int64(self.court_id),
+            self.division_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+            ParameterValue::Str(self.case_type.clone()),
+            ParameterValue::Str(self.on.clone()),
+            exclude.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+        ];
+        let recused = match &self.case {
+            Some((case_id, filed_date)) => {
+                params.extend([ParameterValue::Int64(*case_id), ParameterValue::Str(filed_date.clone())]);
+                "AND NOT EXISTS (
+                     SELECT 1 FROM vw_case_screened_names n
+                     CROSS JOIN LATERAL recusal_matches(jo.id, n.name, n.source) rm
+                     WHERE n.case_id = $6 AND n.case_filed_date = $7::date AND rm.severity = 'BLOCK'
+                 )"
+            }
+            None => "",
+        };
+        let sql = format!(
+            "WITH members AS (
+                 SELECT m.judge_id, MAX(m.weight)::float8 AS weight
+                 FROM judge_wheel_members m
+                 WHERE m.court_id = $1
+                   AND (m.division_id IS NULL OR m.division_id = $2)
+                   AND (m.case_type IS NULL OR upper(m.case_type) = upper($3))
+                 GROUP BY m.judge_id
+                 UNION ALL
+                 SELECT jo.id, 1::float8
+                 FROM judicial_officers jo
+                 WHERE jo.court_id = $1
+                   AND NOT EXISTS (SELECT 1 FROM judge_wheel_members w WHERE w.court_id = $1)
+             )
+             SELECT jo.id, u.full_name, m.weight, COALESCE(w.active_cases, 0)::bigint
+             FROM members m
+             JOIN judicial_officers jo ON jo.id = m.judge_id
+             JOIN users u ON u.id = jo.user_id
+             LEFT JOIN vw_judicial_workload w ON w.judge_id = jo.id
+             WHERE jo.court_id = $1
+               AND jo.status = 'ACTIVE'
+               AND (jo.term_start_date IS NULL OR jo.term_start_date <= $4::date)
+               AND (jo.term_end_date IS NULL OR jo.term_end_date >= $4::date)
+               AND jo.id IS DISTINCT FROM $5
+               {recused}
+             ORDER BY jo.id"
+        );
+        let rowset = con.query(&sql, &params)?;
+        let pool = rowset
+            .rows
+            .iter()
+            .map(|row| PoolEntry {
+                judge_id: int(&row[0]).unwrap_or(-1),
+                judge_name: text(&row[1]).unwrap_or_default(),
+                weight: match row[2] {
+                    DbValue::Floating64(w) => w,
+                    _ => 1.0,
+                },
+                active_cases: int(&row[3]).unwrap_or_default(),
+                share: 0.0,
+            })
+            .collect();
+        with_shares(pool)
+    }
+}
+
+/// Set each judge's chance: their weight discounted by their open caseload,
+/// over the pool's total. A pool whose weights are all zero has nobody to draw.
+fn with_shares(mut pool: Vec<PoolEntry>) -> Result<Vec<PoolEntry>, LexodusAppError> {
+    if pool.is_empty() {
+        return Ok(pool);
+    }
+    let total: f64 = pool.iter().map(|e| e.weight / (1.0 + e.active_cases as f64)).sum();
+    if total.is_nan() || total <= 0.0 {
+        return Err(LexodusAppError::NoEligibleJudge);
+    }
+    for entry in &mut pool {
+        entry.share = entry.weight / (1.0 + entry.active_cases as f64) / total;
+    }
+    Ok(pool)
+}
+
+/// SplitMix64, so a recorded seed replays to the same judge on any machine
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// The judge a seed draws from a pool, walking the shares in pool order
+pub fn pick(pool: &[PoolEntry], seed: u64) -> Option<i64> {
+    let point = (splitmix64(seed) >> 11) as f64 / (1u64 << 53) as f64;
+    let mut reached = 0.0;
+    for entry in pool {
+        reached += entry.share;
+        if point < reached {
+            return Some(entry.judge_id);
+        }
+    }
+    pool.last().map(|entry| entry.judge_id)
+}
+
+/// A judge chosen for a case, with what is recorded about the choice
+#[derive(Debug, Clone, PartialEq)]
+pub struct Draw {
+    pub judge_id: i64,
+    pub method: AssignmentMethod,
+    pub seed: Option<i64>,
+    pub pool: Vec<PoolEntry>,
+    pub related_case_id: Option<i64>,
+}
+
+impl Draw {
+    /// A blind draw from the pool with a fresh seed. None when no judge is eligible.
+    pub fn random(pool: Vec<PoolEntry>) -> Option<Self> {
+        let seed = rand::random::<u64>();
+        Some(Draw {
+            judge_id: pick(&pool, seed)?,
+            method: AssignmentMethod::Random,
+            seed: Some(seed as i64),
+            pool,
+            related_case_id: None,
+        })
+    }
+
+    /// The related case's judge, if they are still eligible for this case
+    pub fn related(pool: Vec<PoolEntry>, judge_id: i64, related_case_id: i64) -> Option<Self> {
+        pool.iter().any(|e| e.judge_id == judge_id).then_some(Draw {
+            judge_id,
+            method: AssignmentMethod::Related,
+            seed: None,
+            pool,
+            related_case_id: Some(related_case_id),
+        })
+    }
+
+    /// A judge picked by hand, who must be eligible for the case
+    pub fn manual(pool: Vec<PoolEntry>, judge_id: i64) -> Option<Self> {
+        pool.iter().any(|e| e.judge_id == judge_id).then_some(Draw {
+            judge_id,
+            method: AssignmentMethod::Manual,
+            seed: None,
+            pool,
+            related_case_id: None,
+        })
+    }
+
+    pub fn pool_json(&self) -> String {
+        serde_json::to_string(&self.pool).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Move a case to the drawn judge and record why in one statement, closing
+    /// any open recusal conflicts with the judge it leaves. The case number's
+    /// judge initials are redrawn, and the new judge screened, by triggers.
+    /// Returns false when the case's judge was no longer `previous`.
+    #[tracing::instrument(level = "info", fields(error), skip(con, self))]
+    pub async fn reassign(
+        &self,
+        case_id: i64,
+        filed_date: &str,
+        previous: Option<i64>,
+        reason: &str,
+        assigned_by: i64,
+        con: &Arc<Connection>,
+    ) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "WITH changed AS (
+                 UPDATE cases
+                 SET assigned_judge_id = $3, updated_by = $8, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1 AND filed_date = $2::date AND assigned_judge_id IS NOT DISTINCT FROM $4
+                 RETURNING id, filed_date
+             ),
+             redrawn AS (
+                 UPDATE recusal_conflicts rc
+                 SET status = 'REDRAWN', resolved_by = $8, resolved_at = CURRENT_TIMESTAMP, resolution_note = $9
+                 FROM changed
+                 WHERE rc.case_id = changed.id AND rc.case_filed_date = changed.filed_date
+                   AND rc.judge_id = $4 AND rc.status = 'OPEN'
+             )
+             INSERT INTO judge_assignments (
+                 case_id, case_filed_date, judge_id, previous_judge_id, method, seed, pool, reason, assigned_by
+             )
+             SELECT id, filed_date, $3, $4, $5, $6, $7::jsonb, $9, $8 FROM changed
+             RETURNING id",
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(self.judge_id),
+                previous.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ParameterValue::Str(self.method.as_str().to_string()),
+                self.seed.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ParameterValue::Str(self.pool_json()),
+                ParameterValue::Int64(assigned_by),
+                ParameterValue::Str(reason.to_string()),
+            ],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+
+impl JudgeAssignment {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(case_id: i64, filed_date: &str, viewer_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let sql = format!(
+            "SELECT a.id, ju.full_name, pu.full_name, a.method, a.seed, a.pool::text,
+                    c.case_number, a.reason, u.full_name, EXTRACT(EPOCH FROM a.assigned_at)::bigint
+             FROM judge_assignments a
+             JOIN judicial_officers jo ON jo.id = a.judge_id
+             JOIN users ju ON ju.id = jo.user_id
+             LEFT JOIN judicial_officers pj ON pj.id = a.previous_judge_id
+             LEFT JOIN users pu ON pu.id = pj.user_id
+             LEFT JOIN cases c ON c.id = a.related_case_id AND {}
+             LEFT JOIN users u ON u.id = a.assigned_by
+             WHERE a.case_id = $1 AND a.case_filed_date = $2::date
+             ORDER BY a.assigned_at DESC, a.id DESC",
+            case_visible_to(3)
+        );
+        let rowset = con.query(
+            &sql,
+            &[
+                ParameterValue::Int64(case_id),
+                ParameterValue::Str(filed_date.to_string()),
+                ParameterValue::Int64(viewer_id),
+            ],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| {
+                let assigned_at = int(&row[9]).unwrap_or_default();
+                JudgeAssignment {
+                    id: int(&row[0]).unwrap_or(-1),
+                    judge: text(&row[1]).unwrap_or_default(),
+                    previous_judge: text(&row[2]),
+                    method: text(&row[3]).unwrap_or_default(),
+                    seed: int(&row[4]),
+                    pool: text(&row[5]).and_then(|p| serde_json::from_str(&p).ok()).unwrap_or_default(),
+                    related_case_number: text(&row[6]),
+                    reason: text(&row[7]),
+                    assigned_by: text(&row[8]),
+                    assigned_at,
+                    assigned_at_pretty: pretty(assigned_at),
+                }
+            })
+            .collect())
+    }
+}
+}
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn entry(judge_id: i64, share: f64) -> PoolEntry {
+        PoolEntry {
+            judge_id,
+            judge_name: format!("Judge {judge_id}"),
+            weight: 1.0,
+            active_cases: 0,
+            share,
+        }
+    }
+
+    #[test]
+    fn splitmix64_matches_the_reference_sequence() {
+        // The first two outputs of SplitMix64 seeded with 0
+        assert_eq!(splitmix64(0), 0xE220_A839_7B1D_CDAF);
+        assert_eq!(splitmix64(0x9E37_79B9_7F4A_7C15), 0x6E78_9E6A_A1B9_65F4);
+    }
+
+    #[test]
+    fn recorded_seed_replays_to_the_same_judge() {
+        let pool = [entry(4, 0.25), entry(7, 0.25), entry(9, 0.5)];
+        for seed in [0, 1, 42, u64::MAX, rand::random::<u64>()] {
+            let judge = pick(&pool, seed);
+            assert!(judge.is_some());
+            assert_eq!(pick(&pool, seed), judge);
+            assert_eq!(pick(&pool.clone(), seed), judge);
+        }
+    }
+
+    #[test]
+    fn judges_are_drawn_in_proportion_to_their_shares() {
+        let pool = [entry(1, 0.5), entry(2, 0.3), entry(3, 0.2)];
+        let draws = 100_000;
+        let mut counts = [0usize; 3];
+        for seed in 0..draws {
+            let judge = pick(&pool, seed).unwrap();
+            counts[(judge - 1) as usize] += 1;
+        }
+        for (entry, count) in pool.iter().zip(counts) {
+            let drawn = count as f64 / draws as f64;
+            assert!((drawn - entry.share).abs() < 0.01, "judge {} drawn {drawn}, share {}", entry.judge_id, entry.share);
+        }
+    }
+
+    #[test]
+    fn judge_with_no_share_is_never_drawn() {
+        let pool = [entry(1, 0.0), entry(2, 1.0)];
+        assert!((0..1_000).all(|seed| pick(&pool, seed) == Some(2)));
+    }
+
+    #[test]
+    fn empty_pool_draws_nobody() {
+        assert_eq!(pick(&[], 42), None);
+        assert_eq!(Draw::random(Vec::new()), None);
+    }
+
+    #[test]
+    fn shares_follow_weight_and_caseload() {
+        let pool = vec![
+            PoolEntry { weight: 2.0, ..entry(1, 0.0) },
+            PoolEntry { active_cases: 1, ..entry(2, 0.0) },
+        ];
+        let shares: Vec<f64> = with_shares(pool).unwrap().iter().map(|e| e.share).collect();
+        assert_eq!(shares, [0.8, 0.2]);
+    }
+
+    #[test]
+    fn all_zero_weight_pool_has_no_eligible_judge() {
+        let pool = vec![
+            PoolEntry { weight: 0.0, ..entry(1, 0.0) },
+            PoolEntry { weight: 0.0, active_cases: 3, ..entry(2, 0.0) },
+        ];
+        assert!(matches!(with_shares(pool), Err(LexodusAppError::NoEligibleJudge)));
+        assert_eq!(with_shares(Vec::new()).unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn shares_short_of_one_fall_to_the_last_judge() {
+        let pool = [entry(1, 0.0), entry(2, 0.0)];
+        assert_eq!(pick(&pool, 42), Some(2));
+    }
+
+    #[test]
+    fn related_and_manual_draws_need_an_eligible_judge() {
+        let pool = vec![entry(1, 0.5), entry(2, 0.5)];
+        let related = Draw::related(pool.clone(), 2, 77).unwrap();
+        assert_eq!((related.judge_id, related.method, related.related_case_id), (2, AssignmentMethod::Related, Some(77)));
+        assert_eq!(Draw::related(pool.clone(), 3, 77), None);
+        assert_eq!(Draw::manual(pool.clone(), 1).map(|d| d.method), Some(AssignmentMethod::Manual));
+        assert_eq!(Draw::manual(pool, 3), None);
+    }
+}
@@ -1 +1,20 @@
+pub mod activity_log;
+pub mod api_token;
+pub mod attorney_registration;
+pub mod auth_log;
+pub mod case;
+pub mod case_link;
+pub mod case_number;
+pub mod case_seal;
+pub mod case_search;
+pub mod case_status;
+pub mod case_transfer;
+#[cfg(feature = "ssr")]
+pub mod db;
+pub mod invitation;
+pub mod judge_assignment;
+pub mod permission;
+pub mod recusal;
 pub mod user;
+pub mod user_identity;
+pub mod user_token;
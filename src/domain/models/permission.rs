@@ -0,0 +1,86 @@
+/// A single `(resource_type, action_type)` pair from the `permissions` table
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Permission {
+    pub resource_type: &'static str,
+    pub action_type: &'static str,
+}
+
+impl Permission {
+    pub const fn new(resource_type: &'static str, action_type: &'static str) -> Self {
+        Self {
+            resource_type,
+            action_type,
+        }
+    }
+
+    // Cases
+    pub const CASE_CREATE: Permission = Permission::new("CASE", "CREATE");
+    pub const CASE_READ: Permission = Permission::new("CASE", "READ");
+    pub const CASE_UPDATE: Permission = Permission::new("CASE", "UPDATE");
+    pub const CASE_ASSIGN: Permission = Permission::new("CASE", "ASSIGN");
+    pub const CASE_SEAL: Permission = Permission::new("CASE", "SEAL");
+    pub const CASE_TRANSFER: Permission = Permission::new("CASE", "TRANSFER");
+    // Parties
+    pub const PARTY_CREATE: Permission = Permission::new("PARTY", "CREATE");
+    pub const PARTY_READ: Permission = Permission::new("PARTY", "READ");
+    // Courts and judicial officers
+    pub const COURT_READ: Permission = Permission::new("COURT", "READ");
+    // Judges' recusal lists
+    pub const RECUSAL_MANAGE: Permission = Permission::new("RECUSAL", "MANAGE");
+    // Users
+    pub const USER_CREATE: Permission = Permission::new("USER", "CREATE");
+    pub const USER_READ: Permission = Permission::new("USER", "READ");
+    pub const USER_UPDATE: Permission = Permission::new("USER", "UPDATE");
+    pub const USER_IMPERSONATE: Permission = Permission::new("USER", "IMPERSONATE");
+    // Audit trails
+    pub const AUDIT_READ: Permission = Permission::new("AUDIT", "READ");
+    // Attorney registration requests
+    pub const REGISTRATION_REVIEW: Permission = Permission::new("REGISTRATION", "REVIEW");
+
+    /// Every permission a server function can require
+    pub const ALL: [Permission; 16] = [
+        Self::CASE_CREATE,
+        Self::CASE_READ,
+        Self::CASE_UPDATE,
+        Self::CASE_ASSIGN,
+        Self::CASE_SEAL,
+        Self::CASE_TRANSFER,
+        Self::PARTY_CREATE,
+        Self::PARTY_READ,
+        Self::COURT_READ,
+        Self::RECUSAL_MANAGE,
+        Self::USER_CREATE,
+        Self::USER_READ,
+        Self::USER_UPDATE,
+        Self::USER_IMPERSONATE,
+        Self::AUDIT_READ,
+        Self::REGISTRATION_REVIEW,
+    ];
+
+    /// Look up a permission by its `RESOURCE:ACTION` name
+    pub fn parse(name: &str) -> Option<Permission> {
+        let (resource_type, action_type) = name.split_once(':')?;
+        Self::ALL
+            .into_iter()
+            .find(|p| p.matches(resource_type, action_type))
+    }
+
+    /// Does a `(resource_type, action_type)` row from the database grant this permission
+    pub fn matches(&self, resource_type: &str, action_type: &str) -> bool {
+        self.resource_type.eq_ignore_ascii_case(resource_type)
+            && self.action_type.eq_ignore_ascii_case(action_type)
+    }
+}
+
+impl std::fmt::Display for Permission {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.resource_type, self.action_type)
+    }
+}
+
+/// Declares the permission a server function requires. Every server function
+/// registered with `server::register_guarded` must implement this, and must call
+/// `functions::authz::authorize::<Self>()` before doing any work.
+pub trait RequiresPermission {
+    const PERMISSION: Permission;
+}
@@ -0,0 +1,333 @@
+use cfg_if::cfg_if;
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+use crate::domain::models::case::case_visible_to;
+use crate::domain::models::db::{int, pretty, text};
+use crate::errors::LexodusAppError;
+use std::sync::Arc;
+    }}
+
+/// Kinds of entry on a recusal list, with their labels
+pub const RECUSAL_ENTITY_TYPES: [(&str, &str); 3] =
+    [("ORGANIZATION", "Organization"), ("INDIVIDUAL", "Individual"), ("LAW_FIRM", "Law firm")];
+
+/// A judge whose recusal list a user maintains
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChambersJudge {
+    pub id: i64,
+    pub name: String,
+    pub court_name: String,
+}
+
+/// One organization, individual or law firm a judge is recused from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecusalEntry {
+    pub id: i64,
+    pub judge_id: i64,
+    pub entity_type: String,
+    pub name: String,
+    pub reason: Option<String>,
+    pub active: bool,
+    pub created_by: Option<String>,
+    pub created_at_pretty: String,
+}
+
+/// A name on a case that matched its judge's recusal list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecusalConflict {
+    pub id: i64,
+    pub case_id: i64,
+    pub case_number: String,
+    pub judge: String,
+    pub recusal_name: String,
+    pub entity_type: String,
+    /// PARTY, ATTORNEY or FIRM
+    pub source: String,
+    pub matched_name: String,
+    /// Trigram similarity of the two names, 0 to 1
+    pub score: f64,
+    /// BLOCK when the names are the same or nearly so, FLAG for review otherwise
+    pub severity: String,
+    pub status: String,
+    pub detected_at_pretty: String,
+    pub resolved_by: Option<String>,
+    pub resolution_note: Option<String>,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+
+/// Words dropped from a name before it is compared, as by `recusal_name_key`
+const NAME_KEY_NOISE: [&str; 15] =
+    ["the", "inc", "incorporated", "llc", "llp", "lp", "ltd", "limited", "corp", "corporation", "co", "company", "plc", "pc", "pllc"];
+
+/// The part of a name that identifies who it is, the same as the database's
+/// `recusal_name_key`: lower case, punctuation and corporate suffixes dropped,
+/// so "Acme, Inc." and "ACME Corporation" compare equal
+pub fn name_key(name: &str) -> String {
+    let name: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_lowercase() || c.is_ascii_digit() { c } else { ' ' })
+        .collect();
+    name.split_whitespace()
+        .filter(|word| !NAME_KEY_NOISE.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// An active entry already on the list under the same name key
+pub fn duplicate<'a>(entries: &'a [RecusalEntry], name: &str) -> Option<&'a RecusalEntry> {
+    let key = name_key(name);
+    entries.iter().find(|entry| entry.active && name_key(&entry.name) == key)
+}
+
+impl ChambersJudge {
+    /// A judge maintains their own list; court staff maintain any active judge's
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn for_user(user_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT jo.id, u.full_name, co.name
+             FROM judicial_officers jo
+             JOIN users u ON u.id = jo.user_id
+             JOIN courts co ON co.id = jo.court_id
+             WHERE jo.user_id = $1
+                OR (jo.status = 'ACTIVE' AND NOT EXISTS (SELECT 1 FROM judicial_officers own WHERE own.user_id = $1))
+             ORDER BY u.full_name, co.name",
+            &[ParameterValue::Int64(user_id)],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| ChambersJudge {
+                id: int(&row[0]).unwrap_or(-1),
+                name: text(&row[1]).unwrap_or_default(),
+                court_name: text(&row[2]).unwrap_or_default(),
+            })
+            .collect())
+    }
+}
+
+impl RecusalEntry {
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn list(judge_id: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT r.id, r.judge_id, r.entity_type, r.name, r.reason, r.active, u.full_name,
+                    EXTRACT(EPOCH FROM r.created_at)::bigint
+             FROM judge_recusals r
+             LEFT JOIN users u ON u.id = r.created_by
+             WHERE r.judge_id = $1
+             ORDER BY r.active DESC, lower(r.name)",
+            &[ParameterValue::Int64(judge_id)],
+        )?;
+        Ok(rowset
+            .rows
+            .iter()
+            .map(|row| RecusalEntry {
+                id: int(&row[0]).unwrap_or(-1),
+                judge_id: int(&row[1]).unwrap_or(-1),
+                entity_type: text(&row[2]).unwrap_or_default(),
+                name: text(&row[3]).unwrap_or_default(),
+                reason: text(&row[4]),
+                active: matches!(row[5], DbValue::Boolean(true)),
+                created_by: text(&row[6]),
+                created_at_pretty: pretty(int(&row[7]).unwrap_or_default()),
+            })
+            .collect())
+    }
+
+    /// Add an entry. The judge's open cases are screened against it as it is saved.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn add(
+        judge_id: i64,
+        entity_type: &str,
+        name: &str,
+        reason: Option<&str>,
+        created_by: i64,
+        con: &Arc<Connection>,
+    ) -> Result<i64, LexodusAppError> {
+        let rowset = con.query(
+            "INSERT INTO judge_recusals (judge_id, entity_type, name, reason, created_by)
+             VALUES ($1, $2, $3, $4, $5)
+             RETURNING id",
+            &[
+                ParameterValue::Int64(judge_id),
+                ParameterValue::Str(entity_type.to_string()),
+                ParameterValue::Str(name.to_string()),
+                reason.map_or(ParameterValue::DbNull, |r| ParameterValue::Str(r.to_string())),
+                ParameterValue::Int64(created_by),
+            ],
+        )?;
+        rowset
+            .rows
+            .first()
+            .and_then(|row| int(&row[0]))
+            .ok_or_else(|| LexodusAppError::DBError("Recusal entry was not saved".to_string()))
+    }
+
+    /// Withdraw or restore an entry. Conflicts already raised from it stay until resolved.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn set_active(id: i64, judge_id: i64, active: bool, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "UPDATE judge_recusals SET active = $3, updated_at = CURRENT_TIMESTAMP
+             WHERE id = $1 AND judge_id = $2
+             RETURNING id",
+            &[ParameterValue::Int64(id), ParameterValue::Int64(judge_id), ParameterValue::Boolean(active)],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+
+const CONFLICT_COLUMNS: &str = "rc.id, c.id, c.case_number, ju.full_name, r.name, r.entity_type, rc.source,
+    rc.matched_name, rc.score::float8, rc.severity, rc.status, EXTRACT(EPOCH FROM rc.detected_at)::bigint,
+    ru.full_name, rc.resolution_note";
+
+const CONFLICT_JOINS: &str = "FROM recusal_conflicts rc
+    JOIN cases c ON c.id = rc.case_id AND c.filed_date = rc.case_filed_date
+    JOIN judge_recusals r ON r.id = rc.recusal_id
+    JOIN judicial_officers jo ON jo.id = rc.judge_id
+    JOIN users ju ON ju.id = jo.user_id
+    LEFT JOIN users ru ON ru.id = rc.resolved_by";
+
+impl RecusalConflict {
+    fn from_row(row: &[DbValue]) -> Self {
+        RecusalConflict {
+            id: int(&row[0]).unwrap_or(-1),
+            case_id: int(&row[1]).unwrap_or(-1),
+            case_number: text(&row[2]).unwrap_or_default(),
+            judge: text(&row[3]).unwrap_or_default(),
+            recusal_name: text(&row[4]).unwrap_or_default(),
+            entity_type: text(&row[5]).unwrap_or_default(),
+            source: text(&row[6]).unwrap_or_default(),
+            matched_name: text(&row[7]).unwrap_or_default(),
+            score: match row[8] {
+                DbValue::Floating64(s) => s,
+                _ => 0.0,
+            },
+            severity: text(&row[9]).unwrap_or_default(),
+            status: text(&row[10]).unwrap_or_default(),
+            detected_at_pretty: pretty(int(&row[11]).unwrap_or_default()),
+            resolved_by: text(&row[12]),
+            resolution_note: text(&row[13]),
+        }
+    }
+
+    /// Every conflict raised on a case, open ones first
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn for_case(case_id: i64, filed_date: &str, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!(
+                "SELECT {CONFLICT_COLUMNS} {CONFLICT_JOINS}
+                 WHERE rc.case_id = $1 AND rc.case_filed_date = $2::date
+                 ORDER BY rc.status = 'OPEN' DESC, rc.detected_at DESC"
+            ),
+            &[ParameterValue::Int64(case_id), ParameterValue::Str(filed_date.to_string())],
+        )?;
+        Ok(rowset.rows.iter().map(|row| Self::from_row(row)).collect())
+    }
+
+    /// Open conflicts on cases the viewer can see, blocking ones first: the
+    /// queue of cases waiting to be redrawn or cleared
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn open(viewer_id: i64, limit: i64, con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+        let rowset = con.query(
+            &format!(
+                "SELECT {CONFLICT_COLUMNS} {CONFLICT_JOINS}
+                 WHERE rc.status = 'OPEN' AND {}
+                 ORDER BY rc.severity = 'BLOCK' DESC, rc.detected_at
+                 LIMIT $2",
+                case_visible_to(1)
+            ),
+            &[ParameterValue::Int64(viewer_id), ParameterValue::Int64(limit)],
+        )?;
+        Ok(rowset.rows.iter().map(|row| Self::from_row(row)).collect())
+    }
+
+    /// Dismiss an open conflict as a false match. Returns false when it was
+    /// no longer open or not on this case.
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn clear(id: i64, case_id: i64, note: &str, cleared_by: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let rowset = con.query(
+            "UPDATE recusal_conflicts
+             SET status = 'CLEARED', resolved_by = $3, resolved_at = CURRENT_TIMESTAMP, resolution_note = $4
+             WHERE id = $1 AND case_id = $2 AND status = 'OPEN'
+             RETURNING id",
+            &[
+                ParameterValue::Int64(id),
+                ParameterValue::Int64(case_id),
+                ParameterValue::Int64(cleared_by),
+                ParameterValue::Str(note.to_string()),
+            ],
+        )?;
+        Ok(!rowset.rows.is_empty())
+    }
+}
+}
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, active: bool) -> RecusalEntry {
+        RecusalEntry {
+            id: 1,
+            judge_id: 1,
+            entity_type: "ORGANIZATION".to_string(),
+            name: name.to_string(),
+            reason: None,
+            active,
+            created_by: None,
+            created_at_pretty: String::new(),
+        }
+    }
+
+    #[test]
+    fn corporate_forms_of_a_name_share_a_key() {
+        assert_eq!(name_key("The Acme, Inc."), "acme");
+        assert_eq!(name_key("ACME Corporation"), "acme");
+        assert_eq!(name_key("acme co"), "acme");
+    }
+
+    #[test]
+    fn punctuation_and_spacing_are_ignored() {
+        assert_eq!(name_key("Smith & Jones LLP"), "smith jones");
+        assert_eq!(name_key("  Smith,   Jones\tPLLC "), "smith jones");
+        assert_eq!(name_key("Coca-Cola Co."), "coca cola");
+    }
+
+    #[test]
+    fn suffixes_are_dropped_only_as_whole_words() {
+        assert_eq!(name_key("Theodore Inc"), "theodore");
+        assert_eq!(name_key("Cornerstone Company"), "cornerstone");
+        assert_eq!(name_key("Colt Industries"), "colt industries");
+    }
+
+    #[test]
+    fn letters_outside_ascii_separate_words_as_in_the_database() {
+        assert_eq!(name_key("Société Générale"), "soci t g n rale");
+    }
+
+    #[test]
+    fn name_of_only_noise_has_an_empty_key() {
+        assert_eq!(name_key("  "), "");
+        assert_eq!(name_key("The Company, Inc."), "");
+    }
+
+    #[test]
+    fn duplicate_is_found_by_key_among_active_entries() {
+        let entries = [entry("Acme, Inc.", false), entry("Globex Corporation", true)];
+        assert_eq!(duplicate(&entries, "GLOBEX LLC").map(|e| e.name.as_str()), Some("Globex Corporation"));
+        assert!(duplicate(&entries, "Acme Corp").is_none());
+        assert!(duplicate(&entries, "Globex Holdings").is_none());
+    }
+
+    #[test]
+    fn entity_types_are_those_the_database_accepts() {
+        let values: Vec<&str> = RECUSAL_ENTITY_TYPES.iter().map(|(value, _)| *value).collect();
+        assert_eq!(values, ["ORGANIZATION", "INDIVIDUAL", "LAW_FIRM"]);
+    }
+}
@@ -5,15 +5,25 @@ cfg_if! {
     if #[cfg(feature = "ssr")] {
 use crate::errors::LexodusAppError;
 use std::sync::Arc;
-use chrono::{DateTime, Utc};
     }}
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct User {
     pub id: i64,
     pub username: String,
-    pub display_name: String,
+    pub email: String,
+    pub full_name: String,
     pub password: String,
+    pub role_id: i64,
+    pub role: String,
+    pub bar_number: Option<String>,
+    pub home_court_id: Option<i64>,
+    pub two_factor_enabled: bool,
+    pub email_verified: bool,
+    pub failed_login_attempts: i64,
+    /// Unix timestamp until which logins are refused
+    pub locked_until: Option<i64>,
+    pub password_changed_at: Option<i64>,
     pub created_at: i64,
     pub created_at_pretty: String,
     pub updated_at: i64,
@@ -25,12 +35,22 @@ impl Default for User {
         Self {
             id: -1,
             username: "Guest".into(),
+            email: "".into(),
+            full_name: "Guest".into(),
+            password: "".into(),
+            role_id: -1,
+            role: "".into(),
+            bar_number: None,
+            home_court_id: None,
+            two_factor_enabled: false,
+            email_verified: false,
+            failed_login_attempts: 0,
+            locked_until: None,
+            password_changed_at: None,
             created_at: 0,
             created_at_pretty: "".to_string(),
             updated_at: 0,
             updated_at_pretty: "".to_string(),
-            password: "".into(),
-            display_name: "Guest".into(),
         }
     }
 }
@@ -39,7 +59,13 @@ impl Default for User {
 pub struct SafeUser {
     pub id: i64,
     pub username: String,
-    pub display_name: String,
+    pub email: String,
+    pub full_name: String,
+    pub role_id: i64,
+    pub role: String,
+    pub home_court_id: Option<i64>,
+    pub two_factor_enabled: bool,
+    pub email_verified: bool,
     pub created_at: i64,
     pub created_at_pretty: String,
 }
@@ -48,9 +74,15 @@ impl Default for SafeUser {
         Self {
             id: -1,
             username: "Guest".into(),
+            email: "".into(),
+            full_name: "Guest".into(),
+            role_id: -1,
+            role: "".into(),
+            home_court_id: None,
+            two_factor_enabled: false,
+            email_verified: false,
             created_at: 0,
             created_at_pretty: "".to_string(),
-            display_name: "Guest".into(),
         }
     }
 }
@@ -60,126 +92,469 @@ impl From<User> for SafeUser {
         Self {
             id: user.id,
             username: user.username,
-            display_name: user.display_name,
+            email: user.email,
+            full_name: user.full_name,
+            role_id: user.role_id,
+            role: user.role,
+            home_court_id: user.home_court_id,
+            two_factor_enabled: user.two_factor_enabled,
+            email_verified: user.email_verified,
             created_at: user.created_at,
             created_at_pretty: user.created_at_pretty,
         }
     }
 }
 
+/// Roles that handle sealed matters. Their users may not use permissioned
+/// features until they have set up two-factor authentication.
+pub const TWO_FACTOR_ROLES: [&str; 2] = ["judge", "clerk"];
+
+/// Must a user with this role enroll in two-factor authentication
+pub fn role_requires_two_factor(role: &str) -> bool {
+    TWO_FACTOR_ROLES.iter().any(|r| r.eq_ignore_ascii_case(role))
+}
+
+impl SafeUser {
+    /// Is this user required to enroll in two-factor authentication and not yet enrolled
+    pub fn owes_two_factor(&self) -> bool {
+        role_requires_two_factor(&self.role) && !self.two_factor_enabled
+    }
+}
+
+/// Fields required to insert a row into `users`
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NewUser {
+    pub username: String,
+    pub email: String,
+    pub full_name: String,
+    pub password_hash: String,
+    pub role_id: i64,
+    pub bar_number: Option<String>,
+    pub home_court_id: Option<i64>,
+}
+
 cfg_if! {
 if #[cfg(feature = "ssr")] {
-    use chrono::naive::NaiveDateTime;
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+use crate::domain::models::db::{int, pretty, text};
 
-use spin_sdk::sqlite::{
-    Connection,
-    Value::{Integer, Text},
-};
+/// Columns selected for every user query, in the order `SqlUser::from_row` expects them
+const USER_COLUMNS: &str = "u.id, u.username, u.email, u.password_hash, u.full_name,
+    u.role_id, r.name, u.bar_number, u.home_court_id,
+    EXTRACT(EPOCH FROM u.created_at)::bigint, EXTRACT(EPOCH FROM u.updated_at)::bigint,
+    COALESCE(u.two_factor_enabled, false), COALESCE(u.failed_login_attempts, 0),
+    EXTRACT(EPOCH FROM u.account_locked_until)::bigint, u.email_verified_at IS NOT NULL,
+    EXTRACT(EPOCH FROM u.password_changed_at)::bigint";
 
 #[derive(Debug, Clone)]
 pub struct SqlUser {
     pub id: i64,
     pub username: String,
-    pub display_name: String,
+    pub email: String,
     pub password: String,
+    pub full_name: String,
+    pub role_id: i64,
+    pub role: String,
+    pub bar_number: Option<String>,
+    pub home_court_id: Option<i64>,
     pub created_at: i64,
     pub updated_at: i64,
+    pub two_factor_enabled: bool,
+    pub failed_login_attempts: i64,
+    pub locked_until: Option<i64>,
+    pub email_verified: bool,
+    pub password_changed_at: Option<i64>,
 }
 
 impl SqlUser {
+    fn from_row(row: &[DbValue]) -> Self {
+        SqlUser {
+            id: int(&row[0]).unwrap_or(-1),
+            username: text(&row[1]).unwrap_or_default(),
+            email: text(&row[2]).unwrap_or_default(),
+            password: text(&row[3]).unwrap_or_default(),
+            full_name: text(&row[4]).unwrap_or_default(),
+            role_id: int(&row[5]).unwrap_or(-1),
+            role: text(&row[6]).unwrap_or_default(),
+            bar_number: text(&row[7]),
+            home_court_id: int(&row[8]),
+            created_at: int(&row[9]).unwrap_or_default(),
+            updated_at: int(&row[10]).unwrap_or_default(),
+            two_factor_enabled: matches!(&row[11], DbValue::Boolean(true)),
+            failed_login_attempts: int(&row[12]).unwrap_or_default(),
+            locked_until: int(&row[13]),
+            email_verified: matches!(&row[14], DbValue::Boolean(true)),
+            password_changed_at: int(&row[15]),
+        }
+    }
+
     #[tracing::instrument(level = "info", fields(error))]
     pub fn into_user(self) -> User {
         User {
             id: self.id,
             username: self.username,
-            display_name: self.display_name,
+            email: self.email,
+            full_name: self.full_name,
             password: self.password,
+            role_id: self.role_id,
+            role: self.role,
+            bar_number: self.bar_number,
+            home_court_id: self.home_court_id,
+            two_factor_enabled: self.two_factor_enabled,
+            email_verified: self.email_verified,
+            failed_login_attempts: self.failed_login_attempts,
+            locked_until: self.locked_until,
+            password_changed_at: self.password_changed_at,
             created_at: self.created_at,
-            created_at_pretty: DateTime::from_timestamp(self.created_at, 0).unwrap_or_default().to_string(),
+            created_at_pretty: pretty(self.created_at),
             updated_at: self.updated_at,
-            updated_at_pretty: DateTime::from_timestamp(self.created_at, 0).unwrap_or_default().to_string(),
+            updated_at_pretty: pretty(self.updated_at),
         }
     }
 }
 
-    impl SafeUser {
+    impl User {
         #[tracing::instrument(level = "info", fields(error))]
         pub async fn get(id: i64, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+            let rowset = con.query(
+                &format!("SELECT {USER_COLUMNS} FROM users u JOIN roles r ON r.id = u.role_id
+                          WHERE u.id = $1 AND u.deleted_at IS NULL"),
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(rowset.rows.first().map(|row| SqlUser::from_row(row).into_user()))
+        }
 
-            let rowset = con.execute("SELECT * FROM users WHERE id = ?", &[Integer(id)])?;
-            let sqluser = rowset.rows().nth(0).map(|row| SqlUser{
-            id: row.get::<i64>("id").unwrap(),
-            username: row.get::<&str>("name").unwrap().to_string(),
-            display_name: row.get::<&str>("display_name").unwrap().to_string(),
-            password: row.get::<&str>("password").unwrap().to_string(),
-            created_at: row.get::<i64>("created_at").unwrap(),
-            updated_at: row.get::<i64>("updated_at").unwrap()
-            });
-
-            match sqluser{
-            Some(su) => Ok(Some(su.into_user().into())),
-            None => Ok(None),
-            }
+        #[tracing::instrument(level = "info", fields(error))]
+        pub async fn get_from_username(name: &str, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+            let rowset = con.query(
+                &format!("SELECT {USER_COLUMNS} FROM users u JOIN roles r ON r.id = u.role_id
+                          WHERE u.username = $1 AND u.deleted_at IS NULL"),
+                &[ParameterValue::Str(name.to_string())],
+            )?;
+            Ok(rowset.rows.first().map(|row| SqlUser::from_row(row).into_user()))
+        }
 
+        /// Emails are unique, but compared case-insensitively as users type them
+        #[tracing::instrument(level = "info", fields(error))]
+        pub async fn get_from_email(email: &str, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+            let rowset = con.query(
+                &format!("SELECT {USER_COLUMNS} FROM users u JOIN roles r ON r.id = u.role_id
+                          WHERE lower(u.email) = lower($1) AND u.deleted_at IS NULL"),
+                &[ParameterValue::Str(email.trim().to_string())],
+            )?;
+            Ok(rowset.rows.first().map(|row| SqlUser::from_row(row).into_user()))
         }
 
-        pub async fn get_from_username(name: String, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
+        /// All active users, ordered by username
+        #[tracing::instrument(level = "info", fields(error))]
+        pub async fn list(con: &Arc<Connection>) -> Result<Vec<Self>, LexodusAppError> {
+            let rowset = con.query(
+                &format!("SELECT {USER_COLUMNS} FROM users u JOIN roles r ON r.id = u.role_id
+                          WHERE u.deleted_at IS NULL ORDER BY u.username"),
+                &[],
+            )?;
+            Ok(rowset.rows.iter().map(|row| SqlUser::from_row(row).into_user()).collect())
+        }
 
-            let rowset = con.execute("SELECT * FROM users WHERE username = ?",&[Text(name.to_string())])?;
+        /// Insert a new user and return its id
+        #[tracing::instrument(level = "info", fields(error), skip(new_user))]
+        pub async fn create(new_user: NewUser, con: &Arc<Connection>) -> Result<i64, LexodusAppError> {
+            let rowset = con.query(
+                "INSERT INTO users (username, email, password_hash, full_name, role_id, bar_number, home_court_id)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 RETURNING id",
+                &[
+                    ParameterValue::Str(new_user.username),
+                    ParameterValue::Str(new_user.email),
+                    ParameterValue::Str(new_user.password_hash),
+                    ParameterValue::Str(new_user.full_name),
+                    ParameterValue::Int64(new_user.role_id),
+                    new_user.bar_number.map_or(ParameterValue::DbNull, ParameterValue::Str),
+                    new_user.home_court_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+                ],
+            )?;
+            match rowset.rows.first().map(|row| &row[0]) {
+                Some(DbValue::Int64(id)) => Ok(*id),
+                _ => Err(LexodusAppError::DBError("Insert did not return a user id".to_string())),
+            }
+        }
 
-            let sqluser = rowset.rows().nth(0).map(|row| SqlUser{
-            id: row.get::<i64>("id").unwrap(),
-            username: row.get::<&str>("name").unwrap().to_string(),
-            display_name: row.get::<&str>("display_name").unwrap().to_string(),
-            password: row.get::<&str>("password").unwrap().to_string(),
-            created_at: row.get::<i64>("created_at").unwrap(),
-            updated_at: row.get::<i64>("updated_at").unwrap()
-            });
+        /// The stored base32 TOTP secret, if the user has finished enrollment
+        pub async fn two_factor_secret(id: i64, con: &Arc<Connection>) -> Result<Option<String>, LexodusAppError> {
+            let rowset = con.query(
+                "SELECT two_factor_secret FROM users WHERE id = $1 AND two_factor_enabled",
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(rowset.rows.first().and_then(|row| match &row[0] {
+                DbValue::Str(s) => Some(s.clone()),
+                _ => None,
+            }))
+        }
 
-            match sqluser{
-            Some(su) => Ok(Some(su.into_user().into())),
-            None => Ok(None),
-            }
+        /// Turn on 2FA with a confirmed secret and a fresh set of hashed recovery codes.
+        /// `confirmed_step` is the time step of the code that confirmed it.
+        #[tracing::instrument(level = "info", fields(error), skip(secret, recovery_code_hashes))]
+        pub async fn enable_two_factor(id: i64, secret: &str, recovery_code_hashes: &[String], confirmed_step: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users
+                 SET two_factor_enabled = true, two_factor_secret = $2, two_factor_last_step = $4,
+                     recovery_codes = string_to_array($3, ','), updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1",
+                &[
+                    ParameterValue::Int64(id),
+                    ParameterValue::Str(secret.to_string()),
+                    ParameterValue::Str(recovery_code_hashes.join(",")),
+                    ParameterValue::Int64(confirmed_step),
+                ],
+            )?;
+            Ok(())
         }
-    }
 
-    impl User {
+        /// Remove the 2FA secret and recovery codes, e.g. after an admin reset
         #[tracing::instrument(level = "info", fields(error))]
-        pub async fn get(id: i64, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
-            let rowset = con.execute("SELECT * FROM users WHERE id = ?", &[Integer(id)])?;
-            let sqluser = rowset.rows().nth(0).map(|row| SqlUser{
-            id: row.get::<i64>("id").unwrap(),
-            username: row.get::<&str>("username").unwrap().to_string(),
-            display_name: row.get::<&str>("display_name").unwrap().to_string(),
-            password: row.get::<&str>("password").unwrap().to_string(),
-            created_at: row.get::<i64>("created_at").unwrap(),
-            updated_at: row.get::<i64>("updated_at").unwrap()
-            });
-
-            match sqluser{
-            Some(su) => Ok(Some(su.into_user())),
-            None => Ok(None),
-            }
-
+        pub async fn clear_two_factor(id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users
+                 SET two_factor_enabled = false, two_factor_secret = NULL, two_factor_last_step = NULL,
+                     recovery_codes = NULL, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1",
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(())
         }
 
+        /// Record `step` as the time step of the last accepted authenticator code. Returns
+        /// false when a code from that step or a later one was already accepted, so each
+        /// code is accepted only once.
         #[tracing::instrument(level = "info", fields(error))]
-        pub async fn get_from_username(name: &str, con: &Arc<Connection>) -> Result<Option<Self>, LexodusAppError> {
-            let rowset = con.execute("SELECT * FROM users WHERE username = ?",&[Text(name.to_string())])?;
-
-            let sqluser = rowset.rows().nth(0).map(|row| SqlUser{
-            id: row.get::<i64>("id").unwrap(),
-            username: row.get::<&str>("username").unwrap().to_string(),
-            display_name: row.get::<&str>("display_name").unwrap().to_string(),
-            password: row.get::<&str>("password").unwrap().to_string(),
-            created_at: row.get::<i64>("created_at").unwrap(),
-            updated_at: row.get::<i64>("updated_at").unwrap()
-            });
-            match sqluser{
-            Some(su) => Ok(Some(su.into_user())),
-            None => Ok(None),
-            }
+        pub async fn accept_two_factor_step(id: i64, step: i64, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+            let affected = con.execute(
+                "UPDATE users
+                 SET two_factor_last_step = $2
+                 WHERE id = $1 AND (two_factor_last_step IS NULL OR two_factor_last_step < $2)",
+                &[ParameterValue::Int64(id), ParameterValue::Int64(step)],
+            )?;
+            Ok(affected > 0)
         }
+
+        /// Remove a recovery code hash if present. Returns whether a code was consumed,
+        /// so a code can only ever be used once.
+        #[tracing::instrument(level = "info", fields(error), skip(code_hash))]
+        pub async fn consume_recovery_code(id: i64, code_hash: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+            let affected = con.execute(
+                "UPDATE users
+                 SET recovery_codes = array_remove(recovery_codes, $2)
+                 WHERE id = $1 AND $2 = ANY(recovery_codes)",
+                &[ParameterValue::Int64(id), ParameterValue::Str(code_hash.to_string())],
+            )?;
+            Ok(affected > 0)
+        }
+
+        /// Count a failed login and, once `threshold` consecutive failures are reached, lock
+        /// the account for `base_secs * 2^(failures - threshold)` seconds, capped at `max_secs`.
+        /// Returns the lock expiry as a Unix timestamp if the account is now locked.
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn record_failed_login(id: i64, threshold: i64, base_secs: i64, max_secs: i64, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+            let rowset = con.query(
+                "UPDATE users
+                 SET failed_login_attempts = COALESCE(failed_login_attempts, 0) + 1,
+                     account_locked_until = CASE
+                         WHEN COALESCE(failed_login_attempts, 0) + 1 >= $2::bigint
+                         THEN CURRENT_TIMESTAMP + make_interval(secs => LEAST(
+                             $3::bigint * power(2, COALESCE(failed_login_attempts, 0) + 1 - $2::bigint),
+                             $4::bigint))
+                         ELSE account_locked_until
+                     END
+                 WHERE id = $1
+                 RETURNING EXTRACT(EPOCH FROM account_locked_until)::bigint",
+                &[
+                    ParameterValue::Int64(id),
+                    ParameterValue::Int64(threshold),
+                    ParameterValue::Int64(base_secs),
+                    ParameterValue::Int64(max_secs),
+                ],
+            )?;
+            Ok(rowset.rows.first().and_then(|row| match &row[0] {
+                DbValue::Int64(ts) => Some(*ts),
+                _ => None,
+            }))
+        }
+
+        /// Clear the failure counter and stamp `last_login_at`
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn record_successful_login(id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users
+                 SET failed_login_attempts = 0, account_locked_until = NULL, last_login_at = CURRENT_TIMESTAMP
+                 WHERE id = $1",
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(())
+        }
+
+        /// Lift a lockout before it expires
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn unlock(id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users SET failed_login_attempts = 0, account_locked_until = NULL WHERE id = $1",
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(())
+        }
+
+        /// Is the account currently refusing logins
+        pub fn is_locked(&self, now: i64) -> bool {
+            self.locked_until.is_some_and(|until| until > now)
+        }
+
+        /// Replace the password hash and stamp `password_changed_at`. The outgoing hash
+        /// is kept in `password_history`, trimmed to the newest `history_count` entries.
+        #[tracing::instrument(level = "info", fields(error), skip(password_hash, con))]
+        pub async fn update_password(id: i64, password_hash: &str, history_count: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "INSERT INTO password_history (user_id, password_hash)
+                 SELECT id, password_hash FROM users WHERE id = $1",
+                &[ParameterValue::Int64(id)],
+            )?;
+            con.execute(
+                "DELETE FROM password_history
+                 WHERE user_id = $1 AND id NOT IN (
+                     SELECT id FROM password_history WHERE user_id = $1
+                     ORDER BY created_at DESC, id DESC LIMIT $2::bigint)",
+                &[ParameterValue::Int64(id), ParameterValue::Int64(history_count)],
+            )?;
+            con.execute(
+                "UPDATE users
+                 SET password_hash = $2, password_changed_at = CURRENT_TIMESTAMP, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = $1",
+                &[ParameterValue::Int64(id), ParameterValue::Str(password_hash.to_string())],
+            )?;
+            Ok(())
+        }
+
+        /// Store a stronger hash of the same password. Unlike `update_password` this
+        /// is not a password change, so history and `password_changed_at` are untouched.
+        #[tracing::instrument(level = "info", fields(error), skip(password_hash, con))]
+        pub async fn rehash_password(id: i64, password_hash: &str, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users SET password_hash = $2 WHERE id = $1",
+                &[ParameterValue::Int64(id), ParameterValue::Str(password_hash.to_string())],
+            )?;
+            Ok(())
+        }
+
+        /// The current hash followed by up to `limit` previous ones, newest first
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn recent_password_hashes(id: i64, limit: i64, con: &Arc<Connection>) -> Result<Vec<String>, LexodusAppError> {
+            let rowset = con.query(
+                "(SELECT password_hash, 0 AS position FROM users WHERE id = $1)
+                 UNION ALL
+                 (SELECT password_hash, row_number() OVER (ORDER BY created_at DESC, id DESC)
+                  FROM password_history WHERE user_id = $1
+                  ORDER BY created_at DESC, id DESC LIMIT $2::bigint)
+                 ORDER BY position",
+                &[ParameterValue::Int64(id), ParameterValue::Int64(limit)],
+            )?;
+            Ok(rowset
+                .rows
+                .iter()
+                .filter_map(|row| match &row[0] {
+                    DbValue::Str(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect())
+        }
+
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn mark_email_verified(id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users SET email_verified_at = COALESCE(email_verified_at, CURRENT_TIMESTAMP) WHERE id = $1",
+                &[ParameterValue::Int64(id)],
+            )?;
+            Ok(())
+        }
+
+        /// Move the user to another role
+        #[tracing::instrument(level = "info", fields(error), skip(con))]
+        pub async fn update_role(id: i64, role_id: i64, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+            con.execute(
+                "UPDATE users SET role_id = $2, updated_at = CURRENT_TIMESTAMP WHERE id = $1",
+                &[ParameterValue::Int64(id), ParameterValue::Int64(role_id)],
+            )?;
+            Ok(())
+        }
+
+        /// Look up a role id by its name, e.g. `attorney`
+        pub async fn role_id_by_name(name: &str, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+            let rowset = con.query(
+                "SELECT id FROM roles WHERE name = $1",
+                &[ParameterValue::Str(name.to_string())],
+            )?;
+            Ok(rowset.rows.first().and_then(|row| match &row[0] {
+                DbValue::Int64(id) => Some(*id),
+                _ => None,
+            }))
+        }
+}
 }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(role: &str, two_factor_enabled: bool) -> SafeUser {
+        SafeUser {
+            role: role.to_string(),
+            two_factor_enabled,
+            ..SafeUser::default()
+        }
+    }
+
+    #[test]
+    fn judges_and_clerks_require_two_factor() {
+        assert!(role_requires_two_factor("judge"));
+        assert!(role_requires_two_factor("Clerk"));
+        assert!(!role_requires_two_factor("attorney"));
+        assert!(!role_requires_two_factor("admin"));
+        assert!(!role_requires_two_factor(""));
+    }
+
+    #[test]
+    fn only_unenrolled_judges_and_clerks_owe_two_factor() {
+        assert!(user("judge", false).owes_two_factor());
+        assert!(!user("judge", true).owes_two_factor());
+        assert!(!user("attorney", false).owes_two_factor());
+    }
+
+    #[cfg(feature = "ssr")]
+    mod ssr {
+        use super::super::*;
+        use crate::session_backend::tests::block_on;
+
+        #[test]
+        #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+        fn authenticator_steps_are_accepted_once() {
+            let con = Arc::new(Connection::open(&std::env::var("LEXODUS_TEST_DB_URL").unwrap()).unwrap());
+            con.execute("BEGIN", &[]).unwrap();
+            let user = con
+                .query(
+                    "INSERT INTO users (username, email, password_hash, full_name, role_id)
+                     SELECT 'totp-step-test', 'totp-step-test@example.com', '-', 'Step Test', MIN(id) FROM roles
+                     RETURNING id",
+                    &[],
+                )
+                .unwrap();
+            let Some(DbValue::Int64(id)) = user.rows.first().map(|row| row[0].clone()) else {
+                panic!("Insert did not return a user id");
+            };
+            block_on(User::enable_two_factor(id, "JBSWY3DPEHPK3PXP", &[], 100, &con)).unwrap();
+            let accepted = [100, 99, 101, 101, 103].map(|step| block_on(User::accept_two_factor_step(id, step, &con)).unwrap());
+            block_on(User::clear_two_factor(id, &con)).unwrap();
+            let after_reset = block_on(User::accept_two_factor_step(id, 50, &con)).unwrap();
+            con.execute("ROLLBACK", &[]).unwrap();
+            assert_eq!(accepted, [false, false, true, false, true]);
+            assert!(after_reset);
+        }
+    }
 }
@@ -0,0 +1,45 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+use std::sync::Arc;
+
+/// A Lexodus user's account at an OpenID Connect identity provider
+pub struct UserIdentity;
+
+impl UserIdentity {
+    /// The user linked to `(issuer, subject)`, stamping the login time
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn find_user(issuer: &str, subject: &str, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        let rowset = con.query(
+            "UPDATE user_identities SET last_login_at = CURRENT_TIMESTAMP
+             WHERE issuer = $1 AND subject = $2
+             RETURNING user_id",
+            &[ParameterValue::Str(issuer.to_string()), ParameterValue::Str(subject.to_string())],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| match &row[0] {
+            DbValue::Int64(id) => Some(*id),
+            _ => None,
+        }))
+    }
+
+    /// Link an identity to a user
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn link(user_id: i64, issuer: &str, subject: &str, email: Option<&str>, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        con.execute(
+            "INSERT INTO user_identities (user_id, issuer, subject, email, last_login_at)
+             VALUES ($1, $2, $3, $4, CURRENT_TIMESTAMP)",
+            &[
+                ParameterValue::Int64(user_id),
+                ParameterValue::Str(issuer.to_string()),
+                ParameterValue::Str(subject.to_string()),
+                email.map_or(ParameterValue::DbNull, |e| ParameterValue::Str(e.to_string())),
+            ],
+        )?;
+        Ok(())
+    }
+}
+}
+}
@@ -0,0 +1,104 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+use std::sync::Arc;
+
+pub mod purpose {
+    pub const PASSWORD_RESET: &str = "PASSWORD_RESET";
+    pub const EMAIL_VERIFICATION: &str = "EMAIL_VERIFICATION";
+}
+
+/// Single-use, expiring tokens mailed to users. The plain token only ever
+/// exists in the email; `user_tokens` holds its SHA-256.
+pub struct UserToken;
+
+/// The SHA-256 a mailed, API or invitation token is stored and looked up by
+pub(crate) fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.trim().as_bytes()))
+}
+
+impl UserToken {
+    /// Create a token for `user_id`, invalidating any unused one with the same purpose,
+    /// and return the plain token to put in the email
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn issue(user_id: i64, purpose: &str, ttl_secs: i64, con: &Arc<Connection>) -> Result<String, LexodusAppError> {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+        con.execute(
+            "UPDATE user_tokens SET used_at = CURRENT_TIMESTAMP
+             WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL",
+            &[ParameterValue::Int64(user_id), ParameterValue::Str(purpose.to_string())],
+        )?;
+        con.execute(
+            "INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+             VALUES ($1, $2, $3, CURRENT_TIMESTAMP + make_interval(secs => $4::bigint))",
+            &[
+                ParameterValue::Int64(user_id),
+                ParameterValue::Str(purpose.to_string()),
+                ParameterValue::Str(hash_token(&token)),
+                ParameterValue::Int64(ttl_secs),
+            ],
+        )?;
+        Ok(token)
+    }
+
+    /// The user an unexpired, unused token belongs to, without redeeming it
+    #[tracing::instrument(level = "info", fields(error), skip(token, con))]
+    pub async fn peek(token: &str, purpose: &str, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT user_id FROM user_tokens
+             WHERE token_hash = $1 AND purpose = $2
+               AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP",
+            &[ParameterValue::Str(hash_token(token)), ParameterValue::Str(purpose.to_string())],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| match &row[0] {
+            DbValue::Int64(id) => Some(*id),
+            _ => None,
+        }))
+    }
+
+    /// Mark an unexpired, unused token as used and return its user. The check and
+    /// the update are one statement, so a token cannot be redeemed twice.
+    #[tracing::instrument(level = "info", fields(error), skip(token, con))]
+    pub async fn consume(token: &str, purpose: &str, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        let rowset = con.query(
+            "UPDATE user_tokens SET used_at = CURRENT_TIMESTAMP
+             WHERE token_hash = $1 AND purpose = $2
+               AND used_at IS NULL AND expires_at > CURRENT_TIMESTAMP
+             RETURNING user_id",
+            &[ParameterValue::Str(hash_token(token)), ParameterValue::Str(purpose.to_string())],
+        )?;
+        Ok(rowset.rows.first().and_then(|row| match &row[0] {
+            DbValue::Int64(id) => Some(*id),
+            _ => None,
+        }))
+    }
+
+    /// Tokens issued to the user for `purpose` within the last `window_secs`, for rate limiting
+    #[tracing::instrument(level = "info", fields(error), skip(con))]
+    pub async fn recent_count(user_id: i64, purpose: &str, window_secs: i64, con: &Arc<Connection>) -> Result<i64, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT COUNT(*) FROM user_tokens
+             WHERE user_id = $1 AND purpose = $2
+               AND created_at > CURRENT_TIMESTAMP - make_interval(secs => $3::bigint)",
+            &[
+                ParameterValue::Int64(user_id),
+                ParameterValue::Str(purpose.to_string()),
+                ParameterValue::Int64(window_secs),
+            ],
+        )?;
+        Ok(rowset.rows.first().map_or(0, |row| match &row[0] {
+            DbValue::Int64(n) => *n,
+            _ => 0,
+        }))
+    }
+}
+}
+}
@@ -11,6 +11,22 @@ pub enum LexodusAppError {
     NotFound,
     #[error("Auth Error")]
     AuthError,
+    #[error("Account temporarily locked. Try again later.")]
+    AccountLocked,
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Forbidden: missing permission {0}")]
+    Forbidden(String),
+    #[error("Invalid or missing CSRF token")]
+    CsrfFailure,
+    #[error("Changes are disabled while acting as another user")]
+    ImpersonationReadOnly,
+    #[error("Set up two-factor authentication on the Account Security page before continuing")]
+    TwoFactorRequired,
+    #[error("Too many requests. Try again later.")]
+    TooManyRequests,
+    #[error("No judge is eligible for this case")]
+    NoEligibleJudge,
     #[error("DB Connection Not Found")]
     DBConnectionNotFound,
     #[error("Internal Server Error")]
@@ -29,6 +45,10 @@ pub enum LexodusAppError {
     JsonError(String),
     #[error("DBError: {0}")]
     DBError(String),
+    #[error("MailError: {0}")]
+    MailError(String),
+    #[error("Single sign-on failed: {0}")]
+    SsoError(String),
     #[error("Invalid Date or Time")]
     InvalidDateTime,
     #[error("Missing or Invalid Frontmatter")]
@@ -48,10 +68,20 @@ impl LexodusAppError {
             LexodusAppError::SessionError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             LexodusAppError::JsonError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             LexodusAppError::DBError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            LexodusAppError::MailError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            LexodusAppError::SsoError(_) => StatusCode::UNAUTHORIZED,
             LexodusAppError::DBConnectionNotFound => StatusCode::INTERNAL_SERVER_ERROR,
             LexodusAppError::InvalidDateTime => StatusCode::BAD_REQUEST,
             LexodusAppError::BadRequest(_) => StatusCode::BAD_REQUEST,
             LexodusAppError::AuthError => StatusCode::BAD_REQUEST,
+            LexodusAppError::AccountLocked => StatusCode::LOCKED,
+            LexodusAppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            LexodusAppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            LexodusAppError::CsrfFailure => StatusCode::FORBIDDEN,
+            LexodusAppError::ImpersonationReadOnly => StatusCode::FORBIDDEN,
+            LexodusAppError::TwoFactorRequired => StatusCode::FORBIDDEN,
+            LexodusAppError::TooManyRequests => StatusCode::TOO_MANY_REQUESTS,
+            LexodusAppError::NoEligibleJudge => StatusCode::BAD_REQUEST,
             LexodusAppError::MissingOrInvalidFrontmatter => StatusCode::INTERNAL_SERVER_ERROR,
             LexodusAppError::TomlError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
@@ -86,6 +116,11 @@ cfg_if! {
                 Self::DBError(error.to_string())
             }
         }
+        impl From<spin_sdk::pg::PgError> for LexodusAppError{
+            fn from(error: spin_sdk::pg::PgError) -> Self {
+                Self::DBError(error.to_string())
+            }
+        }
         impl From<async_session::Error> for LexodusAppError{
             fn from(error: async_session::Error) -> Self {
                 Self::SessionError(error.to_string())
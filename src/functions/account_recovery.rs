@@ -0,0 +1,169 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::auth_log::{event_type, status};
+    use crate::domain::models::user::User;
+    use crate::domain::models::user_token::{purpose, UserToken};
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{auth_cookie_session, record_auth_event, ClientInfo};
+    use crate::functions::con;
+    use crate::functions::csrf::verify_csrf;
+    use crate::functions::password_policy::{store_new_password, validate_new_password};
+    use crate::functions::sessions::revoke_user_sessions;
+    use crate::mail::{app_base_url, Mailer, OutgoingMail};
+    use leptos::expect_context;
+    use leptos_spin::RequestParts;
+    use spin_sdk::pg::Connection;
+    use std::sync::Arc;
+
+    const PASSWORD_RESET_TTL_SECS: i64 = 60 * 60;
+    const EMAIL_VERIFICATION_TTL_SECS: i64 = 24 * 60 * 60;
+    /// Lifetime of the token handed straight to a user whose password has expired
+    const EXPIRED_PASSWORD_TTL_SECS: i64 = 15 * 60;
+    /// At most this many emails of one kind per account per window
+    const MAX_EMAILS_PER_WINDOW: i64 = 3;
+    const RATE_LIMIT_WINDOW_SECS: i64 = 60 * 60;
+
+    async fn within_rate_limit(user_id: i64, purpose: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        Ok(UserToken::recent_count(user_id, purpose, RATE_LIMIT_WINDOW_SECS, con).await? < MAX_EMAILS_PER_WINDOW)
+    }
+
+    /// Mail a fresh verification link to the user's address
+    pub async fn send_verification_email(user: &User, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let token = UserToken::issue(user.id, purpose::EMAIL_VERIFICATION, EMAIL_VERIFICATION_TTL_SECS, con).await?;
+        let link = format!("{}/verify-email?token={token}", app_base_url());
+        expect_context::<Mailer>()
+            .send(
+                OutgoingMail {
+                    to: user.email.clone(),
+                    subject: "Confirm your Lexodus email address".to_string(),
+                    body: format!(
+                        "Hello {},\n\nConfirm that this address belongs to your Lexodus account by opening the link below. It expires in 24 hours.\n\n{link}\n\nIf you did not create an account, ignore this email.\n",
+                        user.full_name
+                    ),
+                },
+                con,
+            )
+            .await
+    }
+
+    /// A reset token for a user who proved their old password at login but must
+    /// choose a new one. It is returned to the browser instead of being mailed.
+    pub async fn issue_expired_password_token(user_id: i64, con: &Arc<Connection>) -> Result<String, LexodusAppError> {
+        UserToken::issue(user_id, purpose::PASSWORD_RESET, EXPIRED_PASSWORD_TTL_SECS, con).await
+    }
+
+    async fn send_password_reset_email(user: &User, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let token = UserToken::issue(user.id, purpose::PASSWORD_RESET, PASSWORD_RESET_TTL_SECS, con).await?;
+        let link = format!("{}/reset-password?token={token}", app_base_url());
+        expect_context::<Mailer>()
+            .send(
+                OutgoingMail {
+                    to: user.email.clone(),
+                    subject: "Reset your Lexodus password".to_string(),
+                    body: format!(
+                        "Hello {},\n\nSomeone asked to reset the password for your Lexodus account. Open the link below to choose a new one. It expires in one hour and works once.\n\n{link}\n\nIf you did not ask for this, ignore this email; your password has not changed.\n",
+                        user.full_name
+                    ),
+                },
+                con,
+            )
+            .await
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(RequestPasswordReset, "/api")]
+/// Mail a reset link if the address belongs to an account. The result is the same
+/// either way so the form cannot be used to discover which addresses are registered.
+pub async fn request_password_reset(csrf_token: String, email: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let con = con()?;
+    let Some(user) = User::get_from_email(&email, &con).await? else {
+        tracing::info!("Password reset requested for an unknown address");
+        return Ok(());
+    };
+    if !within_rate_limit(user.id, purpose::PASSWORD_RESET, &con).await? {
+        tracing::warn!("Password reset rate limit reached for user {}", user.id);
+        return Ok(());
+    }
+    if let Err(e) = send_password_reset_email(&user, &con).await {
+        tracing::error!("Failed to send password reset email to user {}: {e}", user.id);
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(ResetPassword, "/api")]
+/// Redeem a reset token. Every session of the account is signed out and any lockout lifted.
+pub async fn reset_password(
+    csrf_token: String,
+    token: String,
+    password: String,
+    password_confirmation: String,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    if password != password_confirmation {
+        return Err(LexodusAppError::BadRequest("Passwords did not match.".to_string()).into());
+    }
+    let con = con()?;
+    let invalid = || LexodusAppError::BadRequest("This reset link is invalid or has expired.".to_string());
+    // Validate before redeeming so a rejected password does not use up the link
+    let Some(user_id) = UserToken::peek(&token, purpose::PASSWORD_RESET, &con).await? else {
+        return Err(invalid().into());
+    };
+    let Some(user) = User::get(user_id, &con).await? else {
+        return Err(invalid().into());
+    };
+    validate_new_password(&user, &password, &con).await?;
+    if UserToken::consume(&token, purpose::PASSWORD_RESET, &con).await? != Some(user_id) {
+        return Err(invalid().into());
+    }
+
+    store_new_password(user_id, &password, &con).await?;
+    User::unlock(user_id, &con).await?;
+    revoke_user_sessions(user_id, None).await?;
+
+    let client = use_context::<RequestParts>()
+        .map(|req| ClientInfo::from_request(&req))
+        .unwrap_or_default();
+    record_auth_event(client.event(Some(user_id), event_type::PASSWORD_RESET, status::SUCCESS, None), &con).await;
+    tracing::info!("User {user_id} reset their password");
+    leptos_spin::redirect("/login");
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(SendVerificationEmail, "/api")]
+/// Send the logged in user another verification link
+pub async fn send_verification(csrf_token: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+    let con = con()?;
+    let user = auth_cookie_session(&req, &con).await?;
+    if user.email_verified {
+        return Err(LexodusAppError::BadRequest("Your email address is already verified.".to_string()).into());
+    }
+    if !within_rate_limit(user.id, purpose::EMAIL_VERIFICATION, &con).await? {
+        return Err(LexodusAppError::TooManyRequests.into());
+    }
+    send_verification_email(&user, &con).await?;
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(VerifyEmail, "/api")]
+/// Redeem an email verification token
+pub async fn verify_email(csrf_token: String, token: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let con = con()?;
+    let Some(user_id) = UserToken::consume(&token, purpose::EMAIL_VERIFICATION, &con).await? else {
+        return Err(LexodusAppError::BadRequest("This verification link is invalid or has expired.".to_string()).into());
+    };
+    User::mark_email_verified(user_id, &con).await?;
+    tracing::info!("User {user_id} verified their email address");
+    Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::domain::models::api_token::ApiToken;
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// A newly created token. `token` is the only time the secret is available.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CreatedApiToken {
+    pub token: String,
+    pub summary: ApiToken,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::auth_log::{event_type, status};
+    use crate::domain::models::permission::Permission;
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{auth_cookie_session, record_auth_event, ClientInfo};
+    use crate::functions::authz::load_permissions;
+    use crate::functions::con;
+    use crate::functions::csrf::verify_csrf;
+    use chrono::Utc;
+    use leptos_spin::RequestParts;
+    use spin_sdk::pg::Connection;
+    use std::sync::Arc;
+
+    /// Longest lifetime a token may be created with
+    const MAX_TOKEN_DAYS: i64 = 365;
+    const DEFAULT_TOKEN_DAYS: i64 = 90;
+
+    /// The token from an `Authorization: Bearer` header, if the request carries one
+    pub fn bearer_token(req_parts: &RequestParts) -> Option<String> {
+        req_parts
+            .headers()
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case("authorization"))
+            .and_then(|(_, v)| parse_bearer(&String::from_utf8_lossy(v)))
+    }
+
+    /// The token in an `Authorization` header value using the Bearer scheme
+    fn parse_bearer(value: &str) -> Option<String> {
+        let (scheme, token) = value.trim().split_once(' ')?;
+        let token = token.trim();
+        (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token.to_string())
+    }
+
+    /// Resolve the owner of a bearer token. Every use is written to the auth
+    /// log, successful or not, as the record of what each token did.
+    pub async fn auth_api_token(req_parts: &RequestParts, token: &str, con: &Arc<Connection>) -> Result<(User, ApiToken), LexodusAppError> {
+        let client = ClientInfo::from_request(req_parts);
+        let Some(api_token) = ApiToken::authenticate(token, client.ip_address.clone(), con).await? else {
+            record_auth_event(client.event(None, event_type::API_TOKEN_AUTH, status::FAILURE, Some("unknown, expired or revoked token")), con).await;
+            return Err(LexodusAppError::AuthError);
+        };
+        let user = match User::get(api_token.user_id, con).await? {
+            Some(user) if !user.is_locked(Utc::now().timestamp()) => user,
+            _ => {
+                record_auth_event(client.event(Some(api_token.user_id), event_type::API_TOKEN_AUTH, status::FAILURE, Some("account locked")), con).await;
+                return Err(LexodusAppError::AuthError);
+            }
+        };
+        record_auth_event(client.event(Some(user.id), event_type::API_TOKEN_AUTH, status::SUCCESS, None), con).await;
+        Ok((user, api_token))
+    }
+
+    /// Does the token's scope include the permission. The owner's role is checked separately.
+    pub fn token_allows(api_token: &ApiToken, permission: Permission) -> bool {
+        api_token
+            .scopes
+            .iter()
+            .filter_map(|scope| Permission::parse(scope))
+            .any(|scope| scope == permission)
+    }
+
+    /// The permissions the user's role grants, as scope names
+    async fn grantable_scopes(user: &User, con: &Arc<Connection>) -> Result<Vec<String>, LexodusAppError> {
+        let granted = load_permissions(user.role_id, con).await?;
+        Ok(Permission::ALL
+            .into_iter()
+            .filter(|p| granted.iter().any(|(resource, action)| p.matches(resource, action)))
+            .map(|p| p.to_string())
+            .collect())
+    }
+
+    async fn caller() -> Result<(RequestParts, User, Arc<Connection>), LexodusAppError> {
+        let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+        let con = con()?;
+        let user = auth_cookie_session(&req, &con)
+            .await
+            .map_err(|_| LexodusAppError::Unauthorized)?;
+        Ok((req, user, con))
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(ListApiTokenScopes, "/api")]
+/// The scopes the caller may put on a new token
+pub async fn list_api_token_scopes() -> Result<Vec<String>, ServerFnError> {
+    let (_, user, con) = caller().await?;
+    Ok(grantable_scopes(&user, &con).await?)
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(ListApiTokens, "/api")]
+/// The caller's tokens, including revoked and expired ones
+pub async fn list_api_tokens() -> Result<Vec<ApiToken>, ServerFnError> {
+    let (_, user, con) = caller().await?;
+    Ok(ApiToken::list_for_user(user.id, &con).await?)
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(CreateApiToken, "/api")]
+/// Create a token limited to `scopes`, each of which the caller's role must grant
+pub async fn create_api_token(
+    csrf_token: String,
+    name: String,
+    expires_in_days: Option<i64>,
+    scopes: Option<Vec<String>>,
+) -> Result<CreatedApiToken, ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let (req, user, con) = caller().await?;
+
+    let name = name.trim().to_string();
+    if name.is_empty() || name.len() > 100 {
+        return Err(LexodusAppError::BadRequest("Give the token a name of up to 100 characters.".to_string()).into());
+    }
+    let days = expires_in_days.unwrap_or(DEFAULT_TOKEN_DAYS);
+    if !(1..=MAX_TOKEN_DAYS).contains(&days) {
+        return Err(LexodusAppError::BadRequest(format!("Tokens can last between 1 and {MAX_TOKEN_DAYS} days.")).into());
+    }
+    let mut scopes: Vec<String> = scopes
+        .unwrap_or_default()
+        .iter()
+        .map(|s| s.trim().to_uppercase())
+        .collect();
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(LexodusAppError::BadRequest("Choose at least one scope.".to_string()).into());
+    }
+    let grantable = grantable_scopes(&user, &con).await?;
+    if let Some(denied) = scopes.iter().find(|s| !grantable.contains(s)) {
+        return Err(LexodusAppError::Forbidden(denied.clone()).into());
+    }
+
+    let (id, token) = ApiToken::issue(user.id, &name, &scopes, days * 24 * 60 * 60, &con).await?;
+    let client = ClientInfo::from_request(&req);
+    record_auth_event(client.event(Some(user.id), event_type::API_TOKEN_CREATE, status::SUCCESS, None), &con).await;
+    tracing::info!("User {} created API token {id}", user.id);
+
+    let summary = ApiToken::list_for_user(user.id, &con)
+        .await?
+        .into_iter()
+        .find(|t| t.id == id)
+        .ok_or_else(|| LexodusAppError::DBError("New token was not found".to_string()))?;
+    Ok(CreatedApiToken { token, summary })
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(RevokeApiToken, "/api")]
+/// Revoke one of the caller's tokens. It stops working immediately.
+pub async fn revoke_api_token(csrf_token: String, id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let (req, user, con) = caller().await?;
+    if !ApiToken::revoke(id, user.id, &con).await? {
+        return Err(LexodusAppError::NotFound.into());
+    }
+    let client = ClientInfo::from_request(&req);
+    record_auth_event(client.event(Some(user.id), event_type::API_TOKEN_REVOKE, status::SUCCESS, None), &con).await;
+    tracing::info!("User {} revoked API token {id}", user.id);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn token(scopes: &[&str]) -> ApiToken {
+        ApiToken {
+            id: 1,
+            user_id: 1,
+            name: "docket sync".to_string(),
+            token_prefix: "lx_abcd".to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            expires_at: 0,
+            expires_at_pretty: String::new(),
+            last_used_at: None,
+            last_used_at_pretty: None,
+            revoked: false,
+            expired: false,
+            created_at: 0,
+            created_at_pretty: String::new(),
+        }
+    }
+
+    #[test]
+    fn bearer_scheme_is_case_insensitive() {
+        assert_eq!(parse_bearer("Bearer abc123").as_deref(), Some("abc123"));
+        assert_eq!(parse_bearer("bearer  abc123 ").as_deref(), Some("abc123"));
+    }
+
+    #[test]
+    fn other_schemes_and_empty_tokens_are_ignored() {
+        assert_eq!(parse_bearer("Basic dXNlcjpwYXNz"), None);
+        assert_eq!(parse_bearer("Bearer"), None);
+        assert_eq!(parse_bearer("Bearer   "), None);
+        assert_eq!(parse_bearer(""), None);
+    }
+
+    #[test]
+    fn token_reaches_only_its_scopes() {
+        let read_only = token(&["CASE:READ", "PARTY:READ"]);
+        assert!(token_allows(&read_only, Permission::CASE_READ));
+        assert!(token_allows(&read_only, Permission::PARTY_READ));
+        assert!(!token_allows(&read_only, Permission::CASE_UPDATE));
+    }
+
+    #[test]
+    fn unknown_scopes_grant_nothing() {
+        let odd = token(&["CASE:*", "case", "ADMIN:ALL"]);
+        assert!(Permission::ALL.into_iter().all(|p| !token_allows(&odd, p)));
+    }
+}
@@ -1,6 +1,7 @@
 use std::borrow::Cow;
 
-use crate::domain::models::user::User;
+use crate::domain::models::invitation::InvitationGrant;
+use crate::domain::models::user::{NewUser, User};
 use cfg_if::cfg_if;
 use leptos::server;
 use leptos::{expect_context, use_context, ServerFnError};
@@ -12,19 +13,42 @@ if #[cfg(feature = "ssr")] {
         Argon2,
     };
     use crate::functions::{con};
+    use crate::functions::account_recovery::issue_expired_password_token;
+    use crate::functions::api_tokens::{auth_api_token, bearer_token};
+    use crate::domain::models::api_token::ApiToken;
+    use crate::domain::models::invitation::Invitation;
+    use crate::domain::models::user::role_requires_two_factor;
+    use crate::functions::csrf::{verify_csrf, verify_csrf_allow_impersonation};
+    use crate::functions::password_policy::password_policy;
     use crate::errors::LexodusAppError;
-    use rand_core::OsRng;
-    use spin_sdk::sqlite::{Connection, Value::{Text, Integer}};
-    use std::sync::Arc;
+    use rand_core::{OsRng, RngCore};
+    use spin_sdk::pg::Connection;
+    use std::sync::{Arc, OnceLock};
     use tracing::info;
     use async_session::{Session, SessionStore};
-    use crate::session::{SqliteStore};
+    use crate::session_backend::SessionBackend;
     use leptos_spin::ResponseOptions;
     use cookie::Cookie;
     use leptos_spin::RequestParts;
-    /// Hash Argon2 password
+    use crate::domain::models::auth_log::{event_type, status, AuthEvent, NewAuthEvent};
+    use chrono::Utc;
+    use std::net::{IpAddr, SocketAddr};
+    use spin_sdk::variables;
+
+    use crate::functions::sessions::{
+        cookie_max_age, is_remembered, new_user_session, past_absolute_timeout, renew, session_config,
+        IDP_MFA_KEY, IMPERSONATED_USER_KEY, USER_ID_KEY,
+    };
+
+    /// Session key holding the user id while the second login step is outstanding
+    pub const PENDING_2FA_KEY: &str = "pending_2fa_user_id";
+    /// Session key carrying the "remember me" choice across the second login step
+    pub const PENDING_REMEMBER_KEY: &str = "pending_2fa_remember";
+    /// Session key marking a second login step that follows SSO rather than a password
+    pub const PENDING_SSO_KEY: &str = "pending_2fa_sso";
+    /// Hash Argon2 password with the configured cost
     pub fn hash_password(password: &[u8]) -> Result<String, LexodusAppError> {
-        let argon2 = Argon2::default();
+        let argon2 = password_policy().hasher()?;
         let salt = SaltString::generate(&mut OsRng);
         let password_hash = argon2.hash_password(password, &salt)?.to_string();
         Ok(password_hash)
@@ -37,19 +61,185 @@ if #[cfg(feature = "ssr")] {
         Ok(argon2.verify_password(password.as_bytes(), &parsed_hash)?)
     }
 
+    /// A hash of a random password at the configured cost, verified against when
+    /// the username is unknown. Made once per instance, on first use.
+    fn dummy_password_hash() -> Result<&'static str, LexodusAppError> {
+        static DUMMY: OnceLock<String> = OnceLock::new();
+        if let Some(hash) = DUMMY.get() {
+            return Ok(hash);
+        }
+        let mut password = [0u8; 32];
+        OsRng.fill_bytes(&mut password);
+        let hash = hash_password(&password)?;
+        Ok(DUMMY.get_or_init(|| hash))
+    }
+
+    /// Consecutive failures before an account is locked
+    pub const LOCKOUT_THRESHOLD: i64 = 5;
+    /// First lockout period; doubles with each further failure
+    pub const LOCKOUT_BASE_SECS: i64 = 60;
+    /// Longest lockout period
+    pub const LOCKOUT_MAX_SECS: i64 = 24 * 60 * 60;
+
+    /// Proxies allowed to report the client address in `X-Forwarded-For`, from
+    /// the comma separated `trusted_proxies` variable. Empty unless configured.
+    fn trusted_proxies() -> Vec<IpAddr> {
+        variables::get("trusted_proxies")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|ip| ip.trim().parse().ok())
+            .collect()
+    }
+
+    fn parse_ip(value: &str) -> Option<IpAddr> {
+        let value = value.trim();
+        value
+            .parse::<IpAddr>()
+            .or_else(|_| value.parse::<SocketAddr>().map(|a| a.ip()))
+            .ok()
+    }
+
+    /// The client's address given the peer Spin saw and any `X-Forwarded-For`
+    /// chain. Hops are read from the right, skipping trusted proxies, so the
+    /// first untrusted hop is the one our own proxy appended. Anything a client
+    /// put further left is ignored.
+    pub fn client_ip(peer: Option<&str>, forwarded_for: Option<&str>, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+        let peer = peer.and_then(parse_ip);
+        if !peer.is_some_and(|ip| trusted_proxies.contains(&ip)) {
+            return peer;
+        }
+        let mut client = peer;
+        for hop in forwarded_for.unwrap_or_default().rsplit(',').map(parse_ip) {
+            match hop {
+                Some(ip) if trusted_proxies.contains(&ip) => client = Some(ip),
+                Some(ip) => return Some(ip),
+                // An unreadable hop ends the chain we can vouch for
+                None => break,
+            }
+        }
+        client
+    }
+
+    /// Where a request came from, as recorded in `authentication_logs`
+    #[derive(Clone, Debug, Default)]
+    pub struct ClientInfo {
+        pub ip_address: Option<String>,
+        pub user_agent: Option<String>,
+    }
+
+    impl ClientInfo {
+        /// Record the address Spin saw the request come from. `X-Forwarded-For`
+        /// is only believed when that address is a proxy listed in the
+        /// `trusted_proxies` variable, since any client can send the header.
+        pub fn from_request(req_parts: &RequestParts) -> Self {
+            let header = |name: &str| {
+                req_parts
+                    .headers()
+                    .iter()
+                    .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                    .map(|(_, v)| String::from_utf8_lossy(v).to_string())
+            };
+            let ip_address = client_ip(
+                header("spin-client-addr").as_deref(),
+                header("x-forwarded-for").as_deref(),
+                &trusted_proxies(),
+            )
+            .map(|ip| ip.to_string());
+            ClientInfo {
+                ip_address,
+                user_agent: header("user-agent"),
+            }
+        }
+
+        /// Build the log entry for this client
+        pub fn event(&self, user_id: Option<i64>, event_type: &'static str, status: &'static str, failure_reason: Option<&str>) -> NewAuthEvent {
+            NewAuthEvent {
+                user_id,
+                event_type,
+                status,
+                ip_address: self.ip_address.clone(),
+                user_agent: self.user_agent.clone(),
+                failure_reason: failure_reason.map(str::to_string),
+                attempted_username: None,
+            }
+        }
+    }
+
+    /// Write an auth log entry. Logging failures are reported but never block a login.
+    pub async fn record_auth_event(event: NewAuthEvent, con: &Arc<Connection>) {
+        if let Err(e) = AuthEvent::record(event, con).await {
+            tracing::error!("Failed to write authentication log: {e}");
+        }
+    }
+
+    /// Count a failed password or second-factor check against the account
+    pub async fn record_auth_failure(user: &User, event_type: &'static str, client: &ClientInfo, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let locked_until = User::record_failed_login(user.id, LOCKOUT_THRESHOLD, LOCKOUT_BASE_SECS, LOCKOUT_MAX_SECS, con).await?;
+        let reason = match locked_until {
+            Some(until) if until > Utc::now().timestamp() => {
+                tracing::warn!("Locked user {} until {until} after repeated failures", user.id);
+                "invalid credentials; account locked"
+            }
+            _ => "invalid credentials",
+        };
+        record_auth_event(client.event(Some(user.id), event_type, status::FAILURE, Some(reason)), con).await;
+        Ok(())
+    }
+
+    /// Reset the failure counter and log the successful login
+    pub async fn record_login_success(user: &User, client: &ClientInfo, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        User::record_successful_login(user.id, con).await?;
+        record_auth_event(client.event(Some(user.id), event_type::LOGIN, status::SUCCESS, None), con).await;
+        Ok(())
+    }
+
     /// Verify the user is who they say they are
-    pub async fn auth_user(name: &str, password: &str, con: &Arc<Connection>) -> Result<User, LexodusAppError>{
+    pub async fn auth_user(name: &str, password: &str, client: &ClientInfo, con: &Arc<Connection>) -> Result<User, LexodusAppError>{
         // Does the user exist
         let Ok(Some(user)) = User::get_from_username(name, con).await else{
+            // Pay the same Argon2 cost as a real check, so response time does not
+            // reveal which usernames exist
+            let _ = verify_password(password, dummy_password_hash()?);
+            let mut event = client.event(None, event_type::LOGIN, status::FAILURE, Some("unknown user"));
+            event.attempted_username = Some(name.to_string());
+            record_auth_event(event, con).await;
             return Err(LexodusAppError::AuthError);
         };
 
+        if user.is_locked(Utc::now().timestamp()) {
+            record_auth_event(client.event(Some(user.id), event_type::LOGIN, status::FAILURE, Some("account locked")), con).await;
+            return Err(LexodusAppError::AccountLocked);
+        }
+
         // Check that password is correct
         match verify_password(password, &user.password){
-            Ok(_) => Ok(user),
-            Err(e) => {println!("Verify Failed: {e}"); Err(LexodusAppError::AuthError)},
+            Ok(_) => {
+                upgrade_password_hash(&user, password, con).await;
+                Ok(user)
+            },
+            Err(e) => {
+                info!("Password verification failed for user {}: {e}", user.id);
+                record_auth_failure(&user, event_type::LOGIN, client, con).await?;
+                Err(LexodusAppError::AuthError)
+            },
         }
     }
+    /// Rehash with the current Argon2 cost if the stored hash is weaker. The plain
+    /// password is only available at login, so this is the one place it can happen.
+    async fn upgrade_password_hash(user: &User, password: &str, con: &Arc<Connection>) {
+        if !password_policy().needs_rehash(&user.password) {
+            return;
+        }
+        let result = match hash_password(password.as_bytes()) {
+            Ok(hash) => User::rehash_password(user.id, &hash, con).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => info!("Upgraded password hash for user {}", user.id),
+            Err(e) => tracing::error!("Failed to upgrade password hash for user {}: {e}", user.id),
+        }
+    }
+
     pub fn get_session_cookie_value(req_parts: &RequestParts)-> Result<Option<String>, LexodusAppError>{
     let cookies: Vec<(&String, Cow<'_, str>)> = req_parts
         .headers()
@@ -60,7 +250,7 @@ if #[cfg(feature = "ssr")] {
     let cookie_string = cookies.first().map(|(k, v)| v);
     let cookie_jar = match cookie_string {
         Some(c) => Cookie::split_parse(c.clone()),
-        None => return Err(LexodusAppError::AuthError),
+        None => return Ok(None),
     };
 
     let mut session_val = None;
@@ -75,41 +265,233 @@ if #[cfg(feature = "ssr")] {
     Ok(session_val)
     }
 
+    /// How the caller of a request proved who they are
+    #[derive(Clone, Debug)]
+    pub enum Credential {
+        /// A session cookie. `idp_mfa` is set when SSO sign-in included the
+        /// identity provider's multi-factor authentication.
+        Session { idp_mfa: bool },
+        /// A personal API token, limited to its scopes
+        ApiToken(ApiToken),
+        /// A session whose owner is acting as the resolved user
+        Impersonation,
+    }
+
+    /// Resolve the caller of a request. A request with an `Authorization: Bearer`
+    /// header is judged on that token alone and never falls back to the cookie.
+    pub async fn authenticate_request(req_parts: &RequestParts, con: &Arc<Connection>)-> Result<(User, Credential), LexodusAppError>{
+        match bearer_token(req_parts) {
+            Some(token) => {
+                let (user, api_token) = auth_api_token(req_parts, &token, con).await?;
+                Ok((user, Credential::ApiToken(api_token)))
+            }
+            None => match cookie_identity(req_parts, con).await? {
+                (user, None, session) => {
+                    let idp_mfa = session.get::<bool>(IDP_MFA_KEY).unwrap_or(false);
+                    Ok((user, Credential::Session { idp_mfa }))
+                }
+                (user, Some(_), _) => Ok((user, Credential::Impersonation)),
+            },
+        }
+    }
+
+    /// Resolve the logged in user from the session cookie or a bearer token
     pub async fn auth_session(req_parts: &RequestParts, con: &Arc<Connection>)-> Result<User, LexodusAppError>{
+        Ok(authenticate_request(req_parts, con).await?.0)
+    }
+
+    /// Resolve the logged in user from the session cookie only. Enforces the absolute
+    /// timeout and slides the idle expiry forward on activity. Account management
+    /// uses this so an API token cannot mint further credentials or change the password.
+    pub async fn auth_cookie_session(req_parts: &RequestParts, con: &Arc<Connection>)-> Result<User, LexodusAppError>{
+        Ok(auth_cookie_identity(req_parts, con).await?.0)
+    }
 
-    let store = expect_context::<SqliteStore>();
+    /// Like `auth_cookie_session`, but also returns the real user when the session's
+    /// owner is acting as someone else. The first user is always the one acted as.
+    pub async fn auth_cookie_identity(req_parts: &RequestParts, con: &Arc<Connection>)-> Result<(User, Option<User>), LexodusAppError>{
+        let (user, real_user, _) = cookie_identity(req_parts, con).await?;
+        Ok((user, real_user))
+    }
+
+    /// `auth_cookie_identity`, also returning the session that authenticated the request
+    async fn cookie_identity(req_parts: &RequestParts, con: &Arc<Connection>)-> Result<(User, Option<User>, Session), LexodusAppError>{
+    if bearer_token(req_parts).is_some() {
+        return Err(LexodusAppError::AuthError);
+    }
+
+    let store = expect_context::<SessionBackend>();
     let session_val = match get_session_cookie_value(req_parts)?{
     Some(sv) => sv,
     None => return Err(LexodusAppError::AuthError),
     };
 
-    let Some(session) = store.load_session(session_val).await? else{
-        return Err(LexodusAppError::InternalServerError);
+    // Unknown or idle-expired sessions are not returned by the store
+    let Some(mut session) = store.load_session(session_val).await? else{
+        return Err(LexodusAppError::AuthError);
     };
-    let Some(user_id) = session.get("user_id") else{
+    let Some(user_id) = session.get::<i64>(USER_ID_KEY) else{
         return Err(LexodusAppError::AuthError);
     };
+    let acting_as = session.get::<i64>(IMPERSONATED_USER_KEY);
+
+    let config = session_config();
+    let now = Utc::now().timestamp();
+    if past_absolute_timeout(&session, now, &config) {
+        store.destroy_session(session).await?;
+        return Err(LexodusAppError::AuthError);
+    }
+    if renew(&mut session, now, &config)? {
+        store.store_session(session.clone()).await?;
+    }
 
     let user = match User::get(user_id, con).await?{
     Some(u) => u,
     None => return Err(LexodusAppError::AuthError)
     };
-    Ok(user)
+    let Some(acting_as) = acting_as else{
+        return Ok((user, None, session));
+    };
+    match User::get(acting_as, con).await?{
+    Some(effective) => Ok((effective, Some(user), session)),
+    None => Err(LexodusAppError::AuthError)
+    }
+    }
+
+    /// Load the Session referenced by the request's cookie, if any
+    pub async fn current_session(req_parts: &RequestParts)-> Result<Option<Session>, LexodusAppError>{
+        let store = expect_context::<SessionBackend>();
+        let Some(session_val) = get_session_cookie_value(req_parts)? else{
+            return Ok(None);
+        };
+        Ok(store.load_session(session_val).await?)
+    }
+
+    /// Persist changes made to an already issued Session
+    pub async fn save_session(session: Session)-> Result<(), LexodusAppError>{
+        let store = expect_context::<SessionBackend>();
+        store.store_session(session).await?;
+        Ok(())
+    }
+
+    /// Store a freshly built Session and return its cookie value
+    pub async fn store_new_session(session: Session)-> Result<String, LexodusAppError>{
+        let session_store = expect_context::<SessionBackend>();
+        session_store
+            .store_session(session)
+            .await?
+            .ok_or_else(|| LexodusAppError::SessionError("New session has no cookie value".to_string()))
+    }
+
+    /// Start an authenticated session and send its cookie. Any session the request
+    /// already carried is destroyed so the id always changes on login.
+    pub async fn start_session(req_parts: &RequestParts, user_id: i64, remember: bool, client: &ClientInfo)-> Result<(), LexodusAppError>{
+        issue_session(req_parts, user_id, remember, false, client).await
+    }
+
+    /// `start_session` for an SSO sign-in, recording whether the identity provider
+    /// performed multi-factor authentication
+    pub async fn start_sso_session(req_parts: &RequestParts, user_id: i64, idp_mfa: bool, client: &ClientInfo)-> Result<(), LexodusAppError>{
+        issue_session(req_parts, user_id, false, idp_mfa, client).await
+    }
+
+    async fn issue_session(req_parts: &RequestParts, user_id: i64, remember: bool, idp_mfa: bool, client: &ClientInfo)-> Result<(), LexodusAppError>{
+        if let Some(old) = get_session_cookie_value(req_parts).ok().flatten() {
+            logout_session(&old).await?;
+        }
+        let config = session_config();
+        let mut session = new_user_session(user_id, remember, client, &config)?;
+        if idp_mfa {
+            session.insert(IDP_MFA_KEY, true)?;
+        }
+        let cookie_value = store_new_session(session).await?;
+        set_session_cookie(&cookie_value, cookie_max_age(remember, &config));
+        Ok(())
     }
 
-    /// Create a new Session and store User id in it
-    pub async fn create_session(user_id: i64)-> Result<String, LexodusAppError>{
+    /// Reissue the caller's session under a new id, keeping its "remember me" choice
+    /// and any multi-factor sign-in at the identity provider. Used whenever the
+    /// user's privileges or authentication factors change.
+    pub async fn rotate_session(req_parts: &RequestParts, user_id: i64, client: &ClientInfo)-> Result<(), LexodusAppError>{
+        let current = current_session(req_parts).await?;
+        let remember = current.as_ref().is_some_and(is_remembered);
+        let idp_mfa = current.and_then(|s| s.get::<bool>(IDP_MFA_KEY)).unwrap_or(false);
+        issue_session(req_parts, user_id, remember, idp_mfa, client).await
+    }
+
+    /// Start a Session for a user who has passed the password check but still
+    /// owes a second factor, and send its cookie. `auth_session` does not accept it
+    /// until it is promoted. As in `start_session`, any session the request already
+    /// carried is destroyed, so no pre-login id survives a login step.
+    pub async fn start_pending_two_factor_session(req_parts: &RequestParts, user_id: i64, remember: bool, via_sso: bool)-> Result<(), LexodusAppError>{
+        if let Some(old) = get_session_cookie_value(req_parts).ok().flatten() {
+            logout_session(&old).await?;
+        }
         let mut session = Session::new();
-        session.insert("user_id", user_id)?;
+        session.insert(PENDING_2FA_KEY, user_id)?;
+        session.insert(PENDING_REMEMBER_KEY, remember)?;
+        if via_sso {
+            session.insert(PENDING_SSO_KEY, true)?;
+        }
+        session.expire_in(std::time::Duration::from_secs(session_config().pending_secs as u64));
+        let cookie_value = store_new_session(session).await?;
+        set_session_cookie(&cookie_value, None);
+        Ok(())
+    }
+
+    /// Hand the session cookie to the browser. Without `max_age` it is a browser-session cookie.
+    pub fn set_session_cookie(cookie_value: &str, max_age: Option<i64>){
+        let secure = if session_config().secure_cookie { " Secure;" } else { "" };
+        let max_age = max_age.map(|secs| format!(" Max-Age={secs};")).unwrap_or_default();
+        let res_options = expect_context::<ResponseOptions>();
+        res_options.insert_header(
+            "Set-Cookie",
+            format!("Lexodus_session={cookie_value}; Path=/; HttpOnly;{secure} SameSite=Strict;{max_age}").as_bytes(),
+        );
+    }
 
-        let session_store = expect_context::<SqliteStore>();
-        let cookie_value = session_store.store_session(session).await?.unwrap();
-        Ok(cookie_value)
+    /// Tell the browser to drop the session cookie
+    pub fn expire_session_cookie(){
+        let res_options = expect_context::<ResponseOptions>();
+        res_options.insert_header(
+            "Set-Cookie",
+            "Lexodus_session=no; Path=/; HttpOnly; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:01 GMT;",
+        );
+    }
+
+    /// Send a fully authenticated user whose password has expired to choose a
+    /// new one instead of logging them in. Returns whether they were sent.
+    pub async fn redirect_if_password_expired(user: &User, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        if !password_policy().is_expired(user.password_changed_at, Utc::now().timestamp()) {
+            return Ok(false);
+        }
+        let token = issue_expired_password_token(user.id, con).await?;
+        leptos_spin::redirect(&format!("/reset-password?token={token}&expired=true"));
+        Ok(true)
+    }
+
+    /// Redeem the invitation and create its account in one transaction, so a
+    /// failed insert leaves the invitation open. `None` if it was already used.
+    async fn redeem_invitation(token: &str, grant: &InvitationGrant, new_user: NewUser, con: &Arc<Connection>) -> Result<Option<i64>, LexodusAppError> {
+        con.execute("BEGIN", &[])?;
+        let result = async {
+            if Invitation::accept(token, con).await?.map(|g| g.id) != Some(grant.id) {
+                return Ok(None);
+            }
+            let user_id = User::create(new_user, con).await?;
+            Invitation::set_accepted_user(grant.id, user_id, con).await?;
+            // The invitation link was mailed to this address, which proves it
+            User::mark_email_verified(user_id, con).await?;
+            Ok(Some(user_id))
+        }
+        .await;
+        con.execute(if matches!(result, Ok(Some(_))) { "COMMIT" } else { "ROLLBACK" }, &[])?;
+        result
     }
 
     /// Destroy the Session if it exists
     pub async fn logout_session(cookie_value: &str)-> Result<(), LexodusAppError>{
-        let store = expect_context::<SqliteStore>();
+        let store = expect_context::<SessionBackend>();
         let session = match store.load_session(cookie_value.to_string()).await?{
             Some(s) =>s,
             None => return Ok(())
@@ -120,38 +502,64 @@ if #[cfg(feature = "ssr")] {
 }
 }
 
-#[tracing::instrument(level = "info", fields(error), ret, err)]
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token, password), ret, err)]
 #[server(Login, "/api")]
 pub async fn login(
+    csrf_token: String,
     username: String,
     password: String,
     remember: Option<String>,
 ) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
     let Some(req) = use_context::<leptos_spin::RequestParts>() else {
         return Ok(());
     };
     let con = con()?;
-    let user = auth_user(&username, &password, &con).await?;
-    let session_cookie = create_session(user.id).await?;
+    let client = ClientInfo::from_request(&req);
+    let user = auth_user(&username, &password, &client, &con).await?;
+    let remember = remember.is_some();
+
+    // The second factor comes before anything that lets the password be changed
+    if user.two_factor_enabled {
+        start_pending_two_factor_session(&req, user.id, remember, false).await?;
+        leptos_spin::redirect("/login/2fa");
+        return Ok(());
+    }
+    if redirect_if_password_expired(&user, &con).await? {
+        return Ok(());
+    }
 
-    let res_options = expect_context::<ResponseOptions>();
-    res_options.insert_header(
-        "Set-Cookie",
-        format!("Lexodus_session={session_cookie};Path=/;SameSite=Strict;").as_bytes(),
-    );
+    record_login_success(&user, &client, &con).await?;
+    start_session(&req, user.id, remember, &client).await?;
+    if role_requires_two_factor(&user.role) {
+        leptos_spin::redirect("/account/security");
+    }
 
     Ok(())
 }
 
-#[tracing::instrument(level = "info", fields(error), ret, err)]
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(GetInvitation, "/api")]
+/// What an invitation grants, so the signup form can show it before it is redeemed
+pub async fn get_invitation(invitation: String) -> Result<Option<InvitationGrant>, ServerFnError> {
+    let con = con()?;
+    Ok(Invitation::peek(&invitation, &con).await?)
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
 #[server(Signup, "/api")]
+/// Create an account from a staff invitation. The email address and role come
+/// from the invitation; attorneys without one use `RequestAttorneyRegistration`.
 pub async fn signup(
+    csrf_token: String,
+    invitation: String,
     username: String,
-    display_name: String,
+    full_name: String,
     password: String,
     password_confirmation: String,
     remember: Option<String>,
 ) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
     let Some(req) = use_context::<leptos_spin::RequestParts>() else {
         return Ok(());
     };
@@ -161,40 +569,55 @@ pub async fn signup(
             "Passwords did not match.".to_string(),
         ));
     }
-    // Don't want anyone signing up...but us?
-    // if username != "Lexodus" {
-    //     println!("AH AH AH, YOU DIDN'T SAY THE MAGIC WORD");
-    //     leptos_spin::redirect("/");
-    //     return Ok(());
-    // }
-
-    let password_hashed = hash_password(password.as_bytes()).unwrap();
-    con.execute(
-        "INSERT INTO users (username, display_name, password) VALUES (?,?, ?)",
-        &[
-            Text(username.clone()),
-            Text(display_name),
-            Text(password_hashed),
-        ],
-    )
-    .map_err(|e| ServerFnError::<LexodusAppError>::ServerError(e.to_string()))?;
-
-    let user = match User::get_from_username(&username, &con)
-        .await
-        .map_err(|e| ServerFnError::new(e.to_string()))?
-    {
-        Some(u) => u,
-        None => return Err(LexodusAppError::AuthError.into()),
-    };
-
-    leptos_spin::redirect("/cases");
+    let invalid = || LexodusAppError::BadRequest("This invitation is invalid, has expired or was already used.".to_string());
+    // Validate before redeeming so a rejected form does not use up the invitation
+    let Some(grant) = Invitation::peek(&invitation, &con).await? else {
+        return Err(invalid().into());
+    };
+    password_policy().validate(&password, &username, &grant.email).await?;
+    if User::get_from_username(&username, &con).await?.is_some() {
+        return Err(LexodusAppError::BadRequest("That username is taken.".to_string()).into());
+    }
+    if User::get_from_email(&grant.email, &con).await?.is_some() {
+        return Err(LexodusAppError::BadRequest("An account already uses this email address.".to_string()).into());
+    }
+
+    let password_hashed = hash_password(password.as_bytes())?;
+    let new_user = NewUser {
+        username,
+        email: grant.email.clone(),
+        full_name,
+        password_hash: password_hashed,
+        role_id: grant.role_id,
+        bar_number: None,
+        home_court_id: None,
+    };
+    let Some(user_id) = redeem_invitation(&invitation, &grant, new_user, &con).await? else {
+        return Err(invalid().into());
+    };
+
+    let Some(user) = User::get(user_id, &con).await? else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    tracing::info!("User {user_id} joined as {} through invitation {}", grant.role, grant.id);
+
+    let client = ClientInfo::from_request(&req);
+    record_login_success(&user, &client, &con).await?;
+    start_session(&req, user.id, remember.is_some(), &client).await?;
+    // Invited judges and clerks set up 2FA before anything else
+    if role_requires_two_factor(&user.role) {
+        leptos_spin::redirect("/account/security");
+    } else {
+        leptos_spin::redirect("/cases");
+    }
 
     Ok(())
 }
 
 #[tracing::instrument(level = "info", fields(error), ret, err)]
 #[server(Logout, "/api")]
-pub async fn logout() -> Result<(), ServerFnError> {
+pub async fn logout(csrf_token: String) -> Result<(), ServerFnError> {
+    verify_csrf_allow_impersonation(&csrf_token).await?;
     println!("LOGGING OUT");
     let Some(req) = use_context::<leptos_spin::RequestParts>() else {
         return Ok(());
@@ -203,20 +626,79 @@ pub async fn logout() -> Result<(), ServerFnError> {
     let Some(session) = get_session_cookie_value(&req)? else {
         return Ok(());
     };
+    // Logged under the real user when they were acting as someone else
+    if let Ok((user, real_user)) = auth_cookie_identity(&req, &con).await {
+        let user = real_user.unwrap_or(user);
+        let client = ClientInfo::from_request(&req);
+        record_auth_event(client.event(Some(user.id), event_type::LOGOUT, status::SUCCESS, None), &con).await;
+    }
     logout_session(&session).await?;
 
     // Delete session cookie by expiring it
-    let res_parts = expect_context::<ResponseOptions>();
-    res_parts.insert_header(
-        "Set-Cookie",
-        "Lexodus_session=no;Path=/; Expires=Thu, 01 Jan 1970 00:00:01 GMT;",
-    );
-
-    res_parts.insert_header(
-        "Set-Cookie",
-        "sessionid=no;Path=/; Expires=Thu, 01 Jan 1970 00:00:01 GMT;",
-    );
+    expire_session_cookie();
     leptos_spin::redirect("/");
 
     Ok(())
 }
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_without_a_trusted_proxy() {
+        let client = client_ip(Some("203.0.113.7:51234"), Some("10.9.9.9"), &[]);
+        assert_eq!(client, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn forwarded_for_is_ignored_from_an_untrusted_peer() {
+        let client = client_ip(Some("203.0.113.7"), Some("1.2.3.4"), &[ip("10.0.0.1")]);
+        assert_eq!(client, Some(ip("203.0.113.7")));
+    }
+
+    #[test]
+    fn trusted_proxy_reports_the_hop_it_appended() {
+        // The client claimed 1.2.3.4; our proxy appended the address it saw
+        let client = client_ip(Some("10.0.0.1:443"), Some("1.2.3.4, 198.51.100.20"), &[ip("10.0.0.1")]);
+        assert_eq!(client, Some(ip("198.51.100.20")));
+    }
+
+    #[test]
+    fn chained_trusted_proxies_are_skipped() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        let client = client_ip(Some("10.0.0.1"), Some("1.2.3.4, 198.51.100.20, 10.0.0.2"), &trusted);
+        assert_eq!(client, Some(ip("198.51.100.20")));
+    }
+
+    #[test]
+    fn unreadable_hop_stops_at_the_last_trusted_address() {
+        let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+        let client = client_ip(Some("10.0.0.1"), Some("1.2.3.4, not-an-ip, 10.0.0.2"), &trusted);
+        assert_eq!(client, Some(ip("10.0.0.2")));
+        assert_eq!(client_ip(Some("10.0.0.1"), None, &trusted), Some(ip("10.0.0.1")));
+    }
+
+    #[test]
+    fn missing_or_garbled_peer_has_no_address() {
+        assert_eq!(client_ip(None, Some("1.2.3.4"), &[]), None);
+        assert_eq!(client_ip(Some("garbage"), Some("1.2.3.4"), &[]), None);
+    }
+
+    #[test]
+    fn ipv6_peers_are_read() {
+        assert_eq!(client_ip(Some("[2001:db8::1]:8080"), None, &[]), Some(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn unknown_users_are_checked_against_a_real_hash_at_the_policy_cost() {
+        let dummy = dummy_password_hash().unwrap();
+        assert_eq!(dummy, dummy_password_hash().unwrap());
+        assert!(!password_policy().needs_rehash(dummy));
+        assert!(verify_password("password", dummy).is_err());
+    }
+}
@@ -0,0 +1,222 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::permission::{Permission, RequiresPermission};
+    use crate::domain::models::user::{role_requires_two_factor, User};
+    use crate::errors::LexodusAppError;
+    use crate::functions::api_tokens::token_allows;
+    use crate::functions::auth::{authenticate_request, Credential};
+    use crate::functions::con;
+    use leptos::use_context;
+    use leptos_spin::{RequestParts, ResponseOptions};
+    use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+    use std::sync::Arc;
+
+    /// Load the effective `(resource_type, action_type)` pairs granted to a role
+    pub async fn load_permissions(role_id: i64, con: &Arc<Connection>) -> Result<Vec<(String, String)>, LexodusAppError> {
+        let rowset = con.query(
+            "SELECT p.resource_type, p.action_type
+             FROM role_permissions rp
+             JOIN permissions p ON p.id = rp.permission_id
+             WHERE rp.role_id = $1",
+            &[ParameterValue::Int64(role_id)],
+        )?;
+
+        Ok(rowset
+            .rows
+            .iter()
+            .filter_map(|row| match (&row[0], &row[1]) {
+                (DbValue::Str(resource), DbValue::Str(action)) => Some((resource.clone(), action.clone())),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Does the user's role grant the permission
+    pub async fn has_permission(user: &User, permission: Permission, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let granted = load_permissions(user.role_id, con).await?;
+        Ok(grants(&granted, permission))
+    }
+
+    /// Does any granted `(resource_type, action_type)` pair match the permission
+    pub fn grants(granted: &[(String, String)], permission: Permission) -> bool {
+        granted.iter().any(|(resource, action)| permission.matches(resource, action))
+    }
+
+    /// Allow a call only when the credential reaches the permission and the role grants it
+    pub fn decide(granted: &[(String, String)], in_scope: bool, permission: Permission) -> Result<(), LexodusAppError> {
+        if in_scope && grants(granted, permission) {
+            Ok(())
+        } else {
+            Err(LexodusAppError::Forbidden(permission.to_string()))
+        }
+    }
+
+    /// Resolve the caller from the session or API token and check that they hold `permission`.
+    /// On failure the response status is set to 401 or 403 before the error is returned.
+    pub async fn require_permission(permission: Permission) -> Result<User, LexodusAppError> {
+        let result = check_permission(permission).await;
+        if let Err(e) = &result {
+            tracing::warn!("Rejected call requiring {permission}: {e}");
+            if let Some(res) = use_context::<ResponseOptions>() {
+                res.set_status(e.status_code().as_u16());
+            }
+        }
+        result
+    }
+
+    /// Judges and clerks act only with a second factor: local 2FA, or MFA at the
+    /// identity provider they signed in through. An admin acting as one is
+    /// already past their own second factor.
+    pub fn check_second_factor(role: &str, two_factor_enabled: bool, credential: &Credential) -> Result<(), LexodusAppError> {
+        let satisfied = two_factor_enabled
+            || !role_requires_two_factor(role)
+            || matches!(credential, Credential::Impersonation | Credential::Session { idp_mfa: true });
+        if satisfied {
+            Ok(())
+        } else {
+            Err(LexodusAppError::TwoFactorRequired)
+        }
+    }
+
+    async fn check_permission(permission: Permission) -> Result<User, LexodusAppError> {
+        let Some(req) = use_context::<RequestParts>() else {
+            return Err(LexodusAppError::Unauthorized);
+        };
+        let con = con()?;
+        let (user, credential) = authenticate_request(&req, &con)
+            .await
+            .map_err(|_| LexodusAppError::Unauthorized)?;
+
+        let granted = load_permissions(user.role_id, &con).await?;
+        check_caller(&user, &credential, &granted, permission)?;
+        Ok(user)
+    }
+
+    /// Decide for a resolved caller whose role grants `granted`
+    pub fn check_caller(
+        user: &User,
+        credential: &Credential,
+        granted: &[(String, String)],
+        permission: Permission,
+    ) -> Result<(), LexodusAppError> {
+        check_second_factor(&user.role, user.two_factor_enabled, credential)?;
+
+        // A token acts with the intersection of its scopes and the owner's current role
+        let in_scope = match credential {
+            // Acting as another user grants exactly that user's permissions
+            Credential::Session { .. } | Credential::Impersonation => true,
+            Credential::ApiToken(api_token) => token_allows(api_token, permission),
+        };
+        decide(granted, in_scope, permission)
+    }
+
+    /// Authorize the current call against the permission declared by server function `T`
+    pub async fn authorize<T: RequiresPermission>() -> Result<User, LexodusAppError> {
+        require_permission(T::PERMISSION).await
+    }
+}
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    #[cfg(feature = "ssr")]
+    mod decide {
+        use crate::domain::models::permission::Permission;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::decide;
+        use http::StatusCode;
+
+        pub(super) fn granted(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+            pairs.iter().map(|(r, a)| (r.to_string(), a.to_string())).collect()
+        }
+
+        #[test]
+        fn missing_permission_is_forbidden() {
+            let clerk = granted(&[("CASE", "READ"), ("CASE", "UPDATE")]);
+            let err = decide(&clerk, true, Permission::CASE_SEAL).unwrap_err();
+            assert!(matches!(err, LexodusAppError::Forbidden(ref p) if p == "CASE:SEAL"));
+            assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        }
+
+        #[test]
+        fn granted_permission_is_allowed_whatever_its_case() {
+            let clerk = granted(&[("case", "read")]);
+            assert!(decide(&clerk, true, Permission::CASE_READ).is_ok());
+        }
+
+        #[test]
+        fn out_of_scope_token_is_forbidden_even_when_the_role_grants_it() {
+            let admin = granted(&[("CASE", "READ"), ("USER", "CREATE")]);
+            let err = decide(&admin, false, Permission::USER_CREATE).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        }
+
+        #[test]
+        fn no_role_permissions_is_forbidden() {
+            for permission in Permission::ALL {
+                assert!(decide(&[], true, permission).is_err(), "{permission} allowed with no grants");
+            }
+        }
+    }
+
+    mod caller {
+        use super::decide::granted;
+        use crate::domain::models::permission::Permission;
+        use crate::domain::models::user::User;
+        use crate::errors::LexodusAppError;
+        use crate::functions::auth::Credential;
+        use crate::functions::authz::{check_caller, require_permission};
+        use crate::pages::cases::list_case::{get_cases, CaseListQuery};
+        use crate::pages::user_management::create::get_users;
+        use crate::session_backend::tests::block_on;
+        use http::StatusCode;
+
+        fn clerk() -> User {
+            User {
+                id: 7,
+                username: "clerk".into(),
+                role_id: 2,
+                role: "clerk".into(),
+                two_factor_enabled: true,
+                ..User::default()
+            }
+        }
+
+        fn session() -> Credential {
+            Credential::Session { idp_mfa: false }
+        }
+
+        #[test]
+        fn user_with_the_permission_is_allowed() {
+            let grants = granted(&[("CASE", "READ"), ("CASE", "UPDATE")]);
+            assert!(check_caller(&clerk(), &session(), &grants, Permission::CASE_READ).is_ok());
+        }
+
+        #[test]
+        fn user_without_the_permission_is_forbidden() {
+            let grants = granted(&[("CASE", "READ")]);
+            let err = check_caller(&clerk(), &session(), &grants, Permission::USER_CREATE).unwrap_err();
+            assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+        }
+
+        #[test]
+        fn anonymous_caller_is_unauthorized() {
+            let err = block_on(require_permission(Permission::CASE_READ)).unwrap_err();
+            assert!(matches!(err, LexodusAppError::Unauthorized));
+            assert_eq!(err.status_code(), StatusCode::UNAUTHORIZED);
+        }
+
+        /// No connection is in context, so reaching the database would fail
+        /// with a different error than the authorization check
+        #[test]
+        fn guarded_server_fns_refuse_before_touching_the_database() {
+            let unauthorized = LexodusAppError::Unauthorized.to_string();
+            let err = block_on(get_cases(CaseListQuery::default())).unwrap_err();
+            assert!(err.to_string().contains(&unauthorized), "get_cases: {err}");
+            let err = block_on(get_users()).unwrap_err();
+            assert!(err.to_string().contains(&unauthorized), "get_users: {err}");
+        }
+    }
+}
@@ -0,0 +1,178 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::errors::LexodusAppError;
+    use crate::functions::api_tokens::bearer_token;
+    use crate::functions::auth::{current_session, save_session, set_session_cookie};
+    use crate::functions::impersonation::refuse_while_impersonating;
+    use crate::functions::sessions::session_config;
+    use async_session::Session;
+    use leptos_spin::RequestParts;
+    use rand_core::{OsRng, RngCore};
+
+    /// Session key holding the token every form must echo back
+    const CSRF_KEY: &str = "csrf_token";
+
+    fn generate_token() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        bytes.iter().map(|b| format!("{b:02x}")).collect()
+    }
+
+    /// Compare without short-circuiting so timing does not reveal a matching prefix
+    fn tokens_match(a: &str, b: &str) -> bool {
+        a.len() == b.len()
+            && a.bytes().zip(b.bytes()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+    }
+
+    /// Check the token submitted with a mutating server function against the one
+    /// stored in the caller's session. Call this before doing any work. On failure
+    /// the browser is sent to the CSRF error page.
+    ///
+    /// Requests authenticated with an `Authorization: Bearer` header are exempt:
+    /// browsers never attach that header on their own, so it cannot be forged
+    /// cross-site, and such requests are never authenticated by the cookie.
+    ///
+    /// Every mutating server function calls this, so it is also where sessions
+    /// acting as another user are kept read-only.
+    pub async fn verify_csrf(submitted: &str) -> Result<(), LexodusAppError> {
+        let req = use_context::<RequestParts>().ok_or(LexodusAppError::CsrfFailure)?;
+        match check_token(&req, submitted).await? {
+            Some(session) => refuse_while_impersonating(&req, &session).await,
+            None => Ok(()),
+        }
+    }
+
+    /// `verify_csrf` for the few calls an impersonating session may still make,
+    /// such as ending the impersonation or logging out
+    pub async fn verify_csrf_allow_impersonation(submitted: &str) -> Result<(), LexodusAppError> {
+        let req = use_context::<RequestParts>().ok_or(LexodusAppError::CsrfFailure)?;
+        check_token(&req, submitted).await.map(|_| ())
+    }
+
+    /// Returns the caller's session when the token was checked against it, or
+    /// None for a bearer request
+    async fn check_token(req: &RequestParts, submitted: &str) -> Result<Option<Session>, LexodusAppError> {
+        if bearer_token(req).is_some() {
+            return Ok(None);
+        }
+        let session = current_session(req).await?;
+        let expected = session.as_ref().and_then(|session| session.get::<String>(CSRF_KEY));
+
+        match (session, expected) {
+            (Some(session), Some(expected)) if tokens_match(&expected, submitted) => Ok(Some(session)),
+            _ => {
+                tracing::warn!("Rejected request with a missing or invalid CSRF token");
+                leptos_spin::redirect("/csrf-error");
+                Err(LexodusAppError::CsrfFailure)
+            }
+        }
+    }
+
+    /// Origin/Referer check applied to every unsafe request before it is routed,
+    /// comparing the source host against the `Host` header. Browsers send at least
+    /// one of these on cross-site POSTs, so a request carrying neither is from a
+    /// non-browser client and is left to the token check.
+    pub fn same_origin(headers: &[(String, Vec<u8>)]) -> bool {
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| String::from_utf8_lossy(v).to_string())
+        };
+        let Some(source) = header("origin").or_else(|| header("referer")) else {
+            return true;
+        };
+        let Some(host) = header("host") else {
+            return false;
+        };
+        source
+            .split_once("://")
+            .and_then(|(_, rest)| rest.split('/').next())
+            .is_some_and(|origin| origin.eq_ignore_ascii_case(&host))
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(GetCsrfToken, "/api")]
+/// The CSRF token for the caller's session. Visitors without a session get an
+/// anonymous one so the login and signup forms are protected too.
+pub async fn get_csrf_token() -> Result<String, ServerFnError> {
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::CsrfFailure)?;
+
+    if let Some(mut session) = current_session(&req).await? {
+        if let Some(token) = session.get::<String>(CSRF_KEY) {
+            return Ok(token);
+        }
+        let token = generate_token();
+        session.insert(CSRF_KEY, &token)?;
+        save_session(session).await?;
+        return Ok(token);
+    }
+
+    let token = generate_token();
+    let mut session = Session::new();
+    session.insert(CSRF_KEY, &token)?;
+    session.expire_in(std::time::Duration::from_secs(session_config().idle_secs as u64));
+    let cookie_value = crate::functions::auth::store_new_session(session).await?;
+    set_session_cookie(&cookie_value, None);
+    Ok(token)
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&str, &str)]) -> Vec<(String, Vec<u8>)> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.as_bytes().to_vec())).collect()
+    }
+
+    #[test]
+    fn tokens_match_only_when_identical() {
+        let token = generate_token();
+        assert!(tokens_match(&token, &token.clone()));
+        let mut flipped = token.clone().into_bytes();
+        flipped[63] = if flipped[63] == b'0' { b'1' } else { b'0' };
+        assert!(!tokens_match(&token, &String::from_utf8(flipped).unwrap()));
+        assert!(!tokens_match(&token, &token[..63]));
+        assert!(!tokens_match(&token, ""));
+        assert!(!tokens_match("", &token));
+    }
+
+    #[test]
+    fn generated_tokens_are_hex_and_unique() {
+        let a = generate_token();
+        let b = generate_token();
+        assert_eq!(a.len(), 64);
+        assert!(a.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn same_host_origin_is_accepted() {
+        assert!(same_origin(&headers(&[("Origin", "https://lexodus.court.gov"), ("Host", "lexodus.court.gov")])));
+        assert!(same_origin(&headers(&[("referer", "http://127.0.0.1:3000/cases/1"), ("host", "127.0.0.1:3000")])));
+    }
+
+    #[test]
+    fn cross_site_origin_is_refused() {
+        assert!(!same_origin(&headers(&[("Origin", "https://evil.example"), ("Host", "lexodus.court.gov")])));
+        // Origin wins over a same-site Referer
+        assert!(!same_origin(&headers(&[
+            ("Origin", "https://evil.example"),
+            ("Referer", "https://lexodus.court.gov/"),
+            ("Host", "lexodus.court.gov"),
+        ])));
+        assert!(!same_origin(&headers(&[("Origin", "null"), ("Host", "lexodus.court.gov")])));
+        assert!(!same_origin(&headers(&[("Origin", "https://lexodus.court.gov.evil.example"), ("Host", "lexodus.court.gov")])));
+    }
+
+    #[test]
+    fn source_without_host_is_refused_and_neither_is_left_to_the_token() {
+        assert!(!same_origin(&headers(&[("Origin", "https://lexodus.court.gov")])));
+        assert!(same_origin(&headers(&[("Host", "lexodus.court.gov")])));
+    }
+}
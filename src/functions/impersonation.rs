@@ -0,0 +1,234 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::models::permission::{Permission, RequiresPermission};
+
+/// Shown in the banner while an administrator is acting as another user
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpersonationStatus {
+    /// The administrator really signed in
+    pub real_username: String,
+    /// The user being acted as
+    pub username: String,
+    pub full_name: String,
+    pub role: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::activity_log::{action_type, status, ActivityLog, NewActivity};
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{auth_cookie_identity, current_session, get_session_cookie_value, save_session, ClientInfo};
+    use crate::functions::authz::{authorize, has_permission};
+    use crate::functions::con;
+    use crate::functions::csrf::{verify_csrf, verify_csrf_allow_impersonation};
+    use crate::functions::sessions::{IMPERSONATED_USER_KEY, USER_ID_KEY};
+    use crate::session_backend::SessionBackend;
+    use async_session::{Session, SessionStore};
+    use leptos_spin::RequestParts;
+    use spin_sdk::pg::Connection;
+    use std::sync::Arc;
+
+    /// Build an entry for `activity_logs`, always under the real user
+    fn activity(
+        real_user_id: i64,
+        acting_as: i64,
+        action_type: &'static str,
+        status: &'static str,
+        description: String,
+        client: &ClientInfo,
+        session: &Session,
+    ) -> NewActivity {
+        NewActivity {
+            user_id: real_user_id,
+            action_type,
+            action_category: "SECURITY",
+            action_description: description,
+            entity_type: "USER",
+            entity_id: acting_as.to_string(),
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            session_id: Some(session.id().to_string()),
+            status,
+            metadata: Some(serde_json::json!({ "real_user_id": real_user_id, "effective_user_id": acting_as })),
+        }
+    }
+
+    /// Write an activity entry. Failures are reported but never fail the request.
+    async fn record_activity(activity: NewActivity, con: &Arc<Connection>) {
+        if let Err(e) = ActivityLog::record(activity, con).await {
+            tracing::error!("Failed to write impersonation activity: {e}");
+        }
+    }
+
+    /// The real and impersonated user ids held by a session, if it is impersonating
+    fn impersonation(session: &Session) -> Option<(i64, i64)> {
+        Some((session.get::<i64>(USER_ID_KEY)?, session.get::<i64>(IMPERSONATED_USER_KEY)?))
+    }
+
+    /// Record a request made while impersonating. Called once per request from
+    /// `server::handle_lexodus`, before routing, so every page and server
+    /// function the administrator reaches is logged exactly once.
+    pub async fn audit_request(req_parts: &RequestParts, path: &str, store: &SessionBackend, con: &Arc<Connection>) {
+        let Ok(Some(cookie)) = get_session_cookie_value(req_parts) else {
+            return;
+        };
+        let Ok(Some(session)) = store.load_session(cookie).await else {
+            return;
+        };
+        if let Some((real_user_id, acting_as)) = impersonation(&session) {
+            let client = ClientInfo::from_request(req_parts);
+            let description = format!("Requested {path}");
+            record_activity(
+                activity(real_user_id, acting_as, action_type::IMPERSONATED_REQUEST, status::SUCCESS, description, &client, &session),
+                con,
+            )
+            .await;
+        }
+    }
+
+    /// Refuse a state-changing call from an impersonating session, logging the
+    /// attempt. Returns Ok when the session is not impersonating.
+    pub async fn refuse_while_impersonating(req_parts: &RequestParts, session: &Session) -> Result<(), LexodusAppError> {
+        let Some((real_user_id, acting_as)) = impersonation(session) else {
+            return Ok(());
+        };
+        let client = ClientInfo::from_request(req_parts);
+        let description = "Refused a change while acting as another user".to_string();
+        record_activity(
+            activity(real_user_id, acting_as, action_type::IMPERSONATION_BLOCKED, status::DENIED, description, &client, session),
+            &con()?,
+        )
+        .await;
+        tracing::warn!("Refused a change by user {real_user_id} while acting as user {acting_as}");
+        Err(LexodusAppError::ImpersonationReadOnly)
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(GetImpersonation, "/api")]
+/// Who the caller is acting as, if anyone
+pub async fn get_impersonation() -> Result<Option<ImpersonationStatus>, ServerFnError> {
+    let Some(req) = use_context::<RequestParts>() else {
+        return Ok(None);
+    };
+    let con = con()?;
+    match auth_cookie_identity(&req, &con).await {
+        Ok((user, Some(real_user))) => Ok(Some(ImpersonationStatus {
+            real_username: real_user.username,
+            username: user.username,
+            full_name: user.full_name,
+            role: user.role,
+        })),
+        _ => Ok(None),
+    }
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(StartImpersonation, "/api")]
+/// Act as another user in the caller's own session. The session keeps its owner
+/// and stays read-only until `StopImpersonation`; every request is audited.
+pub async fn start_impersonation(csrf_token: String, user_id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let admin = authorize::<StartImpersonation>().await?;
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+    let con = con()?;
+    // Only a browser session can act as someone else, never an API token
+    let Ok((_, None)) = auth_cookie_identity(&req, &con).await else {
+        return Err(LexodusAppError::Unauthorized.into());
+    };
+    let Some(mut session) = current_session(&req).await? else {
+        return Err(LexodusAppError::Unauthorized.into());
+    };
+
+    if user_id == admin.id {
+        return Err(LexodusAppError::BadRequest("You cannot act as yourself.".to_string()).into());
+    }
+    let Some(target) = User::get(user_id, &con).await? else {
+        return Err(LexodusAppError::NotFound.into());
+    };
+    if has_permission(&target, Permission::USER_IMPERSONATE, &con).await? {
+        return Err(LexodusAppError::BadRequest("Users who can act as others cannot be acted as.".to_string()).into());
+    }
+
+    session.insert(IMPERSONATED_USER_KEY, target.id)?;
+    let client = ClientInfo::from_request(&req);
+    let description = format!("Started acting as {}", target.username);
+    let entry = activity(admin.id, target.id, action_type::IMPERSONATION_START, status::SUCCESS, description, &client, &session);
+    save_session(session).await?;
+    record_activity(entry, &con).await;
+    tracing::info!("User {} started acting as user {}", admin.id, target.id);
+    leptos_spin::redirect("/cases");
+    Ok(())
+}
+
+impl RequiresPermission for StartImpersonation {
+    const PERMISSION: Permission = Permission::USER_IMPERSONATE;
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(StopImpersonation, "/api")]
+/// Return the caller's session to its owner
+pub async fn stop_impersonation(csrf_token: String) -> Result<(), ServerFnError> {
+    verify_csrf_allow_impersonation(&csrf_token).await?;
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+    let con = con()?;
+    let Some(mut session) = current_session(&req).await? else {
+        return Err(LexodusAppError::Unauthorized.into());
+    };
+    let Some((real_user_id, acting_as)) = impersonation(&session) else {
+        return Ok(());
+    };
+
+    session.remove(IMPERSONATED_USER_KEY);
+    let client = ClientInfo::from_request(&req);
+    let description = "Stopped acting as another user".to_string();
+    let entry = activity(real_user_id, acting_as, action_type::IMPERSONATION_STOP, status::SUCCESS, description, &client, &session);
+    save_session(session).await?;
+    record_activity(entry, &con).await;
+    tracing::info!("User {real_user_id} stopped acting as user {acting_as}");
+    leptos_spin::redirect("/users");
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_session_is_not_impersonating() {
+        let mut session = Session::new();
+        assert_eq!(impersonation(&session), None);
+        session.insert(USER_ID_KEY, 1_i64).unwrap();
+        assert_eq!(impersonation(&session), None);
+    }
+
+    #[test]
+    fn impersonating_session_names_the_real_and_effective_user() {
+        let mut session = Session::new();
+        session.insert(USER_ID_KEY, 1_i64).unwrap();
+        session.insert(IMPERSONATED_USER_KEY, 42_i64).unwrap();
+        assert_eq!(impersonation(&session), Some((1, 42)));
+        session.remove(IMPERSONATED_USER_KEY);
+        assert_eq!(impersonation(&session), None);
+    }
+
+    #[test]
+    fn activity_is_recorded_under_the_real_user() {
+        let session = Session::new();
+        let client = ClientInfo {
+            ip_address: Some("198.51.100.4".to_string()),
+            user_agent: None,
+        };
+        let entry = activity(1, 42, action_type::IMPERSONATED_REQUEST, status::SUCCESS, "Requested /cases".to_string(), &client, &session);
+        assert_eq!(entry.user_id, 1);
+        assert_eq!(entry.entity_type, "USER");
+        assert_eq!(entry.entity_id, "42");
+        assert_eq!(entry.ip_address.as_deref(), Some("198.51.100.4"));
+        assert_eq!(entry.session_id.as_deref(), Some(session.id()));
+        assert_eq!(entry.metadata, Some(serde_json::json!({ "real_user_id": 1, "effective_user_id": 42 })));
+    }
+}
@@ -1,6 +1,16 @@
 #![allow(unused_variables)]
 #![allow(unused_imports)]
+pub mod account_recovery;
+pub mod api_tokens;
 pub mod auth;
+pub mod authz;
+pub mod csrf;
+pub mod impersonation;
+pub mod oidc;
+pub mod password_policy;
+pub mod registration;
+pub mod sessions;
+pub mod two_factor;
 pub mod user;
 
 use crate::errors::LexodusAppError;
@@ -10,11 +20,12 @@ use leptos::server_fn::server_fn_error;
 cfg_if! {
     if #[cfg(feature = "ssr")] {
 
-        use spin_sdk::sqlite::{Connection};
+        use spin_sdk::pg::{Connection};
 
         use leptos::*;
         use std::sync::Arc;
 
+        /// The Postgres connection provided by `server::handle_lexodus`
         pub fn con() -> Result<Arc<Connection>, LexodusAppError> {
             use_context::<Arc<Connection>>()
                 .ok_or("Connection missing.")
@@ -0,0 +1,875 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::auth_log::{event_type, status};
+    use crate::domain::models::user::{role_requires_two_factor, NewUser, User};
+    use crate::domain::models::user_identity::UserIdentity;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{
+        hash_password, record_auth_event, start_pending_two_factor_session, start_sso_session,
+        store_new_session, ClientInfo,
+    };
+    use crate::functions::con;
+    use crate::functions::csrf::verify_csrf;
+    use crate::functions::sessions::session_config;
+    use crate::mail::app_base_url;
+    use crate::session_backend::SessionBackend;
+    use async_session::{Session, SessionStore};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+    use chrono::Utc;
+    use cookie::Cookie;
+    use leptos::expect_context;
+    use leptos_spin::{RequestParts, ResponseOptions};
+    use rand_core::{OsRng, RngCore};
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::signature::Verifier;
+    use rsa::{BigUint, RsaPublicKey};
+    use serde_json::Value;
+    use sha2::{Digest, Sha256};
+    use spin_sdk::http::{Method, Request, Response};
+    use spin_sdk::pg::Connection;
+    use spin_sdk::variables;
+    use std::sync::Arc;
+
+    /// Cookie naming the pending login while the browser is at the identity provider.
+    /// `Lax`, unlike the session cookie, so it survives the cross-site redirect back.
+    const PENDING_COOKIE: &str = "Lexodus_oidc";
+    const PENDING_TTL_SECS: u64 = 10 * 60;
+    const STATE_KEY: &str = "oidc_state";
+    const NONCE_KEY: &str = "oidc_nonce";
+    const VERIFIER_KEY: &str = "oidc_code_verifier";
+    /// Clock skew tolerated when checking `exp` and `iat`
+    const CLOCK_SKEW_SECS: i64 = 60;
+
+    /// Identity provider settings, read from Spin variables at startup. SSO is off
+    /// unless `oidc_issuer` and `oidc_client_id` are both set.
+    #[derive(Clone, Debug)]
+    pub struct OidcConfig {
+        /// Shown on the login button
+        pub provider_name: String,
+        pub issuer: String,
+        pub client_id: String,
+        /// Sent with `client_secret_post`; leave empty for a public client using PKCE alone
+        pub client_secret: Option<String>,
+        pub redirect_uri: String,
+        pub scopes: String,
+        /// ID token claim holding the user's groups or roles
+        pub role_claim: String,
+        /// `(claim value, Lexodus role)` pairs, checked in order
+        pub role_map: Vec<(String, String)>,
+        /// Create users on their first SSO login
+        pub auto_provision: bool,
+        /// Role for provisioned users whose claims map to no role. Without one they are refused.
+        pub default_role: Option<String>,
+        /// Email domains allowed to be provisioned. Empty allows any.
+        pub allowed_domains: Vec<String>,
+        /// Link an existing account with the same, IdP-verified email on first SSO login
+        pub link_by_email: bool,
+        /// `amr` values showing the provider performed multi-factor authentication
+        pub mfa_amr: Vec<String>,
+        /// `acr` values showing the same, for providers that report assurance levels instead
+        pub mfa_acr: Vec<String>,
+    }
+
+    impl OidcConfig {
+        pub fn from_variables() -> Option<Self> {
+            let get = |name: &str| variables::get(name).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+            let flag = |name: &str| get(name).is_some_and(|v| v.eq_ignore_ascii_case("true"));
+            let list = |name: &str| {
+                get(name)
+                    .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            Some(Self {
+                provider_name: get("oidc_provider_name").unwrap_or_else(|| "Single sign-on".to_string()),
+                issuer: get("oidc_issuer")?.trim_end_matches('/').to_string(),
+                client_id: get("oidc_client_id")?,
+                client_secret: get("oidc_client_secret"),
+                redirect_uri: get("oidc_redirect_uri")
+                    .unwrap_or_else(|| format!("{}/login/sso/callback", app_base_url())),
+                scopes: get("oidc_scopes").unwrap_or_else(|| "openid email profile".to_string()),
+                role_claim: get("oidc_role_claim").unwrap_or_else(|| "roles".to_string()),
+                role_map: list("oidc_role_map")
+                    .into_iter()
+                    .filter_map(|pair: String| {
+                        let (claim, role) = pair.split_once('=')?;
+                        Some((claim.trim().to_string(), role.trim().to_string()))
+                    })
+                    .collect(),
+                auto_provision: flag("oidc_auto_provision"),
+                default_role: get("oidc_default_role"),
+                allowed_domains: list("oidc_allowed_domains")
+                    .into_iter()
+                    .map(|d: String| d.to_lowercase())
+                    .collect(),
+                link_by_email: flag("oidc_link_by_email"),
+                mfa_amr: match list("oidc_mfa_amr") {
+                    amr if amr.is_empty() => vec!["mfa".to_string()],
+                    amr => amr,
+                },
+                mfa_acr: list("oidc_mfa_acr"),
+            })
+        }
+
+        /// The Lexodus role named by the first mapping whose claim value the user holds
+        fn mapped_role(&self, claims: &Value) -> Option<String> {
+            let held: Vec<&str> = match claims.get(&self.role_claim) {
+                Some(Value::String(s)) => vec![s.as_str()],
+                Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            self.role_map
+                .iter()
+                .find(|(claim, _)| held.contains(&claim.as_str()))
+                .map(|(_, role)| role.clone())
+        }
+
+        /// Did the provider report multi-factor authentication for this sign-in.
+        /// Without such a claim the local second factor still applies.
+        fn provider_mfa(&self, claims: &Value) -> bool {
+            let amr_mfa = claims
+                .get("amr")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .any(|method| self.mfa_amr.iter().any(|m| m == method));
+            let acr_mfa = claims
+                .get("acr")
+                .and_then(Value::as_str)
+                .is_some_and(|acr| self.mfa_acr.iter().any(|a| a == acr));
+            amr_mfa || acr_mfa
+        }
+    }
+
+    /// Endpoints from the provider's discovery document
+    struct ProviderMetadata {
+        authorization_endpoint: String,
+        token_endpoint: String,
+        jwks_uri: String,
+    }
+
+    fn sso_error(detail: impl std::fmt::Display) -> LexodusAppError {
+        LexodusAppError::SsoError(detail.to_string())
+    }
+
+    fn random_token() -> String {
+        let mut bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut bytes);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Blocking, like the Postgres calls; the futures from `spin_sdk::http::send`
+    /// are not `Send` and so cannot be held across an await in a server function
+    fn send_json(request: Request) -> Result<Value, LexodusAppError> {
+        let uri = request.uri().to_string();
+        let response: Response = spin_sdk::http::run(spin_sdk::http::send(request))
+            .map_err(|e| sso_error(format!("request to {uri} failed: {e}")))?;
+        if !(200..300).contains(response.status()) {
+            return Err(sso_error(format!(
+                "{uri} returned {}: {}",
+                response.status(),
+                String::from_utf8_lossy(response.body())
+            )));
+        }
+        Ok(serde_json::from_slice(response.body())?)
+    }
+
+    fn string_field(json: &Value, field: &str) -> Result<String, LexodusAppError> {
+        json.get(field)
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .ok_or_else(|| sso_error(format!("missing {field}")))
+    }
+
+    fn discover(config: &OidcConfig) -> Result<ProviderMetadata, LexodusAppError> {
+        let document = send_json(Request::get(format!("{}/.well-known/openid-configuration", config.issuer)).build())?;
+        provider_metadata(config, &document)
+    }
+
+    fn provider_metadata(config: &OidcConfig, document: &Value) -> Result<ProviderMetadata, LexodusAppError> {
+        // The document must describe the issuer we were configured with, or tokens
+        // from a different provider could be accepted
+        if string_field(document, "issuer")?.trim_end_matches('/') != config.issuer {
+            return Err(sso_error("discovery document issuer does not match oidc_issuer"));
+        }
+        Ok(ProviderMetadata {
+            authorization_endpoint: string_field(document, "authorization_endpoint")?,
+            token_endpoint: string_field(document, "token_endpoint")?,
+            jwks_uri: string_field(document, "jwks_uri")?,
+        })
+    }
+
+    /// Redeem the authorization code for an ID token
+    fn exchange_code(config: &OidcConfig, metadata: &ProviderMetadata, code: &str, verifier: &str) -> Result<String, LexodusAppError> {
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("code_verifier", verifier),
+        ];
+        if let Some(secret) = &config.client_secret {
+            form.push(("client_secret", secret.as_str()));
+        }
+        let body = form
+            .iter()
+            .map(|(k, v)| format!("{k}={}", urlencoding::encode(v)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let request = Request::builder()
+            .method(Method::Post)
+            .uri(metadata.token_endpoint.clone())
+            .header("content-type", "application/x-www-form-urlencoded")
+            .header("accept", "application/json")
+            .body(body)
+            .build();
+        string_field(&send_json(request)?, "id_token")
+    }
+
+    /// Check an RS256 ID token's signature against the provider's JWKS and its
+    /// claims against this login, returning the claims
+    fn verify_id_token(config: &OidcConfig, metadata: &ProviderMetadata, id_token: &str, nonce: &str) -> Result<Value, LexodusAppError> {
+        let jwks = send_json(Request::get(metadata.jwks_uri.clone()).build())?;
+        check_id_token(config, &jwks, id_token, nonce, Utc::now().timestamp())
+    }
+
+    /// `verify_id_token` against an already fetched JWKS, at `now` in Unix seconds
+    fn check_id_token(config: &OidcConfig, jwks: &Value, id_token: &str, nonce: &str, now: i64) -> Result<Value, LexodusAppError> {
+        let mut parts = id_token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(sso_error("malformed ID token"));
+        };
+        let decode = |part: &str| URL_SAFE_NO_PAD.decode(part).map_err(|_| sso_error("malformed ID token"));
+        let header: Value = serde_json::from_slice(&decode(header)?)?;
+        if header.get("alg").and_then(Value::as_str) != Some("RS256") {
+            return Err(sso_error("ID token must be signed with RS256"));
+        }
+        let kid = header.get("kid").and_then(Value::as_str);
+
+        let key = jwks
+            .get("keys")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter(|k| k.get("kty").and_then(Value::as_str) == Some("RSA"))
+            .find(|k| kid.is_none() || k.get("kid").and_then(Value::as_str) == kid)
+            .ok_or_else(|| sso_error("no matching signing key in JWKS"))?;
+        let n = BigUint::from_bytes_be(&decode(&string_field(key, "n")?)?);
+        let e = BigUint::from_bytes_be(&decode(&string_field(key, "e")?)?);
+        let public_key = RsaPublicKey::new(n, e).map_err(sso_error)?;
+        let signature = Signature::try_from(decode(signature)?.as_slice()).map_err(sso_error)?;
+        let (signed, _) = id_token.rsplit_once('.').unwrap_or_default();
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(signed.as_bytes(), &signature)
+            .map_err(|_| sso_error("ID token signature is invalid"))?;
+
+        let claims: Value = serde_json::from_slice(&decode(payload)?)?;
+        if string_field(&claims, "iss")?.trim_end_matches('/') != config.issuer {
+            return Err(sso_error("ID token issuer does not match"));
+        }
+        let audience_ok = match claims.get("aud") {
+            Some(Value::String(aud)) => *aud == config.client_id,
+            Some(Value::Array(auds)) => {
+                auds.iter().any(|a| a.as_str() == Some(config.client_id.as_str()))
+                    && (auds.len() == 1 || claims.get("azp").and_then(Value::as_str) == Some(config.client_id.as_str()))
+            }
+            _ => false,
+        };
+        if !audience_ok {
+            return Err(sso_error("ID token was not issued to this client"));
+        }
+        let exp = claims.get("exp").and_then(Value::as_i64).unwrap_or_default();
+        let iat = claims.get("iat").and_then(Value::as_i64).unwrap_or_default();
+        if exp + CLOCK_SKEW_SECS < now || iat - CLOCK_SKEW_SECS > now {
+            return Err(sso_error("ID token has expired or is not yet valid"));
+        }
+        if claims.get("nonce").and_then(Value::as_str) != Some(nonce) {
+            return Err(sso_error("ID token nonce does not match this login"));
+        }
+        Ok(claims)
+    }
+
+    /// Some providers send `email_verified` as a string
+    fn email_verified(claims: &Value) -> bool {
+        match claims.get("email_verified") {
+            Some(Value::Bool(b)) => *b,
+            Some(Value::String(s)) => s.eq_ignore_ascii_case("true"),
+            _ => false,
+        }
+    }
+
+    /// A free username derived from the claims, e.g. `jdoe` or `jdoe-2`
+    async fn available_username(claims: &Value, email: &str, con: &Arc<Connection>) -> Result<String, LexodusAppError> {
+        let wanted = claims
+            .get("preferred_username")
+            .and_then(Value::as_str)
+            .unwrap_or_else(|| email.split('@').next().unwrap_or_default());
+        let base: String = wanted
+            .to_lowercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+            .collect();
+        let base = if base.is_empty() { "user".to_string() } else { base };
+        for n in 1..100 {
+            let candidate = if n == 1 { base.clone() } else { format!("{base}-{n}") };
+            if User::get_from_username(&candidate, con).await?.is_none() {
+                return Ok(candidate);
+            }
+        }
+        Err(sso_error("could not choose a username"))
+    }
+
+    /// Create an account for a first-time SSO user, if the provisioning rules allow it
+    async fn provision_user(config: &OidcConfig, claims: &Value, role: Option<String>, con: &Arc<Connection>) -> Result<User, LexodusAppError> {
+        let refuse = |reason: &str| sso_error(format!("no Lexodus account is linked to this sign-in ({reason})"));
+        if !config.auto_provision {
+            return Err(refuse("automatic provisioning is off"));
+        }
+        let email = claims
+            .get("email")
+            .and_then(Value::as_str)
+            .filter(|_| email_verified(claims))
+            .ok_or_else(|| refuse("the identity provider did not supply a verified email"))?;
+        let domain = email.rsplit_once('@').map(|(_, d)| d.to_lowercase()).unwrap_or_default();
+        if !config.allowed_domains.is_empty() && !config.allowed_domains.contains(&domain) {
+            return Err(refuse("email domain is not allowed"));
+        }
+        let role = role
+            .or_else(|| config.default_role.clone())
+            .ok_or_else(|| refuse("no role is mapped for this user"))?;
+        let role_id = User::role_id_by_name(&role, con)
+            .await?
+            .ok_or_else(|| sso_error(format!("mapped role {role} does not exist")))?;
+
+        let username = available_username(claims, email, con).await?;
+        let full_name = claims
+            .get("name")
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .unwrap_or_else(|| username.clone());
+        // SSO users sign in through the provider; the local password is random and unknown
+        let id = User::create(
+            NewUser {
+                username,
+                email: email.to_string(),
+                full_name,
+                password_hash: hash_password(random_token().as_bytes())?,
+                role_id,
+                bar_number: None,
+                home_court_id: None,
+            },
+            con,
+        )
+        .await?;
+        User::mark_email_verified(id, con).await?;
+        tracing::info!("Provisioned user {id} from SSO");
+        User::get(id, con).await?.ok_or(LexodusAppError::NotFound)
+    }
+
+    /// Find or create the Lexodus user for verified claims and apply the mapped role
+    async fn resolve_user(config: &OidcConfig, claims: &Value, con: &Arc<Connection>) -> Result<User, LexodusAppError> {
+        let subject = string_field(claims, "sub")?;
+        let email = claims.get("email").and_then(Value::as_str);
+        let role = config.mapped_role(claims);
+
+        let mut user = match UserIdentity::find_user(&config.issuer, &subject, con).await? {
+            Some(user_id) => User::get(user_id, con).await?.ok_or(LexodusAppError::NotFound)?,
+            None => {
+                let existing = match email {
+                    Some(email) if config.link_by_email && email_verified(claims) => User::get_from_email(email, con).await?,
+                    _ => None,
+                };
+                let user = match existing {
+                    Some(user) => user,
+                    None => provision_user(config, claims, role.clone(), con).await?,
+                };
+                UserIdentity::link(user.id, &config.issuer, &subject, email, con).await?;
+                user
+            }
+        };
+
+        // The provider is authoritative for mapped roles
+        if let Some(role) = role.filter(|r| *r != user.role) {
+            if let Some(role_id) = User::role_id_by_name(&role, con).await? {
+                User::update_role(user.id, role_id, con).await?;
+                tracing::info!("Moved user {} to role {role} from SSO claims", user.id);
+                // The session starts from this user, so it must carry the new role
+                user.role = role;
+                user.role_id = role_id;
+            }
+        }
+        Ok(user)
+    }
+
+    fn pending_cookie_value(req_parts: &RequestParts) -> Option<String> {
+        req_parts
+            .headers()
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case("cookie"))
+            .flat_map(|(_, v)| Cookie::split_parse(String::from_utf8_lossy(v).to_string()))
+            .flatten()
+            .find(|c| c.name() == PENDING_COOKIE)
+            .map(|c| c.value().to_string())
+    }
+
+    fn set_pending_cookie(value: &str, max_age: u64) {
+        let secure = if session_config().secure_cookie { " Secure;" } else { "" };
+        expect_context::<ResponseOptions>().append_header(
+            "Set-Cookie",
+            format!("{PENDING_COOKIE}={value}; Path=/; HttpOnly;{secure} SameSite=Lax; Max-Age={max_age};").as_bytes(),
+        );
+    }
+
+    /// Load and destroy the pending login so its state can be used once only
+    async fn take_pending_login(req_parts: &RequestParts) -> Result<Option<Session>, LexodusAppError> {
+        let Some(value) = pending_cookie_value(req_parts) else {
+            return Ok(None);
+        };
+        let store = expect_context::<SessionBackend>();
+        let Some(session) = store.load_session(value).await? else {
+            return Ok(None);
+        };
+        store.destroy_session(session.clone()).await?;
+        Ok(Some(session))
+    }
+
+    /// The S256 PKCE challenge sent for `verifier`
+    fn pkce_challenge(verifier: &str) -> String {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes()))
+    }
+
+    /// The nonce and PKCE verifier of the pending login, provided the state the
+    /// provider returned is the one it was sent
+    fn pending_secrets(pending: &Session, state: &str) -> Result<(String, String), LexodusAppError> {
+        let (Some(expected_state), Some(nonce), Some(verifier)) = (
+            pending.get::<String>(STATE_KEY),
+            pending.get::<String>(NONCE_KEY),
+            pending.get::<String>(VERIFIER_KEY),
+        ) else {
+            return Err(sso_error("the pending sign-in is incomplete"));
+        };
+        if expected_state != state {
+            return Err(sso_error("state does not match"));
+        }
+        Ok((nonce, verifier))
+    }
+
+    /// Must a user signed in through SSO still enroll in local 2FA before acting
+    fn sso_owes_two_factor(role: &str, two_factor_enabled: bool, idp_mfa: bool) -> bool {
+        role_requires_two_factor(role) && !two_factor_enabled && !idp_mfa
+    }
+
+    fn oidc_config() -> Result<OidcConfig, LexodusAppError> {
+        use_context::<OidcConfig>().ok_or_else(|| LexodusAppError::BadRequest("Single sign-on is not configured.".to_string()))
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(GetSsoProvider, "/api")]
+/// The name to show on the SSO login button, or `None` when SSO is off
+pub async fn get_sso_provider() -> Result<Option<String>, ServerFnError> {
+    Ok(use_context::<OidcConfig>().map(|c| c.provider_name))
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(csrf_token), err)]
+#[server(BeginSsoLogin, "/api")]
+/// Send the browser to the identity provider with a fresh state, nonce and PKCE challenge
+pub async fn begin_sso_login(csrf_token: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let config = oidc_config()?;
+    let metadata = discover(&config)?;
+
+    let state = random_token();
+    let nonce = random_token();
+    let verifier = random_token();
+    let challenge = pkce_challenge(&verifier);
+
+    let mut pending = Session::new();
+    pending.insert(STATE_KEY, &state)?;
+    pending.insert(NONCE_KEY, &nonce)?;
+    pending.insert(VERIFIER_KEY, &verifier)?;
+    pending.expire_in(std::time::Duration::from_secs(PENDING_TTL_SECS));
+    set_pending_cookie(&store_new_session(pending).await?, PENDING_TTL_SECS);
+
+    let params = [
+        ("response_type", "code"),
+        ("client_id", config.client_id.as_str()),
+        ("redirect_uri", config.redirect_uri.as_str()),
+        ("scope", config.scopes.as_str()),
+        ("state", state.as_str()),
+        ("nonce", nonce.as_str()),
+        ("code_challenge", challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ];
+    let query = params
+        .iter()
+        .map(|(k, v)| format!("{k}={}", urlencoding::encode(v)))
+        .collect::<Vec<_>>()
+        .join("&");
+    let separator = if metadata.authorization_endpoint.contains('?') { '&' } else { '?' };
+    leptos_spin::redirect(&format!("{}{separator}{query}", metadata.authorization_endpoint));
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(CompleteSsoLogin, "/api")]
+/// Finish the login the provider redirected back with. The state must match the
+/// pending login started in this browser.
+pub async fn complete_sso_login(csrf_token: String, code: String, state: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+    let config = oidc_config()?;
+    let con = con()?;
+    let client = ClientInfo::from_request(&req);
+
+    let result = async {
+        let pending = take_pending_login(&req)
+            .await?
+            .ok_or_else(|| sso_error("the sign-in took too long or was started in another browser"))?;
+        let (nonce, verifier) = pending_secrets(&pending, &state)?;
+        let metadata = discover(&config)?;
+        let id_token = exchange_code(&config, &metadata, &code, &verifier)?;
+        let claims = verify_id_token(&config, &metadata, &id_token, &nonce)?;
+        Ok::<_, LexodusAppError>((resolve_user(&config, &claims, &con).await?, config.provider_mfa(&claims)))
+    }
+    .await;
+
+    let (user, idp_mfa) = match result {
+        Ok(resolved) => resolved,
+        Err(e) => {
+            set_pending_cookie("", 0);
+            tracing::warn!("SSO login failed: {e}");
+            record_auth_event(client.event(None, event_type::SSO_LOGIN, status::FAILURE, Some(&e.to_string())), &con).await;
+            return Err(e.into());
+        }
+    };
+    if user.is_locked(Utc::now().timestamp()) {
+        set_pending_cookie("", 0);
+        record_auth_event(client.event(Some(user.id), event_type::SSO_LOGIN, status::FAILURE, Some("account locked")), &con).await;
+        return Err(LexodusAppError::AccountLocked.into());
+    }
+
+    // MFA at the provider stands in for local 2FA. Without it, an enrolled user
+    // still owes their TOTP code, exactly as after a password.
+    if user.two_factor_enabled && !idp_mfa {
+        start_pending_two_factor_session(&req, user.id, false, true).await?;
+        // After the session cookie, which replaces any Set-Cookie already queued
+        set_pending_cookie("", 0);
+        leptos_spin::redirect("/login/2fa");
+        return Ok(());
+    }
+
+    User::record_successful_login(user.id, &con).await?;
+    record_auth_event(client.event(Some(user.id), event_type::SSO_LOGIN, status::SUCCESS, None), &con).await;
+    start_sso_session(&req, user.id, idp_mfa, &client).await?;
+    // After `start_sso_session`, which replaces any Set-Cookie already queued
+    set_pending_cookie("", 0);
+    if sso_owes_two_factor(&user.role, user.two_factor_enabled, idp_mfa) {
+        leptos_spin::redirect("/account/security");
+    } else {
+        leptos_spin::redirect("/cases");
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use rsa::pkcs1v15::SigningKey;
+    use rsa::signature::{SignatureEncoding, Signer};
+    use rsa::traits::PublicKeyParts;
+    use rsa::RsaPrivateKey;
+    use serde_json::json;
+    use std::sync::OnceLock;
+
+    const ISSUER: &str = "https://idp.example.gov";
+    const CLIENT_ID: &str = "lexodus";
+    const KID: &str = "test-key";
+    const NONCE: &str = "n-0S6_WzA2Mj";
+    const NOW: i64 = 1_700_000_000;
+
+    /// The identity provider's signing key. Small, since it only has to be generated quickly.
+    fn idp_key() -> &'static RsaPrivateKey {
+        static KEY: OnceLock<RsaPrivateKey> = OnceLock::new();
+        KEY.get_or_init(|| RsaPrivateKey::new(&mut OsRng, 1024).unwrap())
+    }
+
+    fn config() -> OidcConfig {
+        OidcConfig {
+            provider_name: "Court SSO".to_string(),
+            issuer: ISSUER.to_string(),
+            client_id: CLIENT_ID.to_string(),
+            client_secret: None,
+            redirect_uri: "https://lexodus.example.gov/login/sso/callback".to_string(),
+            scopes: "openid email profile".to_string(),
+            role_claim: "groups".to_string(),
+            role_map: vec![
+                ("court-judges".to_string(), "judge".to_string()),
+                ("court-clerks".to_string(), "clerk".to_string()),
+            ],
+            auto_provision: false,
+            default_role: None,
+            allowed_domains: Vec::new(),
+            link_by_email: false,
+            mfa_amr: vec!["mfa".to_string(), "otp".to_string()],
+            mfa_acr: vec!["urn:court:acr:mfa".to_string()],
+        }
+    }
+
+    /// The provider's JWKS, publishing `idp_key` under `KID`
+    fn jwks() -> Value {
+        let public = idp_key().to_public_key();
+        json!({
+            "keys": [{
+                "kty": "RSA",
+                "kid": KID,
+                "use": "sig",
+                "n": URL_SAFE_NO_PAD.encode(public.n().to_bytes_be()),
+                "e": URL_SAFE_NO_PAD.encode(public.e().to_bytes_be()),
+            }]
+        })
+    }
+
+    fn claims() -> Value {
+        json!({
+            "iss": ISSUER,
+            "sub": "248289761001",
+            "aud": CLIENT_ID,
+            "exp": NOW + 300,
+            "iat": NOW,
+            "nonce": NONCE,
+            "email": "jdoe@example.gov",
+            "email_verified": true,
+            "groups": ["staff", "court-clerks"],
+        })
+    }
+
+    fn encode(json: &Value) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(json).unwrap())
+    }
+
+    /// An ID token for `claims` signed by the provider
+    fn sign(header: &Value, claims: &Value) -> String {
+        let signed = format!("{}.{}", encode(header), encode(claims));
+        let signature = SigningKey::<Sha256>::new(idp_key().clone()).sign(signed.as_bytes());
+        format!("{signed}.{}", URL_SAFE_NO_PAD.encode(signature.to_bytes()))
+    }
+
+    fn token(claims: &Value) -> String {
+        sign(&json!({ "alg": "RS256", "kid": KID, "typ": "JWT" }), claims)
+    }
+
+    fn check(id_token: &str) -> Result<Value, LexodusAppError> {
+        check_id_token(&config(), &jwks(), id_token, NONCE, NOW)
+    }
+
+    fn rejection(id_token: &str) -> String {
+        match check(id_token) {
+            Err(LexodusAppError::SsoError(detail)) => detail,
+            other => panic!("expected an SSO error, got {other:?}"),
+        }
+    }
+
+    fn with(field: &str, value: Value) -> Value {
+        let mut claims = claims();
+        claims[field] = value;
+        claims
+    }
+
+    #[test]
+    fn token_signed_by_the_provider_is_accepted() {
+        let claims = check(&token(&claims())).unwrap();
+        assert_eq!(claims["sub"], "248289761001");
+    }
+
+    #[test]
+    fn token_within_the_clock_skew_is_accepted() {
+        assert!(check(&token(&with("exp", json!(NOW - CLOCK_SKEW_SECS + 1)))).is_ok());
+        assert!(check(&token(&with("iat", json!(NOW + CLOCK_SKEW_SECS - 1)))).is_ok());
+    }
+
+    #[test]
+    fn tampered_claims_fail_the_signature() {
+        let original = token(&claims());
+        let (header, rest) = original.split_once('.').unwrap();
+        let (_, signature) = rest.split_once('.').unwrap();
+        let forged = format!("{header}.{}.{signature}", encode(&with("sub", json!("someone-else"))));
+        assert_eq!(rejection(&forged), "ID token signature is invalid");
+    }
+
+    #[test]
+    fn token_must_be_rs256() {
+        let unsigned = format!("{}.{}.", encode(&json!({ "alg": "none" })), encode(&claims()));
+        assert_eq!(rejection(&unsigned), "ID token must be signed with RS256");
+        let hmac = sign(&json!({ "alg": "HS256", "kid": KID }), &claims());
+        assert_eq!(rejection(&hmac), "ID token must be signed with RS256");
+    }
+
+    #[test]
+    fn token_signed_with_an_unpublished_key_is_refused() {
+        let other_kid = sign(&json!({ "alg": "RS256", "kid": "rotated-out" }), &claims());
+        assert_eq!(rejection(&other_kid), "no matching signing key in JWKS");
+        assert!(check_id_token(&config(), &json!({ "keys": [] }), &token(&claims()), NONCE, NOW).is_err());
+    }
+
+    #[test]
+    fn malformed_tokens_are_refused() {
+        for id_token in ["", "abc", "a.b", "a.b.c.d", "!!.??.**"] {
+            assert!(check(id_token).is_err(), "{id_token:?} accepted");
+        }
+    }
+
+    #[test]
+    fn token_from_another_issuer_is_refused() {
+        let other = token(&with("iss", json!("https://evil.example.com")));
+        assert_eq!(rejection(&other), "ID token issuer does not match");
+        assert!(check(&token(&with("iss", json!(format!("{ISSUER}/"))))).is_ok());
+    }
+
+    #[test]
+    fn token_for_another_client_is_refused() {
+        let expected = "ID token was not issued to this client";
+        assert_eq!(rejection(&token(&with("aud", json!("other-app")))), expected);
+        assert_eq!(rejection(&token(&with("aud", json!(["other-app", CLIENT_ID])))), expected);
+        let mut authorized = with("aud", json!(["other-app", CLIENT_ID]));
+        authorized["azp"] = json!(CLIENT_ID);
+        assert!(check(&token(&authorized)).is_ok());
+        assert!(check(&token(&with("aud", json!([CLIENT_ID])))).is_ok());
+    }
+
+    #[test]
+    fn expired_or_future_token_is_refused() {
+        let expected = "ID token has expired or is not yet valid";
+        assert_eq!(rejection(&token(&with("exp", json!(NOW - CLOCK_SKEW_SECS - 1)))), expected);
+        assert_eq!(rejection(&token(&with("iat", json!(NOW + CLOCK_SKEW_SECS + 1)))), expected);
+        let mut no_exp = claims();
+        no_exp.as_object_mut().unwrap().remove("exp");
+        assert_eq!(rejection(&token(&no_exp)), expected);
+    }
+
+    #[test]
+    fn token_from_another_login_is_refused() {
+        let expected = "ID token nonce does not match this login";
+        assert_eq!(rejection(&token(&with("nonce", json!("replayed")))), expected);
+        let mut no_nonce = claims();
+        no_nonce.as_object_mut().unwrap().remove("nonce");
+        assert_eq!(rejection(&token(&no_nonce)), expected);
+    }
+
+    #[test]
+    fn discovery_document_must_name_the_configured_issuer() {
+        let document = json!({
+            "issuer": format!("{ISSUER}/"),
+            "authorization_endpoint": format!("{ISSUER}/authorize"),
+            "token_endpoint": format!("{ISSUER}/token"),
+            "jwks_uri": format!("{ISSUER}/jwks"),
+        });
+        let metadata = provider_metadata(&config(), &document).unwrap();
+        assert_eq!(metadata.token_endpoint, format!("{ISSUER}/token"));
+        assert_eq!(metadata.jwks_uri, format!("{ISSUER}/jwks"));
+
+        let mut other = document.clone();
+        other["issuer"] = json!("https://evil.example.com");
+        assert!(provider_metadata(&config(), &other).is_err());
+        let mut incomplete = document;
+        incomplete.as_object_mut().unwrap().remove("jwks_uri");
+        assert!(provider_metadata(&config(), &incomplete).is_err());
+    }
+
+    #[test]
+    fn pkce_challenge_is_the_s256_of_the_verifier() {
+        assert_eq!(
+            pkce_challenge("M25iVXpKU3puUjFaYWg3T1NDTDQtcW1ROUY5YXlwalNoc0hhakxifmZHag"),
+            "qjrzSW9gMiUgpUvqgEPE4_-8swvyCtfOVvg55o5S_es"
+        );
+        assert_ne!(pkce_challenge("a"), pkce_challenge("b"));
+    }
+
+    fn pending() -> Session {
+        let mut pending = Session::new();
+        pending.insert(STATE_KEY, "state-123").unwrap();
+        pending.insert(NONCE_KEY, NONCE).unwrap();
+        pending.insert(VERIFIER_KEY, "verifier-456").unwrap();
+        pending
+    }
+
+    #[test]
+    fn matching_state_releases_the_nonce_and_verifier() {
+        let (nonce, verifier) = pending_secrets(&pending(), "state-123").unwrap();
+        assert_eq!(nonce, NONCE);
+        assert_eq!(verifier, "verifier-456");
+    }
+
+    #[test]
+    fn mismatched_state_is_refused() {
+        assert!(pending_secrets(&pending(), "state-999").is_err());
+        assert!(pending_secrets(&pending(), "").is_err());
+    }
+
+    #[test]
+    fn incomplete_pending_login_is_refused() {
+        let mut pending = pending();
+        pending.remove(VERIFIER_KEY);
+        assert!(pending_secrets(&pending, "state-123").is_err());
+        assert!(pending_secrets(&Session::new(), "state-123").is_err());
+    }
+
+    #[test]
+    fn first_mapped_claim_names_the_role() {
+        assert_eq!(config().mapped_role(&claims()), Some("clerk".to_string()));
+        let both = with("groups", json!(["court-clerks", "court-judges"]));
+        assert_eq!(config().mapped_role(&both), Some("judge".to_string()));
+        assert_eq!(config().mapped_role(&with("groups", json!("court-judges"))), Some("judge".to_string()));
+    }
+
+    #[test]
+    fn unmapped_or_missing_claims_name_no_role() {
+        assert_eq!(config().mapped_role(&with("groups", json!(["staff"]))), None);
+        assert_eq!(config().mapped_role(&with("groups", json!(42))), None);
+        let mut none = claims();
+        none.as_object_mut().unwrap().remove("groups");
+        assert_eq!(config().mapped_role(&none), None);
+        // Values are matched exactly
+        assert_eq!(config().mapped_role(&with("groups", json!(["COURT-JUDGES"]))), None);
+    }
+
+    #[test]
+    fn amr_or_acr_shows_provider_mfa() {
+        assert!(!config().provider_mfa(&claims()));
+        assert!(config().provider_mfa(&with("amr", json!(["pwd", "mfa"]))));
+        assert!(config().provider_mfa(&with("amr", json!(["otp"]))));
+        assert!(config().provider_mfa(&with("acr", json!("urn:court:acr:mfa"))));
+        assert!(!config().provider_mfa(&with("amr", json!(["pwd"]))));
+        assert!(!config().provider_mfa(&with("amr", json!("mfa"))));
+        assert!(!config().provider_mfa(&with("acr", json!("urn:court:acr:password"))));
+    }
+
+    /// A judge provisioned by SSO, with no local 2FA, calling a guarded server fn
+    #[test]
+    fn sso_judge_acts_only_after_mfa_at_the_provider() {
+        use crate::functions::auth::Credential;
+        use crate::functions::authz::check_second_factor;
+
+        let judge = with("groups", json!(["court-judges"]));
+        let role = config().mapped_role(&judge).unwrap();
+
+        let idp_mfa = config().provider_mfa(&with("amr", json!(["pwd", "mfa"])));
+        assert!(!sso_owes_two_factor(&role, false, idp_mfa));
+        assert!(check_second_factor(&role, false, &Credential::Session { idp_mfa }).is_ok());
+
+        let idp_mfa = config().provider_mfa(&judge);
+        assert!(sso_owes_two_factor(&role, false, idp_mfa));
+        assert!(matches!(
+            check_second_factor(&role, false, &Credential::Session { idp_mfa }),
+            Err(LexodusAppError::TwoFactorRequired)
+        ));
+        // Enrolling locally satisfies the check either way
+        assert!(check_second_factor(&role, true, &Credential::Session { idp_mfa }).is_ok());
+        // Roles without the 2FA requirement are unaffected
+        assert!(!sso_owes_two_factor("attorney", false, false));
+    }
+}
@@ -0,0 +1,413 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{
+        auth_cookie_session, current_session, hash_password, start_session, verify_password, ClientInfo,
+    };
+    use crate::functions::con;
+    use crate::functions::csrf::verify_csrf;
+    use crate::functions::sessions::{is_remembered, revoke_user_sessions};
+    use argon2::{password_hash::PasswordHash, Algorithm, Argon2, Params, Version};
+    use leptos_spin::RequestParts;
+    use send_wrapper::SendWrapper;
+    use sha1::{Digest, Sha1};
+    use spin_sdk::http::{Request, Response};
+    use spin_sdk::pg::Connection;
+    use spin_sdk::variables;
+    use std::collections::HashSet;
+    use std::sync::{Arc, OnceLock};
+
+    /// The most common and most breached passwords, one per line, lowercase.
+    /// Always checked, and never leaves the server.
+    const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+    /// Password rules and Argon2 cost, read from Spin variables at startup
+    #[derive(Clone, Debug)]
+    pub struct PasswordPolicy {
+        pub min_length: usize,
+        pub max_length: usize,
+        /// Previous passwords that may not be reused, besides the current one
+        pub history_count: i64,
+        /// Days before a password must be changed. 0 disables expiry.
+        pub max_age_days: i64,
+        pub argon2_memory_kib: u32,
+        pub argon2_iterations: u32,
+        pub argon2_parallelism: u32,
+        /// Range endpoint of a breached-password corpus, such as Pwned Passwords'
+        /// `https://api.pwnedpasswords.com/range/`. Off unless configured.
+        pub breach_range_url: Option<String>,
+    }
+
+    impl Default for PasswordPolicy {
+        fn default() -> Self {
+            Self {
+                min_length: 12,
+                max_length: 128,
+                history_count: 5,
+                max_age_days: 0,
+                argon2_memory_kib: Params::DEFAULT_M_COST,
+                argon2_iterations: Params::DEFAULT_T_COST,
+                argon2_parallelism: Params::DEFAULT_P_COST,
+                breach_range_url: None,
+            }
+        }
+    }
+
+    fn variable<T: std::str::FromStr>(name: &str, default: T) -> T {
+        variables::get(name)
+            .ok()
+            .and_then(|v| v.parse::<T>().ok())
+            .unwrap_or(default)
+    }
+
+    impl PasswordPolicy {
+        /// Missing or unparsable variables fall back to the defaults
+        pub fn from_variables() -> Self {
+            let defaults = Self::default();
+            Self {
+                min_length: variable("password_min_length", defaults.min_length),
+                max_length: variable("password_max_length", defaults.max_length),
+                history_count: variable("password_history_count", defaults.history_count),
+                max_age_days: variable("password_max_age_days", defaults.max_age_days),
+                argon2_memory_kib: variable("argon2_memory_kib", defaults.argon2_memory_kib),
+                argon2_iterations: variable("argon2_iterations", defaults.argon2_iterations),
+                argon2_parallelism: variable("argon2_parallelism", defaults.argon2_parallelism),
+                // Sends hash prefixes to a third party, so only a court that sets it opts in
+                breach_range_url: variables::get("password_breach_range_url")
+                    .ok()
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                    .or(defaults.breach_range_url),
+            }
+        }
+
+        fn params(&self) -> Result<Params, LexodusAppError> {
+            Params::new(self.argon2_memory_kib, self.argon2_iterations, self.argon2_parallelism, None)
+                .map_err(|e| LexodusAppError::Argon2Error(e.to_string()))
+        }
+
+        /// Argon2id with the configured cost
+        pub fn hasher(&self) -> Result<Argon2<'static>, LexodusAppError> {
+            Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params()?))
+        }
+
+        /// Is a stored hash weaker than what `hasher` would produce now
+        pub fn needs_rehash(&self, stored_password_hash: &str) -> bool {
+            let Ok(hash) = PasswordHash::new(stored_password_hash) else {
+                return false;
+            };
+            if hash.algorithm != Algorithm::Argon2id.ident() || hash.version != Some(Version::V0x13.into()) {
+                return true;
+            }
+            match Params::try_from(&hash) {
+                Ok(stored) => {
+                    stored.m_cost() < self.argon2_memory_kib
+                        || stored.t_cost() < self.argon2_iterations
+                        || stored.p_cost() < self.argon2_parallelism
+                }
+                Err(_) => true,
+            }
+        }
+
+        /// Must the password be changed before the user may sign in
+        pub fn is_expired(&self, password_changed_at: Option<i64>, now: i64) -> bool {
+            self.max_age_days > 0
+                && password_changed_at.is_some_and(|changed| now >= changed + self.max_age_days * 24 * 60 * 60)
+        }
+
+        /// Check a candidate password against the rules that need no history.
+        /// Every problem found is reported at once.
+        pub fn check(&self, password: &str, username: &str, email: &str) -> Result<(), LexodusAppError> {
+            let mut problems = Vec::new();
+            let length = password.chars().count();
+            if length < self.min_length {
+                problems.push(format!("Use at least {} characters.", self.min_length));
+            }
+            if length > self.max_length {
+                problems.push(format!("Use at most {} characters.", self.max_length));
+            }
+            if is_common(password) {
+                problems.push("This password is too common or has appeared in a data breach.".to_string());
+            }
+            let lower = password.to_lowercase();
+            let email_name = email.split('@').next().unwrap_or_default().to_lowercase();
+            if (!username.is_empty() && lower.contains(&username.to_lowercase()))
+                || (email_name.len() >= 3 && lower.contains(&email_name))
+            {
+                problems.push("Do not include your username or email address.".to_string());
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(LexodusAppError::BadRequest(problems.join(" ")))
+            }
+        }
+    }
+
+    impl PasswordPolicy {
+        /// `check`, then look the password up in the breach corpus if one is configured
+        pub async fn validate(&self, password: &str, username: &str, email: &str) -> Result<(), LexodusAppError> {
+            self.check(password, username, email)?;
+            if self.is_breached(password).await {
+                return Err(LexodusAppError::BadRequest(
+                    "This password has appeared in a data breach. Choose another.".to_string(),
+                ));
+            }
+            Ok(())
+        }
+
+        /// Ask the range endpoint for every breached hash sharing the password's
+        /// five-character SHA-1 prefix, so the password never leaves the server.
+        /// An unreachable endpoint is logged and the password allowed; the local
+        /// denylist has already been applied.
+        async fn is_breached(&self, password: &str) -> bool {
+            let Some(url) = &self.breach_range_url else {
+                return false;
+            };
+            let (prefix, suffix) = sha1_range(password);
+            // Padding hides how many hashes share the prefix from anyone watching
+            let request = Request::get(format!("{url}{prefix}")).header("add-padding", "true").build();
+            // Spin runs a request on one thread, so the non-`Send` future may be
+            // awaited from a server function
+            let response = match SendWrapper::new(spin_sdk::http::send::<_, Response>(request)).await {
+                Ok(response) if (200..300).contains(response.status()) => response,
+                Ok(response) => {
+                    tracing::warn!("Breached password lookup returned {}; skipping it", response.status());
+                    return false;
+                }
+                Err(e) => {
+                    tracing::warn!("Breached password lookup failed: {e}; skipping it");
+                    return false;
+                }
+            };
+            breach_count(&String::from_utf8_lossy(response.body()), &suffix) > 0
+        }
+    }
+
+    /// The uppercase SHA-1 of a password split into the five characters sent to
+    /// the range endpoint and the thirty-five matched locally
+    fn sha1_range(password: &str) -> (String, String) {
+        let hex: String = Sha1::digest(password.as_bytes()).iter().map(|b| format!("{b:02X}")).collect();
+        let (prefix, suffix) = hex.split_at(5);
+        (prefix.to_string(), suffix.to_string())
+    }
+
+    /// How often `suffix` was seen, from a range response of `SUFFIX:COUNT` lines.
+    /// Padding entries carry a count of 0.
+    fn breach_count(range: &str, suffix: &str) -> u64 {
+        range
+            .lines()
+            .filter_map(|line| line.trim().split_once(':'))
+            .find(|(candidate, _)| candidate.eq_ignore_ascii_case(suffix))
+            .and_then(|(_, count)| count.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// The policy provided by `server::handle_lexodus`
+    pub fn password_policy() -> PasswordPolicy {
+        use_context::<PasswordPolicy>().unwrap_or_default()
+    }
+
+    fn common_passwords() -> &'static HashSet<&'static str> {
+        static COMMON: OnceLock<HashSet<&'static str>> = OnceLock::new();
+        COMMON.get_or_init(|| {
+            COMMON_PASSWORDS
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+    }
+
+    /// Undo the usual letter-for-symbol swaps, so `p@ssw0rd` reads as `password`
+    fn unleet(password: &str) -> String {
+        password
+            .chars()
+            .map(|c| match c {
+                '0' => 'o',
+                '1' | '!' => 'i',
+                '3' => 'e',
+                '4' | '@' => 'a',
+                '5' | '$' => 's',
+                '7' => 't',
+                c => c,
+            })
+            .collect()
+    }
+
+    /// Matches the denylist as typed, and with trailing digits and symbols removed
+    /// so `Password123!` is caught by `password`, before and after undoing
+    /// symbol swaps
+    fn is_common(password: &str) -> bool {
+        let common = common_passwords();
+        let lower = password.to_lowercase();
+        let stem = lower.trim_end_matches(|c: char| !c.is_alphabetic());
+        let matches = |candidate: &str| !candidate.is_empty() && common.contains(candidate);
+        matches(&lower) || matches(stem) || matches(&unleet(stem))
+    }
+
+    /// Policy and reuse checks for a new password on an existing account
+    pub async fn validate_new_password(user: &User, password: &str, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let policy = password_policy();
+        policy.validate(password, &user.username, &user.email).await?;
+        let recent = User::recent_password_hashes(user.id, policy.history_count, con).await?;
+        if recent.iter().any(|hash| verify_password(password, hash).is_ok()) {
+            return Err(LexodusAppError::BadRequest(
+                "Choose a password you have not used recently.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Hash and store an already validated password, keeping the old hash in history
+    pub async fn store_new_password(user_id: i64, password: &str, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let hash = hash_password(password.as_bytes())?;
+        User::update_password(user_id, &hash, password_policy().history_count, con).await
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(ChangePassword, "/api")]
+/// Change the logged in user's password. Other sessions are signed out.
+pub async fn change_password(
+    csrf_token: String,
+    current_password: String,
+    password: String,
+    password_confirmation: String,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+    let con = con()?;
+    let user = auth_cookie_session(&req, &con).await?;
+    if verify_password(&current_password, &user.password).is_err() {
+        return Err(LexodusAppError::BadRequest("Your current password is incorrect.".to_string()).into());
+    }
+    if password != password_confirmation {
+        return Err(LexodusAppError::BadRequest("Passwords did not match.".to_string()).into());
+    }
+    validate_new_password(&user, &password, &con).await?;
+    store_new_password(user.id, &password, &con).await?;
+
+    let remember = current_session(&req).await?.is_some_and(|s| is_remembered(&s));
+    revoke_user_sessions(user.id, None).await?;
+    start_session(&req, user.id, remember, &ClientInfo::from_request(&req)).await?;
+    tracing::info!("User {} changed their password", user.id);
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use crate::session_backend::tests::block_on;
+
+    fn offline() -> PasswordPolicy {
+        PasswordPolicy {
+            breach_range_url: None,
+            ..PasswordPolicy::default()
+        }
+    }
+
+    fn problems(password: &str) -> String {
+        match offline().check(password, "jsmith", "john.smith@court.gov") {
+            Ok(()) => String::new(),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn strong_password_passes() {
+        assert_eq!(problems("violet-harbor-tunnel-42"), "");
+        let validated = block_on(offline().validate("violet-harbor-tunnel-42", "jsmith", "john.smith@court.gov"));
+        assert!(validated.is_ok());
+    }
+
+    #[test]
+    fn breach_lookup_is_off_by_default() {
+        assert!(PasswordPolicy::default().breach_range_url.is_none());
+    }
+
+    #[test]
+    fn length_limits_are_enforced() {
+        assert!(problems("short-1").contains("at least 12"));
+        assert!(problems(&"x".repeat(129)).contains("at most 128"));
+        // Characters, not bytes, are counted
+        assert_eq!(problems("ééééééééééééé"), "");
+    }
+
+    #[test]
+    fn common_passwords_are_refused_with_decorations() {
+        assert!(is_common("password"));
+        assert!(is_common("Password123!"));
+        assert!(is_common("P@ssw0rd2024"));
+        assert!(!is_common("violet-harbor-tunnel"));
+        assert!(problems("Password1234!").contains("too common"));
+    }
+
+    #[test]
+    fn username_and_email_name_are_refused() {
+        assert!(problems("my-JSmith-password-2024").contains("username or email"));
+        assert!(problems("john.smith-forever-1").contains("username or email"));
+    }
+
+    #[test]
+    fn every_problem_is_reported_at_once() {
+        let message = problems("jsmith");
+        assert!(message.contains("at least 12"), "{message}");
+        assert!(message.contains("username or email"), "{message}");
+    }
+
+    #[test]
+    fn expiry_is_off_by_default_and_counted_in_days() {
+        let now = 1_700_000_000;
+        assert!(!offline().is_expired(Some(0), now));
+        let policy = PasswordPolicy { max_age_days: 90, ..offline() };
+        assert!(!policy.is_expired(Some(now - 89 * 86_400), now));
+        assert!(policy.is_expired(Some(now - 90 * 86_400), now));
+        // Accounts that never recorded a change are not forced to reset
+        assert!(!policy.is_expired(None, now));
+    }
+
+    #[test]
+    fn weaker_or_foreign_hashes_need_rehash() {
+        let policy = PasswordPolicy {
+            argon2_memory_kib: 8,
+            argon2_iterations: 1,
+            argon2_parallelism: 1,
+            ..offline()
+        };
+        let salt = argon2::password_hash::SaltString::from_b64("c29tZXNhbHRzb21lc2FsdA").unwrap();
+        let hash = |p: &PasswordPolicy| {
+            use argon2::PasswordHasher;
+            p.hasher().unwrap().hash_password(b"pw", &salt).unwrap().to_string()
+        };
+        let current = hash(&policy);
+        assert!(!policy.needs_rehash(&current));
+        let stronger = PasswordPolicy { argon2_iterations: 2, ..policy.clone() };
+        assert!(stronger.needs_rehash(&current));
+        assert!(!policy.needs_rehash("not a phc string"));
+    }
+
+    #[test]
+    fn only_the_hash_prefix_is_sent() {
+        // SHA-1("password") = 5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8
+        let (prefix, suffix) = sha1_range("password");
+        assert_eq!(prefix, "5BAA6");
+        assert_eq!(suffix, "1E4C9B93F3F0682250B6CF8331B7EE68FD8");
+    }
+
+    #[test]
+    fn range_response_is_matched_on_suffix() {
+        let range = "0018A45C4D1DEF81644B54AB7F969B88D65:1\r\n1E4C9B93F3F0682250B6CF8331B7EE68FD8:9659365\r\nFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF:0\r\n";
+        assert_eq!(breach_count(range, "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 9_659_365);
+        assert_eq!(breach_count(range, "1e4c9b93f3f0682250b6cf8331b7ee68fd8"), 9_659_365);
+        // Padding lines carry a zero count
+        assert_eq!(breach_count(range, "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF"), 0);
+        assert_eq!(breach_count(range, "0000000000000000000000000000000000A"), 0);
+        assert_eq!(breach_count("", "1E4C9B93F3F0682250B6CF8331B7EE68FD8"), 0);
+    }
+}
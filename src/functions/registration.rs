@@ -0,0 +1,142 @@
+use cfg_if::cfg_if;
+use leptos::{server, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::attorney_registration::{AttorneyRegistration, NewAttorneyRegistration};
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{hash_password, ClientInfo};
+    use crate::functions::con;
+    use crate::functions::csrf::verify_csrf;
+    use crate::functions::password_policy::password_policy;
+    use crate::mail::{app_base_url, Mailer, OutgoingMail};
+    use leptos::{expect_context, use_context};
+    use leptos_spin::RequestParts;
+    use spin_sdk::pg::Connection;
+    use std::sync::Arc;
+
+    /// At most this many requests from one address per window
+    const MAX_REQUESTS_PER_IP: i64 = 5;
+    /// At most this many requests for one email per window
+    const MAX_REQUESTS_PER_EMAIL: i64 = 3;
+    const RATE_LIMIT_WINDOW_SECS: i64 = 60 * 60;
+
+    async fn within_rate_limit(ip_address: Option<&str>, email: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        let (from_ip, for_email) = AttorneyRegistration::recent_counts(ip_address, email, RATE_LIMIT_WINDOW_SECS, con).await?;
+        Ok(from_ip < MAX_REQUESTS_PER_IP && for_email < MAX_REQUESTS_PER_EMAIL)
+    }
+
+    async fn notify(to: &str, subject: &str, body: String, con: &Arc<Connection>) {
+        let mail = OutgoingMail {
+            to: to.to_string(),
+            subject: subject.to_string(),
+            body,
+        };
+        if let Err(e) = expect_context::<Mailer>().send(mail, con).await {
+            tracing::error!("Failed to send registration email: {e}");
+        }
+    }
+
+    /// Tell the applicant their request was approved and they can log in
+    pub async fn notify_registration_approved(registration: &AttorneyRegistration, con: &Arc<Connection>) {
+        let body = format!(
+            "Hello {},\n\nYour request for a Lexodus attorney account has been approved. Log in as {} with the password you chose when you applied:\n\n{}/login\n",
+            registration.full_name,
+            registration.username,
+            app_base_url()
+        );
+        notify(&registration.email, "Your Lexodus account is ready", body, con).await;
+    }
+
+    /// Tell the applicant their request was denied, with the clerk's note if any
+    pub async fn notify_registration_denied(registration: &AttorneyRegistration, note: Option<&str>, con: &Arc<Connection>) {
+        let reason = note.map(|n| format!("The clerk's note:\n\n{n}\n\n")).unwrap_or_default();
+        let body = format!(
+            "Hello {},\n\nYour request for a Lexodus attorney account was not approved.\n\n{reason}Contact the clerk's office if you believe this is a mistake.\n",
+            registration.full_name
+        );
+        notify(&registration.email, "Your Lexodus registration request", body, con).await;
+    }
+}
+}
+
+/// Fields of the attorney registration form
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RegistrationRequestForm {
+    pub username: String,
+    pub email: String,
+    pub full_name: String,
+    pub bar_number: String,
+    pub admitting_jurisdiction: String,
+    pub firm_name: Option<String>,
+    pub password: String,
+    pub password_confirmation: String,
+}
+
+#[tracing::instrument(level = "info", fields(error), skip_all, err)]
+#[server(RequestAttorneyRegistration, "/api")]
+/// Ask for an attorney account. Nothing is created until a clerk approves the
+/// request; the password is kept only as a hash until then.
+pub async fn request_attorney_registration(csrf_token: String, form: RegistrationRequestForm) -> Result<(), ServerFnError> {
+    let RegistrationRequestForm {
+        username,
+        email,
+        full_name,
+        bar_number,
+        admitting_jurisdiction,
+        firm_name,
+        password,
+        password_confirmation,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    let (username, email, full_name) = (username.trim().to_string(), email.trim().to_string(), full_name.trim().to_string());
+    let (bar_number, admitting_jurisdiction) = (bar_number.trim().to_string(), admitting_jurisdiction.trim().to_string());
+    if [&username, &email, &full_name, &bar_number, &admitting_jurisdiction].iter().any(|v| v.is_empty()) {
+        return Err(LexodusAppError::BadRequest("Fill in every required field.".to_string()).into());
+    }
+    if password != password_confirmation {
+        return Err(LexodusAppError::BadRequest("Passwords did not match.".to_string()).into());
+    }
+    password_policy().validate(&password, &username, &email).await?;
+
+    let con = con()?;
+    let client = use_context::<RequestParts>().map(|req| ClientInfo::from_request(&req));
+    let ip_address = client.and_then(|c| c.ip_address);
+    if !within_rate_limit(ip_address.as_deref(), &email, &con).await? {
+        tracing::warn!("Attorney registration rate limit reached for {}", ip_address.as_deref().unwrap_or("an unknown address"));
+        return Err(LexodusAppError::TooManyRequests.into());
+    }
+    let taken = User::get_from_username(&username, &con).await?.is_some()
+        || User::get_from_email(&email, &con).await?.is_some()
+        || AttorneyRegistration::pending_conflict(&username, &email, &bar_number, &con).await?;
+    if taken {
+        return Err(LexodusAppError::BadRequest(
+            "That username, email address or bar number is already registered or awaiting review.".to_string(),
+        )
+        .into());
+    }
+
+    let id = AttorneyRegistration::create(
+        NewAttorneyRegistration {
+            username,
+            email: email.clone(),
+            full_name: full_name.clone(),
+            password_hash: hash_password(password.as_bytes())?,
+            bar_number,
+            admitting_jurisdiction,
+            firm_name: firm_name.map(|f| f.trim().to_string()).filter(|f| !f.is_empty()),
+            ip_address,
+        },
+        &con,
+    )
+    .await?;
+    tracing::info!("Attorney registration {id} submitted for review");
+
+    let body = format!(
+        "Hello {full_name},\n\nWe received your request for a Lexodus attorney account. The clerk's office will review it and email you at this address once it has been decided.\n"
+    );
+    notify(&email, "We received your Lexodus registration request", body, &con).await;
+    Ok(())
+}
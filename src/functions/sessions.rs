@@ -0,0 +1,388 @@
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// One of the caller's active sessions, as shown on the account sessions page
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionSummary {
+    pub id: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub remember: bool,
+    pub created_at_pretty: String,
+    pub last_seen_pretty: String,
+    pub expires_at_pretty: String,
+    /// The session making this request
+    pub current: bool,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{auth_cookie_session, current_session, expire_session_cookie, ClientInfo};
+    use crate::functions::csrf::verify_csrf;
+    use crate::session::UserSessions;
+    use crate::session_backend::SessionBackend;
+    use async_session::{chrono::{DateTime, Utc}, Session, SessionStore};
+    use leptos::expect_context;
+    use leptos_spin::RequestParts;
+    use spin_sdk::variables;
+
+    pub const USER_ID_KEY: &str = "user_id";
+    /// Set while the session's user is acting as another user; `USER_ID_KEY` stays theirs
+    pub const IMPERSONATED_USER_KEY: &str = "impersonated_user_id";
+    /// Set when the user signed in through SSO and the identity provider reported
+    /// multi-factor authentication. Stands in for local 2FA.
+    pub const IDP_MFA_KEY: &str = "idp_mfa";
+    const CREATED_AT_KEY: &str = "created_at";
+    const LAST_SEEN_KEY: &str = "last_seen";
+    const REMEMBER_KEY: &str = "remember";
+    const IP_KEY: &str = "ip_address";
+    const USER_AGENT_KEY: &str = "user_agent";
+    /// Renewal writes are skipped for requests closer together than this
+    const RENEW_INTERVAL_SECS: i64 = 60;
+
+    /// Session timeouts and cookie attributes, read from Spin variables at startup
+    #[derive(Clone, Debug)]
+    pub struct SessionConfig {
+        /// Inactivity allowed before a normal session expires
+        pub idle_secs: i64,
+        /// Hard limit on a normal session's lifetime, regardless of activity
+        pub absolute_secs: i64,
+        pub remember_idle_secs: i64,
+        pub remember_absolute_secs: i64,
+        /// Lifetime of the half-authenticated session between password and 2FA code
+        pub pending_secs: i64,
+        /// Add `Secure` to the session cookie. Only disable for plain-HTTP development.
+        pub secure_cookie: bool,
+    }
+
+    impl Default for SessionConfig {
+        fn default() -> Self {
+            Self {
+                idle_secs: 30 * 60,
+                absolute_secs: 12 * 60 * 60,
+                remember_idle_secs: 7 * 24 * 60 * 60,
+                remember_absolute_secs: 30 * 24 * 60 * 60,
+                pending_secs: 5 * 60,
+                secure_cookie: true,
+            }
+        }
+    }
+
+    impl SessionConfig {
+        /// Missing or unparsable variables fall back to the defaults
+        pub fn from_variables() -> Self {
+            let defaults = Self::default();
+            let secs = |name: &str, default: i64| {
+                variables::get(name)
+                    .ok()
+                    .and_then(|v| v.parse::<i64>().ok())
+                    .filter(|v| *v > 0)
+                    .unwrap_or(default)
+            };
+            Self {
+                idle_secs: secs("session_idle_timeout_secs", defaults.idle_secs),
+                absolute_secs: secs("session_absolute_timeout_secs", defaults.absolute_secs),
+                remember_idle_secs: secs("session_remember_idle_timeout_secs", defaults.remember_idle_secs),
+                remember_absolute_secs: secs("session_remember_absolute_timeout_secs", defaults.remember_absolute_secs),
+                pending_secs: defaults.pending_secs,
+                secure_cookie: variables::get("session_cookie_secure")
+                    .map(|v| v != "false")
+                    .unwrap_or(defaults.secure_cookie),
+            }
+        }
+
+        fn timeouts(&self, remember: bool) -> (i64, i64) {
+            if remember {
+                (self.remember_idle_secs, self.remember_absolute_secs)
+            } else {
+                (self.idle_secs, self.absolute_secs)
+            }
+        }
+    }
+
+    /// The config provided by `server::handle_lexodus`
+    pub fn session_config() -> SessionConfig {
+        use_context::<SessionConfig>().unwrap_or_default()
+    }
+
+    fn timestamp(secs: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(secs, 0).unwrap_or_default()
+    }
+
+    /// Build a fully authenticated session for `user_id`
+    pub fn new_user_session(user_id: i64, remember: bool, client: &ClientInfo, config: &SessionConfig) -> Result<Session, LexodusAppError> {
+        let now = Utc::now().timestamp();
+        let (idle, _) = config.timeouts(remember);
+        let mut session = Session::new();
+        session.insert(USER_ID_KEY, user_id)?;
+        session.insert(CREATED_AT_KEY, now)?;
+        session.insert(LAST_SEEN_KEY, now)?;
+        session.insert(REMEMBER_KEY, remember)?;
+        session.insert(IP_KEY, &client.ip_address)?;
+        session.insert(USER_AGENT_KEY, &client.user_agent)?;
+        session.set_expiry(timestamp(now + idle));
+        Ok(session)
+    }
+
+    /// Has the session outlived its absolute timeout
+    pub fn past_absolute_timeout(session: &Session, now: i64, config: &SessionConfig) -> bool {
+        let (_, absolute) = config.timeouts(is_remembered(session));
+        match session.get::<i64>(CREATED_AT_KEY) {
+            Some(created_at) => now >= created_at + absolute,
+            // Sessions issued before timeouts existed are treated as expired
+            None => true,
+        }
+    }
+
+    /// Slide the idle expiry forward, never past the absolute timeout.
+    /// Returns false when nothing changed and the session need not be stored.
+    pub fn renew(session: &mut Session, now: i64, config: &SessionConfig) -> Result<bool, LexodusAppError> {
+        let last_seen = session.get::<i64>(LAST_SEEN_KEY).unwrap_or_default();
+        if now - last_seen < RENEW_INTERVAL_SECS {
+            return Ok(false);
+        }
+        let (idle, absolute) = config.timeouts(is_remembered(session));
+        let created_at = session.get::<i64>(CREATED_AT_KEY).unwrap_or(now);
+        session.insert(LAST_SEEN_KEY, now)?;
+        session.set_expiry(timestamp((now + idle).min(created_at + absolute)));
+        Ok(true)
+    }
+
+    pub fn is_remembered(session: &Session) -> bool {
+        session.get::<bool>(REMEMBER_KEY).unwrap_or(false)
+    }
+
+    /// Cookie `Max-Age` for a session: persistent only when remembered
+    pub fn cookie_max_age(remember: bool, config: &SessionConfig) -> Option<i64> {
+        remember.then_some(config.remember_absolute_secs)
+    }
+
+    fn summarize(session: &Session, current_id: Option<&str>) -> SessionSummary {
+        let pretty = |key: &str| {
+            session
+                .get::<i64>(key)
+                .map(|ts| timestamp(ts).to_string())
+                .unwrap_or_default()
+        };
+        SessionSummary {
+            id: session.id().to_string(),
+            ip_address: session.get::<Option<String>>(IP_KEY).flatten(),
+            user_agent: session.get::<Option<String>>(USER_AGENT_KEY).flatten(),
+            remember: is_remembered(session),
+            created_at_pretty: pretty(CREATED_AT_KEY),
+            last_seen_pretty: pretty(LAST_SEEN_KEY),
+            expires_at_pretty: session.expiry().map(|e| e.to_string()).unwrap_or_default(),
+            current: Some(session.id()) == current_id,
+        }
+    }
+
+    /// Destroy every session belonging to the user, optionally sparing one
+    pub async fn revoke_user_sessions(user_id: i64, keep: Option<&str>) -> Result<usize, LexodusAppError> {
+        let store = expect_context::<SessionBackend>();
+        let mut revoked = 0;
+        for session in store.sessions_for_user(user_id).await? {
+            if Some(session.id()) != keep {
+                store.destroy_session(session).await?;
+                revoked += 1;
+            }
+        }
+        Ok(revoked)
+    }
+
+    async fn caller() -> Result<(RequestParts, User, Option<Session>), LexodusAppError> {
+        let req = use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)?;
+        let con = crate::functions::con()?;
+        let user = auth_cookie_session(&req, &con)
+            .await
+            .map_err(|_| LexodusAppError::Unauthorized)?;
+        let session = current_session(&req).await?;
+        Ok((req, user, session))
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(ListSessions, "/api")]
+/// The caller's unexpired sessions, newest activity first
+pub async fn list_sessions() -> Result<Vec<SessionSummary>, ServerFnError> {
+    let (_, user, current) = caller().await?;
+    let current_id = current.as_ref().map(|s| s.id().to_string());
+    let store = expect_context::<SessionBackend>();
+    let mut sessions = store
+        .sessions_for_user(user.id)
+        .await
+        .map_err(LexodusAppError::from)?;
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.get::<i64>(LAST_SEEN_KEY).unwrap_or_default()));
+    Ok(sessions
+        .iter()
+        .map(|s| summarize(s, current_id.as_deref()))
+        .collect())
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(RevokeSession, "/api")]
+/// Sign out one of the caller's own sessions
+pub async fn revoke_session(csrf_token: String, session_id: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let (_, user, current) = caller().await?;
+    let store = expect_context::<SessionBackend>();
+    let Some(session) = store
+        .sessions_for_user(user.id)
+        .await
+        .map_err(LexodusAppError::from)?
+        .into_iter()
+        .find(|s| s.id() == session_id)
+    else {
+        return Err(LexodusAppError::NotFound.into());
+    };
+    let is_current = current.is_some_and(|c| c.id() == session.id());
+    store
+        .destroy_session(session)
+        .await
+        .map_err(LexodusAppError::from)?;
+    if is_current {
+        expire_session_cookie();
+        leptos_spin::redirect("/login");
+    }
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(RevokeAllSessions, "/api")]
+/// Log out everywhere, including this browser
+pub async fn revoke_all_sessions(csrf_token: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let (_, user, _) = caller().await?;
+    let revoked = revoke_user_sessions(user.id, None).await?;
+    tracing::info!("User {} revoked {revoked} sessions", user.id);
+    expire_session_cookie();
+    leptos_spin::redirect("/login");
+    Ok(())
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_700_000_000;
+
+    fn config() -> SessionConfig {
+        SessionConfig {
+            idle_secs: 30 * 60,
+            absolute_secs: 12 * 60 * 60,
+            remember_idle_secs: 7 * 24 * 60 * 60,
+            remember_absolute_secs: 30 * 24 * 60 * 60,
+            pending_secs: 5 * 60,
+            secure_cookie: true,
+        }
+    }
+
+    /// A session for user 7 as if issued at `created_at`
+    fn issued(remember: bool, created_at: i64) -> Session {
+        let client = ClientInfo {
+            ip_address: Some("203.0.113.9".to_string()),
+            user_agent: Some("Mozilla/5.0".to_string()),
+        };
+        let mut session = new_user_session(7, remember, &client, &config()).unwrap();
+        session.insert(CREATED_AT_KEY, created_at).unwrap();
+        session.insert(LAST_SEEN_KEY, created_at).unwrap();
+        session.set_expiry(timestamp(created_at + config().timeouts(remember).0));
+        session
+    }
+
+    fn expiry(session: &Session) -> i64 {
+        session.expiry().unwrap().timestamp()
+    }
+
+    #[test]
+    fn new_session_records_the_user_and_client() {
+        let session = new_user_session(7, false, &ClientInfo { ip_address: None, user_agent: Some("curl".to_string()) }, &config()).unwrap();
+        assert_eq!(session.get::<i64>(USER_ID_KEY), Some(7));
+        assert!(!is_remembered(&session));
+        assert_eq!(session.get::<Option<String>>(USER_AGENT_KEY).flatten().as_deref(), Some("curl"));
+        assert_eq!(session.get::<Option<String>>(IP_KEY).flatten(), None);
+        let created_at = session.get::<i64>(CREATED_AT_KEY).unwrap();
+        assert_eq!(expiry(&session), created_at + config().idle_secs);
+    }
+
+    #[test]
+    fn remembered_session_lasts_the_remembered_idle_timeout() {
+        let session = new_user_session(7, true, &ClientInfo { ip_address: None, user_agent: None }, &config()).unwrap();
+        assert!(is_remembered(&session));
+        let created_at = session.get::<i64>(CREATED_AT_KEY).unwrap();
+        assert_eq!(expiry(&session), created_at + config().remember_idle_secs);
+    }
+
+    #[test]
+    fn absolute_timeout_depends_on_remember() {
+        let config = config();
+        let normal = issued(false, NOW);
+        assert!(!past_absolute_timeout(&normal, NOW + config.absolute_secs - 1, &config));
+        assert!(past_absolute_timeout(&normal, NOW + config.absolute_secs, &config));
+
+        let remembered = issued(true, NOW);
+        assert!(!past_absolute_timeout(&remembered, NOW + config.absolute_secs, &config));
+        assert!(past_absolute_timeout(&remembered, NOW + config.remember_absolute_secs, &config));
+    }
+
+    #[test]
+    fn session_without_a_creation_time_is_past_its_timeout() {
+        let mut legacy = issued(false, NOW);
+        legacy.remove(CREATED_AT_KEY);
+        assert!(past_absolute_timeout(&legacy, NOW, &config()));
+    }
+
+    #[test]
+    fn renewal_within_the_interval_is_skipped() {
+        let mut session = issued(false, NOW);
+        let before = expiry(&session);
+        assert!(!renew(&mut session, NOW + RENEW_INTERVAL_SECS - 1, &config()).unwrap());
+        assert_eq!(expiry(&session), before);
+        assert_eq!(session.get::<i64>(LAST_SEEN_KEY), Some(NOW));
+    }
+
+    #[test]
+    fn renewal_slides_the_idle_expiry() {
+        let config = config();
+        let mut session = issued(false, NOW);
+        let later = NOW + 10 * 60;
+        assert!(renew(&mut session, later, &config).unwrap());
+        assert_eq!(session.get::<i64>(LAST_SEEN_KEY), Some(later));
+        assert_eq!(expiry(&session), later + config.idle_secs);
+    }
+
+    #[test]
+    fn renewal_never_passes_the_absolute_timeout() {
+        let config = config();
+        let mut session = issued(false, NOW);
+        let late = NOW + config.absolute_secs - 5 * 60;
+        assert!(renew(&mut session, late, &config).unwrap());
+        assert_eq!(expiry(&session), NOW + config.absolute_secs);
+
+        let mut remembered = issued(true, NOW);
+        let late = NOW + config.remember_absolute_secs - 60 * 60;
+        assert!(renew(&mut remembered, late, &config).unwrap());
+        assert_eq!(expiry(&remembered), NOW + config.remember_absolute_secs);
+    }
+
+    #[test]
+    fn only_remembered_sessions_get_a_persistent_cookie() {
+        let config = config();
+        assert_eq!(cookie_max_age(false, &config), None);
+        assert_eq!(cookie_max_age(true, &config), Some(config.remember_absolute_secs));
+    }
+
+    #[test]
+    fn summary_marks_the_current_session() {
+        let session = issued(true, NOW);
+        let summary = summarize(&session, Some(session.id()));
+        assert!(summary.current);
+        assert!(summary.remember);
+        assert_eq!(summary.ip_address.as_deref(), Some("203.0.113.9"));
+        assert!(!summarize(&session, Some("another")).current);
+        assert!(!summarize(&session, None).current);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use cfg_if::cfg_if;
+use leptos::{server, use_context, ServerFnError};
+use serde::{Deserialize, Serialize};
+
+/// What the browser needs to show while a user enrolls an authenticator app
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TwoFactorEnrollment {
+    /// Base32 secret, for apps that cannot scan the QR code
+    pub secret: String,
+    pub otpauth_uri: String,
+    /// SVG markup of the otpauth URI
+    pub qr_svg: String,
+}
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+    use crate::domain::models::user::User;
+    use crate::errors::LexodusAppError;
+    use crate::functions::auth::{
+        auth_cookie_session, current_session, expire_session_cookie, get_session_cookie_value,
+        logout_session, record_auth_failure, record_login_success, redirect_if_password_expired,
+        rotate_session, save_session, start_session, verify_password, ClientInfo, PENDING_2FA_KEY,
+        PENDING_REMEMBER_KEY, PENDING_SSO_KEY,
+    };
+    use crate::functions::sessions::revoke_user_sessions;
+    use crate::domain::models::auth_log::event_type;
+    use chrono::Utc;
+    use crate::functions::authz::authorize;
+    use crate::functions::csrf::verify_csrf;
+    use crate::functions::con;
+    use async_session::Session;
+    use leptos_spin::RequestParts;
+    use spin_sdk::pg::Connection;
+    use std::sync::Arc;
+    use qrcode::{render::svg, QrCode};
+    use rand_core::{OsRng, RngCore};
+    use sha2::{Digest, Sha256};
+    use totp_rs::{Algorithm, Secret, TOTP};
+
+    /// Session key holding the not yet confirmed secret during enrollment
+    const PENDING_SECRET_KEY: &str = "pending_2fa_secret";
+    /// Session key counting failed second-step attempts
+    const ATTEMPTS_KEY: &str = "pending_2fa_attempts";
+    /// Failed codes allowed before the pending session is thrown away
+    const MAX_ATTEMPTS: u32 = 5;
+    const RECOVERY_CODE_COUNT: usize = 10;
+    const ISSUER: &str = "Lexodus";
+
+    fn build_totp(secret: &str, username: &str) -> Result<TOTP, LexodusAppError> {
+        let bytes = Secret::Encoded(secret.to_string())
+            .to_bytes()
+            .map_err(|e| LexodusAppError::ServerError(format!("Invalid TOTP secret: {e:?}")))?;
+        TOTP::new(Algorithm::SHA1, 6, 1, 30, bytes, Some(ISSUER.to_string()), username.to_string())
+            .map_err(|e| LexodusAppError::ServerError(format!("Invalid TOTP parameters: {e}")))
+    }
+
+    fn generate_secret() -> String {
+        let mut bytes = [0u8; 20];
+        OsRng.fill_bytes(&mut bytes);
+        match Secret::Raw(bytes.to_vec()).to_encoded() {
+            Secret::Encoded(s) => s,
+            Secret::Raw(_) => unreachable!("to_encoded always returns Secret::Encoded"),
+        }
+    }
+
+    /// The time step `code` was generated for, allowing the TOTP's skew of steps
+    /// either side of the one `time` falls in
+    fn code_step(totp: &TOTP, code: &str, time: u64) -> Option<u64> {
+        let code = code.trim();
+        let current = time / totp.step;
+        let skew = u64::from(totp.skew);
+        (current.saturating_sub(skew)..=current + skew).find(|step| totp.generate(step * totp.step) == code)
+    }
+
+    /// Accept a current authenticator code once. A code from the step of one accepted
+    /// earlier, or from before it, is refused even while it is still current.
+    async fn accept_code(totp: &TOTP, user_id: i64, code: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        match code_step(totp, code, Utc::now().timestamp() as u64) {
+            Some(step) => User::accept_two_factor_step(user_id, step as i64, con).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Whether `proof` is the user's password or a current code from their authenticator
+    async fn proves_enrolled_user(user: &User, proof: &str, con: &Arc<Connection>) -> Result<bool, LexodusAppError> {
+        if verify_password(proof, &user.password).is_ok() {
+            return Ok(true);
+        }
+        match User::two_factor_secret(user.id, con).await? {
+            Some(secret) => accept_code(&build_totp(&secret, &user.username)?, user.id, proof, con).await,
+            None => Ok(false),
+        }
+    }
+
+    /// Recovery codes look like `a1b2c3d4-e5f6a7b8` and are shown to the user exactly once
+    fn generate_recovery_codes() -> Vec<String> {
+        (0..RECOVERY_CODE_COUNT)
+            .map(|_| {
+                let mut bytes = [0u8; 8];
+                OsRng.fill_bytes(&mut bytes);
+                let hex: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+                format!("{}-{}", &hex[..8], &hex[8..])
+            })
+            .collect()
+    }
+
+    /// Only the SHA-256 of a recovery code is stored
+    pub fn hash_recovery_code(code: &str) -> String {
+        format!("{:x}", Sha256::digest(code.trim().to_lowercase().as_bytes()))
+    }
+
+    fn render_qr(uri: &str) -> Result<String, LexodusAppError> {
+        let code = QrCode::new(uri.as_bytes())
+            .map_err(|e| LexodusAppError::ServerError(format!("Failed to render QR code: {e}")))?;
+        Ok(code.render::<svg::Color>().min_dimensions(200, 200).build())
+    }
+
+    fn request_parts() -> Result<RequestParts, LexodusAppError> {
+        use_context::<RequestParts>().ok_or(LexodusAppError::Unauthorized)
+    }
+}
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(current_proof), err)]
+#[server(BeginTwoFactorEnrollment, "/api")]
+/// Generate a new secret for the logged in user. It is kept in the session until confirmed.
+/// A user replacing an enrolled authenticator proves it is them with `current_proof`, their
+/// password or a current code, so a hijacked session cannot move the second factor.
+pub async fn begin_two_factor_enrollment(csrf_token: String, current_proof: String) -> Result<TwoFactorEnrollment, ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = request_parts()?;
+    let con = con()?;
+    let user = auth_cookie_session(&req, &con).await?;
+    let Some(mut session) = current_session(&req).await? else {
+        return Err(LexodusAppError::Unauthorized.into());
+    };
+    if user.two_factor_enabled && !proves_enrolled_user(&user, &current_proof, &con).await? {
+        record_auth_failure(&user, event_type::TWO_FACTOR, &ClientInfo::from_request(&req), &con).await?;
+        return Err(LexodusAppError::BadRequest(
+            "Enter your password or a current authentication code to replace your authenticator.".to_string(),
+        )
+        .into());
+    }
+
+    let secret = generate_secret();
+    let totp = build_totp(&secret, &user.username)?;
+    let otpauth_uri = totp.get_url();
+    let qr_svg = render_qr(&otpauth_uri)?;
+
+    session.insert(PENDING_SECRET_KEY, secret.clone())?;
+    save_session(session).await?;
+
+    Ok(TwoFactorEnrollment {
+        secret,
+        otpauth_uri,
+        qr_svg,
+    })
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(code), err)]
+#[server(ConfirmTwoFactorEnrollment, "/api")]
+/// Check a code against the pending secret and turn on 2FA. Returns the plain recovery codes.
+pub async fn confirm_two_factor_enrollment(csrf_token: String, code: String) -> Result<Vec<String>, ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = request_parts()?;
+    let con = con()?;
+    let user = auth_cookie_session(&req, &con).await?;
+    let Some(mut session) = current_session(&req).await? else {
+        return Err(LexodusAppError::Unauthorized.into());
+    };
+    let Some(secret) = session.get::<String>(PENDING_SECRET_KEY) else {
+        return Err(LexodusAppError::BadRequest("No enrollment in progress".to_string()).into());
+    };
+
+    let Some(step) = code_step(&build_totp(&secret, &user.username)?, &code, Utc::now().timestamp() as u64) else {
+        return Err(LexodusAppError::BadRequest("Invalid authentication code".to_string()).into());
+    };
+
+    let recovery_codes = generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|c| hash_recovery_code(c)).collect();
+    User::enable_two_factor(user.id, &secret, &hashes, step as i64, &con).await?;
+
+    session.remove(PENDING_SECRET_KEY);
+    save_session(session).await?;
+    rotate_session(&req, user.id, &ClientInfo::from_request(&req)).await?;
+    tracing::info!("User {} enabled two-factor authentication", user.id);
+
+    Ok(recovery_codes)
+}
+
+#[tracing::instrument(level = "info", fields(error), skip(code), err)]
+#[server(VerifyTwoFactor, "/api")]
+/// Second login step. Accepts a TOTP code or an unused recovery code and, on success,
+/// replaces the pending session with a fully authenticated one.
+pub async fn verify_two_factor(csrf_token: String, code: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let req = request_parts()?;
+    let con = con()?;
+    let Some(cookie_value) = get_session_cookie_value(&req)? else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    let Some(mut session) = current_session(&req).await? else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    let Some(user_id) = session.get::<i64>(PENDING_2FA_KEY) else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    let Some(user) = User::get(user_id, &con).await? else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    let Some(secret) = User::two_factor_secret(user.id, &con).await? else {
+        return Err(LexodusAppError::AuthError.into());
+    };
+    let client = ClientInfo::from_request(&req);
+    if user.is_locked(Utc::now().timestamp()) {
+        logout_session(&cookie_value).await?;
+        return Err(LexodusAppError::AccountLocked.into());
+    }
+
+    let accepted = accept_code(&build_totp(&secret, &user.username)?, user.id, &code, &con).await?
+        || User::consume_recovery_code(user.id, &hash_recovery_code(&code), &con).await?;
+
+    if !accepted {
+        record_auth_failure(&user, event_type::TWO_FACTOR, &client, &con).await?;
+        let attempts = session.get::<u32>(ATTEMPTS_KEY).unwrap_or(0) + 1;
+        if attempts >= MAX_ATTEMPTS {
+            tracing::warn!("Too many second-factor failures for user {}", user.id);
+            logout_session(&cookie_value).await?;
+            leptos_spin::redirect("/login");
+            return Err(LexodusAppError::AuthError.into());
+        }
+        session.insert(ATTEMPTS_KEY, attempts)?;
+        save_session(session).await?;
+        return Err(LexodusAppError::AuthError.into());
+    }
+
+    // Only now, with both factors proven, may an expired password be replaced.
+    // SSO users never sign in with their local password, so it cannot expire for them.
+    let via_sso = session.get::<bool>(PENDING_SSO_KEY).unwrap_or(false);
+    if !via_sso && redirect_if_password_expired(&user, &con).await? {
+        logout_session(&cookie_value).await?;
+        expire_session_cookie();
+        return Ok(());
+    }
+
+    record_login_success(&user, &client, &con).await?;
+    // Issue a new session id once the user is fully authenticated
+    let remember = session.get::<bool>(PENDING_REMEMBER_KEY).unwrap_or(false);
+    start_session(&req, user.id, remember, &client).await?;
+    leptos_spin::redirect("/cases");
+
+    Ok(())
+}
+
+#[tracing::instrument(level = "info", fields(error), err)]
+#[server(ResetTwoFactor, "/api")]
+/// Admin reset for a user who lost their authenticator and recovery codes
+pub async fn reset_two_factor(csrf_token: String, user_id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let admin = authorize::<ResetTwoFactor>().await?;
+    let con = con()?;
+    User::clear_two_factor(user_id, &con).await?;
+    // Sessions established with the old factor must not outlive it
+    revoke_user_sessions(user_id, None).await?;
+    tracing::info!("User {} reset two-factor authentication for user {}", admin.id, user_id);
+    Ok(())
+}
+
+impl RequiresPermission for ResetTwoFactor {
+    const PERMISSION: Permission = Permission::USER_UPDATE;
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    /// A fixed time in the middle of a 30 second step
+    const NOW: u64 = 1_700_000_015;
+
+    #[test]
+    fn current_code_matches_and_others_do_not() {
+        let secret = generate_secret();
+        let totp = build_totp(&secret, "judge.smith").unwrap();
+        let code = totp.generate(NOW);
+        assert_eq!(code_step(&totp, &code, NOW), Some(NOW / 30));
+        assert_eq!(code_step(&totp, &format!(" {code}\n"), NOW), Some(NOW / 30));
+        let wrong = format!("{:06}", (code.parse::<u32>().unwrap() + 500_000) % 1_000_000);
+        assert_eq!(code_step(&totp, &wrong, NOW), None);
+        assert_eq!(code_step(&totp, "", NOW), None);
+        assert_eq!(code_step(&totp, "abcdef", NOW), None);
+    }
+
+    #[test]
+    fn code_is_placed_in_the_step_it_was_generated_for() {
+        let totp = build_totp(&generate_secret(), "judge.smith").unwrap();
+        let step = NOW / 30;
+        assert_eq!(code_step(&totp, &totp.generate(NOW - 30), NOW), Some(step - 1));
+        assert_eq!(code_step(&totp, &totp.generate(NOW + 30), NOW), Some(step + 1));
+        assert_eq!(code_step(&totp, &totp.generate(NOW - 60), NOW), None);
+    }
+
+    #[test]
+    fn code_for_another_secret_does_not_match() {
+        let ours = build_totp(&generate_secret(), "clerk").unwrap();
+        let theirs = build_totp(&generate_secret(), "clerk").unwrap();
+        assert_eq!(code_step(&ours, &theirs.generate(NOW), NOW), None);
+    }
+
+    #[test]
+    fn otpauth_uri_names_issuer_and_account() {
+        let totp = build_totp(&generate_secret(), "judge.smith").unwrap();
+        let uri = totp.get_url();
+        assert!(uri.starts_with("otpauth://totp/Lexodus:judge.smith?"), "{uri}");
+        assert!(uri.contains("issuer=Lexodus"), "{uri}");
+    }
+
+    #[test]
+    fn invalid_secret_is_rejected() {
+        assert!(build_totp("not base32!", "judge").is_err());
+    }
+
+    #[test]
+    fn recovery_codes_are_unique_and_well_formed() {
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        for code in &codes {
+            let (a, b) = code.split_once('-').unwrap();
+            assert_eq!((a.len(), b.len()), (8, 8));
+            assert!(code.chars().all(|c| c == '-' || c.is_ascii_hexdigit()));
+        }
+        let mut unique = codes.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), codes.len());
+    }
+
+    #[test]
+    fn recovery_code_hash_ignores_case_and_whitespace() {
+        let hash = hash_recovery_code("a1b2c3d4-e5f6a7b8");
+        assert_eq!(hash.len(), 64);
+        assert_eq!(hash, hash_recovery_code("  A1B2C3D4-E5F6A7B8 "));
+        assert_ne!(hash, hash_recovery_code("a1b2c3d4-e5f6a7b9"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::domain::models::user::{SafeUser, User};
+use crate::domain::models::user::SafeUser;
 use cfg_if::cfg_if;
 use leptos::{expect_context, server, use_context, ServerFnError};
 
@@ -11,17 +11,14 @@ cfg_if! {
 
 #[tracing::instrument(level = "info", fields(error), ret, err)]
 #[server(GetUser, "/api")]
-/// Get the current user if it exists by checking the user's session against the DB
-pub async fn get_user() -> Result<Option<User>, ServerFnError> {
+/// Get the current user if it exists by checking the user's session against the DB.
+/// Never returns the password hash, lockout or 2FA state to the browser.
+pub async fn get_user() -> Result<Option<SafeUser>, ServerFnError> {
     let Some(req) = use_context::<leptos_spin::RequestParts>() else {
         return Err(ServerFnError::MissingArg("Missing Request".to_string()));
     };
     let con = con()?;
-    let user = match auth_session(&req, &con).await {
-        Ok(u) => Some(u),
-        Err(_) => None,
-    };
-    Ok(user)
+    Ok(auth_session(&req, &con).await.ok().map(SafeUser::from))
 }
 
 #[tracing::instrument(level = "info", fields(error), ret, err)]
@@ -0,0 +1,342 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use async_session::{Session, Result, chrono::Utc, SessionStore};
+use spin_sdk::key_value::Store;
+use crate::session::UserSessions;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+/// The parts of Spin's key-value interface the session store relies on, so
+/// the store can also run over an in-memory map
+pub trait KeyValue: std::fmt::Debug + Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>>;
+    fn set(&self, key: &str, value: &[u8]) -> Result<()>;
+    fn delete(&self, key: &str) -> Result<()>;
+    fn get_keys(&self) -> Result<Vec<String>>;
+}
+
+impl KeyValue for Store {
+    fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+        Ok(Store::get(self, key)?)
+    }
+
+    fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+        Ok(Store::set(self, key, value)?)
+    }
+
+    fn delete(&self, key: &str) -> Result<()> {
+        Ok(Store::delete(self, key)?)
+    }
+
+    fn get_keys(&self) -> Result<Vec<String>> {
+        Ok(Store::get_keys(self)?)
+    }
+}
+
+/// Session store on Spin key-value. The KV interface has no native TTL, so each
+/// value carries its expiry and expired entries are dropped when read or by `cleanup`.
+#[derive(Debug, Clone)]
+pub struct KvStore {
+    store: Arc<dyn KeyValue>,
+    prefix: String,
+}
+
+/// What is written under each session key
+#[derive(Debug, Serialize, Deserialize)]
+struct KvSessionEntry {
+    expiry: Option<i64>,
+    session: String,
+}
+
+impl KvSessionEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        self.expiry.is_some_and(|expiry| expiry <= now)
+    }
+}
+
+impl KvStore {
+    pub fn from_store(store: Arc<dyn KeyValue>) -> Self {
+        Self {
+            store,
+            prefix: "session".to_string(),
+        }
+    }
+
+    fn session_key(&self, id: &str) -> String {
+        format!("{}:{}", self.prefix, id)
+    }
+
+    fn user_index_key(&self, user_id: i64) -> String {
+        format!("{}-user:{}", self.prefix, user_id)
+    }
+
+    fn read_entry(&self, key: &str) -> Result<Option<KvSessionEntry>> {
+        match self.store.get(key)? {
+            Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn read_index(&self, user_id: i64) -> Result<Vec<String>> {
+        match self.store.get(&self.user_index_key(user_id))? {
+            Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn write_index(&self, user_id: i64, ids: &[String]) -> Result<()> {
+        let key = self.user_index_key(user_id);
+        if ids.is_empty() {
+            self.store.delete(&key)?;
+        } else {
+            self.store.set(&key, &serde_json::to_vec(ids)?)?;
+        }
+        Ok(())
+    }
+
+    /// Load a live session by id, deleting it if it has expired
+    fn load_by_id(&self, id: &str, now: i64) -> Result<Option<Session>> {
+        let key = self.session_key(id);
+        let Some(entry) = self.read_entry(&key)? else {
+            return Ok(None);
+        };
+        if entry.is_expired(now) {
+            self.store.delete(&key)?;
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&entry.session)?))
+    }
+
+    /// Performs a one-time cleanup task that clears out stale
+    /// (expired) sessions. You may want to call this from cron.
+    pub async fn cleanup(&self) -> Result<()> {
+        self.cleanup_at(Utc::now().timestamp())
+    }
+
+    /// Delete the sessions that have expired as of `now`
+    fn cleanup_at(&self, now: i64) -> Result<()> {
+        info!("cleaning up expired sessions under `{}`", self.prefix);
+        let session_prefix = format!("{}:", self.prefix);
+        for key in self.store.get_keys()? {
+            if !key.starts_with(&session_prefix) {
+                continue;
+            }
+            if self.read_entry(&key)?.is_some_and(|entry| entry.is_expired(now)) {
+                self.store.delete(&key)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl UserSessions for KvStore {
+    async fn sessions_for_user(&self, user_id: i64) -> Result<Vec<Session>> {
+        let now = Utc::now().timestamp();
+        let ids = self.read_index(user_id)?;
+        let mut live_ids = Vec::with_capacity(ids.len());
+        let mut sessions = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(session) = self.load_by_id(&id, now)? {
+                live_ids.push(id);
+                sessions.push(session);
+            }
+        }
+        // Drop ids of sessions that expired or were destroyed elsewhere
+        self.write_index(user_id, &live_ids)?;
+        Ok(sessions)
+    }
+}
+
+#[async_trait]
+impl SessionStore for KvStore {
+    async fn load_session(&self, cookie_value: String) -> Result<Option<Session>> {
+        let id = Session::id_from_cookie_value(&cookie_value)?;
+        self.load_by_id(&id, Utc::now().timestamp())
+    }
+
+    async fn store_session(&self, session: Session) -> Result<Option<String>> {
+        let id = session.id().to_string();
+        let entry = KvSessionEntry {
+            expiry: session.expiry().map(|expiry| expiry.timestamp()),
+            session: serde_json::to_string(&session)?,
+        };
+        self.store.set(&self.session_key(&id), &serde_json::to_vec(&entry)?)?;
+
+        if let Some(user_id) = session.get::<i64>("user_id") {
+            let mut ids = self.read_index(user_id)?;
+            if !ids.contains(&id) {
+                ids.push(id);
+                self.write_index(user_id, &ids)?;
+            }
+        }
+
+        Ok(session.into_cookie_value())
+    }
+
+    async fn destroy_session(&self, session: Session) -> Result {
+        let id = session.id();
+        self.store.delete(&self.session_key(id))?;
+
+        if let Some(user_id) = session.get::<i64>("user_id") {
+            let mut ids = self.read_index(user_id)?;
+            ids.retain(|i| i != id);
+            self.write_index(user_id, &ids)?;
+        }
+        Ok(())
+    }
+
+    async fn clear_store(&self) -> Result {
+        let session_prefix = format!("{}:", self.prefix);
+        let index_prefix = format!("{}-user:", self.prefix);
+        for key in self.store.get_keys()? {
+            if key.starts_with(&session_prefix) || key.starts_with(&index_prefix) {
+                self.store.delete(&key)?;
+            }
+        }
+        Ok(())
+    }
+}
+}}
+
+/// An in-memory stand-in for Spin key-value, for running the session store in tests
+#[cfg(all(test, feature = "ssr"))]
+pub(crate) mod memory {
+    use super::KeyValue;
+    use async_session::Result;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Default)]
+    pub struct MemoryKv(Mutex<BTreeMap<String, Vec<u8>>>);
+
+    impl KeyValue for MemoryKv {
+        fn get(&self, key: &str) -> Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn set(&self, key: &str, value: &[u8]) -> Result<()> {
+            self.0.lock().unwrap().insert(key.to_string(), value.to_vec());
+            Ok(())
+        }
+
+        fn delete(&self, key: &str) -> Result<()> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        fn get_keys(&self) -> Result<Vec<String>> {
+            Ok(self.0.lock().unwrap().keys().cloned().collect())
+        }
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::memory::MemoryKv;
+    use super::*;
+    use crate::session_backend::tests::block_on;
+    use async_session::chrono::{Duration, TimeZone};
+
+    fn store() -> (Arc<MemoryKv>, KvStore) {
+        let kv = Arc::new(MemoryKv::default());
+        (kv.clone(), KvStore::from_store(kv))
+    }
+
+    #[test]
+    fn entry_without_expiry_never_expires() {
+        let entry = KvSessionEntry { expiry: None, session: String::new() };
+        assert!(!entry.is_expired(i64::MAX));
+    }
+
+    #[test]
+    fn entry_expires_at_its_expiry() {
+        let entry = KvSessionEntry { expiry: Some(1_000), session: String::new() };
+        assert!(!entry.is_expired(999));
+        assert!(entry.is_expired(1_000));
+        assert!(entry.is_expired(1_001));
+    }
+
+    #[test]
+    fn stored_entry_carries_the_session_expiry() {
+        let (kv, store) = store();
+        let mut session = Session::new();
+        let expiry = Utc.timestamp_opt(2_000_000_000, 0).unwrap();
+        session.set_expiry(expiry);
+        let id = session.id().to_string();
+        block_on(store.store_session(session)).unwrap();
+
+        let bytes = kv.get(&format!("session:{id}")).unwrap().unwrap();
+        let entry: KvSessionEntry = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(entry.expiry, Some(2_000_000_000));
+    }
+
+    #[test]
+    fn expired_entry_is_deleted_when_read() {
+        let (kv, store) = store();
+        let mut session = Session::new();
+        session.set_expiry(Utc.timestamp_opt(1_000, 0).unwrap());
+        let id = session.id().to_string();
+        block_on(store.store_session(session)).unwrap();
+
+        assert!(store.load_by_id(&id, 999).unwrap().is_some());
+        assert!(store.load_by_id(&id, 1_000).unwrap().is_none());
+        assert!(kv.get(&format!("session:{id}")).unwrap().is_none());
+    }
+
+    #[test]
+    fn renewing_moves_the_expiry_forward() {
+        let (_, store) = store();
+        let mut session = Session::new();
+        session.set_expiry(Utc.timestamp_opt(1_000, 0).unwrap());
+        let id = session.id().to_string();
+        block_on(store.store_session(session)).unwrap();
+
+        let mut renewed = store.load_by_id(&id, 500).unwrap().unwrap();
+        renewed.set_expiry(Utc.timestamp_opt(1_000, 0).unwrap() + Duration::seconds(600));
+        block_on(store.store_session(renewed)).unwrap();
+
+        assert!(store.load_by_id(&id, 1_500).unwrap().is_some());
+        assert!(store.load_by_id(&id, 1_600).unwrap().is_none());
+    }
+
+    #[test]
+    fn cleanup_keeps_live_sessions_and_other_keys() {
+        let (kv, store) = store();
+        let mut stale = Session::new();
+        stale.set_expiry(Utc.timestamp_opt(1_000, 0).unwrap());
+        let stale_id = stale.id().to_string();
+        let live = Session::new();
+        let live_id = live.id().to_string();
+        block_on(store.store_session(stale)).unwrap();
+        block_on(store.store_session(live)).unwrap();
+        kv.set("unrelated", b"kept").unwrap();
+
+        store.cleanup_at(2_000).unwrap();
+        assert!(kv.get(&format!("session:{stale_id}")).unwrap().is_none());
+        assert!(kv.get(&format!("session:{live_id}")).unwrap().is_some());
+        assert!(kv.get("unrelated").unwrap().is_some());
+    }
+
+    #[test]
+    fn user_index_drops_sessions_that_expired() {
+        let (kv, store) = store();
+        let mut stale = Session::new();
+        stale.insert("user_id", 7_i64).unwrap();
+        stale.set_expiry(Utc.timestamp_opt(1_000, 0).unwrap());
+        let mut live = Session::new();
+        live.insert("user_id", 7_i64).unwrap();
+        let live_id = live.id().to_string();
+        block_on(store.store_session(stale)).unwrap();
+        block_on(store.store_session(live)).unwrap();
+
+        let sessions = block_on(store.sessions_for_user(7)).unwrap();
+        assert_eq!(sessions.len(), 1);
+        let index: Vec<String> = serde_json::from_slice(&kv.get("session-user:7").unwrap().unwrap()).unwrap();
+        assert_eq!(index, vec![live_id]);
+    }
+}
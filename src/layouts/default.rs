@@ -1,9 +1,37 @@
+use crate::functions::impersonation::{get_impersonation, StopImpersonation};
+use crate::providers::csrf::CsrfField;
 use leptos::*;
+use leptos_router::ActionForm;
+
+/// Shown on every page while an administrator is acting as another user
+#[component]
+pub fn ImpersonationBanner() -> impl IntoView {
+    let stop = create_server_action::<StopImpersonation>();
+    let status = create_resource(move || stop.version().get(), |_| get_impersonation());
+
+    view! {
+        <Transition fallback=move || ()>
+            {move || status.get().and_then(Result::ok).flatten().map(|status| view! {
+                <div role="status" class="flex items-center justify-between bg-amber-500 px-4 py-2 text-sm font-semibold text-white">
+                    <span>
+                        "Acting as " {status.full_name} " (" {status.username} ", " {status.role} ") for "
+                        {status.real_username} ". Changes are disabled and every request is audited."
+                    </span>
+                    <ActionForm action=stop>
+                        <CsrfField/>
+                        <button type="submit" class="rounded bg-white px-3 py-1 text-amber-700 hover:bg-amber-50">"Stop acting as user"</button>
+                    </ActionForm>
+                </div>
+            })}
+        </Transition>
+    }
+}
 
 #[component]
 pub fn DefaultLayout(children: Children) -> impl IntoView {
     view! {
       <div class="flex min-h-screen flex-col bg-lexodus-100 text-lexodus-900">
+          <ImpersonationBanner/>
           // Lexodus Header with Notifications and Hamburger Menu
           <header class="bg-white shadow-md">
               <div class="flex items-center justify-between p-4">
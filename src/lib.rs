@@ -6,11 +6,15 @@ mod pages;
 
 mod domain;
 mod errors;
+mod mail;
 mod presentation;
 mod providers;
 #[cfg(feature = "ssr")]
 mod server;
 mod session;
+mod pg_session;
+mod kv_session;
+mod session_backend;
 
 use cfg_if::cfg_if;
 
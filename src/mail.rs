@@ -0,0 +1,236 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use crate::errors::LexodusAppError;
+use spin_sdk::pg::{Connection, ParameterValue};
+use spin_sdk::variables;
+use wasip2::sockets::{
+    instance_network::instance_network,
+    ip_name_lookup::resolve_addresses,
+    network::{ErrorCode, IpAddress, IpAddressFamily, IpSocketAddress, Ipv4SocketAddress, Ipv6SocketAddress},
+    tcp::{InputStream, OutputStream, TcpSocket},
+    tcp_create_socket::create_tcp_socket,
+};
+use std::sync::Arc;
+
+/// A plain-text message to a single recipient
+#[derive(Clone, Debug)]
+pub struct OutgoingMail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Where outbound mail goes, chosen through the `mail_transport` Spin variable.
+/// `outbox` (the default) only records messages in `mail_outbox`, so the app runs
+/// without a mail server; `smtp` also delivers them, e.g. to a local test sink.
+#[derive(Clone, Debug)]
+pub enum Mailer {
+    Outbox,
+    Smtp(SmtpConfig),
+}
+
+#[derive(Clone, Debug)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub from: String,
+}
+
+impl Mailer {
+    pub fn from_variables() -> Self {
+        match variables::get("mail_transport").as_deref() {
+            Ok("smtp") => Mailer::Smtp(SmtpConfig {
+                host: variables::get("smtp_host").unwrap_or_else(|_| "127.0.0.1".to_string()),
+                port: variables::get("smtp_port")
+                    .ok()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(1025),
+                from: mail_from(),
+            }),
+            _ => Mailer::Outbox,
+        }
+    }
+
+    fn transport_name(&self) -> &'static str {
+        match self {
+            Mailer::Outbox => "outbox",
+            Mailer::Smtp(_) => "smtp",
+        }
+    }
+
+    /// Deliver a message and record it in `mail_outbox`. SMTP failures are
+    /// recorded with the message and returned.
+    #[tracing::instrument(level = "info", fields(error), skip(self, mail, con), err)]
+    pub async fn send(&self, mail: OutgoingMail, con: &Arc<Connection>) -> Result<(), LexodusAppError> {
+        let result = match self {
+            Mailer::Outbox => Ok(()),
+            Mailer::Smtp(config) => smtp::send(config, &mail),
+        };
+        let (sent, error) = match (&result, self) {
+            (Ok(()), Mailer::Smtp(_)) => (true, None),
+            (Ok(()), Mailer::Outbox) => (false, None),
+            (Err(e), _) => (false, Some(e.to_string())),
+        };
+        con.execute(
+            "INSERT INTO mail_outbox (recipient, subject, body, transport, sent_at, error)
+             VALUES ($1, $2, $3, $4, CASE WHEN $5 THEN CURRENT_TIMESTAMP END, $6)",
+            &[
+                ParameterValue::Str(mail.to),
+                ParameterValue::Str(mail.subject),
+                ParameterValue::Str(mail.body),
+                ParameterValue::Str(self.transport_name().to_string()),
+                ParameterValue::Boolean(sent),
+                error.map_or(ParameterValue::DbNull, ParameterValue::Str),
+            ],
+        )?;
+        result
+    }
+}
+
+/// Envelope and `From:` address for outbound mail
+pub fn mail_from() -> String {
+    variables::get("mail_from").unwrap_or_else(|_| "no-reply@lexodus.local".to_string())
+}
+
+/// Absolute base for links placed in emails, without a trailing slash
+pub fn app_base_url() -> String {
+    variables::get("app_base_url")
+        .unwrap_or_else(|_| "http://127.0.0.1:3000".to_string())
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// A minimal SMTP client over WASI sockets: no TLS and no AUTH, which is all a
+/// local sink such as Mailpit or MailHog needs.
+mod smtp {
+    use super::*;
+
+    fn mail_error(context: &str, detail: impl std::fmt::Debug) -> LexodusAppError {
+        LexodusAppError::MailError(format!("{context}: {detail:?}"))
+    }
+
+    struct SmtpConnection {
+        input: InputStream,
+        output: OutputStream,
+        buffer: Vec<u8>,
+        // Dropped last; the streams must go before the socket
+        _socket: TcpSocket,
+    }
+
+    impl SmtpConnection {
+        fn open(host: &str, port: u16) -> Result<Self, LexodusAppError> {
+            let network = instance_network();
+            let address = resolve(&network, host)?;
+            let (family, remote) = match address {
+                IpAddress::Ipv4(address) => (IpAddressFamily::Ipv4, IpSocketAddress::Ipv4(Ipv4SocketAddress { port, address })),
+                IpAddress::Ipv6(address) => (
+                    IpAddressFamily::Ipv6,
+                    IpSocketAddress::Ipv6(Ipv6SocketAddress { port, flow_info: 0, address, scope_id: 0 }),
+                ),
+            };
+            let socket = create_tcp_socket(family).map_err(|e| mail_error("socket", e))?;
+            socket
+                .start_connect(&network, remote)
+                .map_err(|e| mail_error("connect", e))?;
+            let pollable = socket.subscribe();
+            let (input, output) = loop {
+                match socket.finish_connect() {
+                    Ok(streams) => break streams,
+                    Err(ErrorCode::WouldBlock) => pollable.block(),
+                    Err(e) => return Err(mail_error("connect", e)),
+                }
+            };
+            drop(pollable);
+            Ok(Self { input, output, buffer: Vec::new(), _socket: socket })
+        }
+
+        fn read_line(&mut self) -> Result<String, LexodusAppError> {
+            loop {
+                if let Some(end) = self.buffer.windows(2).position(|w| w == b"\r\n") {
+                    let line: Vec<u8> = self.buffer.drain(..end + 2).collect();
+                    return Ok(String::from_utf8_lossy(&line[..end]).to_string());
+                }
+                let chunk = self.input.blocking_read(1024).map_err(|e| mail_error("read", e))?;
+                self.buffer.extend_from_slice(&chunk);
+            }
+        }
+
+        /// Read a possibly multi-line reply and check its status code
+        fn expect(&mut self, code: &str) -> Result<(), LexodusAppError> {
+            loop {
+                let line = self.read_line()?;
+                if !line.starts_with(code) {
+                    return Err(LexodusAppError::MailError(format!("expected {code}, got {line}")));
+                }
+                // `250-` continues a reply, `250 ` ends it
+                if line.as_bytes().get(3) != Some(&b'-') {
+                    return Ok(());
+                }
+            }
+        }
+
+        fn write(&mut self, data: &str) -> Result<(), LexodusAppError> {
+            // A single blocking write is limited to 4096 bytes
+            for chunk in data.as_bytes().chunks(4096) {
+                self.output
+                    .blocking_write_and_flush(chunk)
+                    .map_err(|e| mail_error("write", e))?;
+            }
+            Ok(())
+        }
+
+        fn command(&mut self, line: &str, code: &str) -> Result<(), LexodusAppError> {
+            self.write(&format!("{line}\r\n"))?;
+            self.expect(code)
+        }
+    }
+
+    fn resolve(network: &wasip2::sockets::network::Network, host: &str) -> Result<IpAddress, LexodusAppError> {
+        let stream = resolve_addresses(network, host).map_err(|e| mail_error("resolve", e))?;
+        let pollable = stream.subscribe();
+        loop {
+            match stream.resolve_next_address() {
+                Ok(Some(address)) => return Ok(address),
+                Ok(None) => return Err(LexodusAppError::MailError(format!("no address for {host}"))),
+                Err(ErrorCode::WouldBlock) => pollable.block(),
+                Err(e) => return Err(mail_error("resolve", e)),
+            }
+        }
+    }
+
+    /// Lines starting with a dot are escaped so they cannot end the DATA section
+    fn dot_stuff(body: &str) -> String {
+        body.lines()
+            .map(|line| if line.starts_with('.') { format!(".{line}") } else { line.to_string() })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+
+    pub fn send(config: &SmtpConfig, mail: &OutgoingMail) -> Result<(), LexodusAppError> {
+        if mail.to.contains(['\r', '\n', '<', '>']) {
+            return Err(LexodusAppError::MailError(format!("invalid recipient {:?}", mail.to)));
+        }
+        let mut conn = SmtpConnection::open(&config.host, config.port)?;
+        conn.expect("220")?;
+        conn.command("EHLO lexodus", "250")?;
+        conn.command(&format!("MAIL FROM:<{}>", config.from), "250")?;
+        conn.command(&format!("RCPT TO:<{}>", mail.to), "250")?;
+        conn.command("DATA", "354")?;
+        let message = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n.\r\n",
+            config.from,
+            mail.to,
+            mail.subject,
+            dot_stuff(&mail.body),
+        );
+        conn.write(&message)?;
+        conn.expect("250")?;
+        // The message is accepted; a failed QUIT does not matter
+        let _ = conn.command("QUIT", "221");
+        Ok(())
+    }
+}
+}
+}
@@ -0,0 +1,156 @@
+use crate::functions::api_tokens::{
+    list_api_token_scopes, list_api_tokens, CreateApiToken, CreatedApiToken, RevokeApiToken,
+};
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+/// Name, lifetime and scopes for a new token. The secret is shown once on success.
+#[component]
+pub fn CreateApiTokenForm(create: Action<CreateApiToken, Result<CreatedApiToken, ServerFnError>>) -> impl IntoView {
+    let scopes = create_resource(|| (), |_| list_api_token_scopes());
+    let response = create.value();
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mb-8">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"New Token"</h3>
+            <ActionForm action=create>
+                <CsrfField/>
+                <div class="mb-4">
+                    <label for="name" class="block text-lexodus-700 mb-1">"Name:"</label>
+                    <input type="text" id="name" name="name" maxlength="100" placeholder="e.g. docketing script" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="expires_in_days" class="block text-lexodus-700 mb-1">"Expires after (days):"</label>
+                    <input type="number" id="expires_in_days" name="expires_in_days" min="1" max="365" value="90" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                </div>
+                <fieldset class="mb-4">
+                    <legend class="block text-lexodus-700 mb-1">"Scopes:"</legend>
+                    <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                    {move || scopes.get().map(|result| match result {
+                        Ok(scopes) => scopes.into_iter().enumerate().map(|(i, scope)| {
+                            let id = format!("scope_{i}");
+                            view! {
+                                <div>
+                                    <input type="checkbox" id=id.clone() name=format!("scopes[{i}]") value=scope.clone() class="mr-2"/>
+                                    <label for=id class="text-lexodus-800 font-mono">{scope}</label>
+                                </div>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                    })}
+                    </Suspense>
+                </fieldset>
+                <button type="submit" class="w-full px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600 focus:outline-none focus:ring-2 focus:ring-lexodus-500">"Create token"</button>
+            </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(created) => view! {
+                    <div class="mt-4">
+                        <p class="text-green-600 mb-2">"Copy this token now. It will not be shown again."</p>
+                        <code class="block p-2 bg-gray-100 text-lexodus-800 break-all">{created.token}</code>
+                    </div>
+                }.into_view(),
+                Err(e) => view! { <p class="mt-4 text-red-500">{e.to_string()}</p> }.into_view(),
+            })}
+        </section>
+    }
+}
+
+/// The caller's tokens with their scopes and last use
+#[component]
+pub fn ApiTokenList(create: Action<CreateApiToken, Result<CreatedApiToken, ServerFnError>>) -> impl IntoView {
+    let revoke = create_server_action::<RevokeApiToken>();
+    let tokens = create_resource(
+        move || (create.version().get(), revoke.version().get()),
+        |_| list_api_tokens(),
+    );
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 relative">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Your Tokens"</h3>
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Name"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Token"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Scopes"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Created"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Last Used"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Expires"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="7" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || tokens.get().map(|result| match result {
+                        Ok(tokens) if tokens.is_empty() => view! {
+                            <tr><td colspan="7" class="text-center text-lexodus-500 border-b py-4">"No tokens yet."</td></tr>
+                        }.into_view(),
+                        Ok(tokens) => tokens.into_iter().map(|token| {
+                            let state = if token.revoked {
+                                Some("Revoked")
+                            } else if token.expired {
+                                Some("Expired")
+                            } else {
+                                None
+                            };
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{token.name}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800 font-mono">{format!("{}…", token.token_prefix)}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800 font-mono">{token.scopes.join(", ")}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{token.created_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{token.last_used_at_pretty.unwrap_or_else(|| "Never".to_string())}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{token.expires_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        {match state {
+                                            Some(state) => view! { <span class="text-lexodus-500">{state}</span> }.into_view(),
+                                            None => view! {
+                                                <ActionForm action=revoke>
+                                                    <CsrfField/>
+                                                    <input type="hidden" name="id" value=token.id/>
+                                                    <button type="submit" class="text-red-600 underline">"Revoke"</button>
+                                                </ActionForm>
+                                            }.into_view(),
+                                        }}
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="7" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn AccountApiTokens() -> impl IntoView {
+    let create = create_server_action::<CreateApiToken>();
+
+    view! {
+        <Meta property="og:title" content="API Tokens | Lexodus"/>
+        <Title text="API Tokens | Lexodus"/>
+        <Meta name="description" content="Create and revoke personal API tokens for scripts and integrations."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"API Tokens"</h2>
+                    <a href="/account/security" class="text-lexodus-500 underline">"Account Security"</a>
+                </div>
+                <p class="text-lexodus-700 mb-8">"Send a token in an " <code>"Authorization: Bearer"</code> " header. It can do only what its scopes and your role both allow."</p>
+                <CreateApiTokenForm create=create/>
+                <ApiTokenList create=create/>
+            </div>
+        </DefaultLayout>
+    }
+}
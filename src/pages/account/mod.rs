@@ -0,0 +1,6 @@
+pub mod api_tokens;
+pub use api_tokens::*;
+pub mod security;
+pub use security::*;
+pub mod sessions;
+pub use sessions::*;
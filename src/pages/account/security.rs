@@ -0,0 +1,179 @@
+use crate::functions::two_factor::{
+    begin_two_factor_enrollment, ConfirmTwoFactorEnrollment,
+};
+use crate::functions::account_recovery::SendVerificationEmail;
+use crate::functions::password_policy::ChangePassword;
+use crate::layouts::default::*;
+use crate::providers::auth::AuthContext;
+use crate::providers::csrf::{CsrfContext, CsrfField};
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+/// Enroll an authenticator app: scan the QR code, confirm with a code, keep the recovery codes.
+/// An `enrolled` user replacing their authenticator first gives their password or a current code.
+#[component]
+pub fn TwoFactorEnrollmentForm(#[prop(optional)] enrolled: bool) -> impl IntoView {
+    let csrf = expect_context::<CsrfContext>();
+    let (current_proof, set_current_proof) = create_signal(String::new());
+    let enroll = create_action(move |current_proof: &String| {
+        let csrf_token = untrack(move || csrf.value());
+        begin_two_factor_enrollment(csrf_token, current_proof.clone())
+    });
+    let enrollment = enroll.value();
+    let confirm = create_server_action::<ConfirmTwoFactorEnrollment>();
+    let confirmed = confirm.value();
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Set Up Two-Factor Authentication"</h3>
+            <Show
+                when=move || matches!(enrollment.get(), Some(Ok(_)))
+                fallback=move || view! {
+                    <Show when=move || enrolled fallback=|| ()>
+                        <p class="text-lexodus-700 mb-4">"Two-factor authentication is enabled for this account. To move it to a new authenticator app, enter your password or a code from your current app."</p>
+                        <div class="mb-4">
+                            <label for="current_proof" class="block text-lexodus-700 mb-1">"Password or authentication code:"</label>
+                            <input type="password" id="current_proof" autocomplete="current-password" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500"
+                                on:input=move |ev| set_current_proof.set(event_target_value(&ev))/>
+                        </div>
+                    </Show>
+                    <button
+                        class="px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600"
+                        on:click=move |_| enroll.dispatch(current_proof.get_untracked())
+                    >
+                        {if enrolled { "Replace authenticator" } else { "Enable two-factor authentication" }}
+                    </button>
+                    {move || match enrollment.get() {
+                        Some(Err(e)) => view! { <div class="mt-4 text-red-500">{e.to_string()}</div> }.into_view(),
+                        _ => ().into_view(),
+                    }}
+                }
+            >
+                {move || enrollment.get().map(|result| match result {
+                    Ok(e) => view! {
+                        <p class="text-lexodus-700 mb-4">"Scan this code with your authenticator app, then enter the code it shows."</p>
+                        <div class="mb-4" inner_html=e.qr_svg></div>
+                        <p class="text-sm text-lexodus-700 mb-4">"Or enter this key manually: "<code>{e.secret}</code></p>
+                        <ActionForm action=confirm>
+                            <CsrfField/>
+                            <div class="mb-4">
+                                <label for="code" class="block text-lexodus-700 mb-1">"Authentication code:"</label>
+                                <input type="text" id="code" name="code" inputmode="numeric" autocomplete="one-time-code" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                            </div>
+                            <button type="submit" class="w-full px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600 focus:outline-none focus:ring-2 focus:ring-lexodus-500">"Confirm"</button>
+                        </ActionForm>
+                    }.into_view(),
+                    Err(e) => view! { <div class="mt-4 text-red-500">{e.to_string()}</div> }.into_view(),
+                })}
+            </Show>
+
+            {move || confirmed.get().map(|result| match result {
+                Ok(codes) => view! {
+                    <div class="mt-4">
+                        <p class="text-green-600 mb-2">"Two-factor authentication is on. Store these recovery codes somewhere safe; each works once and they will not be shown again."</p>
+                        <ul class="font-mono text-lexodus-800">
+                            {codes.into_iter().map(|c| view! { <li>{c}</li> }).collect_view()}
+                        </ul>
+                    </div>
+                }.into_view(),
+                Err(e) => view! { <div class="mt-4 text-red-500">{e.to_string()}</div> }.into_view(),
+            })}
+        </section>
+    }
+}
+
+/// Resend the verification link for an unverified email address
+#[component]
+pub fn EmailVerificationNotice() -> impl IntoView {
+    let resend = create_server_action::<SendVerificationEmail>();
+    let response = resend.value();
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mb-8">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-4">"Email Address"</h3>
+            <p class="text-lexodus-700 mb-4">"Your email address has not been verified. Use the link we sent you, or request a new one."</p>
+            <ActionForm action=resend>
+                <CsrfField/>
+                <button type="submit" class="px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600">"Send verification email"</button>
+            </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(_) => view! { <p class="mt-4 text-green-600">"Verification email sent."</p> }.into_view(),
+                Err(e) => view! { <p class="mt-4 text-red-500">{e.to_string()}</p> }.into_view(),
+            })}
+        </section>
+    }
+}
+
+#[component]
+pub fn ChangePasswordForm() -> impl IntoView {
+    let change = create_server_action::<ChangePassword>();
+    let response = change.value();
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mb-8">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Change Password"</h3>
+            <ActionForm action=change>
+                <CsrfField/>
+                <div class="mb-4">
+                    <label for="current_password" class="block text-lexodus-700 mb-1">"Current password:"</label>
+                    <input type="password" id="current_password" name="current_password" autocomplete="current-password" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="password" class="block text-lexodus-700 mb-1">"New password:"</label>
+                    <input type="password" id="password" name="password" autocomplete="new-password" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="password_confirmation" class="block text-lexodus-700 mb-1">"Confirm new password:"</label>
+                    <input type="password" id="password_confirmation" name="password_confirmation" autocomplete="new-password" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500" required/>
+                </div>
+                <button type="submit" class="w-full px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600 focus:outline-none focus:ring-2 focus:ring-lexodus-500">"Change password"</button>
+            </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(_) => view! { <p class="mt-4 text-green-600">"Password changed. Your other sessions have been signed out."</p> }.into_view(),
+                Err(e) => view! { <p class="mt-4 text-red-500">{e.to_string()}</p> }.into_view(),
+            })}
+        </section>
+    }
+}
+
+#[component]
+pub fn AccountSecurity() -> impl IntoView {
+    let auth_context = use_context::<AuthContext>().expect("Failed to get AuthContext");
+
+    view! {
+        <Meta property="og:title" content="Account Security | Lexodus"/>
+        <Title text="Account Security | Lexodus"/>
+        <Meta name="description" content="Manage sign-in security for your Lexodus account."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Account Security"</h2>
+                    <div class="space-x-4">
+                        <a href="/account/sessions" class="text-lexodus-500 underline">"Active Sessions"</a>
+                        <a href="/account/api-tokens" class="text-lexodus-500 underline">"API Tokens"</a>
+                    </div>
+                </div>
+                <Transition fallback=move || ()>
+                  {move || match auth_context.user.get() {
+                      Some(Ok(Some(user))) if !user.email_verified => view! { <EmailVerificationNotice/> }.into_view(),
+                      _ => ().into_view(),
+                  }}
+                </Transition>
+                <ChangePasswordForm/>
+                <Transition fallback=move || ()>
+                  {move || match auth_context.user.get() {
+                      Some(Ok(Some(user))) if user.two_factor_enabled => view! { <TwoFactorEnrollmentForm enrolled=true/> }.into_view(),
+                      Some(Ok(Some(user))) if user.owes_two_factor() => view! {
+                          <p class="mb-4 text-red-600">"Your role requires two-factor authentication. Set it up below before using the rest of Lexodus."</p>
+                          <TwoFactorEnrollmentForm/>
+                      }.into_view(),
+                      Some(Ok(Some(_))) => view! { <TwoFactorEnrollmentForm/> }.into_view(),
+                      _ => ().into_view(),
+                  }}
+                </Transition>
+            </div>
+        </DefaultLayout>
+    }
+}
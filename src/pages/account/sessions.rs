@@ -0,0 +1,91 @@
+use crate::functions::sessions::{list_sessions, RevokeAllSessions, RevokeSession};
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+/// The caller's signed-in browsers, each with a sign-out action
+#[component]
+pub fn SessionList() -> impl IntoView {
+    let revoke = create_server_action::<RevokeSession>();
+    let revoke_all = create_server_action::<RevokeAllSessions>();
+    let sessions = create_resource(move || revoke.version().get(), |_| list_sessions());
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <div class="flex justify-between items-center mb-6">
+                <h3 class="text-xl font-semibold text-lexodus-800">"Active Sessions"</h3>
+                <ActionForm action=revoke_all>
+                    <CsrfField/>
+                    <button type="submit" class="px-4 py-2 bg-red-600 text-white rounded font-semibold hover:bg-red-700">"Log out everywhere"</button>
+                </ActionForm>
+            </div>
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Device"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"IP Address"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Signed In"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Last Seen"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Expires"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="6" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || sessions.get().map(|result| match result {
+                        Ok(sessions) => sessions.into_iter().map(|session| {
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        {session.user_agent.unwrap_or_else(|| "Unknown device".to_string())}
+                                        {session.current.then(|| view! { <span class="ml-2 text-green-600">"(this browser)"</span> })}
+                                        {session.remember.then(|| view! { <span class="ml-2 text-lexodus-500">"(remembered)"</span> })}
+                                    </td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{session.ip_address.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{session.created_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{session.last_seen_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{session.expires_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <ActionForm action=revoke>
+                                            <CsrfField/>
+                                            <input type="hidden" name="session_id" value=session.id/>
+                                            <button type="submit" class="text-red-600 underline">"Sign out"</button>
+                                        </ActionForm>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="6" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn AccountSessions() -> impl IntoView {
+    view! {
+        <Meta property="og:title" content="Sessions | Lexodus"/>
+        <Title text="Sessions | Lexodus"/>
+        <Meta name="description" content="Review and sign out of your active Lexodus sessions."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Sessions"</h2>
+                    <a href="/account/security" class="text-lexodus-500 underline">"Account Security"</a>
+                </div>
+                <SessionList/>
+            </div>
+        </DefaultLayout>
+    }
+}
@@ -0,0 +1,236 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::judge_assignment::{JudgeAssignment, PoolEntry};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::conflicts::{get_case_conflicts, ClearRecusalConflict, ConflictTable};
+use crate::pages::cases::view_case::SectionCard;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::judge_assignment::{Draw, Wheel};
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::view_case::visible_case;
+    }
+}
+
+#[server(GetJudgeAssignments, "/api")]
+pub async fn get_judge_assignments(case: CaseRef) -> Result<Vec<JudgeAssignment>, ServerFnError> {
+    let user = authorize::<GetJudgeAssignments>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(JudgeAssignment::list(case_id, &filed_date, user.id, &con).await?)
+}
+
+impl RequiresPermission for GetJudgeAssignments {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// The judges a case could be reassigned to: its wheel today, less its current judge
+#[server(GetReassignmentPool, "/api")]
+pub async fn get_reassignment_pool(case_id: i64) -> Result<Vec<PoolEntry>, ServerFnError> {
+    let user = authorize::<GetReassignmentPool>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let (wheel, current) = Wheel::for_case(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+    Ok(wheel.pool(current, &con).await?)
+}
+
+impl RequiresPermission for GetReassignmentPool {
+    const PERMISSION: Permission = Permission::CASE_ASSIGN;
+}
+
+/// Move a case to another judge, either by a fresh draw that leaves out the
+/// current judge or by naming an eligible one. Refused if the case was
+/// reassigned after the form was loaded.
+#[server(ReassignJudge, "/api")]
+pub async fn reassign_judge(
+    csrf_token: String,
+    case_id: i64,
+    current_judge_id: Option<String>,
+    method: String,
+    judge_id: Option<String>,
+    reason: String,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<ReassignJudge>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+
+    let reason = reason.trim();
+    if reason.is_empty() {
+        return Err(LexodusAppError::BadRequest("Give a reason for the reassignment.".to_string()).into());
+    }
+    let (wheel, current) = Wheel::for_case(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+    let expected = current_judge_id
+        .filter(|id| !id.is_empty())
+        .map(|id| id.parse::<i64>())
+        .transpose()
+        .map_err(|_| LexodusAppError::BadRequest("Invalid judge ID format".to_string()))?;
+    if expected != current {
+        return Err(LexodusAppError::BadRequest("The case was reassigned while you were working; review it and try again.".to_string()).into());
+    }
+
+    let pool = wheel.pool(current, &con).await?;
+    let draw = match method.as_str() {
+        "RANDOM" => Draw::random(pool)
+            .ok_or_else(|| LexodusAppError::BadRequest("No other judge is eligible for this case.".to_string()))?,
+        "MANUAL" => {
+            let judge_id = judge_id
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or_else(|| LexodusAppError::BadRequest("Choose the judge to assign.".to_string()))?;
+            Draw::manual(pool, judge_id)
+                .ok_or_else(|| LexodusAppError::BadRequest("That judge is not eligible for this case.".to_string()))?
+        }
+        _ => return Err(LexodusAppError::BadRequest(format!("Unknown assignment method {method}")).into()),
+    };
+
+    if !draw.reassign(case_id, &filed_date, current, reason, user.id, &con).await? {
+        return Err(LexodusAppError::BadRequest("The case was reassigned while you were working; try again.".to_string()).into());
+    }
+    tracing::info!("User {} reassigned case {case_id} from {current:?} to {}", user.id, draw.judge_id);
+    Ok(())
+}
+
+impl RequiresPermission for ReassignJudge {
+    const PERMISSION: Permission = Permission::CASE_ASSIGN;
+}
+
+/// The case's judge assignments with the pool each draw was made from, and,
+/// for users allowed to reassign, any recusal conflicts and a reassignment form
+#[component]
+pub fn AssignmentSection(
+    case_id: i64,
+    judge_id: Option<i64>,
+    judge: Option<String>,
+    history: Resource<(Option<CaseRef>, usize), Result<Vec<JudgeAssignment>, ServerFnError>>,
+    action: Action<ReassignJudge, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let pool = create_resource(move || action.version().get(), move |_| get_reassignment_pool(case_id));
+    let clear = create_server_action::<ClearRecusalConflict>();
+    let conflicts = create_resource(
+        move || (action.version().get(), clear.version().get()),
+        move |_| get_case_conflicts(CaseRef::Id(case_id)),
+    );
+    let (manual, set_manual) = create_signal(false);
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+
+    view! {
+        <SectionCard title="Judge Assignment">
+            <p class="mb-4 text-lexodus-700">{judge.map_or_else(|| "Not assigned".to_string(), |judge| format!("Assigned to {judge}"))}</p>
+            // Only users who may reassign can load conflicts and the pool, so these show for them alone
+            <Suspense fallback=|| ()>
+                {move || conflicts.get().and_then(Result::ok).filter(|c| !c.is_empty()).map(|conflicts| {
+                    let open = conflicts.iter().any(|c| c.status == "OPEN");
+                    view! {
+                        <div class="mb-6">
+                            {open.then(|| view! {
+                                <p class="mb-2 text-red-600">"The judge matches their recusal list. Redraw the case below, or clear a match that is not a conflict."</p>
+                            })}
+                            <ConflictTable conflicts clear/>
+                            {move || clear.value().get().and_then(Result::err).map(|e| view! {
+                                <p class="mt-2 text-red-500">{e.to_string()}</p>
+                            })}
+                        </div>
+                    }
+                })}
+            </Suspense>
+            <Suspense fallback=|| ()>
+                {move || pool.get().and_then(Result::ok).map(|pool| view! {
+                    <ActionForm action class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end mb-6">
+                        <CsrfField/>
+                        <input type="hidden" name="case_id" value=case_id/>
+                        <input type="hidden" name="current_judge_id" value=judge_id.map(|id| id.to_string()).unwrap_or_default()/>
+                        <div>
+                            <label for="method" class="block text-sm font-medium text-lexodus-700">"Reassign By"</label>
+                            <select
+                                id="method"
+                                name="method"
+                                class=input_class
+                                on:change=move |ev| set_manual.set(event_target_value(&ev) == "MANUAL")
+                            >
+                                <option value="RANDOM">"Random draw"</option>
+                                <option value="MANUAL">"Choosing a judge"</option>
+                            </select>
+                        </div>
+                        <Show when=move || manual.get() fallback=|| ()>
+                            <div>
+                                <label for="judge_id" class="block text-sm font-medium text-lexodus-700">"Judge"</label>
+                                <select id="judge_id" name="judge_id" required class=input_class>
+                                    <option value="">"Choose a judge"</option>
+                                    {pool.iter().map(|e| view! {
+                                        <option value=e.judge_id>{format!("{} ({} active)", e.judge_name, e.active_cases)}</option>
+                                    }).collect_view()}
+                                </select>
+                            </div>
+                        </Show>
+                        <div class="sm:col-span-2">
+                            <label for="assignment_reason" class="block text-sm font-medium text-lexodus-700">"Reason"</label>
+                            <input type="text" id="assignment_reason" name="reason" required class=input_class/>
+                        </div>
+                        <div>
+                            <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Reassign"</button>
+                        </div>
+                    </ActionForm>
+                })}
+            </Suspense>
+            {move || action.value().get().and_then(Result::err).map(|e| view! {
+                <p class="mb-4 text-red-500">{e.to_string()}</p>
+            })}
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || history.get().map(|result| match result {
+                    Ok(history) if history.is_empty() => view! { <p class="text-lexodus-700">"No judge assignments have been recorded."</p> }.into_view(),
+                    Ok(history) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Judge"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Method"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Pool"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Reason"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Recorded"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {history.into_iter().map(|a| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            {match a.previous_judge {
+                                                Some(previous) => format!("{previous} → {}", a.judge),
+                                                None => a.judge,
+                                            }}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            {match (a.seed, a.related_case_number) {
+                                                (Some(seed), _) => format!("{} (seed {seed})", a.method),
+                                                (None, Some(related)) => format!("{} to {related}", a.method),
+                                                (None, None) => a.method,
+                                            }}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                            {a.pool.iter().map(|e| format!(
+                                                "{}: weight {}, {} active, {:.1}%",
+                                                e.judge_name, e.weight, e.active_cases, e.share * 100.0
+                                            )).map(|line| view! { <div>{line}</div> }).collect_view()}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{a.reason.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                            {format!("{} by {}", a.assigned_at_pretty, a.assigned_by.unwrap_or_else(|| "unknown".to_string()))}
+                                        </td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
@@ -0,0 +1,241 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+use std::collections::HashMap;
+
+use crate::domain::models::case_link::{CaseLinkGraph, CASE_LINK_CHOICES};
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::view_case::SectionCard;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case_link::{CaseLink, CaseLinkKind, NewCaseLink};
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::view_case::visible_case;
+    }
+}
+
+#[server(GetCaseLinks, "/api")]
+pub async fn get_case_links(case: CaseRef) -> Result<CaseLinkGraph, ServerFnError> {
+    let user = authorize::<GetCaseLinks>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseLinkGraph::load(case_id, &filed_date, user.id, &con).await?)
+}
+
+impl RequiresPermission for GetCaseLinks {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// Link a case to another by id or number. Consolidating a member into its
+/// lead can copy entries later filed in the lead onto the member's docket.
+#[server(LinkCase, "/api")]
+pub async fn link_case(
+    csrf_token: String,
+    case_id: i64,
+    relationship: String,
+    other_case: String,
+    mirror_docket: Option<String>,
+    notes: Option<String>,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<LinkCase>().await?;
+    let con = con()?;
+    let this = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let (kind, outgoing) = CaseLinkKind::parse_choice(&relationship)
+        .ok_or_else(|| LexodusAppError::BadRequest(format!("Unknown relationship {relationship}")))?;
+    let other_case = other_case.trim();
+    let other_ref = other_case
+        .parse::<CaseRef>()
+        .map_err(|_| LexodusAppError::BadRequest(format!("{other_case} is not a case number")))?;
+    let other = visible_case(&other_ref, &user, &con)
+        .await
+        .map_err(|_| LexodusAppError::BadRequest(format!("No case {other_case} was found")))?;
+
+    let (from, to) = if outgoing { (this, other) } else { (other, this) };
+    let link = NewCaseLink {
+        from,
+        to,
+        kind,
+        mirror_docket: mirror_docket.is_some(),
+        notes: notes.map(|n| n.trim().to_string()).filter(|n| !n.is_empty()),
+    };
+    link.validate(&con).await?;
+    let id = link.insert(user.id, &con).await?;
+    tracing::info!("User {} linked case {} {} case {} (link {id})", user.id, link.from.0, kind.as_str(), link.to.0);
+    Ok(())
+}
+
+impl RequiresPermission for LinkCase {
+    const PERMISSION: Permission = Permission::CASE_UPDATE;
+}
+
+#[server(UnlinkCase, "/api")]
+pub async fn unlink_case(csrf_token: String, case_id: i64, link_id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<UnlinkCase>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    if !CaseLink::remove(link_id, case_id, &filed_date, &con).await? {
+        return Err(LexodusAppError::NotFound.into());
+    }
+    tracing::info!("User {} removed link {link_id} from case {case_id}", user.id);
+    Ok(())
+}
+
+impl RequiresPermission for UnlinkCase {
+    const PERMISSION: Permission = Permission::CASE_UPDATE;
+}
+
+/// The link graph drawn in columns by distance from the viewed case
+#[component]
+fn LinkGraph(case_id: i64, graph: CaseLinkGraph) -> impl IntoView {
+    const COLUMN: i64 = 200;
+    const ROW: i64 = 56;
+    let mut rows: HashMap<i64, i64> = HashMap::new();
+    let mut positions: HashMap<i64, (i64, i64)> = HashMap::new();
+    for node in &graph.nodes {
+        let row = rows.entry(node.depth).or_insert(0);
+        positions.insert(node.id, (20 + node.depth * COLUMN, 20 + *row * ROW));
+        *row += 1;
+    }
+    let width = 20 + (graph.nodes.iter().map(|n| n.depth).max().unwrap_or(0) + 1) * COLUMN;
+    let height = 20 + rows.values().copied().max().unwrap_or(1) * ROW;
+    let (node_width, node_height) = (150, 36);
+
+    let edges = graph
+        .links
+        .iter()
+        .filter_map(|link| {
+            let (x1, y1) = positions.get(&link.from_id)?;
+            let (x2, y2) = positions.get(&link.to_id)?;
+            let (x1, y1, x2, y2) = (x1 + node_width / 2, y1 + node_height / 2, x2 + node_width / 2, y2 + node_height / 2);
+            let label = link.kind.replace('_', " ").to_lowercase();
+            Some(view! {
+                <line x1=x1 y1=y1 x2=x2 y2=y2 stroke="#94a3b8" stroke-width="2"/>
+                <text x=(x1 + x2) / 2 y=(y1 + y2) / 2 - 4 text-anchor="middle" font-size="11" fill="#475569">{label}</text>
+            })
+        })
+        .collect_view();
+    let nodes = graph
+        .nodes
+        .iter()
+        .filter_map(|node| {
+            let (x, y) = *positions.get(&node.id)?;
+            let fill = if node.id == case_id { "#e0e7ff" } else { "#ffffff" };
+            Some(view! {
+                <rect x=x y=y width=node_width height=node_height rx="6" fill=fill stroke="#6366f1"/>
+                <text x=x + node_width / 2 y=y + 15 text-anchor="middle" font-size="12" fill="#1e293b">{node.case_number.clone()}</text>
+                <text x=x + node_width / 2 y=y + 29 text-anchor="middle" font-size="10" fill="#64748b">{node.status.clone()}</text>
+            })
+        })
+        .collect_view();
+
+    view! {
+        <svg width=width height=height viewBox=format!("0 0 {width} {height}") class="mb-6">
+            {edges}
+            {nodes}
+        </svg>
+    }
+}
+
+/// Cases linked to this one, drawn as a graph, with forms to link and unlink
+#[component]
+pub fn CaseLinksSection(
+    case_id: i64,
+    graph: Resource<(Option<CaseRef>, usize), Result<CaseLinkGraph, ServerFnError>>,
+    link: Action<LinkCase, Result<(), ServerFnError>>,
+    unlink: Action<UnlinkCase, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let (consolidating, set_consolidating) = create_signal(false);
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+
+    view! {
+        <SectionCard title="Linked Cases">
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || graph.get().map(|result| match result {
+                    Ok(graph) if graph.links.is_empty() => view! { <p class="mb-6 text-lexodus-700">"This case is not linked to any other."</p> }.into_view(),
+                    Ok(graph) => {
+                        let direct: Vec<_> = graph.links.iter().filter(|l| l.from_id == case_id || l.to_id == case_id).cloned().collect();
+                        let titles: HashMap<i64, (String, String)> =
+                            graph.nodes.iter().map(|n| (n.id, (n.case_number.clone(), n.title.clone()))).collect();
+                        view! {
+                            <LinkGraph case_id graph/>
+                            <table class="w-full bg-white table-auto mb-6">
+                                <tbody>
+                                    {direct.into_iter().map(|l| {
+                                        let other = l.other(case_id);
+                                        let (number, title) = titles.get(&other).cloned().unwrap_or_default();
+                                        view! {
+                                            <tr class="hover:bg-lexodus-50 align-top">
+                                                <td class="py-2 px-4 border-b text-lexodus-700">{l.label_for(case_id)}</td>
+                                                <td class="py-2 px-4 border-b text-lexodus-800">
+                                                    <a href=format!("/cases/{other}") class="text-lexodus-500 underline">{number}</a>
+                                                    <div class="text-sm text-gray-600">{title}</div>
+                                                    {l.notes.clone().map(|notes| view! { <div class="text-sm">{notes}</div> })}
+                                                </td>
+                                                <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                                    {l.mirror_docket.then_some("Mirrors lead docket")}
+                                                    <div>{format!("{} by {}", l.created_at_pretty, l.created_by.clone().unwrap_or_else(|| "unknown".to_string()))}</div>
+                                                </td>
+                                                <td class="py-2 px-4 border-b">
+                                                    <ActionForm action=unlink>
+                                                        <CsrfField/>
+                                                        <input type="hidden" name="case_id" value=case_id/>
+                                                        <input type="hidden" name="link_id" value=l.id/>
+                                                        <button type="submit" class="text-red-600 underline">"Unlink"</button>
+                                                    </ActionForm>
+                                                </td>
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        }.into_view()
+                    }
+                    Err(e) => view! { <p class="mb-6 text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+            {move || link.value().get().or_else(|| unlink.value().get()).and_then(Result::err).map(|e| view! {
+                <p class="mb-4 text-red-500">{e.to_string()}</p>
+            })}
+            <ActionForm action=link class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end">
+                <CsrfField/>
+                <input type="hidden" name="case_id" value=case_id/>
+                <div>
+                    <label for="relationship" class="block text-sm font-medium text-lexodus-700">"This Case Is"</label>
+                    <select
+                        id="relationship"
+                        name="relationship"
+                        class=input_class
+                        on:change=move |ev| set_consolidating.set(event_target_value(&ev).starts_with("CONSOLIDATED:"))
+                    >
+                        {CASE_LINK_CHOICES.iter().map(|(value, label)| view! { <option value=*value>{*label}</option> }).collect_view()}
+                    </select>
+                </div>
+                <div>
+                    <label for="other_case" class="block text-sm font-medium text-lexodus-700">"Case Number"</label>
+                    <input type="text" id="other_case" name="other_case" required class=input_class/>
+                </div>
+                <div>
+                    <label for="link_notes" class="block text-sm font-medium text-lexodus-700">"Notes"</label>
+                    <input type="text" id="link_notes" name="notes" class=input_class/>
+                </div>
+                <div>
+                    <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Link"</button>
+                </div>
+                <Show when=move || consolidating.get() fallback=|| ()>
+                    <label class="sm:col-span-4 text-sm text-lexodus-700">
+                        <input type="checkbox" name="mirror_docket" class="mr-2"/>
+                        "Copy entries filed in the lead case onto the member's docket from now on"
+                    </label>
+                </Show>
+            </ActionForm>
+        </SectionCard>
+    }
+}
@@ -0,0 +1,174 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::case_status::{CaseStatus, StatusChange, DISPOSITION_METHODS};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::view_case::SectionCard;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::CaseHeader;
+        use crate::domain::models::case_status::StatusTransition;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::forms::parse_date;
+        use crate::pages::cases::view_case::visible_case;
+    }
+}
+
+#[server(GetCaseStatusHistory, "/api")]
+pub async fn get_case_status_history(case: CaseRef) -> Result<Vec<StatusChange>, ServerFnError> {
+    let user = authorize::<GetCaseStatusHistory>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(StatusChange::list(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetCaseStatusHistory {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// Move a case along its lifecycle. The transition must be allowed from the
+/// case's current status, and is refused if someone else changed the status
+/// after the form was loaded.
+#[server(UpdateCaseStatus, "/api")]
+pub async fn update_case_status(
+    csrf_token: String,
+    case_id: i64,
+    current_status: String,
+    new_status: String,
+    reason: String,
+    effective_date: String,
+    disposition_method: Option<String>,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<UpdateCaseStatus>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let case = CaseHeader::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+
+    if case.status != current_status {
+        return Err(LexodusAppError::BadRequest(format!(
+            "The case became {} while you were working; review it and try again.",
+            case.status
+        ))
+        .into());
+    }
+    let from = CaseStatus::parse(&case.status)
+        .ok_or_else(|| LexodusAppError::BadRequest(format!("The case's status {} is not part of the lifecycle.", case.status)))?;
+    let to = CaseStatus::parse(&new_status).ok_or_else(|| LexodusAppError::BadRequest(format!("Unknown status {new_status}")))?;
+    let transition = StatusTransition::new(
+        from,
+        to,
+        &reason,
+        parse_date(&effective_date, "effective date")?,
+        disposition_method,
+        parse_date(&case.filed_date, "filed date")?,
+        case.closed_date.as_deref().map(|d| parse_date(d, "closed date")).transpose()?,
+    )?;
+
+    if !transition.apply(case_id, &filed_date, user.id, &con).await? {
+        return Err(LexodusAppError::BadRequest("The case's status changed while you were working; try again.".to_string()).into());
+    }
+    tracing::info!("User {} moved case {case_id} from {from} to {to}", user.id);
+    Ok(())
+}
+
+impl RequiresPermission for UpdateCaseStatus {
+    const PERMISSION: Permission = Permission::CASE_UPDATE;
+}
+
+/// The case's status history, with a form for the transitions its current status allows
+#[component]
+pub fn StatusSection(
+    case_id: i64,
+    status: String,
+    history: Resource<(Option<CaseRef>, usize), Result<Vec<StatusChange>, ServerFnError>>,
+    action: Action<UpdateCaseStatus, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let next = CaseStatus::parse(&status).map(CaseStatus::next).unwrap_or_default();
+    let (closing, set_closing) = create_signal(false);
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+
+    view! {
+        <SectionCard title="Status">
+            {(!next.is_empty()).then(|| view! {
+                <ActionForm action class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end mb-6">
+                    <CsrfField/>
+                    <input type="hidden" name="case_id" value=case_id/>
+                    <input type="hidden" name="current_status" value=status/>
+                    <div>
+                        <label for="new_status" class="block text-sm font-medium text-lexodus-700">"Change To"</label>
+                        <select
+                            id="new_status"
+                            name="new_status"
+                            class=input_class
+                            on:change=move |ev| set_closing.set(event_target_value(&ev) == CaseStatus::Closed.as_str())
+                        >
+                            <option value="">"Choose a status"</option>
+                            {next.iter().map(|s| view! { <option value=s.as_str()>{s.label()}</option> }).collect_view()}
+                        </select>
+                    </div>
+                    <div>
+                        <label for="effective_date" class="block text-sm font-medium text-lexodus-700">"Effective"</label>
+                        <input type="date" id="effective_date" name="effective_date" required class=input_class/>
+                    </div>
+                    <div class="sm:col-span-2">
+                        <label for="reason" class="block text-sm font-medium text-lexodus-700">"Reason"</label>
+                        <input type="text" id="reason" name="reason" required class=input_class/>
+                    </div>
+                    <Show when=move || closing.get() fallback=|| ()>
+                        <div>
+                            <label for="disposition_method" class="block text-sm font-medium text-lexodus-700">"Disposition"</label>
+                            <select id="disposition_method" name="disposition_method" required class=input_class>
+                                <option value="">"Choose a disposition"</option>
+                                {DISPOSITION_METHODS.iter().map(|m| view! { <option value=*m>{m.replace('_', " ")}</option> }).collect_view()}
+                            </select>
+                        </div>
+                    </Show>
+                    <div>
+                        <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Change Status"</button>
+                    </div>
+                </ActionForm>
+            })}
+            {move || action.value().get().and_then(Result::err).map(|e| view! {
+                <p class="mb-4 text-red-500">{e.to_string()}</p>
+            })}
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || history.get().map(|result| match result {
+                    Ok(history) if history.is_empty() => view! { <p class="text-lexodus-700">"No status changes have been recorded."</p> }.into_view(),
+                    Ok(history) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Effective"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Change"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Reason"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Recorded"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {history.into_iter().map(|change| view! {
+                                    <tr class="hover:bg-lexodus-50">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{change.effective_date.unwrap_or_else(|| "-".to_string())}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{format!("{} → {}", change.old_status, change.new_status)}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{change.reason.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                            {format!("{} by {}", change.change_date_pretty, change.changed_by.unwrap_or_else(|| "unknown".to_string()))}
+                                        </td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
@@ -0,0 +1,177 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_meta::{Meta, Title};
+use leptos_router::ActionForm;
+
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::domain::models::recusal::RecusalConflict;
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::view_case::visible_case;
+
+        /// Number of open conflicts shown in the queue, blocking ones first
+        const CONFLICT_QUEUE_LIMIT: i64 = 200;
+    }
+}
+
+/// Recusal conflicts raised on a case. Limited to users who can reassign it,
+/// since they reveal what is on a judge's recusal list.
+#[server(GetCaseConflicts, "/api")]
+pub async fn get_case_conflicts(case: CaseRef) -> Result<Vec<RecusalConflict>, ServerFnError> {
+    let user = authorize::<GetCaseConflicts>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(RecusalConflict::for_case(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetCaseConflicts {
+    const PERMISSION: Permission = Permission::CASE_ASSIGN;
+}
+
+#[server(ListRecusalConflicts, "/api")]
+pub async fn list_recusal_conflicts() -> Result<Vec<RecusalConflict>, ServerFnError> {
+    let user = authorize::<ListRecusalConflicts>().await?;
+    let con = con()?;
+    Ok(RecusalConflict::open(user.id, CONFLICT_QUEUE_LIMIT, &con).await?)
+}
+
+impl RequiresPermission for ListRecusalConflicts {
+    const PERMISSION: Permission = Permission::CASE_ASSIGN;
+}
+
+/// Dismiss a conflict as a false match, such as a different person with the
+/// same name. Conflicts that hold are resolved by reassigning the case.
+#[server(ClearRecusalConflict, "/api")]
+pub async fn clear_recusal_conflict(csrf_token: String, id: i64, case_id: i64, note: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<ClearRecusalConflict>().await?;
+    let con = con()?;
+    let (case_id, _) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let note = note.trim();
+    if note.is_empty() {
+        return Err(LexodusAppError::BadRequest("Say why this is not a conflict.".to_string()).into());
+    }
+    if !RecusalConflict::clear(id, case_id, note, user.id, &con).await? {
+        return Err(LexodusAppError::BadRequest("This conflict has already been resolved.".to_string()).into());
+    }
+    tracing::info!("User {} cleared recusal conflict {id} on case {case_id}", user.id);
+    Ok(())
+}
+
+impl RequiresPermission for ClearRecusalConflict {
+    const PERMISSION: Permission = Permission::CASE_ASSIGN;
+}
+
+/// Conflicts with, for open ones, a form to clear them. `show_case` adds a
+/// column linking to each case for the queue.
+#[component]
+pub fn ConflictTable(
+    conflicts: Vec<RecusalConflict>,
+    clear: Action<ClearRecusalConflict, Result<(), ServerFnError>>,
+    #[prop(optional)] show_case: bool,
+) -> impl IntoView {
+    view! {
+        <table class="w-full bg-white table-auto">
+            <thead>
+                <tr>
+                    {show_case.then(|| view! { <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Case"</th> })}
+                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Judge"</th>
+                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Matched"</th>
+                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Recusal Entry"</th>
+                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Severity"</th>
+                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Resolution"</th>
+                </tr>
+            </thead>
+            <tbody>
+                {conflicts.into_iter().map(|conflict| {
+                    let open = conflict.status == "OPEN";
+                    let resolution = format!(
+                        "{} by {}: {}",
+                        conflict.status.to_lowercase(),
+                        conflict.resolved_by.clone().unwrap_or_else(|| "unknown".to_string()),
+                        conflict.resolution_note.clone().unwrap_or_default(),
+                    );
+                    view! {
+                        <tr class="hover:bg-lexodus-50 align-top">
+                            {show_case.then(|| view! {
+                                <td class="py-2 px-4 border-b">
+                                    <a href=format!("/cases/{}", conflict.case_id) class="text-lexodus-500 underline">{conflict.case_number.clone()}</a>
+                                </td>
+                            })}
+                            <td class="py-2 px-4 border-b text-lexodus-800">{conflict.judge}</td>
+                            <td class="py-2 px-4 border-b text-lexodus-800">
+                                <div>{conflict.matched_name}</div>
+                                <div class="text-sm text-gray-600">{conflict.source.to_lowercase()}</div>
+                            </td>
+                            <td class="py-2 px-4 border-b text-lexodus-800">
+                                <div>{conflict.recusal_name}</div>
+                                <div class="text-sm text-gray-600">{format!("{}, {:.0}% alike", conflict.entity_type.replace('_', " ").to_lowercase(), conflict.score * 100.0)}</div>
+                            </td>
+                            <td class=if conflict.severity == "BLOCK" { "py-2 px-4 border-b text-red-600 font-medium" } else { "py-2 px-4 border-b text-amber-600" }>
+                                {if conflict.severity == "BLOCK" { "Blocked" } else { "Flagged" }}
+                            </td>
+                            <td class="py-2 px-4 border-b text-lexodus-800">
+                                {if open {
+                                    view! {
+                                        <ActionForm action=clear class="flex gap-2">
+                                            <CsrfField/>
+                                            <input type="hidden" name="id" value=conflict.id/>
+                                            <input type="hidden" name="case_id" value=conflict.case_id/>
+                                            <input type="text" name="note" required placeholder="Why it is not a conflict" class="px-2 py-1 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200"/>
+                                            <button type="submit" class="text-lexodus-500 underline">"Clear"</button>
+                                        </ActionForm>
+                                    }.into_view()
+                                } else {
+                                    view! { <span class="text-sm text-gray-600">{resolution}</span> }.into_view()
+                                }}
+                            </td>
+                        </tr>
+                    }
+                }).collect_view()}
+            </tbody>
+        </table>
+    }
+}
+
+/// Cases whose judge matched their recusal list and still need a redraw or a
+/// decision that the match is false
+#[component]
+pub fn RecusalConflicts() -> impl IntoView {
+    let clear = create_server_action::<ClearRecusalConflict>();
+    let conflicts = create_resource(move || clear.version().get(), |_| list_recusal_conflicts());
+
+    view! {
+        <Meta property="og:title" content="Recusal Conflicts | Lexodus"/>
+        <Title text="Recusal Conflicts | Lexodus"/>
+        <Meta name="description" content="Cases whose judge may have to recuse."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Recusal Conflicts"</h2>
+                    <a href="/recusals" class="text-lexodus-500 underline">"Recusal Lists"</a>
+                </div>
+                <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+                    <p class="mb-4 text-lexodus-700">"Open a case to redraw its judge, or clear a match that is not a conflict."</p>
+                    {move || clear.value().get().and_then(Result::err).map(|e| view! {
+                        <div class="mb-4 text-red-500">{e.to_string()}</div>
+                    })}
+                    <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                        {move || conflicts.get().map(|result| match result {
+                            Ok(conflicts) if conflicts.is_empty() => view! { <p class="text-lexodus-700">"No open conflicts."</p> }.into_view(),
+                            Ok(conflicts) => view! { <ConflictTable conflicts clear show_case=true/> }.into_view(),
+                            Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                        })}
+                    </Suspense>
+                </section>
+            </div>
+        </DefaultLayout>
+    }
+}
@@ -1,6 +1,10 @@
-use crate::domain::models::user::SafeUser;
+use crate::domain::models::case_seal::SECURITY_LEVELS;
+use crate::domain::models::case_status::CaseStatus;
+use crate::domain::models::permission::{Permission, RequiresPermission};
 use crate::layouts::default::*;
+use crate::pages::cases::list_case::CaseList;
 use crate::providers::auth::AuthContext;
+use crate::providers::csrf::CsrfField;
 use leptos::*;
 use leptos_meta::{Meta, Title};
 use leptos_router::ActionForm;
@@ -12,120 +16,39 @@ cfg_if! {
     if #[cfg(feature = "ssr")] {
         use spin_sdk::pg::{Connection, ParameterValue, DbValue};
         use spin_sdk::{variables};
-        use std::collections::HashMap;
         use std::sync::Arc;
-        use spin_sdk::http::{Request, Headers};
-        use chrono::Utc;
-        use rand::Rng;
+        use crate::domain::models::case::CaseAccess;
+        use crate::domain::models::db::int;
+        use crate::domain::models::case_number::CaseRef;
+        use crate::domain::models::judge_assignment::{Draw, Wheel};
         use crate::errors::LexodusAppError;
-        use log::{info, error};
-
-        fn case_number_exists(conn: &Connection, case_number: &str, filed_date: &str) -> Result<bool, ServerFnError> {
-            let result = conn.query(
-                "SELECT 1 FROM cases WHERE case_number = $1 AND filed_date = $2",
-                &[
-                    ParameterValue::Str(case_number.to_string()),
-                    ParameterValue::Str(filed_date.to_string()),
-                ]
-            )?;
-
-            Ok(!result.rows.is_empty())
-        }
-
-        fn generate_case_number(conn: &Connection, court_id: i64, filed_date: &str, judge_name: Option<&str>) -> Result<String, ServerFnError> {
-            let year = filed_date[2..4].to_string(); // Extract year from filed_date (assuming format is YYYY-MM-DD)
-
-            for attempt in 1..=100 {  // Increase max attempts
-                // Get the latest case number for this court and year
-                let result = conn.query(
-                    "SELECT case_number
-                     FROM cases
-                     WHERE court_id = $1 AND case_number LIKE $2 || '%' AND filed_date = $3
-                     ORDER BY REGEXP_REPLACE(case_number, '^(\\d+)-(\\d+)-(\\d+)-.*$', '\\3')::integer DESC
-                     LIMIT 1",
-                    &[
-                        ParameterValue::Int64(court_id),
-                        ParameterValue::Str(format!("{}-{}-", court_id, year)),
-                        ParameterValue::Str(filed_date.to_string()),
-                    ]
-                )?;
-
-                let new_sequence = if let Some(row) = result.rows.first() {
-                    if let DbValue::Str(last_case_number) = &row[0] {
-                        // Extract the sequence number and increment it
-                        let parts: Vec<&str> = last_case_number.split('-').collect();
-                        if parts.len() >= 3 {
-                            if let Ok(seq) = parts[2].parse::<i64>() {
-                                seq + 1
-                            } else {
-                                1
-                            }
-                        } else {
-                            1
-                        }
-                    } else {
-                        1
-                    }
-                } else {
-                    1  // No existing cases for this court, year, and filed_date
-                };
-
-                let judge_identifier = match judge_name {
-                    Some(name) => generate_judge_initials(name),
-                    None => "XX".to_string()
-                };
-
-                let case_number = format!("{}-{}-{:04}-{}", court_id, year, new_sequence, judge_identifier);
-
-                println!("Attempt {}: Trying to generate case number: {} for date: {}", attempt, case_number, filed_date);
-
-                // Check if the generated case number already exists for this filed_date
-                if !case_number_exists(conn, &case_number, filed_date)? {
-                    return Ok(case_number);
-                }
-
-                println!("Case number {} already exists for date {}. Retrying...", case_number, filed_date);
-            }
-
-            Err(ServerFnError::ServerError(format!("Failed to generate unique case number after 100 attempts for court_id: {}, year: {}, filed_date: {}", court_id, year, filed_date)))
-        }
-
-        fn generate_judge_initials(name: &str) -> String {
-            let mut initials = name
-                .split_whitespace()
-                .filter_map(|word| word.chars().next())
-                .take(2)
-                .map(|c| c.to_ascii_uppercase())
-                .collect::<String>();
-
-            // If we don't have 2 initials, pad with 'X'
-            while initials.len() < 2 {
-                initials.push('X');
-            }
-
-            initials
-        }
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use log::{info, warn};
     }
 }
 
 #[component]
-pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
+pub fn CreateCaseForm() -> impl IntoView {
     let create_case = create_server_action::<CreateCase>();
     let response = create_case.value();
 
-    let judges = create_resource(|| (), |_| get_judges());
     let courts = create_resource(|| (), |_| get_courts());
+    let divisions = create_resource(|| (), |_| get_divisions());
+    // The divisions offered are those of the court chosen
+    let (court, set_court) = create_signal(String::new());
 
     view! {
         <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
             <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Create New Case"</h3>
 
             <ActionForm action=create_case class="space-y-6">
+                <CsrfField/>
                 <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
                     // Title
                     <div>
                         <label for="title" class="block text-sm font-medium text-lexodus-700">"Title"</label>
-                        <input type="text" id="title" name="title" required
+                        <input type="text" id="title" name="form[title]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         />
                     </div>
@@ -133,7 +56,7 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Case Type
                     <div>
                         <label for="case_type" class="block text-sm font-medium text-lexodus-700">"Case Type"</label>
-                        <select id="case_type" name="case_type" required
+                        <select id="case_type" name="form[case_type]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
                             <option value="CIVIL">"Civil"</option>
@@ -146,7 +69,7 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Nature of Suit
                     <div>
                         <label for="nature_of_suit" class="block text-sm font-medium text-lexodus-700">"Nature of Suit"</label>
-                        <select id="nature_of_suit" name="nature_of_suit"
+                        <select id="nature_of_suit" name="form[nature_of_suit]"
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
                             <option value="">"Select Nature of Suit"</option>
@@ -162,7 +85,7 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Filing Type
                     <div>
                         <label for="filing_type" class="block text-sm font-medium text-lexodus-700">"Filing Type"</label>
-                        <select id="filing_type" name="filing_type" required
+                        <select id="filing_type" name="form[filing_type]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
                             <option value="INITIAL">"Initial Filing"</option>
@@ -174,20 +97,17 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Status
                     <div>
                         <label for="status" class="block text-sm font-medium text-lexodus-700">"Status"</label>
-                        <select id="status" name="status" required
+                        <select id="status" name="form[status]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
-                            <option value="OPEN">"Open"</option>
-                            <option value="PENDING">"Pending"</option>
-                            <option value="CLOSED">"Closed"</option>
-                            <option value="STAYED">"Stayed"</option>
+                            {CaseStatus::initial().iter().map(|s| view! { <option value=s.as_str()>{s.label()}</option> }).collect_view()}
                         </select>
                     </div>
 
                     // Filed Date
                     <div>
                         <label for="filed_date" class="block text-sm font-medium text-lexodus-700">"Filed Date"</label>
-                        <input type="date" id="filed_date" name="filed_date" required
+                        <input type="date" id="filed_date" name="form[filed_date]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         />
                     </div>
@@ -195,9 +115,11 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Court
                     <div>
                         <label for="court_id" class="block text-sm font-medium text-lexodus-700">"Court"</label>
-                        <select id="court_id" name="court_id" required
+                        <select id="court_id" name="form[court_id]" required
+                            on:change=move |ev| set_court.set(event_target_value(&ev))
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
+                            <option value="">"Select Court"</option>
                             <Suspense fallback=move || view! { <option>"Loading courts..."</option> }>
                                 {move || courts.get().map(|result| match result {
                                     Ok(courts) => courts.into_iter().map(|court| {
@@ -211,42 +133,45 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                         </select>
                     </div>
 
-                    // Assigned Judge
+                    // Division; the judge is drawn from its wheel
                     <div>
-                        <label for="assigned_judge_id" class="block text-sm font-medium text-lexodus-700">"Assigned Judge"</label>
-                        <select id="assigned_judge_id" name="assigned_judge_id"
+                        <label for="division_id" class="block text-sm font-medium text-lexodus-700">"Division"</label>
+                        <select id="division_id" name="form[division_id]"
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
-                            <option value="">"Select Judge (Optional)"</option>
-                            <Suspense fallback=move || view! { <option>"Loading judges..."</option> }>
-                                {move || judges.get().map(|result| match result {
-                                    Ok(judges) => judges.into_iter().map(|judge| {
-                                        view! {
-                                            <option value={judge.id.to_string()}>{judge.name}</option>
-                                        }
-                                    }).collect_view(),
-                                    Err(_) => view! { <option>"Failed to load judges"</option> }.into_view(),
+                            <option value="">"No Division"</option>
+                            <Suspense fallback=|| ()>
+                                {move || divisions.get().and_then(Result::ok).map(|divisions| {
+                                    divisions.into_iter().filter(|d| d.court_id.to_string() == court.get()).map(|division| {
+                                        view! { <option value={division.id.to_string()}>{division.name}</option> }
+                                    }).collect_view()
                                 })}
                             </Suspense>
                         </select>
                     </div>
 
+                    // Related case; the new case goes to its judge when they are eligible
+                    <div>
+                        <label for="related_case" class="block text-sm font-medium text-lexodus-700">"Related Case Number"</label>
+                        <input type="text" id="related_case" name="form[related_case]" placeholder="Optional"
+                            class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
+                        />
+                    </div>
+
                     // Security Level
                     <div>
                         <label for="security_level" class="block text-sm font-medium text-lexodus-700">"Security Level"</label>
-                        <select id="security_level" name="security_level" required
+                        <select id="security_level" name="form[security_level]" required
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
-                            <option value="PUBLIC">"Public"</option>
-                            <option value="SEALED">"Sealed"</option>
-                            <option value="RESTRICTED">"Restricted"</option>
+                            {SECURITY_LEVELS.iter().map(|(value, label)| view! { <option value=*value>{*label}</option> }).collect_view()}
                         </select>
                     </div>
 
                     // Jury Demand
                     <div>
                         <label for="jury_demand" class="block text-sm font-medium text-lexodus-700">"Jury Demand"</label>
-                        <select id="jury_demand" name="jury_demand"
+                        <select id="jury_demand" name="form[jury_demand]"
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
                             <option value="">"None"</option>
@@ -266,7 +191,7 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     // Jurisdictional Basis
                     <div>
                         <label for="jurisdictional_basis" class="block text-sm font-medium text-lexodus-700">"Jurisdictional Basis"</label>
-                        <select id="jurisdictional_basis" name="jurisdictional_basis"
+                        <select id="jurisdictional_basis" name="form[jurisdictional_basis]"
                             class="mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500"
                         >
                             <option value="">"Select Basis"</option>
@@ -277,13 +202,6 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
                     </div>
                 </div>
 
-                <input type="hidden" name="user_id"
-                    value=match user {
-                        Some(u) => u.id.to_string(),
-                        None => "-1".to_string(),
-                    }
-                />
-
                 <div class="mt-6">
                     <button type="submit"
                         class="w-full inline-flex justify-center py-2 px-4 border border-transparent shadow-sm text-sm font-medium rounded-md text-white bg-lexodus-600 hover:bg-lexodus-700 focus:outline-none focus:ring-2 focus:ring-offset-2 focus:ring-lexodus-500"
@@ -308,88 +226,6 @@ pub fn CreateCaseForm(user: Option<SafeUser>) -> impl IntoView {
     }
 }
 
-#[component]
-pub fn CaseList() -> impl IntoView {
-    let cases = create_resource(|| (), |_| async move { get_cases().await });
-
-    view! {
-        <div class="mt-8 -mx-4 sm:mx-0">
-            <h3 class="text-xl font-semibold text-lexodus-800 mb-4 px-4 sm:px-0">"Existing Cases"</h3>
-            <div class="bg-white shadow-lg border border-lexodus-200 overflow-hidden sm:rounded-lg">
-                <div class="overflow-x-auto">
-                    <table class="w-full bg-white table-auto">
-                        <thead>
-                            <tr>
-                                <th class="py-2 pl-4 pr-2 sm:px-4 border-b text-left text-lexodus-700 font-medium">"Case Number"</th>
-                                <th class="py-2 px-2 sm:px-4 border-b text-left text-lexodus-700 font-medium">"Title"</th>
-                                <th class="py-2 px-2 sm:px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
-                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Filed Date"</th>
-                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Court"</th>
-                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Current Court"</th>
-                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Judge"</th>
-                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Actions"</th>
-                            </tr>
-                        </thead>
-                        <tbody>
-                            <Suspense fallback=move || view! { <tr><td colspan="8" class="text-center py-4">"Loading..."</td></tr> }>
-                                {move || cases.get().map(|result| match result {
-                                    Ok(cases) => cases.into_iter().map(|case| {
-                                        view! {
-                                            <>
-                                                <tr class="hover:bg-lexodus-50">
-                                                    <td class="py-2 pl-4 pr-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.case_number}</td>
-                                                    <td class="py-2 px-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.title}</td>
-                                                    <td class="py-2 px-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.status}</td>
-                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.filed_date}</td>
-                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.court_name}</td>
-                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.current_court_name}</td>
-                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">
-                                                        {case.judge_name.unwrap_or_else(|| "Not assigned".to_string())}
-                                                    </td>
-                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-sm text-lexodus-800">
-                                                        <button
-                                                            class="bg-lexodus-500 text-white px-3 py-1 rounded hover:bg-lexodus-600"
-                                                            on:click=move |_| {
-                                                                // TODO: Implement view case details
-                                                            }
-                                                        >
-                                                            "View"
-                                                        </button>
-                                                    </td>
-                                                </tr>
-                                                <tr class="sm:hidden hover:bg-lexodus-50">
-                                                    <td colspan="3" class="py-2 px-4 border-b text-right">
-                                                        <button
-                                                            class="w-full bg-lexodus-500 text-white px-4 py-2 rounded hover:bg-lexodus-600 text-sm font-medium"
-                                                            on:click=move |_| {
-                                                                // TODO: Implement view case details
-                                                            }
-                                                        >
-                                                            "View Case Details"
-                                                        </button>
-                                                    </td>
-                                                </tr>
-                                            </>
-                                        }
-                                    }).collect_view(),
-                                    Err(e) => view! {
-                                        <tr>
-                                            <td colspan="8" class="text-center text-red-500 border-b py-4">"No existing cases found."</td>
-                                        </tr>
-                                        <tr>
-                                            <td colspan="8" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
-                                        </tr>
-                                    }.into_view(),
-                                })}
-                            </Suspense>
-                        </tbody>
-                    </table>
-                </div>
-            </div>
-        </div>
-    }
-}
-
 #[component]
 pub fn CaseManagement() -> impl IntoView {
     let auth_context = use_context::<AuthContext>().expect("Failed to get AuthContext");
@@ -403,6 +239,8 @@ pub fn CaseManagement() -> impl IntoView {
             <div class="w-full px-0 sm:p-8 bg-lexodus-50">
                 <div class="flex justify-between items-center mb-8 px-4 sm:px-0">
                     <h2 class="text-xl sm:text-2xl font-semibold text-lexodus-800">"Case Management"</h2>
+                    <a href="/cases/search" class="ml-auto mr-4 text-sm sm:text-base text-lexodus-500 underline">"Search"</a>
+                    <a href="/cases/conflicts" class="mr-4 text-sm sm:text-base text-lexodus-500 underline">"Conflicts"</a>
                     <button
                         class="bg-lexodus-600 text-white text-sm sm:text-base px-2 py-1 sm:px-4 sm:py-2 rounded hover:bg-lexodus-700"
                         on:click=move |_| set_show_form.update(|v| *v = !*v)
@@ -423,7 +261,7 @@ pub fn CaseManagement() -> impl IntoView {
                       view! {
                         <Show when=move || user().is_some() fallback=|| ().into_view()>
                           <div class=move || format!("form-container px-4 sm:px-0 {}", if show_form.get() { "visible" } else { "" })>
-                          <CreateCaseForm user=user()/>
+                          <CreateCaseForm/>
                             </div>
                             <CaseList/>
                         </Show>
@@ -500,23 +338,14 @@ fn ActivityItem(action: &'static str, details: &'static str) -> impl IntoView {
     }
 }
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Case {
+pub struct Judge {
     pub id: i64,
-    pub case_number: String,
-    pub title: String,
-    pub status: String,
-    pub filed_date: String,
+    pub name: String,
     pub court_id: i64,
-    pub court_name: String,
-    pub current_court_id: i64,
-    pub current_court_name: String,
-    pub judge_id: Option<i64>,
-    pub judge_name: Option<String>,
-    pub user_id: String,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct Judge {
+pub struct Division {
     pub id: i64,
     pub name: String,
     pub court_id: i64,
@@ -530,116 +359,205 @@ pub struct Court {
     pub circuit: String,
 }
 
+/// Fields of the create form
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewCaseForm {
+    pub title: String,
+    pub status: String,
+    pub filed_date: String,
+    pub court_id: String,
+    pub division_id: Option<String>,
+    pub case_type: String,
+    pub nature_of_suit: Option<String>,
+    pub filing_type: String,
+    pub security_level: String,
+    pub related_case: Option<String>,
+    pub jury_demand: Option<String>,
+    pub jurisdictional_basis: Option<String>,
+}
+
 #[server(CreateCase, "/api")]
-pub async fn create_case(
-    title: String,
-    status: String,
-    filed_date: String,
-    court_id: String,
-    case_type: String,
-    nature_of_suit: Option<String>,
-    filing_type: String,
-    security_level: String,
-    assigned_judge_id: Option<String>,
-    jury_demand: Option<String>,
-    jurisdictional_basis: Option<String>,
-    user_id: String,
-) -> Result<String, ServerFnError> {
+pub async fn create_case(csrf_token: String, form: NewCaseForm) -> Result<String, ServerFnError> {
+    let NewCaseForm {
+        title,
+        status,
+        filed_date,
+        court_id,
+        division_id,
+        case_type,
+        nature_of_suit,
+        filing_type,
+        security_level,
+        related_case,
+        jury_demand,
+        jurisdictional_basis,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<CreateCase>().await?;
     info!("Starting case creation process");
 
-    // Parse user ID
-    let user_id_i64 = user_id
-        .parse::<i64>()
-        .map_err(|_| LexodusAppError::BadRequest("Invalid user ID format".to_string()))?;
+    let user_id_i64 = user.id;
+
+    // Cases are filed open or pending; other statuses are reached through the lifecycle
+    if !CaseStatus::parse(&status).is_some_and(|s| CaseStatus::initial().contains(&s)) {
+        return Err(LexodusAppError::BadRequest(format!("A case cannot be filed as {status}")).into());
+    }
+    // Filing under seal needs no order; sealing later, or unsealing, does
+    if !SECURITY_LEVELS.iter().any(|(value, _)| *value == security_level) {
+        return Err(LexodusAppError::BadRequest(format!("Unknown security level {security_level}")).into());
+    }
 
     // Parse court ID
     let court_id_i64 = court_id
         .parse::<i64>()
         .map_err(|_| LexodusAppError::BadRequest("Invalid court ID format".to_string()))?;
 
-    // Parse judge ID if present
-    let assigned_judge_id_i64 =
-        if let Some(judge_id) = assigned_judge_id {
-            if judge_id.is_empty() {
-                None
-            } else {
-                Some(judge_id.parse::<i64>().map_err(|_| {
-                    LexodusAppError::BadRequest("Invalid judge ID format".to_string())
-                })?)
-            }
-        } else {
-            None
-        };
-
-    // Get database connection
     let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
+    let conn = Arc::new(Connection::open(&db_url).map_err(|e| LexodusAppError::DBError(e.to_string()))?);
+
+    // A division, which must belong to the court
+    let division_id = match division_id.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(division_id) => {
+            let division_id = division_id
+                .parse::<i64>()
+                .map_err(|_| LexodusAppError::BadRequest("Invalid division ID format".to_string()))?;
+            let rowset = conn.query(
+                "SELECT 1 FROM court_divisions WHERE id = $1 AND court_id = $2",
+                &[ParameterValue::Int64(division_id), ParameterValue::Int64(court_id_i64)],
+            )?;
+            if rowset.rows.is_empty() {
+                return Err(LexodusAppError::BadRequest("That division is not part of the court".to_string()).into());
+            }
+            Some(division_id)
+        }
+        None => None,
+    };
 
-    let conn = Connection::open(&db_url).map_err(|e| LexodusAppError::DBError(e.to_string()))?;
+    // A related case the filer can see, and the judge it is assigned to
+    let related = match related_case.as_deref().map(str::trim).filter(|r| !r.is_empty()) {
+        Some(related_case) => {
+            let case_ref = related_case
+                .parse::<CaseRef>()
+                .map_err(|_| LexodusAppError::BadRequest(format!("{related_case} is not a case number")))?;
+            let case_id = match &case_ref {
+                CaseRef::Id(id) => Some(*id),
+                CaseRef::Number(number) => number.find(user.id, &conn).await?,
+            };
+            let filed = match case_id {
+                Some(case_id) => CaseAccess::visible_filed_date(case_id, user.id, &conn).await?.map(|filed| (case_id, filed)),
+                None => None,
+            };
+            let (case_id, filed) = filed.ok_or_else(|| LexodusAppError::BadRequest(format!("No case {related_case} was found")))?;
+            let judge = conn
+                .query(
+                    "SELECT assigned_judge_id FROM cases WHERE id = $1 AND filed_date = $2::date",
+                    &[ParameterValue::Int64(case_id), ParameterValue::Str(filed.clone())],
+                )?
+                .rows
+                .first()
+                .and_then(|row| int(&row[0]));
+            Some((case_id, filed, judge))
+        }
+        None => None,
+    };
 
-    // Generate case number
-    let case_number = generate_case_number(&conn, court_id_i64, &filed_date, None)?;
-
-    let execute_result = if let Some(judge_id) = assigned_judge_id_i64 {
-        // SQL with judge
-        let sql = "INSERT INTO cases (
-            case_number, title, case_type, nature_of_suit,
-            filing_type, status, filed_date, court_id,
-            assigned_judge_id, security_level, jury_demand,
-            jurisdictional_basis, created_by, updated_by
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7::date, $8, $9, $10, $11, $12, $13, $13)
-        RETURNING id";
-
-        conn.execute(
-            sql,
-            &[
-                ParameterValue::Str(case_number.clone()),
-                ParameterValue::Str(title),
-                ParameterValue::Str(case_type),
-                ParameterValue::Str(nature_of_suit.unwrap_or_default()),
-                ParameterValue::Str(filing_type),
-                ParameterValue::Str(status),
-                ParameterValue::Str(filed_date),
-                ParameterValue::Int64(court_id_i64),
-                ParameterValue::Int64(judge_id),
-                ParameterValue::Str(security_level),
-                ParameterValue::Str(jury_demand.unwrap_or_default()),
-                ParameterValue::Str(jurisdictional_basis.unwrap_or_default()),
-                ParameterValue::Int64(user_id_i64),
-            ],
-        )
-    } else {
-        // SQL without judge
-        let sql = "INSERT INTO cases (
-            case_number, title, case_type, nature_of_suit,
-            filing_type, status, filed_date, court_id,
-            security_level, jury_demand,
-            jurisdictional_basis, created_by, updated_by
-        ) VALUES ($1, $2, $3, $4, $5, $6, $7::date, $8, $9, $10, $11, $12, $12)
-        RETURNING id";
-
-        conn.execute(
-            sql,
-            &[
-                ParameterValue::Str(case_number.clone()),
-                ParameterValue::Str(title),
-                ParameterValue::Str(case_type),
-                ParameterValue::Str(nature_of_suit.unwrap_or_default()),
-                ParameterValue::Str(filing_type),
-                ParameterValue::Str(status),
-                ParameterValue::Str(filed_date),
-                ParameterValue::Int64(court_id_i64),
-                ParameterValue::Str(security_level),
-                ParameterValue::Str(jury_demand.unwrap_or_default()),
-                ParameterValue::Str(jurisdictional_basis.unwrap_or_default()),
-                ParameterValue::Int64(user_id_i64),
-            ],
-        )
+    // Draw the judge blind from the court's wheel, unless the related case's
+    // judge can take it. With nobody eligible the case is filed unassigned.
+    let wheel = Wheel {
+        court_id: court_id_i64,
+        division_id,
+        case_type: case_type.clone(),
+        on: filed_date.clone(),
+        case: None,
     };
+    let pool = wheel.pool(None, &conn).await?;
+    let draw = match &related {
+        Some((related_id, _, Some(judge_id))) => Draw::related(pool.clone(), *judge_id, *related_id),
+        _ => None,
+    }
+    .or_else(|| Draw::random(pool));
+    if draw.is_none() {
+        warn!("No judge is eligible for a {case_type} case in court {court_id_i64}; filing it unassigned");
+    }
+
+    let mut params = vec![
+        ParameterValue::Str(title),
+        ParameterValue::Str(case_type),
+        ParameterValue::Str(nature_of_suit.unwrap_or_default()),
+        ParameterValue::Str(filing_type),
+        ParameterValue::Str(status),
+        ParameterValue::Str(filed_date),
+        ParameterValue::Int64(court_id_i64),
+        draw.as_ref().map_or(ParameterValue::DbNull, |d| ParameterValue::Int64(d.judge_id)),
+        ParameterValue::Str(security_level),
+        ParameterValue::Str(jury_demand.unwrap_or_default()),
+        ParameterValue::Str(jurisdictional_basis.unwrap_or_default()),
+        ParameterValue::Int64(user_id_i64),
+        division_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+    ];
+    // The draw is recorded, and the related case linked, by the same statement
+    let mut records = String::new();
+    if let Some(draw) = &draw {
+        let n = params.len();
+        records.push_str(&format!(
+            ", assignment AS (
+                INSERT INTO judge_assignments (case_id, case_filed_date, judge_id, method, seed, pool, related_case_id, assigned_by)
+                SELECT id, filed_date, $8, ${}, ${}, ${}::jsonb, ${}, $12 FROM inserted
+            )",
+            n + 1,
+            n + 2,
+            n + 3,
+            n + 4
+        ));
+        params.extend([
+            ParameterValue::Str(draw.method.as_str().to_string()),
+            draw.seed.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+            ParameterValue::Str(draw.pool_json()),
+            draw.related_case_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+        ]);
+    }
+    if let Some((related_id, related_filed, _)) = related {
+        let n = params.len();
+        records.push_str(&format!(
+            ", link AS (
+                INSERT INTO related_cases (case_id, case_filed_date, related_case_id, related_case_filed_date, relationship_type, created_by)
+                SELECT id, filed_date, ${}, ${}::date, 'RELATED', $12 FROM inserted
+            )",
+            n + 1,
+            n + 2
+        ));
+        params.extend([ParameterValue::Int64(related_id), ParameterValue::Str(related_filed)]);
+    }
+
+    // The number is drawn inside the INSERT, so concurrent filings never share
+    // a sequence and a failed insert does not use one up
+    let sql = format!(
+        "WITH number AS (SELECT * FROM allocate_case_number($7, $2, $6::date, $8)),
+        inserted AS (
+            INSERT INTO cases (
+                case_number, case_sequence, case_number_template,
+                title, case_type, nature_of_suit,
+                filing_type, status, filed_date, court_id, division_id,
+                assigned_judge_id, security_level, jury_demand,
+                jurisdictional_basis, created_by, updated_by
+            )
+            SELECT number.number, number.sequence, number.template,
+                   $1, $2, $3, $4, $5, $6::date, $7, $13, $8, $9, $10, $11, $12, $12
+            FROM number
+            RETURNING id, filed_date, case_number
+        ){records}
+        SELECT case_number FROM inserted"
+    );
+
+    let execute_result = conn.query(&sql, &params);
 
     match execute_result {
-        Ok(_) => Ok(format!(
+        Ok(rowset) => Ok(format!(
             "Case created successfully with number {}",
-            case_number
+            match rowset.rows.first().map(|row| &row[0]) {
+                Some(DbValue::Str(case_number)) => case_number.clone(),
+                _ => String::new(),
+            }
         )),
         Err(e) => Err(ServerFnError::ServerError(format!(
             "Failed to create case: {}",
@@ -648,22 +566,18 @@ pub async fn create_case(
     }
 }
 
+impl RequiresPermission for CreateCase {
+    const PERMISSION: Permission = Permission::CASE_CREATE;
+}
+
 #[server(LogFailedCaseCreation, "/api")]
-pub async fn log_failed_case_creation(
-    reason: String,
-    user_id: String,
-) -> Result<(), ServerFnError> {
-    let user_id_i64 = match user_id.parse::<i64>() {
-        Ok(id) => id,
-        Err(_) => {
-            return Err(ServerFnError::ServerError(
-                "Invalid user ID format".to_string(),
-            ))
-        }
-    };
+pub async fn log_failed_case_creation(csrf_token: String, reason: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<LogFailedCaseCreation>().await?;
+    let user_id_i64 = user.id;
 
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
+    let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
+    let conn = Connection::open(&db_url).map_err(|e| LexodusAppError::DBError(e.to_string()))?;
 
     conn.execute(
         "INSERT INTO failed_operations (user_id, operation, reason, timestamp)
@@ -677,113 +591,14 @@ pub async fn log_failed_case_creation(
 
     Ok(())
 }
-#[server(GetCases, "/api")]
-pub async fn get_cases() -> Result<Vec<Case>, ServerFnError> {
-    // Get database connection
-    let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
-    // Open Connection
-    let conn = Connection::open(&db_url).map_err(|e| LexodusAppError::DBError(e.to_string()))?;
-
-    let sql = "SELECT c.id, c.case_number, c.title, c.status, c.filed_date,
-               c.court_id, co.name as court_name,
-               c.court_id as current_court_id, co.name as current_court_name,
-               c.assigned_judge_id, j.name as judge_name,
-               COALESCE(c.created_by::text, '-1') as user_id
-               FROM cases c
-               LEFT JOIN courts co ON c.court_id = co.id
-               LEFT JOIN judicial_officers j ON c.assigned_judge_id = j.id
-               ORDER BY c.filed_date DESC";
-
-    let rowset = conn
-        .query(sql, &[])
-        .map_err(|e| LexodusAppError::DBError("Failed to execute query".to_string()))?;
-    let cases: Vec<Case> = rowset
-        .rows
-        .iter()
-        .map(|row| Case {
-            id: match &row[0] {
-                DbValue::Int64(id) => *id,
-                _ => 0,
-            },
-            case_number: match &row[1] {
-                DbValue::Str(case_number) => case_number.clone(),
-                _ => String::new(),
-            },
-            title: match &row[2] {
-                DbValue::Str(title) => title.clone(),
-                _ => String::new(),
-            },
-            status: match &row[3] {
-                DbValue::Str(status) => status.clone(),
-                _ => String::new(),
-            },
-            filed_date: match &row[4] {
-                DbValue::Str(filed_date) => filed_date.clone(),
-                _ => String::new(),
-            },
-            court_id: match &row[5] {
-                DbValue::Int64(court_id) => *court_id,
-                _ => 0,
-            },
-            court_name: match &row[6] {
-                DbValue::Str(court_name) => court_name.clone(),
-                _ => String::new(),
-            },
-            current_court_id: match &row[7] {
-                DbValue::Int64(current_court_id) => *current_court_id,
-                _ => 0,
-            },
-            current_court_name: match &row[8] {
-                DbValue::Str(current_court_name) => current_court_name.clone(),
-                _ => String::new(),
-            },
-            judge_id: match &row[9] {
-                DbValue::Int64(judge_id) => Some(*judge_id),
-                _ => None,
-            },
-            judge_name: match &row[10] {
-                DbValue::Str(judge_name) => Some(judge_name.clone()),
-                _ => None,
-            },
-            user_id: match &row[11] {
-                DbValue::Str(user_id) => user_id.clone(),
-                _ => "-1".to_string(), // Default value if user_id is not found
-            },
-        })
-        .collect();
-
-    Ok(cases)
-}
-
-#[server(UpdateCaseStatus, "/api")]
-pub async fn update_case_status(case_id: i64, new_status: String) -> Result<String, ServerFnError> {
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
-
-    let sql = "UPDATE cases SET status = $1 WHERE id = $2";
-
-    let execute_result = conn.execute(
-        sql,
-        &[
-            ParameterValue::Str(new_status),
-            ParameterValue::Int64(case_id),
-        ],
-    );
 
-    match execute_result {
-        Ok(rows_affected) => Ok(format!(
-            "Case status updated successfully: {}",
-            rows_affected
-        )),
-        Err(e) => Err(ServerFnError::ServerError(format!(
-            "Failed to update case status: {}",
-            e
-        ))),
-    }
+impl RequiresPermission for LogFailedCaseCreation {
+    const PERMISSION: Permission = Permission::CASE_CREATE;
 }
 
 #[server(GetJudges, "/api")]
 pub async fn get_judges() -> Result<Vec<Judge>, ServerFnError> {
+    authorize::<GetJudges>().await?;
     // Get database connection
     let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
 
@@ -801,7 +616,7 @@ pub async fn get_judges() -> Result<Vec<Judge>, ServerFnError> {
 
     let rowset = conn
         .query(sql, &[])
-        .map_err(|e| LexodusAppError::DBError("Failed to execute query".to_string()))?;
+        .map_err(|e| LexodusAppError::DBError(e.to_string()))?;
 
     let judges: Vec<Judge> = rowset
         .rows
@@ -825,8 +640,41 @@ pub async fn get_judges() -> Result<Vec<Judge>, ServerFnError> {
     Ok(judges)
 }
 
+impl RequiresPermission for GetJudges {
+    const PERMISSION: Permission = Permission::COURT_READ;
+}
+
+#[server(GetDivisions, "/api")]
+pub async fn get_divisions() -> Result<Vec<Division>, ServerFnError> {
+    authorize::<GetDivisions>().await?;
+    let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
+    let conn = Connection::open(&db_url).map_err(|e| LexodusAppError::DBError(e.to_string()))?;
+
+    let rowset = conn
+        .query("SELECT id, name, court_id FROM court_divisions ORDER BY name", &[])
+        .map_err(|e| LexodusAppError::DBError(e.to_string()))?;
+
+    Ok(rowset
+        .rows
+        .iter()
+        .map(|row| Division {
+            id: int(&row[0]).unwrap_or_default(),
+            name: match &row[1] {
+                DbValue::Str(name) => name.clone(),
+                _ => String::new(),
+            },
+            court_id: int(&row[2]).unwrap_or_default(),
+        })
+        .collect())
+}
+
+impl RequiresPermission for GetDivisions {
+    const PERMISSION: Permission = Permission::COURT_READ;
+}
+
 #[server(GetCourts, "/api")]
 pub async fn get_courts() -> Result<Vec<Court>, ServerFnError> {
+    authorize::<GetCourts>().await?;
     // Get database connection
     let db_url = variables::get("db_url").map_err(|_| LexodusAppError::DBConnectionNotFound)?;
 
@@ -843,7 +691,7 @@ pub async fn get_courts() -> Result<Vec<Court>, ServerFnError> {
 
     let rowset = conn
         .query(sql, &[])
-        .map_err(|e| LexodusAppError::DBError("Failed to execute query".to_string()))?;
+        .map_err(|e| LexodusAppError::DBError(e.to_string()))?;
 
     let courts: Vec<Court> = rowset
         .rows
@@ -870,3 +718,7 @@ pub async fn get_courts() -> Result<Vec<Court>, ServerFnError> {
 
     Ok(courts)
 }
+
+impl RequiresPermission for GetCourts {
+    const PERMISSION: Permission = Permission::COURT_READ;
+}
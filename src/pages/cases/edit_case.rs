@@ -1,6 +1,393 @@
+use cfg_if::cfg_if;
 use leptos::*;
+use leptos_meta::{Meta, Title};
+use leptos_router::*;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::models::case::{CaseEdit, CaseEditOptions, CaseSaveOutcome, FieldChange};
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::layouts::default::DefaultLayout;
+use crate::pages::cases::view_case::{for_case, is_not_found};
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::CaseHeader;
+        use crate::domain::models::activity_log::{action_type, status, ActivityLog, NewActivity};
+        use crate::errors::LexodusAppError;
+        use crate::functions::auth::ClientInfo;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::view_case::visible_case;
+        use leptos_spin::RequestParts;
+
+        /// Trimmed text, with blank input meaning no value
+        fn optional(value: Option<String>) -> Option<String> {
+            value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        }
+
+        /// A submitted division or judge, which must be one of the offered options
+        fn choice(value: Option<String>, options: &[(i64, String)], field: &str) -> Result<Option<i64>, LexodusAppError> {
+            let Some(value) = optional(value) else {
+                return Ok(None);
+            };
+            value
+                .parse::<i64>()
+                .ok()
+                .filter(|id| options.iter().any(|(option, _)| option == id))
+                .map(Some)
+                .ok_or_else(|| LexodusAppError::BadRequest(format!("Choose a {field} from the list.")))
+        }
+    }
+}
+
+pub const JURY_DEMANDS: [&str; 2] = ["YES", "NO"];
+pub const JURISDICTIONAL_BASES: [&str; 3] = ["FEDERAL_QUESTION", "DIVERSITY", "SUPPLEMENTAL"];
+
+/// Everything the edit page needs to draw its form
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseEditForm {
+    pub case_id: i64,
+    pub case_number: String,
+    pub case: CaseEdit,
+    pub options: CaseEditOptions,
+}
+
+#[derive(Params, PartialEq, Clone)]
+struct CaseParams {
+    id: Option<String>,
+}
+
+#[server(GetCaseForEdit, "/api")]
+pub async fn get_case_for_edit(case: CaseRef) -> Result<CaseEditForm, ServerFnError> {
+    let user = authorize::<GetCaseForEdit>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    let case = CaseEdit::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+    let case_number = CaseHeader::get(case_id, &filed_date, &con).await?.map(|header| header.case_number).unwrap_or_default();
+    let options = CaseEditOptions::load(case_id, &filed_date, &con).await?;
+    Ok(CaseEditForm { case_id, case_number, case, options })
+}
+
+impl RequiresPermission for GetCaseForEdit {
+    const PERMISSION: Permission = Permission::CASE_UPDATE;
+}
+
+/// Fields of the edit form. `version` is the one the form was loaded at.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaseUpdateForm {
+    pub case_id: i64,
+    pub version: i64,
+    pub title: String,
+    pub nature_of_suit: Option<String>,
+    pub cause_of_action: Option<String>,
+    pub jury_demand: Option<String>,
+    pub demand_amount: Option<String>,
+    pub jurisdictional_basis: Option<String>,
+    pub division_id: Option<String>,
+    pub magistrate_judge_id: Option<String>,
+}
+
+/// Save an edit made from `version`. When the case has changed since, nothing
+/// is written and the caller gets both versions back to reconcile.
+#[server(UpdateCase, "/api")]
+pub async fn update_case(csrf_token: String, form: CaseUpdateForm) -> Result<CaseSaveOutcome, ServerFnError> {
+    let CaseUpdateForm {
+        case_id,
+        version,
+        title,
+        nature_of_suit,
+        cause_of_action,
+        jury_demand,
+        demand_amount,
+        jurisdictional_basis,
+        division_id,
+        magistrate_judge_id,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<UpdateCase>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let options = CaseEditOptions::load(case_id, &filed_date, &con).await?;
+
+    let title = title.trim().to_string();
+    if title.is_empty() {
+        return Err(LexodusAppError::BadRequest("A case needs a title.".to_string()).into());
+    }
+    let jury_demand = optional(jury_demand);
+    if jury_demand.as_deref().is_some_and(|v| !JURY_DEMANDS.contains(&v)) {
+        return Err(LexodusAppError::BadRequest("Unknown jury demand.".to_string()).into());
+    }
+    let jurisdictional_basis = optional(jurisdictional_basis);
+    if jurisdictional_basis.as_deref().is_some_and(|v| !JURISDICTIONAL_BASES.contains(&v)) {
+        return Err(LexodusAppError::BadRequest("Unknown jurisdictional basis.".to_string()).into());
+    }
+    // Stored as NUMERIC(15,2); normalize so "1500" and "1500.00" are the same value
+    let demand_amount = match optional(demand_amount) {
+        Some(amount) => match amount.replace(',', "").parse::<f64>() {
+            Ok(n) if n.is_finite() && (0.0..1e13).contains(&n) => Some(format!("{n:.2}")),
+            _ => return Err(LexodusAppError::BadRequest("Enter the demand amount as a number.".to_string()).into()),
+        },
+        None => None,
+    };
+    let mine = CaseEdit {
+        title,
+        nature_of_suit: optional(nature_of_suit),
+        cause_of_action: optional(cause_of_action),
+        jury_demand,
+        demand_amount,
+        jurisdictional_basis,
+        division_id: choice(division_id, &options.divisions, "division")?,
+        magistrate_judge_id: choice(magistrate_judge_id, &options.magistrates, "magistrate judge")?,
+        version,
+    };
+
+    let Some(before) = CaseEdit::get(case_id, &filed_date, &con).await? else {
+        return Err(LexodusAppError::NotFound.into());
+    };
+    let conflict = |current: CaseEdit| {
+        tracing::info!("Refused a stale edit of case {case_id} by user {}", user.id);
+        let changes = current.changes(&mine, &options);
+        Ok(CaseSaveOutcome::Conflict { mine: Box::new(mine.clone()), current: Box::new(current), changes })
+    };
+    if before.version != mine.version {
+        return conflict(before);
+    }
+    let changes = before.changes(&mine, &options);
+    if !changes.is_empty() {
+        let Some(new_version) = mine.save(case_id, &filed_date, user.id, &con).await? else {
+            let current = CaseEdit::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+            return conflict(current);
+        };
+        let client = use_context::<RequestParts>().map(|req| ClientInfo::from_request(&req)).unwrap_or_default();
+        let activity = NewActivity {
+            user_id: user.id,
+            action_type: action_type::CASE_UPDATE,
+            action_category: "CASE",
+            action_description: format!("Edited {} field(s) of case {case_id}", changes.len()),
+            entity_type: "CASE",
+            entity_id: case_id.to_string(),
+            ip_address: client.ip_address,
+            user_agent: client.user_agent,
+            session_id: None,
+            status: status::SUCCESS,
+            metadata: Some(serde_json::json!({
+                "from_version": before.version,
+                "to_version": new_version,
+                "changes": changes,
+            })),
+        };
+        if let Err(e) = ActivityLog::record(activity, &con).await {
+            tracing::error!("Failed to audit the edit of case {case_id}: {e}");
+        }
+        tracing::info!("User {} edited case {case_id}", user.id);
+    }
+    leptos_spin::redirect(&format!("/cases/{case_id}"));
+    Ok(CaseSaveOutcome::Saved)
+}
+
+impl RequiresPermission for UpdateCase {
+    const PERMISSION: Permission = Permission::CASE_UPDATE;
+}
 
 #[component]
 pub fn EditCase() -> impl IntoView {
-    view! { <h2>"Edit Case"</h2> }
+    let params = use_params::<CaseParams>();
+    let case = move || {
+        params.with(|p| {
+            p.as_ref()
+                .ok()
+                .and_then(|p| p.id.as_deref())
+                .and_then(|id| id.parse::<CaseRef>().ok())
+        })
+    };
+    let form = create_resource(case, |case| for_case(case, get_case_for_edit));
+    let update = create_server_action::<UpdateCase>();
+    let response = update.value();
+    let discard = move |_| {
+        response.set(None);
+        form.refetch();
+    };
+
+    view! {
+        <Meta property="og:title" content="Edit Case | Lexodus"/>
+        <Title text="Edit Case | Lexodus"/>
+        <Meta name="description" content="Edit case details in Lexodus"/>
+        <DefaultLayout>
+            <div class="w-full px-0 sm:p-8 bg-lexodus-50">
+                <Suspense fallback=move || view! { <p class="px-4 sm:px-0">"Loading..."</p> }>
+                    {move || form.get().map(|result| match result {
+                        Ok(form) => {
+                            let case_id = form.case_id;
+                            // After a conflict the form keeps the editor's values on top of the newer version
+                            let (case, conflict) = match response.get() {
+                                Some(Ok(CaseSaveOutcome::Conflict { mine, current, changes })) => {
+                                    (CaseEdit { version: current.version, ..*mine }, Some(changes))
+                                }
+                                _ => (form.case, None),
+                            };
+                            view! {
+                                <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+                                    <div class="flex justify-between items-center mb-6">
+                                        <h2 class="text-2xl font-semibold text-lexodus-800">{format!("Edit {}", form.case_number)}</h2>
+                                        <a href=format!("/cases/{case_id}") class="text-lexodus-500 underline">"Back to case"</a>
+                                    </div>
+                                    {conflict.map(|changes| view! {
+                                        <ConflictNotice changes/>
+                                        <button type="button" on:click=discard class="mb-6 text-lexodus-500 underline">
+                                            "Discard my changes and start from the current version"
+                                        </button>
+                                    })}
+                                    {move || response.get().and_then(Result::err).map(|e| view! {
+                                        <div class="mb-4 text-red-500">{e.to_string()}</div>
+                                    })}
+                                    <CaseEditFields action=update case_id case options=form.options/>
+                                </section>
+                            }.into_view()
+                        }
+                        Err(e) if is_not_found(&e) => view! {
+                            <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+                                <h2 class="text-xl font-semibold text-lexodus-800 mb-2">"Case not found"</h2>
+                                <p class="text-lexodus-700">"There is no case at this address, or you do not have access to it."</p>
+                            </section>
+                        }.into_view(),
+                        Err(e) => view! {
+                            <div class="px-4 sm:px-0 text-red-500">"Error loading case: "{e.to_string()}</div>
+                        }.into_view(),
+                    })}
+                </Suspense>
+            </div>
+        </DefaultLayout>
+    }
+}
+
+/// Shown when someone else saved the case while it was being edited
+#[component]
+fn ConflictNotice(changes: Vec<FieldChange>) -> impl IntoView {
+    view! {
+        <div class="mb-4 p-4 rounded border border-red-300 bg-red-50">
+            <h3 class="text-lg font-semibold text-red-700 mb-2">"Someone else changed this case"</h3>
+            <p class="text-lexodus-800 mb-4">
+                "Your changes were not saved. Where your values differ from the case as it is now, they are listed below. Review the form and save again to keep yours."
+            </p>
+            <table class="w-full bg-white table-auto">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Field"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Current value"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Your value"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    {changes.into_iter().map(|change| view! {
+                        <tr>
+                            <td class="py-2 px-4 border-b text-lexodus-800 font-medium">{change.field}</td>
+                            <td class="py-2 px-4 border-b text-lexodus-800">{change.before.unwrap_or_else(|| "-".to_string())}</td>
+                            <td class="py-2 px-4 border-b text-lexodus-800">{change.after.unwrap_or_else(|| "-".to_string())}</td>
+                        </tr>
+                    }).collect_view()}
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+#[component]
+fn CaseEditFields(
+    action: Action<UpdateCase, Result<CaseSaveOutcome, ServerFnError>>,
+    case_id: i64,
+    case: CaseEdit,
+    options: CaseEditOptions,
+) -> impl IntoView {
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+    let select = |options: Vec<(String, String)>, current: Option<String>| {
+        options
+            .into_iter()
+            .map(|(value, label)| {
+                let selected = current.as_deref() == Some(value.as_str());
+                view! { <option value=value selected=selected>{label}</option> }
+            })
+            .collect_view()
+    };
+    let coded = |codes: &[&str]| codes.iter().map(|c| (c.to_string(), c.replace('_', " "))).collect::<Vec<_>>();
+    let named = |pairs: Vec<(i64, String)>| pairs.into_iter().map(|(id, name)| (id.to_string(), name)).collect::<Vec<_>>();
+
+    view! {
+        <ActionForm action class="space-y-4">
+            <CsrfField/>
+            <input type="hidden" name="form[case_id]" value=case_id/>
+            <input type="hidden" name="form[version]" value=case.version/>
+            <div class="grid grid-cols-1 sm:grid-cols-2 gap-4">
+                <div class="sm:col-span-2">
+                    <label for="title" class="block text-sm font-medium text-lexodus-700">"Title"</label>
+                    <input type="text" id="title" name="form[title]" value=case.title required class=input_class/>
+                </div>
+                <div>
+                    <label for="nature_of_suit" class="block text-sm font-medium text-lexodus-700">"Nature of Suit"</label>
+                    <input type="text" id="nature_of_suit" name="form[nature_of_suit]" value=case.nature_of_suit.unwrap_or_default() class=input_class/>
+                </div>
+                <div>
+                    <label for="cause_of_action" class="block text-sm font-medium text-lexodus-700">"Cause of Action"</label>
+                    <input type="text" id="cause_of_action" name="form[cause_of_action]" placeholder="e.g. 28:1332" value=case.cause_of_action.unwrap_or_default() class=input_class/>
+                </div>
+                <div>
+                    <label for="demand_amount" class="block text-sm font-medium text-lexodus-700">"Demand Amount"</label>
+                    <input type="number" id="demand_amount" name="form[demand_amount]" step="0.01" min="0" value=case.demand_amount.unwrap_or_default() class=input_class/>
+                </div>
+                <div>
+                    <label for="jury_demand" class="block text-sm font-medium text-lexodus-700">"Jury Demand"</label>
+                    <select id="jury_demand" name="form[jury_demand]" class=input_class>
+                        <option value="">"None"</option>
+                        {select(coded(&JURY_DEMANDS), case.jury_demand)}
+                    </select>
+                </div>
+                <div>
+                    <label for="jurisdictional_basis" class="block text-sm font-medium text-lexodus-700">"Jurisdictional Basis"</label>
+                    <select id="jurisdictional_basis" name="form[jurisdictional_basis]" class=input_class>
+                        <option value="">"Not stated"</option>
+                        {select(coded(&JURISDICTIONAL_BASES), case.jurisdictional_basis)}
+                    </select>
+                </div>
+                <div>
+                    <label for="division_id" class="block text-sm font-medium text-lexodus-700">"Division"</label>
+                    <select id="division_id" name="form[division_id]" class=input_class>
+                        <option value="">"None"</option>
+                        {select(named(options.divisions), case.division_id.map(|id| id.to_string()))}
+                    </select>
+                </div>
+                <div>
+                    <label for="magistrate_judge_id" class="block text-sm font-medium text-lexodus-700">"Magistrate Judge"</label>
+                    <select id="magistrate_judge_id" name="form[magistrate_judge_id]" class=input_class>
+                        <option value="">"None"</option>
+                        {select(named(options.magistrates), case.magistrate_judge_id.map(|id| id.to_string()))}
+                    </select>
+                </div>
+            </div>
+            <button type="submit" class="px-4 py-2 bg-lexodus-600 text-white rounded font-semibold hover:bg-lexodus-700">"Save Changes"</button>
+        </ActionForm>
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_input_is_no_value() {
+        assert_eq!(optional(None), None);
+        assert_eq!(optional(Some("   ".to_string())), None);
+        assert_eq!(optional(Some(" 190 ".to_string())), Some("190".to_string()));
+    }
+
+    #[test]
+    fn choice_must_be_an_offered_option() {
+        let options = vec![(1, "Northern".to_string()), (2, "Southern".to_string())];
+        assert_eq!(choice(Some("2".to_string()), &options, "division").unwrap(), Some(2));
+        assert_eq!(choice(Some(String::new()), &options, "division").unwrap(), None);
+        assert_eq!(choice(None, &options, "division").unwrap(), None);
+        assert!(choice(Some("3".to_string()), &options, "division").is_err());
+        assert!(choice(Some("north".to_string()), &options, "division").is_err());
+    }
 }
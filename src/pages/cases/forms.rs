@@ -0,0 +1,9 @@
+//! Reading the values the case forms submit
+
+use crate::errors::LexodusAppError;
+
+/// A `YYYY-MM-DD` form field, refused with a message naming the field
+pub fn parse_date(value: &str, field: &str) -> Result<chrono::NaiveDate, LexodusAppError> {
+    chrono::NaiveDate::parse_from_str(value.trim(), "%Y-%m-%d")
+        .map_err(|_| LexodusAppError::BadRequest(format!("Enter the {field} as YYYY-MM-DD.")))
+}
@@ -1,6 +1,608 @@
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::create::{get_courts, get_judges};
+use cfg_if::cfg_if;
 use leptos::*;
+use leptos_router::{use_query_map, Form, ParamsMap, A};
+use serde::{Deserialize, Serialize};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::case_visible_to;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::pages::cases::forms::parse_date;
+        use spin_sdk::pg::{DbValue, ParameterValue};
+
+        /// Bind a parameter and return its `$n` placeholder
+        fn bind(params: &mut Vec<ParameterValue>, value: ParameterValue) -> String {
+            params.push(value);
+            format!("${}", params.len())
+        }
+
+        fn optional(value: &Option<String>) -> Option<String> {
+            value.as_ref().map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        }
+
+        fn id(value: &Option<String>, field: &str) -> Result<Option<i64>, LexodusAppError> {
+            optional(value)
+                .map(|v| v.parse::<i64>().map_err(|_| LexodusAppError::BadRequest(format!("Invalid {field}"))))
+                .transpose()
+        }
+
+        /// The `(prev, next)` cursors around a page running from `first` to
+        /// `last`. `more` says whether a row lies beyond the page in the
+        /// direction read; a page reached through a cursor always has one behind it.
+        fn page_links(paged: bool, backwards: bool, more: bool, first: Option<String>, last: Option<String>) -> (Option<String>, Option<String>) {
+            match (paged, backwards) {
+                (false, _) => (None, last.filter(|_| more)),
+                (true, false) => (first, last.filter(|_| more)),
+                (true, true) => (first.filter(|_| more), last),
+            }
+        }
+    }
+}
+
+/// Rows per page when the URL does not say
+pub const DEFAULT_PAGE_SIZE: i64 = 25;
+/// The most rows a single page may hold, whatever the URL asks for
+pub const MAX_PAGE_SIZE: i64 = 100;
+pub const PAGE_SIZES: [i64; 3] = [25, 50, 100];
+pub const CASE_STATUSES: [&str; 5] = ["OPEN", "PENDING", "STAYED", "CLOSED", "REOPENED"];
+pub const CASE_TYPES: [&str; 4] = ["CIVIL", "CRIMINAL", "BANKRUPTCY", "ADMINISTRATIVE"];
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Case {
+    pub id: i64,
+    pub case_number: String,
+    pub title: String,
+    pub status: String,
+    pub filed_date: String,
+    pub court_id: i64,
+    pub court_name: String,
+    pub current_court_id: i64,
+    pub current_court_name: String,
+    pub judge_id: Option<i64>,
+    pub judge_name: Option<String>,
+    pub user_id: String,
+}
+
+/// Columns the list can be ordered by. Every ordering ends in
+/// `(filed_date, id)` so pages can be walked by key rather than offset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CaseSort {
+    #[default]
+    FiledDate,
+    CaseNumber,
+    Title,
+    Status,
+}
+
+impl CaseSort {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "filed_date" => Some(Self::FiledDate),
+            "case_number" => Some(Self::CaseNumber),
+            "title" => Some(Self::Title),
+            "status" => Some(Self::Status),
+            _ => None,
+        }
+    }
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Self::FiledDate => "filed_date",
+            Self::CaseNumber => "case_number",
+            Self::Title => "title",
+            Self::Status => "status",
+        }
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        impl CaseSort {
+            /// The sorted column ahead of `(filed_date, id)`, if it is not filed_date itself
+            fn column(self) -> Option<&'static str> {
+                match self {
+                    Self::FiledDate => None,
+                    Self::CaseNumber => Some("c.case_number"),
+                    Self::Title => Some("c.title"),
+                    Self::Status => Some("c.status"),
+                }
+            }
+
+            /// The cursor for a row: `filed_date~id`, then the sorted value if any
+            fn cursor(self, case: &Case) -> String {
+                let value = match self {
+                    Self::FiledDate => return format!("{}~{}", case.filed_date, case.id),
+                    Self::CaseNumber => &case.case_number,
+                    Self::Title => &case.title,
+                    Self::Status => &case.status,
+                };
+                format!("{}~{}~{value}", case.filed_date, case.id)
+            }
+        }
+    }
+}
+
+/// The case list's filters, ordering and position, exactly as in the URL query
+/// string so every view can be bookmarked. Values are checked on the server.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaseListQuery {
+    pub status: Option<String>,
+    pub court_id: Option<String>,
+    pub judge_id: Option<String>,
+    pub case_type: Option<String>,
+    pub filed_from: Option<String>,
+    pub filed_to: Option<String>,
+    pub sort: Option<String>,
+    pub dir: Option<String>,
+    pub per_page: Option<String>,
+    /// Show the page after this cursor
+    pub after: Option<String>,
+    /// Show the page before this cursor
+    pub before: Option<String>,
+}
+
+impl CaseListQuery {
+    pub fn from_query(query: &ParamsMap) -> Self {
+        let get = |key: &str| query.get(key).cloned();
+        CaseListQuery {
+            status: get("status"),
+            court_id: get("court_id"),
+            judge_id: get("judge_id"),
+            case_type: get("case_type"),
+            filed_from: get("filed_from"),
+            filed_to: get("filed_to"),
+            sort: get("sort"),
+            dir: get("dir"),
+            per_page: get("per_page"),
+            after: get("after"),
+            before: get("before"),
+        }
+    }
+
+    pub fn sort(&self) -> CaseSort {
+        self.sort.as_deref().and_then(CaseSort::parse).unwrap_or_default()
+    }
+
+    /// Newest first unless the URL asks for `dir=asc`
+    pub fn descending(&self) -> bool {
+        self.dir.as_deref() != Some("asc")
+    }
+
+    pub fn page_size(&self) -> i64 {
+        self.per_page
+            .as_deref()
+            .and_then(|n| n.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE)
+    }
+}
+
+/// One page of the case list
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CasePage {
+    pub cases: Vec<Case>,
+    /// Cases matching the filters across all pages
+    pub total: i64,
+    pub page_size: i64,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+#[server(GetCases, "/api")]
+pub async fn get_cases(query: CaseListQuery) -> Result<CasePage, ServerFnError> {
+    let user = authorize::<GetCases>().await?;
+    let con = con()?;
+    let sort = query.sort();
+    let descending = query.descending();
+    let page_size = query.page_size();
+
+    let mut params = vec![ParameterValue::Int64(user.id)];
+    let mut filters = vec![case_visible_to(1)];
+    if let Some(status) = optional(&query.status) {
+        filters.push(format!("c.status = {}", bind(&mut params, ParameterValue::Str(status))));
+    }
+    if let Some(court_id) = id(&query.court_id, "court")? {
+        filters.push(format!("c.court_id = {}", bind(&mut params, ParameterValue::Int64(court_id))));
+    }
+    if let Some(judge_id) = id(&query.judge_id, "judge")? {
+        filters.push(format!("c.assigned_judge_id = {}", bind(&mut params, ParameterValue::Int64(judge_id))));
+    }
+    if let Some(case_type) = optional(&query.case_type) {
+        filters.push(format!("c.case_type = {}", bind(&mut params, ParameterValue::Str(case_type))));
+    }
+    if let Some(from) = optional(&query.filed_from).map(|d| parse_date(&d, "from date")).transpose()? {
+        filters.push(format!("c.filed_date >= {}::date", bind(&mut params, ParameterValue::Str(from.to_string()))));
+    }
+    if let Some(to) = optional(&query.filed_to).map(|d| parse_date(&d, "to date")).transpose()? {
+        filters.push(format!("c.filed_date <= {}::date", bind(&mut params, ParameterValue::Str(to.to_string()))));
+    }
+
+    let count = con.query(&format!("SELECT COUNT(*) FROM cases c WHERE {}", filters.join(" AND ")), &params)?;
+    let total = match count.rows.first().map(|row| &row[0]) {
+        Some(DbValue::Int64(n)) => *n,
+        _ => 0,
+    };
+
+    // Walking backwards reads the preceding rows in reverse, then flips them
+    let (cursor, backwards) = match (optional(&query.after), optional(&query.before)) {
+        (Some(after), _) => (Some(after), false),
+        (None, Some(before)) => (Some(before), true),
+        (None, None) => (None, false),
+    };
+    let paged = cursor.is_some();
+    let keys = match sort.column() {
+        Some(column) => format!("{column}, c.filed_date, c.id"),
+        None => "c.filed_date, c.id".to_string(),
+    };
+    if let Some(cursor) = cursor {
+        let mut parts = cursor.splitn(3, '~');
+        let bad_cursor = || LexodusAppError::BadRequest("Invalid page cursor".to_string());
+        let filed_date = parse_date(parts.next().ok_or_else(bad_cursor)?, "page cursor")?;
+        let case_id = parts.next().and_then(|n| n.parse::<i64>().ok()).ok_or_else(bad_cursor)?;
+        let filed_date = bind(&mut params, ParameterValue::Str(filed_date.to_string()));
+        let case_id = bind(&mut params, ParameterValue::Int64(case_id));
+        let values = match sort.column() {
+            Some(_) => {
+                let value = parts.next().ok_or_else(bad_cursor)?.to_string();
+                format!("{}, {filed_date}::date, {case_id}", bind(&mut params, ParameterValue::Str(value)))
+            }
+            None => format!("{filed_date}::date, {case_id}"),
+        };
+        let op = if descending != backwards { "<" } else { ">" };
+        filters.push(format!("({keys}) {op} ({values})"));
+    }
+    let order = if descending != backwards { "DESC" } else { "ASC" };
+    let order_by = keys.split(", ").map(|key| format!("{key} {order}")).collect::<Vec<_>>().join(", ");
+    let limit = bind(&mut params, ParameterValue::Int64(page_size + 1));
+
+    // A transferred case was filed in the court its first move left
+    let sql = format!(
+        "SELECT c.id, c.case_number, c.title, c.status, c.filed_date::text,
+                origin.court_id, oc.name as court_name,
+                c.court_id as current_court_id, co.name as current_court_name,
+                c.assigned_judge_id, u.full_name as judge_name,
+                COALESCE(c.created_by::text, '-1') as user_id
+         FROM cases c
+         CROSS JOIN LATERAL (
+             SELECT COALESCE(
+                 (SELECT t.from_court_id FROM case_transfers t
+                  WHERE t.case_id = c.id AND t.case_filed_date = c.filed_date
+                  ORDER BY t.transferred_at, t.id
+                  LIMIT 1),
+                 c.court_id
+             ) AS court_id
+         ) origin
+         LEFT JOIN courts oc ON origin.court_id = oc.id
+         LEFT JOIN courts co ON c.court_id = co.id
+         LEFT JOIN judicial_officers j ON c.assigned_judge_id = j.id
+         LEFT JOIN users u ON j.user_id = u.id
+         WHERE {}
+         ORDER BY {order_by}
+         LIMIT {limit}",
+        filters.join(" AND ")
+    );
+    let rowset = con.query(&sql, &params).map_err(|e| LexodusAppError::DBError(e.to_string()))?;
+    let text = |v: &DbValue| match v {
+        DbValue::Str(s) => s.clone(),
+        _ => String::new(),
+    };
+    let mut cases: Vec<Case> = rowset
+        .rows
+        .iter()
+        .map(|row| Case {
+            id: match &row[0] {
+                DbValue::Int64(id) => *id,
+                _ => 0,
+            },
+            case_number: text(&row[1]),
+            title: text(&row[2]),
+            status: text(&row[3]),
+            filed_date: text(&row[4]),
+            court_id: match &row[5] {
+                DbValue::Int64(court_id) => *court_id,
+                _ => 0,
+            },
+            court_name: text(&row[6]),
+            current_court_id: match &row[7] {
+                DbValue::Int64(current_court_id) => *current_court_id,
+                _ => 0,
+            },
+            current_court_name: text(&row[8]),
+            judge_id: match &row[9] {
+                DbValue::Int64(judge_id) => Some(*judge_id),
+                _ => None,
+            },
+            judge_name: match &row[10] {
+                DbValue::Str(judge_name) => Some(judge_name.clone()),
+                _ => None,
+            },
+            user_id: match &row[11] {
+                DbValue::Str(user_id) => user_id.clone(),
+                _ => "-1".to_string(),
+            },
+        })
+        .collect();
+
+    let more = cases.len() as i64 > page_size;
+    cases.truncate(page_size as usize);
+    if backwards {
+        cases.reverse();
+    }
+    let first = cases.first().map(|c| sort.cursor(c));
+    let last = cases.last().map(|c| sort.cursor(c));
+    let (prev, next) = page_links(paged, backwards, more, first, last);
+
+    Ok(CasePage {
+        cases,
+        total,
+        page_size,
+        next,
+        prev,
+    })
+}
+
+impl RequiresPermission for GetCases {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// The current URL query with some keys replaced and the others kept. Keys set
+/// to None are removed; the page position is always reset unless given.
+fn with_query(query: &ParamsMap, changes: &[(&str, Option<String>)]) -> String {
+    let mut query = query.clone();
+    query.remove("after");
+    query.remove("before");
+    for (key, value) in changes {
+        match value {
+            Some(value) => query.insert(key.to_string(), value.clone()),
+            None => query.remove(key),
+        };
+    }
+    format!("/cases{}", query.to_query_string())
+}
+
+#[component]
+fn SortHeader(label: &'static str, sort: CaseSort, #[prop(optional)] class: &'static str) -> impl IntoView {
+    let query = use_query_map();
+    let href = move || {
+        query.with(|q| {
+            let current = CaseListQuery::from_query(q);
+            // Clicking the sorted column flips it; a new column starts in its natural order
+            let dir = match (current.sort() == sort, current.descending()) {
+                (true, true) => "asc",
+                (true, false) => "desc",
+                (false, _) if sort == CaseSort::FiledDate => "desc",
+                (false, _) => "asc",
+            };
+            with_query(q, &[("sort", Some(sort.key().to_string())), ("dir", Some(dir.to_string()))])
+        })
+    };
+    let arrow = move || {
+        query.with(|q| {
+            let current = CaseListQuery::from_query(q);
+            match (current.sort() == sort, current.descending()) {
+                (true, true) => " ▼",
+                (true, false) => " ▲",
+                (false, _) => "",
+            }
+        })
+    };
+    let class = if class.is_empty() { "py-2 px-2 sm:px-4 border-b text-left text-lexodus-700 font-medium" } else { class };
+    view! {
+        <th class=class>
+            <A href=href class="hover:underline">{label}{arrow}</A>
+        </th>
+    }
+}
 
 #[component]
-pub fn ListCases() -> impl IntoView {
-    view! { <h2>"List Cases"</h2> }
+fn CaseFilters() -> impl IntoView {
+    let query = use_query_map();
+    let current = move || query.with(CaseListQuery::from_query);
+    let courts = create_resource(|| (), |_| get_courts());
+    let judges = create_resource(|| (), |_| get_judges());
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+    let selected = move |key: &'static str, value: String| {
+        move || query.with(|q| q.get(key).map(String::as_str) == Some(value.as_str()))
+    };
+
+    view! {
+        <Form method="GET" action="/cases" class="grid grid-cols-2 sm:grid-cols-4 lg:grid-cols-8 gap-4 items-end px-4 sm:px-0 mb-4">
+            <input type="hidden" name="sort" value=move || current().sort().key()/>
+            <input type="hidden" name="dir" value=move || if current().descending() { "desc" } else { "asc" }/>
+            <div>
+                <label for="filter_status" class="block text-sm font-medium text-lexodus-700">"Status"</label>
+                <select id="filter_status" name="status" class=input_class>
+                    <option value="">"Any"</option>
+                    {CASE_STATUSES.into_iter().map(|s| view! { <option value=s selected=selected("status", s.to_string())>{s}</option> }).collect_view()}
+                </select>
+            </div>
+            <div>
+                <label for="filter_case_type" class="block text-sm font-medium text-lexodus-700">"Case Type"</label>
+                <select id="filter_case_type" name="case_type" class=input_class>
+                    <option value="">"Any"</option>
+                    {CASE_TYPES.into_iter().map(|t| view! { <option value=t selected=selected("case_type", t.to_string())>{t}</option> }).collect_view()}
+                </select>
+            </div>
+            <div>
+                <label for="filter_court" class="block text-sm font-medium text-lexodus-700">"Court"</label>
+                <select id="filter_court" name="court_id" class=input_class>
+                    <option value="">"Any"</option>
+                    <Suspense fallback=|| ()>
+                        {move || courts.get().and_then(Result::ok).map(|courts| courts.into_iter().map(|court| {
+                            let id = court.id.to_string();
+                            view! { <option value=id.clone() selected=selected("court_id", id)>{court.name}</option> }
+                        }).collect_view())}
+                    </Suspense>
+                </select>
+            </div>
+            <div>
+                <label for="filter_judge" class="block text-sm font-medium text-lexodus-700">"Judge"</label>
+                <select id="filter_judge" name="judge_id" class=input_class>
+                    <option value="">"Any"</option>
+                    <Suspense fallback=|| ()>
+                        {move || judges.get().and_then(Result::ok).map(|judges| judges.into_iter().map(|judge| {
+                            let id = judge.id.to_string();
+                            view! { <option value=id.clone() selected=selected("judge_id", id)>{judge.name}</option> }
+                        }).collect_view())}
+                    </Suspense>
+                </select>
+            </div>
+            <div>
+                <label for="filter_from" class="block text-sm font-medium text-lexodus-700">"Filed From"</label>
+                <input type="date" id="filter_from" name="filed_from" value=move || current().filed_from.unwrap_or_default() class=input_class/>
+            </div>
+            <div>
+                <label for="filter_to" class="block text-sm font-medium text-lexodus-700">"Filed To"</label>
+                <input type="date" id="filter_to" name="filed_to" value=move || current().filed_to.unwrap_or_default() class=input_class/>
+            </div>
+            <div>
+                <label for="filter_per_page" class="block text-sm font-medium text-lexodus-700">"Per Page"</label>
+                <select id="filter_per_page" name="per_page" class=input_class>
+                    {PAGE_SIZES.into_iter().map(|n| view! {
+                        <option value=n selected=move || current().page_size() == n>{n}</option>
+                    }).collect_view()}
+                </select>
+            </div>
+            <div class="flex gap-2">
+                <button type="submit" class="bg-lexodus-600 text-white px-3 py-2 rounded hover:bg-lexodus-700">"Filter"</button>
+                <A href="/cases" class="px-3 py-2 text-lexodus-500 underline">"Reset"</A>
+            </div>
+        </Form>
+    }
+}
+
+#[component]
+pub fn CaseList() -> impl IntoView {
+    let query = use_query_map();
+    let cases = create_resource(move || query.with(CaseListQuery::from_query), get_cases);
+    let page_link = move |key: &'static str, cursor: String| query.with(|q| with_query(q, &[(key, Some(cursor))]));
+
+    view! {
+        <div class="mt-8 -mx-4 sm:mx-0">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-4 px-4 sm:px-0">"Existing Cases"</h3>
+            <CaseFilters/>
+            <div class="bg-white shadow-lg border border-lexodus-200 overflow-hidden sm:rounded-lg">
+                <div class="overflow-x-auto">
+                    <table class="w-full bg-white table-auto">
+                        <thead>
+                            <tr>
+                                <SortHeader label="Case Number" sort=CaseSort::CaseNumber class="py-2 pl-4 pr-2 sm:px-4 border-b text-left text-lexodus-700 font-medium"/>
+                                <SortHeader label="Title" sort=CaseSort::Title/>
+                                <SortHeader label="Status" sort=CaseSort::Status/>
+                                <SortHeader label="Filed Date" sort=CaseSort::FiledDate class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium"/>
+                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Court"</th>
+                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Current Court"</th>
+                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Judge"</th>
+                                <th class="hidden sm:table-cell py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Actions"</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            <Suspense fallback=move || view! { <tr><td colspan="8" class="text-center py-4">"Loading..."</td></tr> }>
+                                {move || cases.get().map(|result| match result {
+                                    Ok(page) if page.cases.is_empty() => view! {
+                                        <tr>
+                                            <td colspan="8" class="text-center text-lexodus-700 border-b py-4">"No cases match these filters."</td>
+                                        </tr>
+                                    }.into_view(),
+                                    Ok(page) => page.cases.into_iter().map(|case| {
+                                        view! {
+                                            <>
+                                                <tr class="hover:bg-lexodus-50">
+                                                    <td class="py-2 pl-4 pr-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.case_number}</td>
+                                                    <td class="py-2 px-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.title}</td>
+                                                    <td class="py-2 px-2 sm:px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.status}</td>
+                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.filed_date}</td>
+                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.court_name}</td>
+                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">{case.current_court_name}</td>
+                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-left text-xs font-medium text-lexodus-700 uppercase tracking-wider">
+                                                        {case.judge_name.unwrap_or_else(|| "Not assigned".to_string())}
+                                                    </td>
+                                                    <td class="hidden sm:table-cell py-2 px-4 border-b text-sm text-lexodus-800">
+                                                        <a
+                                                            href=format!("/cases/{}", case.id)
+                                                            class="bg-lexodus-500 text-white px-3 py-1 rounded hover:bg-lexodus-600"
+                                                        >
+                                                            "View"
+                                                        </a>
+                                                    </td>
+                                                </tr>
+                                                <tr class="sm:hidden hover:bg-lexodus-50">
+                                                    <td colspan="3" class="py-2 px-4 border-b text-right">
+                                                        <a
+                                                            href=format!("/cases/{}", case.id)
+                                                            class="block w-full bg-lexodus-500 text-white px-4 py-2 rounded hover:bg-lexodus-600 text-sm font-medium text-center"
+                                                        >
+                                                            "View Case Details"
+                                                        </a>
+                                                    </td>
+                                                </tr>
+                                            </>
+                                        }
+                                    }).collect_view(),
+                                    Err(e) => view! {
+                                        <tr>
+                                            <td colspan="8" class="text-center text-red-500 border-b py-4">"No existing cases found."</td>
+                                        </tr>
+                                        <tr>
+                                            <td colspan="8" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                                        </tr>
+                                    }.into_view(),
+                                })}
+                            </Suspense>
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+            <Suspense fallback=|| ()>
+                {move || cases.get().and_then(Result::ok).map(|page| view! {
+                    <div class="flex justify-between items-center px-4 sm:px-0 mt-4 text-lexodus-700">
+                        <span>{format!("{} case(s), {} per page", page.total, page.page_size)}</span>
+                        <div class="flex gap-4">
+                            {page.prev.map(|cursor| view! { <A href=page_link("before", cursor) class="text-lexodus-500 underline">"Previous"</A> })}
+                            {page.next.map(|cursor| view! { <A href=page_link("after", cursor) class="text-lexodus-500 underline">"Next"</A> })}
+                        </div>
+                    </div>
+                })}
+            </Suspense>
+        </div>
+    }
+}
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+
+    fn cursor(value: &str) -> Option<String> {
+        Some(value.to_string())
+    }
+
+    #[test]
+    fn blank_query_values_are_absent() {
+        assert_eq!(optional(&None), None);
+        assert_eq!(optional(&Some(String::new())), None);
+        assert_eq!(optional(&Some("  ".to_string())), None);
+        assert_eq!(optional(&Some(" OPEN ".to_string())), Some("OPEN".to_string()));
+    }
+
+    #[test]
+    fn first_page_links_forward_only_when_there_is_more() {
+        assert_eq!(page_links(false, false, true, cursor("a"), cursor("z")), (None, cursor("z")));
+        assert_eq!(page_links(false, false, false, cursor("a"), cursor("z")), (None, None));
+    }
+
+    #[test]
+    fn page_after_a_cursor_links_back() {
+        assert_eq!(page_links(true, false, true, cursor("a"), cursor("z")), (cursor("a"), cursor("z")));
+        assert_eq!(page_links(true, false, false, cursor("a"), cursor("z")), (cursor("a"), None));
+    }
+
+    #[test]
+    fn page_before_a_cursor_links_forward() {
+        assert_eq!(page_links(true, true, true, cursor("a"), cursor("z")), (cursor("a"), cursor("z")));
+        assert_eq!(page_links(true, true, false, cursor("a"), cursor("z")), (None, cursor("z")));
+    }
 }
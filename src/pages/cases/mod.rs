@@ -8,3 +8,11 @@ pub mod search_case;
 pub use search_case::*;
 pub mod edit_case;
 pub use edit_case::*;
+#[cfg(feature = "ssr")]
+pub mod forms;
+pub mod case_status;
+pub mod assign_judge;
+pub mod conflicts;
+pub mod case_links;
+pub mod sealing;
+pub mod transfer;
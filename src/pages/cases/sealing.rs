@@ -0,0 +1,206 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::case_seal::{security_label, SealingOrder, SECURITY_LEVELS};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::view_case::SectionCard;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::CaseHeader;
+        use crate::domain::models::case_seal::{CaseSecurity, SealingChange};
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::pages::cases::forms::parse_date;
+        use crate::pages::cases::view_case::visible_case;
+    }
+}
+
+#[server(GetSealingOrders, "/api")]
+pub async fn get_sealing_orders(case: CaseRef) -> Result<Vec<SealingOrder>, ServerFnError> {
+    let user = authorize::<GetSealingOrders>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(SealingOrder::list(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetSealingOrders {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// Fields of the sealing form. `current_sealed` and `current_level` are the
+/// seal as the form was loaded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaseSealingForm {
+    pub case_id: i64,
+    pub current_sealed: String,
+    pub current_level: String,
+    pub sealed: String,
+    pub security_level: String,
+    pub order_reference: String,
+    pub order_date: String,
+    pub reason: String,
+}
+
+/// Seal, unseal or reclassify a case as a judicial order directs. Refused if
+/// someone else changed the case's seal after the form was loaded.
+#[server(UpdateCaseSealing, "/api")]
+pub async fn update_case_sealing(csrf_token: String, form: CaseSealingForm) -> Result<(), ServerFnError> {
+    let CaseSealingForm {
+        case_id,
+        current_sealed,
+        current_level,
+        sealed,
+        security_level,
+        order_reference,
+        order_date,
+        reason,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<UpdateCaseSealing>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let case = CaseHeader::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+
+    let from = CaseSecurity {
+        sealed: case.sealed,
+        security_level: case.security_level.clone(),
+    };
+    if from.sealed != (current_sealed == "true") || from.security_level != current_level {
+        return Err(LexodusAppError::BadRequest(format!(
+            "The case became {} while you were working; review it and try again.",
+            security_label(case.sealed, &case.security_level).to_lowercase()
+        ))
+        .into());
+    }
+    let change = SealingChange::new(
+        from,
+        CaseSecurity {
+            sealed: sealed == "true",
+            security_level,
+        },
+        &order_reference,
+        parse_date(&order_date, "order date")?,
+        &reason,
+        parse_date(&case.filed_date, "filed date")?,
+    )?;
+
+    if !change.apply(case_id, &filed_date, user.id, &con).await? {
+        return Err(LexodusAppError::BadRequest("The case's seal changed while you were working; try again.".to_string()).into());
+    }
+    tracing::info!(
+        "User {} changed case {case_id} from {} to {} under order {}",
+        user.id,
+        security_label(change.from.sealed, &change.from.security_level),
+        security_label(change.to.sealed, &change.to.security_level),
+        change.order_reference
+    );
+    Ok(())
+}
+
+impl RequiresPermission for UpdateCaseSealing {
+    const PERMISSION: Permission = Permission::CASE_SEAL;
+}
+
+/// The orders that sealed or unsealed the case, with a form to enter another
+#[component]
+pub fn SealingSection(
+    case_id: i64,
+    sealed: bool,
+    security_level: String,
+    orders: Resource<(Option<CaseRef>, usize), Result<Vec<SealingOrder>, ServerFnError>>,
+    action: Action<UpdateCaseSealing, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+    let current = security_label(sealed, &security_level);
+
+    view! {
+        <SectionCard title="Sealing">
+            <p class="mb-4 text-lexodus-700">"This case is "<span class="font-medium">{current.to_lowercase()}</span>"."</p>
+            <ActionForm action class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end mb-6">
+                <CsrfField/>
+                <input type="hidden" name="form[case_id]" value=case_id/>
+                <input type="hidden" name="form[current_sealed]" value=sealed.to_string()/>
+                <input type="hidden" name="form[current_level]" value=security_level.clone()/>
+                <div>
+                    <label for="sealed" class="block text-sm font-medium text-lexodus-700">"Seal"</label>
+                    <select id="sealed" name="form[sealed]" class=input_class>
+                        <option value="true" selected=sealed>"Sealed"</option>
+                        <option value="false" selected=!sealed>"Unsealed"</option>
+                    </select>
+                </div>
+                <div>
+                    <label for="seal_security_level" class="block text-sm font-medium text-lexodus-700">"Security Level"</label>
+                    <select id="seal_security_level" name="form[security_level]" class=input_class>
+                        {SECURITY_LEVELS.iter().map(|(value, label)| view! {
+                            <option value=*value selected=*value == security_level>{*label}</option>
+                        }).collect_view()}
+                    </select>
+                </div>
+                <div>
+                    <label for="order_reference" class="block text-sm font-medium text-lexodus-700">"Order"</label>
+                    <input type="text" id="order_reference" name="form[order_reference]" required placeholder="e.g. ECF No. 42" class=input_class/>
+                </div>
+                <div>
+                    <label for="order_date" class="block text-sm font-medium text-lexodus-700">"Order Date"</label>
+                    <input type="date" id="order_date" name="form[order_date]" required class=input_class/>
+                </div>
+                <div class="sm:col-span-3">
+                    <label for="seal_reason" class="block text-sm font-medium text-lexodus-700">"Reason"</label>
+                    <input type="text" id="seal_reason" name="form[reason]" required class=input_class/>
+                </div>
+                <div>
+                    <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Enter Order"</button>
+                </div>
+            </ActionForm>
+            {move || action.value().get().and_then(Result::err).map(|e| view! {
+                <p class="mb-4 text-red-500">{e.to_string()}</p>
+            })}
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || orders.get().map(|result| match result {
+                    Ok(orders) if orders.is_empty() => view! { <p class="text-lexodus-700">"No sealing orders have been entered."</p> }.into_view(),
+                    Ok(orders) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Order"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Change"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Reason"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Entered"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {orders.into_iter().map(|order| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div class="font-medium">{order.order_reference}</div>
+                                            <div class="text-sm text-gray-600">{order.order_date}</div>
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            {format!(
+                                                "{} → {}",
+                                                security_label(order.old_sealed, &order.old_security_level),
+                                                security_label(order.sealed, &order.security_level)
+                                            )}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{order.reason}</td>
+                                        <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                            {format!("{} by {}", order.entered_at_pretty, order.entered_by.unwrap_or_else(|| "unknown".to_string()))}
+                                        </td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
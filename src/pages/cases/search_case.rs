@@ -1,23 +1,52 @@
+use crate::domain::models::case_search::CaseSearchHit;
+use crate::domain::models::permission::{Permission, RequiresPermission};
 use crate::layouts::default::DefaultLayout;
+use cfg_if::cfg_if;
 use leptos::*;
+use leptos_meta::{Meta, Title};
 use leptos_router::ActionForm;
 use serde::{Deserialize, Serialize};
 
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case_number::CaseNumber;
+        use crate::domain::models::case_search::{CaseSearch, MatchMode, NameScope};
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::pages::cases::forms::parse_date;
+
+        /// Trimmed text, with blank input meaning no value
+        fn optional(value: Option<String>) -> Option<String> {
+            value.map(|v| v.trim().to_string()).filter(|v| !v.is_empty())
+        }
+    }
+}
+
 #[component]
 pub fn SearchCases() -> impl IntoView {
     let search_cases = create_server_action::<SearchCasesAction>();
     let value = search_cases.value();
+    let input_class = "w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500";
 
     view! {
+        <Meta property="og:title" content="Search Cases | Lexodus"/>
+        <Title text="Search Cases | Lexodus"/>
+        <Meta name="description" content="Search cases by number, title and party names in Lexodus"/>
         <DefaultLayout>
             <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
                 <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Search Cases"</h3>
 
                 <ActionForm action=search_cases>
-                    // Case Number Input
-                    <div class="mb-4">
-                        <label for="case_number" class="block text-lexodus-700 mb-1">"Case Number:"</label>
-                        <input type="text" id="case_number" name="case_number" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                    <div class="grid grid-cols-1 gap-6 md:grid-cols-2 mb-4">
+                        <div>
+                            <label for="case_number" class="block text-lexodus-700 mb-1">"Case Number:"</label>
+                            <input type="text" id="case_number" name="form[case_number]" class=input_class/>
+                        </div>
+                        <div>
+                            <label for="keywords" class="block text-lexodus-700 mb-1">"Title or Keywords:"</label>
+                            <input type="text" id="keywords" name="form[keywords]" class=input_class/>
+                        </div>
                     </div>
 
                     // Case Status Radio Buttons
@@ -25,15 +54,15 @@ pub fn SearchCases() -> impl IntoView {
                         <label class="block text-lexodus-700 mb-1">"Case Status:"</label>
                         <div class="flex space-x-4">
                             <label class="inline-flex items-center">
-                                <input type="radio" name="case_status" value="open" class="form-radio text-lexodus-600"/>
+                                <input type="radio" name="form[case_status]" value="open" class="form-radio text-lexodus-600"/>
                                 <span class="ml-2">"Open"</span>
                             </label>
                             <label class="inline-flex items-center">
-                                <input type="radio" name="case_status" value="closed" class="form-radio text-lexodus-600"/>
+                                <input type="radio" name="form[case_status]" value="closed" class="form-radio text-lexodus-600"/>
                                 <span class="ml-2">"Closed"</span>
                             </label>
                             <label class="inline-flex items-center">
-                                <input type="radio" name="case_status" value="all" class="form-radio text-lexodus-600"/>
+                                <input type="radio" name="form[case_status]" value="all" checked class="form-radio text-lexodus-600"/>
                                 <span class="ml-2">"All"</span>
                             </label>
                         </div>
@@ -43,68 +72,67 @@ pub fn SearchCases() -> impl IntoView {
                     <div class="grid grid-cols-1 gap-6 md:grid-cols-2 mb-4">
                         <div>
                             <label for="filed_date_from" class="block text-lexodus-700 mb-1">"Filed Date (from):"</label>
-                            <input type="date" id="filed_date_from" name="filed_date_from" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                            <input type="date" id="filed_date_from" name="form[filed_date_from]" class=input_class/>
                         </div>
                         <div>
                             <label for="filed_date_to" class="block text-lexodus-700 mb-1">"Filed Date (to):"</label>
-                            <input type="date" id="filed_date_to" name="filed_date_to" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                            <input type="date" id="filed_date_to" name="form[filed_date_to]" class=input_class/>
                         </div>
                     </div>
 
-                    // Cause of Action Select
-                    <div class="mb-4">
-                        <label for="cause_of_action" class="block text-lexodus-700 mb-1">"Cause of Action:"</label>
-                        <select id="cause_of_action" name="cause_of_action" multiple class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500">
-                            <option value="0">"0 (No cause code entered)"</option>
-                            <option value="02:0431">"02:0431 (Federal Election Commission: Failure Enforce Compliance)"</option>
-                            <option value="05:0552">"05:0552 (Freedom of Information Act)"</option>
-                            // Add more options as needed
-                        </select>
-                    </div>
-
-                    // Nature of Suit Select
-                    <div class="mb-4">
-                        <label for="nature_suit" class="block text-lexodus-700 mb-1">"Nature of Suit:"</label>
-                        <select id="nature_suit" name="nature_suit" multiple class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500">
-                            <option value="0">"0 (zero)"</option>
-                            <option value="110">"110 (Insurance)"</option>
-                            // Add more options as needed
-                        </select>
-                    </div>
-
-                    // Last/Business Name Input
-                    <div class="mb-4">
-                        <label for="last_business_name" class="block text-lexodus-700 mb-1">"Last/Business Name:"</label>
-                        <input type="text" id="last_business_name" name="last_business_name" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
-                    </div>
-
-                    // Exact Matches Checkbox
-                    <div class="mb-4">
-                        <label class="inline-flex items-center">
-                            <input type="checkbox" id="exact_matches_only" name="exact_matches_only" class="form-checkbox text-lexodus-600"/>
-                            <span class="ml-2">"Exact matches only"</span>
-                        </label>
+                    <div class="grid grid-cols-1 gap-6 md:grid-cols-2 mb-4">
+                        <div>
+                            <label for="cause_of_action" class="block text-lexodus-700 mb-1">"Cause of Action:"</label>
+                            <input type="text" id="cause_of_action" name="form[cause_of_action]" placeholder="e.g. 28:1332" class=input_class/>
+                        </div>
+                        <div>
+                            <label for="nature_suit" class="block text-lexodus-700 mb-1">"Nature of Suit:"</label>
+                            <input type="text" id="nature_suit" name="form[nature_suit]" placeholder="e.g. 110" class=input_class/>
+                        </div>
                     </div>
 
-                    // First Name, Middle Name, and Type Inputs
-                    <div class="grid grid-cols-1 gap-6 md:grid-cols-3 mb-4">
+                    // Name Inputs
+                    <div class="grid grid-cols-1 gap-6 md:grid-cols-4 mb-4">
+                        <div>
+                            <label for="last_business_name" class="block text-lexodus-700 mb-1">"Last/Business Name:"</label>
+                            <input type="text" id="last_business_name" name="form[last_business_name]" class=input_class/>
+                        </div>
                         <div>
                             <label for="first_name" class="block text-lexodus-700 mb-1">"First Name:"</label>
-                            <input type="text" id="first_name" name="first_name" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                            <input type="text" id="first_name" name="form[first_name]" class=input_class/>
                         </div>
                         <div>
                             <label for="middle_name" class="block text-lexodus-700 mb-1">"Middle Name:"</label>
-                            <input type="text" id="middle_name" name="middle_name" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                            <input type="text" id="middle_name" name="form[middle_name]" class=input_class/>
                         </div>
                         <div>
-                            <label for="type_field" class="block text-lexodus-700 mb-1">"Select Type:"</label>
-                            <select id="type_field" name="type_field" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500">
-                                <option value="attorney">"Attorney"</option>
+                            <label for="type_field" class="block text-lexodus-700 mb-1">"Name Of:"</label>
+                            <select id="type_field" name="form[type_field]" class=input_class>
                                 <option value="party">"Party"</option>
+                                <option value="attorney">"Attorney"</option>
                             </select>
                         </div>
                     </div>
 
+                    // Match Mode Radio Buttons
+                    <div class="mb-4">
+                        <label class="block text-lexodus-700 mb-1">"Match:"</label>
+                        <div class="flex space-x-4">
+                            <label class="inline-flex items-center">
+                                <input type="radio" name="form[match_mode]" value="prefix" checked class="form-radio text-lexodus-600"/>
+                                <span class="ml-2">"Words starting with"</span>
+                            </label>
+                            <label class="inline-flex items-center">
+                                <input type="radio" name="form[match_mode]" value="fuzzy" class="form-radio text-lexodus-600"/>
+                                <span class="ml-2">"Similar spelling"</span>
+                            </label>
+                            <label class="inline-flex items-center">
+                                <input type="radio" name="form[match_mode]" value="exact" class="form-radio text-lexodus-600"/>
+                                <span class="ml-2">"Exact matches only"</span>
+                            </label>
+                        </div>
+                    </div>
+
                     // Submit and Clear Buttons
                     <div class="flex justify-end space-x-4">
                         <button type="submit" class="px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600 focus:outline-none focus:ring-2 focus:ring-lexodus-500">"Run Query"</button>
@@ -125,7 +153,6 @@ pub fn SearchCases() -> impl IntoView {
                     Ok(cases) => view! {
                         <div class="mt-4">
                             <h4 class="text-lg font-semibold text-lexodus-800 mb-2">"Search Results"</h4>
-                            // Implement a component to display the search results
                             <SearchResultsTable cases=cases />
                         </div>
                     },
@@ -138,50 +165,125 @@ pub fn SearchCases() -> impl IntoView {
     }
 }
 
-#[server(SearchCasesAction, "/api/search_cases")]
-pub async fn search_cases(
-    case_number: Option<String>,
-    case_status: Option<String>,
-    filed_date_from: Option<String>,
-    filed_date_to: Option<String>,
-    cause_of_action: Option<Vec<String>>,
-    nature_suit: Option<Vec<String>>,
-    last_business_name: Option<String>,
-    first_name: Option<String>,
-    middle_name: Option<String>,
-    type_field: Option<String>,
-    exact_matches_only: Option<bool>,
-) -> Result<Vec<Case>, ServerFnError> {
-    // Implement your server-side search logic here
-    // This is a placeholder that returns an empty vector
-    Ok(vec![])
+/// Fields of the search form
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaseSearchForm {
+    pub case_number: Option<String>,
+    pub keywords: Option<String>,
+    pub case_status: Option<String>,
+    pub filed_date_from: Option<String>,
+    pub filed_date_to: Option<String>,
+    pub cause_of_action: Option<String>,
+    pub nature_suit: Option<String>,
+    pub last_business_name: Option<String>,
+    pub first_name: Option<String>,
+    pub middle_name: Option<String>,
+    pub type_field: Option<String>,
+    pub match_mode: Option<String>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct Case {
-    // Define your Case struct fields here
-    case_number: String,
-    // Add other fields as needed
+/// Search cases the caller may see, best match first. Name parts are combined
+/// into one name and matched against parties or their attorneys.
+#[server(SearchCasesAction, "/api")]
+pub async fn search_cases(form: CaseSearchForm) -> Result<Vec<CaseSearchHit>, ServerFnError> {
+    let CaseSearchForm {
+        case_number,
+        keywords,
+        case_status,
+        filed_date_from,
+        filed_date_to,
+        cause_of_action,
+        nature_suit,
+        last_business_name,
+        first_name,
+        middle_name,
+        type_field,
+        match_mode,
+    } = form;
+    let user = authorize::<SearchCasesAction>().await?;
+    let open = match case_status.as_deref() {
+        Some("open") => Some(true),
+        Some("closed") => Some(false),
+        None | Some("all") | Some("") => None,
+        Some(other) => return Err(LexodusAppError::BadRequest(format!("Unknown case status {other}")).into()),
+    };
+    let name_scope = match type_field.as_deref() {
+        Some("attorney") => NameScope::Attorney,
+        None | Some("party") | Some("") => NameScope::Party,
+        Some(other) => return Err(LexodusAppError::BadRequest(format!("Unknown name type {other}")).into()),
+    };
+    let mode = match optional(match_mode) {
+        Some(mode) => MatchMode::parse(&mode).ok_or_else(|| LexodusAppError::BadRequest(format!("Unknown match mode {mode}")))?,
+        None => MatchMode::default(),
+    };
+    // A complete number must be well formed to search on exactly; partial
+    // numbers are still allowed for prefix and fuzzy searches
+    let case_number = optional(case_number);
+    let parsed_number = match case_number.as_deref().map(str::parse::<CaseNumber>) {
+        Some(Ok(number)) => Some(number),
+        Some(Err(e)) if mode == MatchMode::Exact => return Err(LexodusAppError::from(e).into()),
+        _ => None,
+    };
+    let name: Vec<String> = [first_name, middle_name, last_business_name].into_iter().filter_map(optional).collect();
+
+    let search = CaseSearch {
+        case_number,
+        parsed_number,
+        keywords: optional(keywords),
+        open,
+        filed_from: optional(filed_date_from).map(|d| parse_date(&d, "from date")).transpose()?.map(|d| d.to_string()),
+        filed_to: optional(filed_date_to).map(|d| parse_date(&d, "to date")).transpose()?.map(|d| d.to_string()),
+        nature_of_suit: optional(nature_suit),
+        cause_of_action: optional(cause_of_action),
+        name: (!name.is_empty()).then(|| name.join(" ")),
+        name_scope,
+        mode,
+    };
+    if search.case_number.is_none() && search.keywords.is_none() && search.name.is_none() && search.nature_of_suit.is_none()
+        && search.cause_of_action.is_none() && search.filed_from.is_none() && search.filed_to.is_none()
+    {
+        return Err(LexodusAppError::BadRequest("Enter something to search for.".to_string()).into());
+    }
+    let con = con()?;
+    Ok(search.run(user.id, &con).await?)
+}
+
+impl RequiresPermission for SearchCasesAction {
+    const PERMISSION: Permission = Permission::CASE_READ;
 }
 
 #[component]
-fn SearchResultsTable(cases: Vec<Case>) -> impl IntoView {
+fn SearchResultsTable(cases: Vec<CaseSearchHit>) -> impl IntoView {
+    if cases.is_empty() {
+        return view! { <p class="text-lexodus-700">"No cases matched."</p> }.into_view();
+    }
     view! {
         <table class="w-full mt-4 border-collapse border border-lexodus-200">
             <thead>
                 <tr class="bg-lexodus-100">
                     <th class="p-2 text-left">"Case Number"</th>
-                    // Add more table headers as needed
+                    <th class="p-2 text-left">"Title"</th>
+                    <th class="p-2 text-left">"Status"</th>
+                    <th class="p-2 text-left">"Filed"</th>
+                    <th class="p-2 text-left">"Court"</th>
+                    <th class="p-2 text-left">"Matched Name"</th>
                 </tr>
             </thead>
             <tbody>
                 {cases.into_iter().map(|case| view! {
                     <tr class="border-t border-lexodus-200">
-                        <td class="p-2">{case.case_number}</td>
-                        // Add more table cells as needed
+                        <td class="p-2">
+                            <a href=format!("/cases/{}", case.id) class="text-lexodus-500 underline">{case.case_number}</a>
+                        </td>
+                        <td class="p-2">{case.title}</td>
+                        <td class="p-2">{case.status}</td>
+                        <td class="p-2">{case.filed_date}</td>
+                        <td class="p-2">{case.court_name}</td>
+                        <td class="p-2">{case.matched_name.unwrap_or_default()}</td>
                     </tr>
-                }).collect::<Vec<_>>()}
+                }).collect_view()}
             </tbody>
         </table>
     }
+    .into_view()
 }
@@ -0,0 +1,276 @@
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::case_transfer::{CaseTransfer, TransferDestination};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::pages::cases::view_case::SectionCard;
+use crate::providers::csrf::CsrfField;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::CaseHeader;
+        use crate::domain::models::case_status::CaseStatus;
+        use crate::domain::models::case_transfer::{NewTransfer, TransferKind};
+        use crate::domain::models::judge_assignment::{Draw, Wheel};
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use crate::functions::csrf::verify_csrf;
+        use crate::mail::{Mailer, OutgoingMail};
+        use crate::pages::cases::forms::parse_date;
+        use crate::pages::cases::view_case::visible_case;
+        use spin_sdk::pg::Connection;
+        use std::sync::Arc;
+
+        /// Notify both courts' clerks' offices. The move has already been
+        /// made, so a failed notice is logged rather than returned.
+        async fn notify_clerks(transfer: &NewTransfer, new_case_number: &str, con: &Arc<Connection>) {
+            let notices = match transfer.notify_clerks(new_case_number, con).await {
+                Ok(notices) => notices,
+                Err(e) => {
+                    tracing::error!("Failed to notify clerks of the move of case {}: {e}", transfer.case_id);
+                    return;
+                }
+            };
+            for (to, subject, body) in notices {
+                let mail = OutgoingMail { to, subject, body };
+                if let Err(e) = expect_context::<Mailer>().send(mail, con).await {
+                    tracing::error!("Failed to send transfer notice: {e}");
+                }
+            }
+        }
+    }
+}
+
+#[server(GetCaseTransfers, "/api")]
+pub async fn get_case_transfers(case: CaseRef) -> Result<Vec<CaseTransfer>, ServerFnError> {
+    let user = authorize::<GetCaseTransfers>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseTransfer::list(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetCaseTransfers {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+#[server(GetTransferDestinations, "/api")]
+pub async fn get_transfer_destinations(case_id: i64) -> Result<Vec<TransferDestination>, ServerFnError> {
+    let user = authorize::<GetTransferDestinations>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let (wheel, _) = Wheel::for_case(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+    Ok(TransferDestination::for_case(case_id, &filed_date, wheel.court_id, &con).await?)
+}
+
+impl RequiresPermission for GetTransferDestinations {
+    const PERMISSION: Permission = Permission::CASE_TRANSFER;
+}
+
+/// Fields of the transfer form. `current_case_number` is the number as the form
+/// was loaded.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CaseTransferForm {
+    pub case_id: i64,
+    pub current_case_number: String,
+    pub destination: String,
+    pub order_reference: String,
+    pub order_date: String,
+    pub effective_date: String,
+    pub reason: Option<String>,
+}
+
+/// Transfer or remand a case to another court under a court order. The case
+/// takes a new number and judge there and keeps answering to its old number.
+/// `destination` is `KIND:court_id`, as offered by `get_transfer_destinations`.
+#[server(TransferCase, "/api")]
+pub async fn transfer_case(csrf_token: String, form: CaseTransferForm) -> Result<(), ServerFnError> {
+    let CaseTransferForm {
+        case_id,
+        current_case_number,
+        destination,
+        order_reference,
+        order_date,
+        effective_date,
+        reason,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<TransferCase>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let case = CaseHeader::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+    if case.case_number != current_case_number {
+        return Err(LexodusAppError::BadRequest(format!(
+            "The case became {} while you were working; review it and try again.",
+            case.case_number
+        ))
+        .into());
+    }
+    let (wheel, _) = Wheel::for_case(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?;
+
+    let chosen = destination
+        .split_once(':')
+        .and_then(|(kind, court_id)| Some((TransferKind::parse(kind)?, court_id.parse::<i64>().ok()?)));
+    let destination = match chosen {
+        Some((kind, court_id)) => TransferDestination::for_case(case_id, &filed_date, wheel.court_id, &con)
+            .await?
+            .into_iter()
+            .find(|d| d.kind == kind && d.court_id == court_id),
+        None => None,
+    }
+    .ok_or_else(|| LexodusAppError::BadRequest("The case cannot be sent to that court.".to_string()))?;
+
+    let status = CaseStatus::parse(&case.status)
+        .ok_or_else(|| LexodusAppError::BadRequest(format!("The case's status {} is not part of the lifecycle.", case.status)))?;
+    let transfer = NewTransfer {
+        case_id,
+        filed_date: filed_date.clone(),
+        from_court_id: wheel.court_id,
+        case_number: case.case_number,
+        status,
+        destination,
+        order_reference,
+        order_date: parse_date(&order_date, "order date")?,
+        effective_date: parse_date(&effective_date, "effective date")?,
+        reason: reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty()),
+    };
+    transfer.validate(parse_date(&case.filed_date, "filed date")?)?;
+
+    // The receiving court's wheel, leaving out judges the case's parties block
+    let receiving = Wheel {
+        court_id: transfer.destination.court_id,
+        division_id: None,
+        ..wheel
+    };
+    let draw = Draw::random(receiving.pool(None, &con).await?).ok_or_else(|| {
+        LexodusAppError::BadRequest(format!("No judge in {} is eligible to take the case.", transfer.destination.court_name))
+    })?;
+
+    let Some(new_case_number) = transfer.apply(&draw, user.id, &con).await? else {
+        return Err(LexodusAppError::BadRequest("The case moved while you were working; try again.".to_string()).into());
+    };
+    tracing::info!(
+        "User {} {} case {case_id} from court {} to court {} as {new_case_number}",
+        user.id,
+        transfer.destination.kind.past(),
+        transfer.from_court_id,
+        transfer.destination.court_id
+    );
+    notify_clerks(&transfer, &new_case_number, &con).await;
+    Ok(())
+}
+
+impl RequiresPermission for TransferCase {
+    const PERMISSION: Permission = Permission::CASE_TRANSFER;
+}
+
+/// The courts the case has moved between and, for users allowed to move it,
+/// a form to transfer or remand it
+#[component]
+pub fn TransferSection(
+    case_id: i64,
+    case_number: String,
+    court: String,
+    history: Resource<(Option<CaseRef>, usize), Result<Vec<CaseTransfer>, ServerFnError>>,
+    action: Action<TransferCase, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let destinations = create_resource(move || action.version().get(), move |_| get_transfer_destinations(case_id));
+    let input_class = "mt-1 block w-full rounded-md border border-lexodus-300 shadow-sm focus:border-lexodus-500 focus:ring-lexodus-500";
+    let pending = format!("Pending in {court} as {case_number}");
+
+    view! {
+        <SectionCard title="Court">
+            <p class="mb-4 text-lexodus-700">{pending}</p>
+            // Only users who may move the case can load where it may go
+            <Suspense fallback=|| ()>
+                {
+                    let case_number = case_number.clone();
+                    move || destinations.get().and_then(Result::ok).filter(|d| !d.is_empty()).map(|destinations| {
+                        let case_number = case_number.clone();
+                        view! {
+                            <ActionForm action class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end mb-6">
+                                <CsrfField/>
+                                <input type="hidden" name="form[case_id]" value=case_id/>
+                                <input type="hidden" name="form[current_case_number]" value=case_number/>
+                                <div>
+                                    <label for="destination" class="block text-sm font-medium text-lexodus-700">"Send To"</label>
+                                    <select id="destination" name="form[destination]" required class=input_class>
+                                        <option value="">"Choose a court"</option>
+                                        {destinations.into_iter().map(|d| view! {
+                                            <option value=format!("{}:{}", d.kind.as_str(), d.court_id)>
+                                                {format!("{} to {}", d.kind.label(), d.court_name)}
+                                            </option>
+                                        }).collect_view()}
+                                    </select>
+                                </div>
+                                <div>
+                                    <label for="transfer_order_reference" class="block text-sm font-medium text-lexodus-700">"Order"</label>
+                                    <input type="text" id="transfer_order_reference" name="form[order_reference]" required placeholder="e.g. ECF No. 57" class=input_class/>
+                                </div>
+                                <div>
+                                    <label for="transfer_order_date" class="block text-sm font-medium text-lexodus-700">"Order Date"</label>
+                                    <input type="date" id="transfer_order_date" name="form[order_date]" required class=input_class/>
+                                </div>
+                                <div>
+                                    <label for="transfer_effective_date" class="block text-sm font-medium text-lexodus-700">"Effective"</label>
+                                    <input type="date" id="transfer_effective_date" name="form[effective_date]" required class=input_class/>
+                                </div>
+                                <div class="sm:col-span-3">
+                                    <label for="transfer_reason" class="block text-sm font-medium text-lexodus-700">"Reason"</label>
+                                    <input type="text" id="transfer_reason" name="form[reason]" placeholder="e.g. 28 U.S.C. § 1404(a)" class=input_class/>
+                                </div>
+                                <div>
+                                    <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Move Case"</button>
+                                </div>
+                            </ActionForm>
+                        }
+                    })
+                }
+            </Suspense>
+            {move || action.value().get().and_then(Result::err).map(|e| view! {
+                <p class="mb-4 text-red-500">{e.to_string()}</p>
+            })}
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || history.get().map(|result| match result {
+                    Ok(history) if history.is_empty() => view! { <p class="text-lexodus-700">"The case has not moved between courts."</p> }.into_view(),
+                    Ok(history) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Effective"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Move"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Number"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Order"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Recorded"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {history.into_iter().map(|t| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{t.effective_date}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div>{format!("{} → {}", t.from_court, t.to_court)}</div>
+                                            <div class="text-sm text-gray-600">{t.kind.to_lowercase()}</div>
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{format!("{} → {}", t.old_case_number, t.new_case_number)}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div>{format!("{}, {}", t.order_reference, t.order_date)}</div>
+                                            {t.reason.map(|reason| view! { <div class="text-sm text-gray-600">{reason}</div> })}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-sm text-gray-600">
+                                            {format!("{} by {}", t.transferred_at_pretty, t.transferred_by.unwrap_or_else(|| "unknown".to_string()))}
+                                        </td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
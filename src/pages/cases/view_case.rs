@@ -1,84 +1,530 @@
+use cfg_if::cfg_if;
 use leptos::*;
+use leptos_meta::{Meta, Title};
 use leptos_router::*;
-use serde::{Deserialize, Serialize};
 
+use crate::domain::models::case_number::CaseRef;
+use crate::domain::models::case::{CaseDocument, CaseHeader, CaseMotion, CaseParty, DocketEntry, OpenDeadline};
+use crate::domain::models::case_seal::security_label;
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::errors::LexodusAppError;
 use crate::layouts::default::DefaultLayout;
+use crate::pages::cases::assign_judge::{get_judge_assignments, AssignmentSection, ReassignJudge};
+use crate::pages::cases::case_links::{get_case_links, CaseLinksSection, LinkCase, UnlinkCase};
+use crate::pages::cases::case_status::{get_case_status_history, StatusSection, UpdateCaseStatus};
+use crate::pages::cases::sealing::{get_sealing_orders, SealingSection, UpdateCaseSealing};
+use crate::pages::cases::transfer::{get_case_transfers, TransferCase, TransferSection};
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::case::CaseAccess;
+        use crate::domain::models::user::User;
+        use crate::functions::authz::authorize;
+        use crate::functions::con;
+        use leptos_spin::ResponseOptions;
+        use spin_sdk::pg::Connection;
+        use std::sync::Arc;
+
+        /// The id and partition key of a case the viewer may see. Missing and
+        /// hidden cases both answer 404 so a sealed case's existence is not revealed.
+        pub async fn visible_case(case: &CaseRef, viewer: &User, con: &Arc<Connection>) -> Result<(i64, String), LexodusAppError> {
+            let case_id = match case {
+                CaseRef::Id(id) => Some(*id),
+                CaseRef::Number(number) => number.find(viewer.id, con).await?,
+            };
+            if let Some(case_id) = case_id {
+                if let Some(filed_date) = CaseAccess::visible_filed_date(case_id, viewer.id, con).await? {
+                    return Ok((case_id, filed_date));
+                }
+            }
+            tracing::info!("Case {case} is missing or hidden from user {}", viewer.id);
+            let e = LexodusAppError::NotFound;
+            if let Some(res) = use_context::<ResponseOptions>() {
+                res.set_status(e.status_code().as_u16());
+            }
+            Err(e)
+        }
+    }
+}
+
 #[derive(Params, PartialEq, Clone)]
 struct CaseParams {
     id: Option<String>,
 }
 
+#[server(GetCaseDetails, "/api")]
+pub async fn get_case_details(case: CaseRef) -> Result<CaseHeader, ServerFnError> {
+    let user = authorize::<GetCaseDetails>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseHeader::get(case_id, &filed_date, &con).await?.ok_or(LexodusAppError::NotFound)?)
+}
+
+impl RequiresPermission for GetCaseDetails {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+#[server(GetCaseParties, "/api")]
+pub async fn get_case_parties(case: CaseRef) -> Result<Vec<CaseParty>, ServerFnError> {
+    let user = authorize::<GetCaseParties>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseParty::list(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetCaseParties {
+    const PERMISSION: Permission = Permission::PARTY_READ;
+}
+
+#[server(GetCaseDocket, "/api")]
+pub async fn get_case_docket(case: CaseRef) -> Result<Vec<DocketEntry>, ServerFnError> {
+    let user = authorize::<GetCaseDocket>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(DocketEntry::list(case_id, &filed_date, user.id, &con).await?)
+}
+
+impl RequiresPermission for GetCaseDocket {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+#[server(GetCaseDeadlines, "/api")]
+pub async fn get_case_deadlines(case: CaseRef) -> Result<Vec<OpenDeadline>, ServerFnError> {
+    let user = authorize::<GetCaseDeadlines>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(OpenDeadline::list(case_id, &filed_date, &con).await?)
+}
+
+impl RequiresPermission for GetCaseDeadlines {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+#[server(GetCaseMotions, "/api")]
+pub async fn get_case_motions(case: CaseRef) -> Result<Vec<CaseMotion>, ServerFnError> {
+    let user = authorize::<GetCaseMotions>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseMotion::list(case_id, &filed_date, user.id, &con).await?)
+}
+
+impl RequiresPermission for GetCaseMotions {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+#[server(GetCaseDocuments, "/api")]
+pub async fn get_case_documents(case: CaseRef) -> Result<Vec<CaseDocument>, ServerFnError> {
+    let user = authorize::<GetCaseDocuments>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&case, &user, &con).await?;
+    Ok(CaseDocument::list(case_id, &filed_date, user.id, &con).await?)
+}
+
+impl RequiresPermission for GetCaseDocuments {
+    const PERMISSION: Permission = Permission::CASE_READ;
+}
+
+/// Server errors arrive as their display text, so compare against `NotFound`'s
+pub fn is_not_found(e: &ServerFnError) -> bool {
+    matches!(e, ServerFnError::ServerError(message) if *message == LexodusAppError::NotFound.to_string())
+}
+
+/// Load a section only when the route holds a case id or a well formed case number
+pub async fn for_case<T, F>(case: Option<CaseRef>, load: impl FnOnce(CaseRef) -> F) -> Result<T, ServerFnError>
+where
+    F: std::future::Future<Output = Result<T, ServerFnError>>,
+{
+    match case {
+        Some(case) => load(case).await,
+        None => Err(LexodusAppError::NotFound.into()),
+    }
+}
+
 #[component]
 pub fn ViewCase() -> impl IntoView {
     let params = use_params::<CaseParams>();
-    let case_id = move || {
+    let case = move || {
         params.with(|p| {
             p.as_ref()
                 .ok()
-                .and_then(|p| p.id.clone())
-                .unwrap_or_default()
+                .and_then(|p| p.id.as_deref())
+                .and_then(|id| id.parse::<CaseRef>().ok())
         })
     };
 
-    let case_details = create_resource(case_id, move |id| async move {
-        match get_case_details(id).await {
-            Ok(details) => Ok(details),
-            Err(err) => {
-                log::error!("Error fetching case details: {:?}", err);
-                Err(err)
-            }
-        }
-    });
+    // A status change, reassignment, sealing order or transfer alters the
+    // header and its history, so both reload after one
+    let update_status = create_server_action::<UpdateCaseStatus>();
+    let reassign = create_server_action::<ReassignJudge>();
+    let seal = create_server_action::<UpdateCaseSealing>();
+    let transfer = create_server_action::<TransferCase>();
+    let changed = move || {
+        (
+            case(),
+            update_status.version().get() + reassign.version().get() + seal.version().get() + transfer.version().get(),
+        )
+    };
+    let header = create_resource(changed, |(case, _)| for_case(case, get_case_details));
+    let history = create_resource(changed, |(case, _)| for_case(case, get_case_status_history));
+    let assignments = create_resource(changed, |(case, _)| for_case(case, get_judge_assignments));
+    let sealing_orders = create_resource(changed, |(case, _)| for_case(case, get_sealing_orders));
+    let transfers = create_resource(changed, |(case, _)| for_case(case, get_case_transfers));
+    let link = create_server_action::<LinkCase>();
+    let unlink = create_server_action::<UnlinkCase>();
+    let links = create_resource(
+        move || (case(), link.version().get() + unlink.version().get()),
+        |(case, _)| for_case(case, get_case_links),
+    );
+    let parties = create_resource(case, |id| for_case(id, get_case_parties));
+    let docket = create_resource(case, |id| for_case(id, get_case_docket));
+    let deadlines = create_resource(case, |id| for_case(id, get_case_deadlines));
+    let motions = create_resource(case, |id| for_case(id, get_case_motions));
+    let documents = create_resource(case, |id| for_case(id, get_case_documents));
 
     view! {
+        <Meta property="og:title" content="Case | Lexodus"/>
+        <Title text="Case | Lexodus"/>
+        <Meta name="description" content="Case details, parties and docket in Lexodus"/>
         <DefaultLayout>
-            <h2>"View Case"</h2>
+            <div class="w-full px-0 sm:p-8 bg-lexodus-50">
+                <div class="mb-4 px-4 sm:px-0">
+                    <a href="/cases" class="text-lexodus-500 underline">"All cases"</a>
+                </div>
+                <Suspense fallback=move || view! { <p class="px-4 sm:px-0">"Loading..."</p> }>
+                    {move || header.get().map(|result| match result {
+                        Ok(case) => view! {
+                            <div class="space-y-6">
+                                <CaseHeaderCard case=case.clone()/>
+                                <StatusSection case_id=case.id status=case.status.clone() history action=update_status/>
+                                <AssignmentSection
+                                    case_id=case.id
+                                    judge_id=case.assigned_judge_id
+                                    judge=case.assigned_judge.clone()
+                                    history=assignments
+                                    action=reassign
+                                />
+                                <SealingSection
+                                    case_id=case.id
+                                    sealed=case.sealed
+                                    security_level=case.security_level.clone()
+                                    orders=sealing_orders
+                                    action=seal
+                                />
+                                <TransferSection
+                                    case_id=case.id
+                                    case_number=case.case_number.clone()
+                                    court=case.court_name.clone()
+                                    history=transfers
+                                    action=transfer
+                                />
+                                <CaseLinksSection case_id=case.id graph=links link unlink/>
+                                <PartiesSection parties/>
+                                <DeadlinesSection deadlines/>
+                                <MotionsSection motions/>
+                                <DocumentsSection documents/>
+                                <DocketSection docket/>
+                            </div>
+                        }.into_view(),
+                        Err(e) if is_not_found(&e) => view! {
+                            <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+                                <h2 class="text-xl font-semibold text-lexodus-800 mb-2">"Case not found"</h2>
+                                <p class="text-lexodus-700">"There is no case at this address, or you do not have access to it."</p>
+                            </section>
+                        }.into_view(),
+                        Err(e) => view! {
+                            <div class="px-4 sm:px-0 text-red-500">"Error loading case: "{e.to_string()}</div>
+                        }.into_view(),
+                    })}
+                </Suspense>
+            </div>
+        </DefaultLayout>
+    }
+}
+
+#[component]
+fn Field(label: &'static str, value: Option<String>) -> impl IntoView {
+    view! {
+        <div>
+            <dt class="text-sm font-medium text-lexodus-600">{label}</dt>
+            <dd class="text-lexodus-800">{value.unwrap_or_else(|| "-".to_string())}</dd>
+        </div>
+    }
+}
+
+#[component]
+fn CaseHeaderCard(case: CaseHeader) -> impl IntoView {
+    let restricted = case.sealed || case.security_level != "PUBLIC";
+    let level = security_label(case.sealed, &case.security_level);
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+            <div class="flex flex-wrap justify-between items-start gap-4 mb-6">
+                <div>
+                    <p class="text-sm text-lexodus-600">{case.case_number.clone()}</p>
+                    <h2 class="text-2xl font-semibold text-lexodus-800">{case.title.clone()}</h2>
+                </div>
+                <div class="flex gap-2 items-center">
+                    <a href=format!("/cases/{}/edit", case.id) class="text-lexodus-500 underline">"Edit"</a>
+                    <span class="px-3 py-1 rounded bg-lexodus-100 text-lexodus-800 text-sm font-medium">{case.status.clone()}</span>
+                    <Show when=move || restricted fallback=|| ()>
+                        <span class="px-3 py-1 rounded bg-red-100 text-red-700 text-sm font-medium">{level.clone()}</span>
+                    </Show>
+                </div>
+            </div>
+            <dl class="grid grid-cols-1 sm:grid-cols-3 gap-4">
+                <Field label="Court" value=Some(case.court_name)/>
+                <Field label="Division" value=case.division_name/>
+                <Field label="Case Type" value=Some(case.case_type)/>
+                <Field label="Assigned Judge" value=case.assigned_judge/>
+                <Field label="Magistrate Judge" value=case.magistrate_judge/>
+                <Field label="Filing Type" value=Some(case.filing_type)/>
+                <Field label="Filed" value=Some(case.filed_date)/>
+                <Field label="Closed" value=case.closed_date/>
+                <Field label="Reopened" value=case.reopened_date/>
+                <Field label="Disposition" value=case.disposition_method.map(|m| m.replace('_', " "))/>
+                <Field label="Disposed" value=case.disposition_date/>
+                <Field label="Nature of Suit" value=case.nature_of_suit/>
+                <Field label="Jurisdictional Basis" value=case.jurisdictional_basis/>
+                <Field label="Jury Demand" value=case.jury_demand/>
+                <Field label="Demand Amount" value=case.demand_amount/>
+            </dl>
+        </section>
+    }
+}
+
+#[component]
+pub(crate) fn SectionCard(title: &'static str, children: Children) -> impl IntoView {
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200">
+            <h3 class="text-xl font-semibold text-lexodus-800 mb-4">{title}</h3>
+            <div class="overflow-x-auto">{children()}</div>
+        </section>
+    }
+}
+
+#[component]
+fn PartiesSection(parties: Resource<Option<CaseRef>, Result<Vec<CaseParty>, ServerFnError>>) -> impl IntoView {
+    view! {
+        <SectionCard title="Parties">
             <Suspense fallback=move || view! { <p>"Loading..."</p> }>
-                {move || case_details.get().map(|result| match result {
-                    Ok(case) => view! {
-                        <div>
-                            <p>"Case ID: "{&case.id}</p>
-                            <p>"Case Number: "{&case.number}</p>
-                            <p>"Title: "{&case.title}</p>
-                            <p>"Status: "{&case.status}</p>
-                        </div>
-                    },
-                    Err(e) => view! {
-                        <div>
-                            <p>"Error loading case details: "{e.to_string()}</p>
-                            <p>"Please check the server logs for more information."</p>
-                        </div>
-                    },
+                {move || parties.get().map(|result| match result {
+                    Ok(parties) if parties.is_empty() => view! { <p class="text-lexodus-700">"No parties have been added."</p> }.into_view(),
+                    Ok(parties) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Party"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Type"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Attorneys"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {parties.into_iter().map(|party| {
+                                    let pro_se = party.is_pro_se;
+                                    let no_counsel = party.attorneys.is_empty();
+                                    view! {
+                                        <tr class="hover:bg-lexodus-50 align-top">
+                                            <td class="py-2 px-4 border-b text-lexodus-800">
+                                                <span class="font-medium">{party.name}</span>
+                                                <Show when=move || party.is_lead fallback=|| ()>
+                                                    <span class="ml-2 text-xs text-lexodus-600">"(lead)"</span>
+                                                </Show>
+                                            </td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">{party.party_type}</td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">
+                                                <Show when=move || pro_se fallback=|| ()>
+                                                    <div>"Pro se"</div>
+                                                </Show>
+                                                <Show when=move || no_counsel && !pro_se fallback=|| ()>
+                                                    <div class="text-gray-600">"No attorney of record"</div>
+                                                </Show>
+                                                {party.attorneys.into_iter().map(|attorney| view! {
+                                                    <div>
+                                                        {attorney.name}
+                                                        <span class="text-sm text-gray-600">
+                                                            {format!(" {} {}", attorney.representation_type, attorney.status)}
+                                                        </span>
+                                                        <div class="text-sm text-gray-600">{attorney.email.unwrap_or_default()}</div>
+                                                    </div>
+                                                }).collect_view()}
+                                            </td>
+                                        </tr>
+                                    }
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
                 })}
             </Suspense>
-        </DefaultLayout>
+        </SectionCard>
     }
 }
 
-#[server(GetCaseDetails, "/api")]
-pub async fn get_case_details(case_id: String) -> Result<CaseDetails, ServerFnError> {
-    // For debugging purposes, let's log the incoming case_id
-    log::info!("Fetching details for case ID: {}", case_id);
-
-    // Implement the logic to fetch case details from the database
-    // For now, we'll return mock data
-    let details = CaseDetails {
-        id: case_id,
-        number: "2023-CV-001".to_string(),
-        title: "Sample Case".to_string(),
-        status: "Open".to_string(),
-    };
+#[component]
+fn DeadlinesSection(deadlines: Resource<Option<CaseRef>, Result<Vec<OpenDeadline>, ServerFnError>>) -> impl IntoView {
+    view! {
+        <SectionCard title="Open Deadlines">
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || deadlines.get().map(|result| match result {
+                    Ok(deadlines) if deadlines.is_empty() => view! { <p class="text-lexodus-700">"No open deadlines."</p> }.into_view(),
+                    Ok(deadlines) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Due"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Deadline"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Type"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Priority"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {deadlines.into_iter().map(|deadline| {
+                                    let due_class = if deadline.overdue { "py-2 px-4 border-b text-red-600 font-medium" } else { "py-2 px-4 border-b text-lexodus-800" };
+                                    view! {
+                                        <tr class="hover:bg-lexodus-50">
+                                            <td class=due_class>{deadline.due_date_pretty}</td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">{deadline.title}</td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">{deadline.deadline_type}</td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">{deadline.priority}</td>
+                                            <td class="py-2 px-4 border-b text-lexodus-800">{deadline.status}</td>
+                                        </tr>
+                                    }
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
 
-    // Log the details we're about to return
-    log::info!("Returning case details: {:?}", details);
+#[component]
+fn MotionsSection(motions: Resource<Option<CaseRef>, Result<Vec<CaseMotion>, ServerFnError>>) -> impl IntoView {
+    view! {
+        <SectionCard title="Motions">
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || motions.get().map(|result| match result {
+                    Ok(motions) if motions.is_empty() => view! { <p class="text-lexodus-700">"No motions have been filed."</p> }.into_view(),
+                    Ok(motions) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Filed"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Motion"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Filed By"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Response Due"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Hearing"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {motions.into_iter().map(|motion| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{motion.filed_date_pretty}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div class="font-medium">{motion.title}</div>
+                                            <div class="text-sm text-gray-600">{motion.motion_type}</div>
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{motion.filed_by.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{motion.status}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{motion.response_deadline.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{motion.hearing_date_pretty.unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
+}
 
-    Ok(details)
+#[component]
+fn DocumentsSection(documents: Resource<Option<CaseRef>, Result<Vec<CaseDocument>, ServerFnError>>) -> impl IntoView {
+    view! {
+        <SectionCard title="Documents">
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || documents.get().map(|result| match result {
+                    Ok(documents) if documents.is_empty() => view! { <p class="text-lexodus-700">"No documents have been filed."</p> }.into_view(),
+                    Ok(documents) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"No."</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Document"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Filed By"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Filed"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Pages"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {documents.into_iter().map(|document| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{document.document_number.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div class="font-medium">{document.title}</div>
+                                            <div class="text-sm text-gray-600">{document.document_type.unwrap_or_default()}</div>
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{document.filed_by.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{document.filed_date_pretty}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{document.page_count.map(|n| n.to_string()).unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CaseDetails {
-    id: String,
-    number: String,
-    title: String,
-    status: String,
+#[component]
+fn DocketSection(docket: Resource<Option<CaseRef>, Result<Vec<DocketEntry>, ServerFnError>>) -> impl IntoView {
+    view! {
+        <SectionCard title="Docket">
+            <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                {move || docket.get().map(|result| match result {
+                    Ok(entries) if entries.is_empty() => view! { <p class="text-lexodus-700">"The docket is empty."</p> }.into_view(),
+                    Ok(entries) => view! {
+                        <table class="w-full bg-white table-auto">
+                            <thead>
+                                <tr>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Date"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"No."</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Entry"</th>
+                                    <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Pages"</th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                {entries.into_iter().map(|entry| view! {
+                                    <tr class="hover:bg-lexodus-50 align-top">
+                                        <td class="py-2 px-4 border-b text-lexodus-800 whitespace-nowrap">{entry.event_date_pretty}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{entry.document_number.unwrap_or_default()}</td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">
+                                            <div class="font-medium">{entry.title}</div>
+                                            <div class="text-sm text-gray-600">{entry.event_type}</div>
+                                            <div>{entry.entry.unwrap_or_default()}</div>
+                                            {entry.mirrored_from.map(|lead| view! {
+                                                <div class="text-sm text-gray-600">"Filed in lead case "{lead}</div>
+                                            })}
+                                        </td>
+                                        <td class="py-2 px-4 border-b text-lexodus-800">{entry.page_count.map(|n| n.to_string()).unwrap_or_default()}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+            </Suspense>
+        </SectionCard>
+    }
 }
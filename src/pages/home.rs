@@ -17,7 +17,7 @@ pub fn Home() -> impl IntoView {
                             <p class="mb-8">"Lexodus is an advanced case management and electronic court filing system designed to streamline federal court processes, enhance accessibility, and improve judicial efficiency."</p>
                             <div class="space-x-4">
                             <a href="/login" class="bg-slate-600 hover:bg-slate-700 text-white font-bold py-3 px-6 rounded-lg transition duration-300">"Login"</a>
-                                <a href="/signup" class="bg-transparent hover:bg-white hover:text-slate-900 text-white font-bold py-3 px-6 rounded-lg border border-white transition duration-300">"Register"</a>
+                                <a href="/register/attorney" class="bg-transparent hover:bg-white hover:text-slate-900 text-white font-bold py-3 px-6 rounded-lg border border-white transition duration-300">"Register"</a>
                             </div>
                         </div>
                         <div class="hidden md:block">
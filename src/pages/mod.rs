@@ -1,3 +1,5 @@
+pub mod account;
+
 pub mod cases;
 
 pub mod user_management;
@@ -5,3 +7,5 @@ pub mod user_management;
 pub mod home;
 
 pub mod parties;
+
+pub mod recusals;
@@ -2,13 +2,19 @@ use leptos::*;
 use leptos_router::*;
 use serde::{Deserialize, Serialize};
 
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::providers::csrf::CsrfField;
 use cfg_if::cfg_if;
 
 cfg_if! {
     if #[cfg(feature = "ssr")] {
-        use spin_sdk::pg::{Connection, ParameterValue};
-        use spin_sdk::{variables};
-        use spin_sdk::pg::*;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::functions::con;
+        use crate::domain::models::case::CaseParty;
+        use crate::domain::models::case_number::CaseRef;
+        use crate::pages::cases::view_case::visible_case;
     }
 }
 
@@ -63,6 +69,7 @@ pub fn PartiesManagement(case_id: i64) -> impl IntoView {
             <div class="mt-8">
                 <h3 class="text-lg font-semibold text-lexodus-800 mb-4">"Add New Party"</h3>
                 <ActionForm action=add_party>
+                    <CsrfField/>
                     <input type="hidden" name="case_id" value=case_id.to_string()/>
                     <div class="grid grid-cols-1 md:grid-cols-2 gap-4">
                         <input
@@ -85,13 +92,6 @@ pub fn PartiesManagement(case_id: i64) -> impl IntoView {
                             placeholder="Attorney ID (optional)"
                             class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500"
                         />
-                        <input
-                            type="date"
-                            name="case_filed_date"
-                            placeholder="Case Filed Date"
-                            class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexodus-500"
-                            required
-                        />
                     </div>
                     <button
                         type="submit"
@@ -105,79 +105,55 @@ pub fn PartiesManagement(case_id: i64) -> impl IntoView {
     }
 }
 
+/// The case's parties and their active attorneys, for a case the user may see
 #[server(GetParties, "/api")]
 pub async fn get_parties(case_id: i64) -> Result<Vec<Party>, ServerFnError> {
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
-
-    let sql = "SELECT p.id, p.name, p.role, u.username AS attorney_name, p.case_filed_date
-               FROM parties p
-               LEFT JOIN users u ON p.attorney_id = u.id
-               WHERE p.case_id = $1";
+    let user = authorize::<GetParties>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    let parties = CaseParty::list(case_id, &filed_date, &con).await?;
 
-    let rowset = conn.query(sql, &[ParameterValue::Int64(case_id)])?;
-    let parties: Vec<Party> = rowset
-        .rows
-        .iter()
-        .map(|row| Party {
-            id: match &row[0] {
-                DbValue::Int64(id) => *id,
-                _ => 0,
-            },
-            name: match &row[1] {
-                DbValue::Str(name) => name.clone(),
-                _ => String::new(),
-            },
-            role: match &row[2] {
-                DbValue::Str(role) => role.clone(),
-                _ => String::new(),
-            },
-            attorney_name: match &row[3] {
-                DbValue::Str(attorney_name) => attorney_name.clone(),
-                _ => String::new(),
-            },
-            case_filed_date: match &row[4] {
-                DbValue::Str(date) => date.clone(),
-                _ => String::new(),
-            },
+    Ok(parties
+        .into_iter()
+        .map(|party| Party {
+            id: party.id,
+            name: party.name,
+            role: party.party_type,
+            attorney_name: party
+                .attorneys
+                .iter()
+                .filter(|a| a.status == "ACTIVE")
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            case_filed_date: filed_date.clone(),
         })
-        .collect();
+        .collect())
+}
 
-    Ok(parties)
+impl RequiresPermission for GetParties {
+    const PERMISSION: Permission = Permission::PARTY_READ;
 }
 
+/// Adds a party to a case the user may see, under the case's own filed date
 #[server(AddParty, "/api")]
 pub async fn add_party(
+    csrf_token: String,
     case_id: i64,
     name: String,
     role: String,
     attorney_id: Option<i64>,
-    case_filed_date: String,
 ) -> Result<String, ServerFnError> {
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
-
-    let sql = "INSERT INTO parties (case_id, name, role, attorney_id, case_filed_date) VALUES ($1, $2, $3, $4, $5)";
-
-    let execute_result = conn.execute(
-        sql,
-        &[
-            ParameterValue::Int64(case_id),
-            ParameterValue::Str(name),
-            ParameterValue::Str(role),
-            attorney_id.map_or(ParameterValue::DbNull, ParameterValue::Int64),
-            ParameterValue::Str(case_filed_date),
-        ],
-    );
-
-    match execute_result {
-        Ok(rows_affected) => {
-            println!("Rows affected: {}", rows_affected);
-            Ok(format!("Party added successfully: {}", rows_affected))
-        }
-        Err(e) => Err(ServerFnError::ServerError(format!(
-            "Failed to execute SQL: {}",
-            e
-        ))),
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<AddParty>().await?;
+    let con = con()?;
+    let (case_id, filed_date) = visible_case(&CaseRef::Id(case_id), &user, &con).await?;
+    match CaseParty::add(case_id, &filed_date, name.trim(), &role, attorney_id, user.id, &con).await? {
+        Some(_) => Ok("Party added".to_string()),
+        None => Err(LexodusAppError::BadRequest(format!("Unknown party role {}", role.trim())).into()),
     }
 }
+
+impl RequiresPermission for AddParty {
+    const PERMISSION: Permission = Permission::PARTY_CREATE;
+}
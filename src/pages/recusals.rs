@@ -0,0 +1,236 @@
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::domain::models::recusal::{ChambersJudge, RecusalEntry, RECUSAL_ENTITY_TYPES};
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::recusal::duplicate;
+        use crate::domain::models::user::User;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::functions::con;
+        use spin_sdk::pg::Connection;
+        use std::sync::Arc;
+
+        /// The judge, if the user maintains their recusal list
+        async fn chambers_judge(user: &User, judge_id: i64, con: &Arc<Connection>) -> Result<i64, LexodusAppError> {
+            ChambersJudge::for_user(user.id, con)
+                .await?
+                .into_iter()
+                .find(|judge| judge.id == judge_id)
+                .map(|judge| judge.id)
+                .ok_or(LexodusAppError::NotFound)
+        }
+    }
+}
+
+#[server(GetChambersJudges, "/api")]
+pub async fn get_chambers_judges() -> Result<Vec<ChambersJudge>, ServerFnError> {
+    let user = authorize::<GetChambersJudges>().await?;
+    let con = con()?;
+    Ok(ChambersJudge::for_user(user.id, &con).await?)
+}
+
+impl RequiresPermission for GetChambersJudges {
+    const PERMISSION: Permission = Permission::RECUSAL_MANAGE;
+}
+
+#[server(GetRecusals, "/api")]
+pub async fn get_recusals(judge_id: i64) -> Result<Vec<RecusalEntry>, ServerFnError> {
+    let user = authorize::<GetRecusals>().await?;
+    let con = con()?;
+    let judge_id = chambers_judge(&user, judge_id, &con).await?;
+    Ok(RecusalEntry::list(judge_id, &con).await?)
+}
+
+impl RequiresPermission for GetRecusals {
+    const PERMISSION: Permission = Permission::RECUSAL_MANAGE;
+}
+
+/// Add to a judge's recusal list; the judge's open cases are screened against the new entry
+#[server(AddRecusal, "/api")]
+pub async fn add_recusal(
+    csrf_token: String,
+    judge_id: i64,
+    entity_type: String,
+    name: String,
+    reason: Option<String>,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<AddRecusal>().await?;
+    let con = con()?;
+    let judge_id = chambers_judge(&user, judge_id, &con).await?;
+    if !RECUSAL_ENTITY_TYPES.iter().any(|(value, _)| *value == entity_type) {
+        return Err(LexodusAppError::BadRequest(format!("Unknown entry type {entity_type}")).into());
+    }
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(LexodusAppError::BadRequest("Enter the name to recuse from.".to_string()).into());
+    }
+    if let Some(existing) = duplicate(&RecusalEntry::list(judge_id, &con).await?, name) {
+        return Err(LexodusAppError::BadRequest(format!("{name} is already on the list as {}.", existing.name)).into());
+    }
+    let reason = reason.as_deref().map(str::trim).filter(|r| !r.is_empty());
+    let id = RecusalEntry::add(judge_id, &entity_type, name, reason, user.id, &con).await?;
+    tracing::info!("User {} added recusal entry {id} for judge {judge_id}", user.id);
+    Ok(())
+}
+
+impl RequiresPermission for AddRecusal {
+    const PERMISSION: Permission = Permission::RECUSAL_MANAGE;
+}
+
+/// Withdraw an entry from a judge's list, or restore a withdrawn one
+#[server(SetRecusalActive, "/api")]
+pub async fn set_recusal_active(csrf_token: String, id: i64, judge_id: i64, active: String) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let user = authorize::<SetRecusalActive>().await?;
+    let con = con()?;
+    let judge_id = chambers_judge(&user, judge_id, &con).await?;
+    if !RecusalEntry::set_active(id, judge_id, active == "true", &con).await? {
+        return Err(LexodusAppError::NotFound.into());
+    }
+    tracing::info!("User {} set recusal entry {id} active={active}", user.id);
+    Ok(())
+}
+
+impl RequiresPermission for SetRecusalActive {
+    const PERMISSION: Permission = Permission::RECUSAL_MANAGE;
+}
+
+#[component]
+pub fn RecusalList(judge_id: i64) -> impl IntoView {
+    let add = create_server_action::<AddRecusal>();
+    let toggle = create_server_action::<SetRecusalActive>();
+    let entries = create_resource(
+        move || (add.version().get(), toggle.version().get()),
+        move |_| get_recusals(judge_id),
+    );
+    let input_class = "w-full px-2 py-1 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200";
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 relative">
+            <ActionForm action=add class="grid grid-cols-1 sm:grid-cols-4 gap-4 items-end mb-6">
+                <CsrfField/>
+                <input type="hidden" name="judge_id" value=judge_id/>
+                <select name="entity_type" class=input_class>
+                    {RECUSAL_ENTITY_TYPES.iter().map(|(value, label)| view! { <option value=*value>{*label}</option> }).collect_view()}
+                </select>
+                <input type="text" name="name" placeholder="Name" required class=input_class/>
+                <input type="text" name="reason" placeholder="Reason (e.g. stock holding)" class=input_class/>
+                <button type="submit" class="bg-lexodus-600 text-white px-4 py-2 rounded hover:bg-lexodus-700">"Add"</button>
+            </ActionForm>
+
+            {move || add.value().get().or_else(|| toggle.value().get()).and_then(Result::err).map(|e| view! {
+                <div class="mb-4 text-red-500">{e.to_string()}</div>
+            })}
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Name"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Type"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Reason"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Added"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="5" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || entries.get().map(|result| match result {
+                        Ok(entries) if entries.is_empty() => view! {
+                            <tr><td colspan="5" class="text-center text-lexodus-700 border-b py-4">"This judge has no recusal entries."</td></tr>
+                        }.into_view(),
+                        Ok(entries) => entries.into_iter().map(|entry| {
+                            let label = RECUSAL_ENTITY_TYPES
+                                .iter()
+                                .find(|(value, _)| *value == entry.entity_type)
+                                .map_or(entry.entity_type.clone(), |(_, label)| label.to_string());
+                            view! {
+                                <tr class=if entry.active { "hover:bg-lexodus-50" } else { "hover:bg-lexodus-50 text-gray-400 line-through" }>
+                                    <td class="py-2 px-4 border-b">{entry.name}</td>
+                                    <td class="py-2 px-4 border-b">{label}</td>
+                                    <td class="py-2 px-4 border-b">{entry.reason.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-sm">
+                                        {format!("{} by {}", entry.created_at_pretty, entry.created_by.unwrap_or_else(|| "unknown".to_string()))}
+                                    </td>
+                                    <td class="py-2 px-4 border-b">
+                                        <ActionForm action=toggle>
+                                            <CsrfField/>
+                                            <input type="hidden" name="id" value=entry.id/>
+                                            <input type="hidden" name="judge_id" value=entry.judge_id/>
+                                            <input type="hidden" name="active" value=(!entry.active).to_string()/>
+                                            <button type="submit" class="text-lexodus-500 underline">
+                                                {if entry.active { "Withdraw" } else { "Restore" }}
+                                            </button>
+                                        </ActionForm>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="5" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+/// Chambers maintain a judge's recusal list here. Every case assigned to the
+/// judge is screened against it, as are parties and attorneys as they appear.
+#[component]
+pub fn Recusals() -> impl IntoView {
+    let judges = create_resource(|| (), |_| get_chambers_judges());
+    let (chosen, set_chosen) = create_signal(None::<i64>);
+    // The first judge listed until another is chosen
+    let judge_id = move || {
+        chosen.get().or_else(|| {
+            judges.with(|j| j.as_ref().and_then(|j| j.as_ref().ok()).and_then(|j| j.first()).map(|judge| judge.id))
+        })
+    };
+
+    view! {
+        <Meta property="og:title" content="Recusal Lists | Lexodus"/>
+        <Title text="Recusal Lists | Lexodus"/>
+        <Meta name="description" content="Maintain judges' recusal lists in Lexodus."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Recusal Lists"</h2>
+                    <a href="/cases/conflicts" class="text-lexodus-500 underline">"Conflicts"</a>
+                </div>
+                <Suspense fallback=move || view! { <p>"Loading..."</p> }>
+                    {move || judges.get().map(|result| match result {
+                        Ok(judges) if judges.is_empty() => view! { <p class="text-lexodus-700">"There are no judges whose list you maintain."</p> }.into_view(),
+                        Ok(judges) => view! {
+                            <select
+                                class="mb-6 px-2 py-1 bg-white text-lexodus-800 rounded border border-lexodus-200"
+                                on:change=move |ev| set_chosen.set(event_target_value(&ev).parse().ok())
+                            >
+                                {judges.into_iter().map(|judge| view! {
+                                    <option value=judge.id selected=move || judge_id() == Some(judge.id)>
+                                        {format!("{} ({})", judge.name, judge.court_name)}
+                                    </option>
+                                }).collect_view()}
+                            </select>
+                        }.into_view(),
+                        Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                    })}
+                </Suspense>
+                {move || judge_id().map(|judge_id| view! { <RecusalList judge_id/> })}
+            </div>
+        </DefaultLayout>
+    }
+}
@@ -6,52 +6,76 @@ use leptos_meta::Title;
 use leptos_router::ActionForm;
 use serde::{Deserialize, Serialize};
 use crate::providers::auth::AuthContext;
+use crate::domain::models::permission::{Permission, RequiresPermission};
 use crate::domain::models::user::SafeUser;
+use crate::functions::impersonation::StartImpersonation;
+use crate::functions::two_factor::ResetTwoFactor;
+use crate::providers::csrf::CsrfField;
+use crate::pages::user_management::InvitationPanel;
 
 
 cfg_if! {
     if #[cfg(feature = "ssr")] {
-        use spin_sdk::pg::*;
-        use spin_sdk::variables;
-
+        use crate::domain::models::user::{NewUser, User};
+        use crate::functions::auth::hash_password;
+        use crate::functions::password_policy::password_policy;
+        use crate::functions::con;
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::errors::LexodusAppError;
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
-pub struct User {
-    pub id: i64,
+/// Fields of the new user form
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewUserForm {
     pub username: String,
+    pub email: String,
+    pub full_name: String,
+    pub password: String,
     pub role_id: i64,
+    pub bar_number: Option<String>,
+    pub home_court_id: Option<String>,
 }
 
 #[server(CreateUser, "/api")]
-pub async fn create_user(
-    username: String,
-    password: String,
-    role_id: i64,
-) -> Result<String, ServerFnError> {
+pub async fn create_user(csrf_token: String, form: NewUserForm) -> Result<String, ServerFnError> {
+    let NewUserForm {
+        username,
+        email,
+        full_name,
+        password,
+        role_id,
+        bar_number,
+        home_court_id,
+    } = form;
+    verify_csrf(&csrf_token).await?;
+    authorize::<CreateUser>().await?;
     println!("--> Adding a new user: {}", username);
 
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
-
-    let sql = "INSERT INTO users (username, password_hash, role_id)
-               VALUES ($1, $2, $3)";
-
-    let execute_result = conn.execute(
-        sql,
-        &[
-            ParameterValue::Str(username),
-            ParameterValue::Str(password), // Note: In a real app, you should hash the password
-            ParameterValue::Int64(role_id),
-        ],
-    );
-
-    match execute_result {
-        Ok(rows_affected) => {
-            println!("Rows affected: {}", rows_affected);
-            Ok(format!("User added successfully: {}", rows_affected))
-        }
+    let conn = con()?;
+
+    let home_court_id = match home_court_id.filter(|id| !id.is_empty()) {
+        Some(id) => Some(id.parse::<i64>().map_err(|_| {
+            LexodusAppError::BadRequest("Invalid home court ID format".to_string())
+        })?),
+        None => None,
+    };
+
+    password_policy().validate(&password, &username, &email).await?;
+
+    let new_user = NewUser {
+        username,
+        email,
+        full_name,
+        password_hash: hash_password(password.as_bytes())?,
+        role_id,
+        bar_number: bar_number.filter(|b| !b.is_empty()),
+        home_court_id,
+    };
+
+    match User::create(new_user, &conn).await {
+        Ok(id) => Ok(format!("User added successfully: {}", id)),
         Err(e) => Err(ServerFnError::ServerError(format!(
             "Failed to execute SQL: {}",
             e
@@ -59,36 +83,20 @@ pub async fn create_user(
     }
 }
 
+impl RequiresPermission for CreateUser {
+    const PERMISSION: Permission = Permission::USER_CREATE;
+}
+
 #[server(GetUsers, "/api")]
-pub async fn get_users() -> Result<Vec<User>, ServerFnError> {
-    let db_url = variables::get("db_url").unwrap();
-    let conn = Connection::open(&db_url)?;
-
-    let sql = "SELECT id, username, role_id FROM users";
-
-    let rowset = conn.query(sql, &[])?;
-    let users: Vec<User> = rowset
-        .rows
-        .iter()
-        .map(|row| {
-            User {
-                id: match &row[0] {
-                    DbValue::Int64(id) => *id,
-                    _ => 0, // Default value if not an Int64
-                },
-                username: match &row[1] {
-                    DbValue::Str(username) => username.clone(),
-                    _ => String::new(), // Default value if not a String
-                },
-                role_id: match &row[2] {
-                    DbValue::Int64(role_id) => *role_id,
-                    _ => 0, // Default value if not an Int64
-                },
-            }
-        })
-        .collect();
-
-    Ok(users)
+pub async fn get_users() -> Result<Vec<SafeUser>, ServerFnError> {
+    authorize::<GetUsers>().await?;
+    let conn = con()?;
+    let users = User::list(&conn).await?;
+    Ok(users.into_iter().map(SafeUser::from).collect())
+}
+
+impl RequiresPermission for GetUsers {
+    const PERMISSION: Permission = Permission::USER_READ;
 }
 #[component]
 pub fn CreateUserForm(user: Option<SafeUser>) -> impl IntoView {
@@ -102,23 +110,40 @@ pub fn CreateUserForm(user: Option<SafeUser>) -> impl IntoView {
             <h3 class="text-xl font-semibold text-lexodus-800 mb-6">"Add New User"</h3>
 
             <ActionForm action=create_user>
+                <CsrfField/>
             <input type="hidden" name="user_id"
                 value=match user {
                     Some(u) => u.id.to_string(),
                     None => "-1".to_string(),
                 }
-            />q
+            />
                 <div class="mb-4">
                     <label for="username" class="block text-lexodus-700 mb-1">"Username:"</label>
-                    <input type="text" id="username" name="username" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                    <input type="text" id="username" name="form[username]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="email" class="block text-lexodus-700 mb-1">"Email:"</label>
+                    <input type="email" id="email" name="form[email]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="full_name" class="block text-lexodus-700 mb-1">"Full Name:"</label>
+                    <input type="text" id="full_name" name="form[full_name]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
                 </div>
                 <div class="mb-4">
                     <label for="password" class="block text-lexodus-700 mb-1">"Password:"</label>
-                    <input type="password" id="password" name="password" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                    <input type="password" id="password" name="form[password]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
                 </div>
                 <div class="mb-4">
                     <label for="role_id" class="block text-lexodus-700 mb-1">"Role ID:"</label>
-                    <input type="number" id="role_id" name="role_id" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                    <input type="number" id="role_id" name="form[role_id]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="bar_number" class="block text-lexodus-700 mb-1">"Bar Number (attorneys):"</label>
+                    <input type="text" id="bar_number" name="form[bar_number]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
+                </div>
+                <div class="mb-4">
+                    <label for="home_court_id" class="block text-lexodus-700 mb-1">"Home Court ID:"</label>
+                    <input type="number" id="home_court_id" name="form[home_court_id]" class="w-full px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200 focus:outline-none focus:ring-2 focus:ring-lexouds-500"/>
                 </div>
                 <button type="submit" class="w-full px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600 focus:outline-none focus:ring-2 focus:ring-lexodus-500">"Add User"</button>
             </ActionForm>
@@ -143,35 +168,63 @@ pub fn CreateUserForm(user: Option<SafeUser>) -> impl IntoView {
 }
 #[component]
 pub fn UserList() -> impl IntoView {
-    let users = create_resource(|| (), |_| get_users());
+    let reset_two_factor = create_server_action::<ResetTwoFactor>();
+    let start_impersonation = create_server_action::<StartImpersonation>();
+    let impersonation_error = start_impersonation.value();
+    let users = create_resource(move || reset_two_factor.version().get(), |_| get_users());
 
     view! {
         <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
             <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Existing Users"</h2>
+            {move || impersonation_error.get().and_then(Result::err).map(|e| view! {
+                <div class="mb-4 text-red-500">{e.to_string()}</div>
+            })}
 
             <table class="min-w-full bg-white">
                 <thead>
                     <tr>
                         <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"ID"</th>
                         <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Username"</th>
-                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Role ID"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Full Name"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Email"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Role"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"2FA"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
                     </tr>
                 </thead>
                 <tbody>
-                    <Suspense fallback=move || view! { <tr><td colspan="3" class="text-center py-4">"Loading..."</td></tr> }>
+                    <Suspense fallback=move || view! { <tr><td colspan="7" class="text-center py-4">"Loading..."</td></tr> }>
                     {move || users.get().map(|result| match result {
                         Ok(users) => users.into_iter().map(|user| {
                             view! {
                                 <tr class="hover:bg-lexodus-50">
                                     <td class="py-2 px-4 border-b text-lexodus-800">{user.id}</td>
                                     <td class="py-2 px-4 border-b text-lexodus-800">{user.username}</td>
-                                    <td class="py-2 px-4 border-b text-lexodus-800">{user.role_id}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{user.full_name}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{user.email}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{user.role}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <Show when=move || user.two_factor_enabled fallback=|| view! { "Off" }>
+                                            <ActionForm action=reset_two_factor>
+                                                <CsrfField/>
+                                                <input type="hidden" name="user_id" value=user.id/>
+                                                <button type="submit" class="text-red-600 underline">"Reset 2FA"</button>
+                                            </ActionForm>
+                                        </Show>
+                                    </td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <ActionForm action=start_impersonation>
+                                            <CsrfField/>
+                                            <input type="hidden" name="user_id" value=user.id/>
+                                            <button type="submit" class="text-lexodus-500 underline">"Act as"</button>
+                                        </ActionForm>
+                                    </td>
                                 </tr>
                             }
                         }).collect_view(),
                         Err(e) => view! {
                             <tr>
-                                <td colspan="3" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                                <td colspan="7" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
                             </tr>
                         }.into_view(),
                     })}
@@ -194,6 +247,10 @@ pub fn UserManagement() -> impl IntoView {
             <div class="w-full p-8 bg-lexodus-50">
                 <div class="flex justify-between items-center mb-8">
                     <h2 class="text-2xl font-semibold text-lexodus-800">"User Management"</h2>
+                    <div class="flex gap-6">
+                        <a href="/users/registrations" class="text-lexodus-500 underline">"Attorney Registrations"</a>
+                        <a href="/users/security" class="text-lexodus-500 underline">"Security Audit"</a>
+                    </div>
                 </div>
                 <div class="space-y-6">
                 <Transition fallback=move || ()>
@@ -209,6 +266,7 @@ pub fn UserManagement() -> impl IntoView {
                       view! {
                         <Show when=move || user().is_some() fallback=|| ().into_view()>
                           <UserList/>
+                          <InvitationPanel/>
                           <CreateUserForm user=user()/>
                         </Show>
                       }
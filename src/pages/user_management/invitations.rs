@@ -0,0 +1,189 @@
+use crate::domain::models::invitation::Invitation;
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::providers::csrf::CsrfField;
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_router::ActionForm;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::user::User;
+        use crate::errors::LexodusAppError;
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::functions::con;
+        use crate::mail::{app_base_url, Mailer, OutgoingMail};
+
+        /// How long an invitation link stays valid
+        const INVITATION_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+        /// Number of invitations shown on the user management page
+        const RECENT_INVITATION_LIMIT: i64 = 50;
+    }
+}
+
+/// Roles staff can be invited to; attorneys register through the review queue
+pub const INVITABLE_ROLES: [&str; 3] = ["judge", "clerk", "admin"];
+
+#[server(CreateInvitation, "/api")]
+pub async fn create_invitation(csrf_token: String, email: String, role: String) -> Result<String, ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let inviter = authorize::<CreateInvitation>().await?;
+    let conn = con()?;
+    let email = email.trim().to_string();
+    if !email.contains('@') {
+        return Err(LexodusAppError::BadRequest("Enter a valid email address.".to_string()).into());
+    }
+    if !INVITABLE_ROLES.contains(&role.as_str()) {
+        return Err(LexodusAppError::BadRequest(format!("Staff cannot be invited as {role}.")).into());
+    }
+    if User::get_from_email(&email, &conn).await?.is_some() {
+        return Err(LexodusAppError::BadRequest("An account already uses this email address.".to_string()).into());
+    }
+    let Some(role_id) = User::role_id_by_name(&role, &conn).await? else {
+        return Err(LexodusAppError::ServerError(format!("Role {role} is missing")).into());
+    };
+
+    let token = Invitation::issue(&email, role_id, inviter.id, INVITATION_TTL_SECS, &conn).await?;
+    let link = format!("{}/signup?invitation={token}", app_base_url());
+    expect_context::<Mailer>()
+        .send(
+            OutgoingMail {
+                to: email.clone(),
+                subject: "You have been invited to Lexodus".to_string(),
+                body: format!(
+                    "Hello,\n\n{} has invited you to join Lexodus as {role}. Open the link below to choose a username and password. It expires in 7 days and works once.\n\n{link}\n",
+                    inviter.full_name
+                ),
+            },
+            &conn,
+        )
+        .await?;
+    tracing::info!("User {} invited a new {role}", inviter.id);
+    Ok(format!("Invitation sent to {email}"))
+}
+
+impl RequiresPermission for CreateInvitation {
+    const PERMISSION: Permission = Permission::USER_CREATE;
+}
+
+#[server(ListInvitations, "/api")]
+pub async fn list_invitations() -> Result<Vec<Invitation>, ServerFnError> {
+    authorize::<ListInvitations>().await?;
+    let conn = con()?;
+    Ok(Invitation::list(RECENT_INVITATION_LIMIT, &conn).await?)
+}
+
+impl RequiresPermission for ListInvitations {
+    const PERMISSION: Permission = Permission::USER_READ;
+}
+
+#[server(RevokeInvitation, "/api")]
+pub async fn revoke_invitation(csrf_token: String, id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    authorize::<RevokeInvitation>().await?;
+    let conn = con()?;
+    if !Invitation::revoke(id, &conn).await? {
+        return Err(LexodusAppError::BadRequest("That invitation was already used or revoked.".to_string()).into());
+    }
+    Ok(())
+}
+
+impl RequiresPermission for RevokeInvitation {
+    const PERMISSION: Permission = Permission::USER_UPDATE;
+}
+
+#[component]
+pub fn InvitationPanel() -> impl IntoView {
+    let create = create_server_action::<CreateInvitation>();
+    let revoke = create_server_action::<RevokeInvitation>();
+    let response = create.value();
+    let invitations = create_resource(
+        move || (create.version().get(), revoke.version().get()),
+        |_| list_invitations(),
+    );
+    let state = |invitation: &Invitation| {
+        if invitation.accepted {
+            "Accepted"
+        } else if invitation.revoked {
+            "Revoked"
+        } else if invitation.expired {
+            "Expired"
+        } else {
+            "Open"
+        }
+    };
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Staff Invitations"</h2>
+
+            <ActionForm action=create class="flex gap-4 items-end mb-6">
+                <CsrfField/>
+                <div>
+                    <label for="invite_email" class="block text-lexodus-700 mb-1">"Email:"</label>
+                    <input type="email" id="invite_email" name="email" class="px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200" required/>
+                </div>
+                <div>
+                    <label for="invite_role" class="block text-lexodus-700 mb-1">"Role:"</label>
+                    <select id="invite_role" name="role" class="px-4 py-2 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200">
+                        {INVITABLE_ROLES.into_iter().map(|role| view! { <option value=role>{role}</option> }).collect_view()}
+                    </select>
+                </div>
+                <button type="submit" class="px-4 py-2 bg-lexodus-500 text-white rounded font-semibold hover:bg-lexodus-600">"Send Invitation"</button>
+            </ActionForm>
+
+            {move || response.get().map(|result| match result {
+                Ok(message) => view! { <div class="mb-4 text-green-500">{message}</div> },
+                Err(e) => view! { <div class="mb-4 text-red-500">{e.to_string()}</div> },
+            })}
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Email"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Role"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Invited By"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Expires"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"State"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="6" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || invitations.get().map(|result| match result {
+                        Ok(invitations) if invitations.is_empty() => view! {
+                            <tr><td colspan="6" class="text-center text-lexodus-700 border-b py-4">"No invitations have been sent."</td></tr>
+                        }.into_view(),
+                        Ok(invitations) => invitations.into_iter().map(|invitation| {
+                            let state = state(&invitation);
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{invitation.email}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{invitation.role}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{invitation.invited_by.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{invitation.expires_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{state}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <Show when=move || state == "Open" fallback=|| ()>
+                                            <ActionForm action=revoke>
+                                                <CsrfField/>
+                                                <input type="hidden" name="id" value=invitation.id/>
+                                                <button type="submit" class="text-red-600 underline">"Revoke"</button>
+                                            </ActionForm>
+                                        </Show>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="6" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
@@ -1,2 +1,8 @@
 pub mod create;
 pub use create::*;
+pub mod security;
+pub use security::*;
+pub mod invitations;
+pub use invitations::*;
+pub mod registrations;
+pub use registrations::*;
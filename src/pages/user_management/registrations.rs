@@ -0,0 +1,192 @@
+use crate::domain::models::attorney_registration::{registration_status, AttorneyRegistration};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::user::User;
+        use crate::errors::LexodusAppError;
+        use crate::functions::account_recovery::send_verification_email;
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::functions::con;
+        use crate::functions::registration::{notify_registration_approved, notify_registration_denied};
+
+        /// Number of requests shown in the review queue, pending ones first
+        const REGISTRATION_LIST_LIMIT: i64 = 100;
+    }
+}
+
+#[server(ListAttorneyRegistrations, "/api")]
+pub async fn list_attorney_registrations() -> Result<Vec<AttorneyRegistration>, ServerFnError> {
+    authorize::<ListAttorneyRegistrations>().await?;
+    let conn = con()?;
+    Ok(AttorneyRegistration::list(REGISTRATION_LIST_LIMIT, &conn).await?)
+}
+
+impl RequiresPermission for ListAttorneyRegistrations {
+    const PERMISSION: Permission = Permission::REGISTRATION_REVIEW;
+}
+
+/// Approve or deny a pending request. Approval creates the attorney's account;
+/// either way the applicant is emailed the outcome.
+#[server(ReviewAttorneyRegistration, "/api")]
+pub async fn review_attorney_registration(
+    csrf_token: String,
+    id: i64,
+    decision: String,
+    note: Option<String>,
+) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let reviewer = authorize::<ReviewAttorneyRegistration>().await?;
+    let conn = con()?;
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    let already_decided = || LexodusAppError::BadRequest("This request has already been decided.".to_string());
+    let Some(registration) = AttorneyRegistration::get_pending(id, &conn).await? else {
+        return Err(already_decided().into());
+    };
+
+    match decision.as_str() {
+        registration_status::APPROVED => {
+            let Some(role_id) = User::role_id_by_name("attorney", &conn).await? else {
+                return Err(LexodusAppError::ServerError("Attorney role is missing".to_string()).into());
+            };
+            let Some(user_id) = AttorneyRegistration::approve(id, role_id, reviewer.id, note.as_deref(), &conn).await? else {
+                return Err(already_decided().into());
+            };
+            tracing::info!("User {} approved attorney registration {id} as user {user_id}", reviewer.id);
+            notify_registration_approved(&registration, &conn).await;
+            if let Some(user) = User::get(user_id, &conn).await? {
+                if let Err(e) = send_verification_email(&user, &conn).await {
+                    tracing::error!("Failed to send verification email to user {user_id}: {e}");
+                }
+            }
+        }
+        registration_status::DENIED => {
+            if !AttorneyRegistration::deny(id, reviewer.id, note.as_deref(), &conn).await? {
+                return Err(already_decided().into());
+            }
+            tracing::info!("User {} denied attorney registration {id}", reviewer.id);
+            notify_registration_denied(&registration, note.as_deref(), &conn).await;
+        }
+        other => return Err(LexodusAppError::BadRequest(format!("Unknown decision {other}")).into()),
+    }
+    Ok(())
+}
+
+impl RequiresPermission for ReviewAttorneyRegistration {
+    const PERMISSION: Permission = Permission::REGISTRATION_REVIEW;
+}
+
+#[component]
+pub fn RegistrationQueue() -> impl IntoView {
+    let review = create_server_action::<ReviewAttorneyRegistration>();
+    let response = review.value();
+    let registrations = create_resource(move || review.version().get(), |_| list_attorney_registrations());
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Attorney Registration Requests"</h2>
+
+            {move || response.get().and_then(Result::err).map(|e| view! {
+                <div class="mb-4 text-red-500">{e.to_string()}</div>
+            })}
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Submitted"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Applicant"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Bar Number"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Jurisdiction"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Firm"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Review"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="7" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || registrations.get().map(|result| match result {
+                        Ok(registrations) if registrations.is_empty() => view! {
+                            <tr><td colspan="7" class="text-center text-lexodus-700 border-b py-4">"No registration requests."</td></tr>
+                        }.into_view(),
+                        Ok(registrations) => registrations.into_iter().map(|registration| {
+                            let pending = registration.status == registration_status::PENDING;
+                            let decided = format!(
+                                "{} {}",
+                                registration.reviewed_by.clone().unwrap_or_default(),
+                                registration.reviewed_at_pretty.clone().unwrap_or_default(),
+                            );
+                            let note = registration.review_note.clone().unwrap_or_default();
+                            view! {
+                                <tr class="hover:bg-lexodus-50 align-top">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{registration.created_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <div class="font-medium">{registration.full_name}</div>
+                                        <div>{registration.username}</div>
+                                        <div class="text-sm text-gray-600">{registration.email}</div>
+                                    </td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{registration.bar_number}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{registration.admitting_jurisdiction}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{registration.firm_name.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{registration.status}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <Show
+                                            when=move || pending
+                                            fallback=move || view! {
+                                                <div>{decided.clone()}</div>
+                                                <div class="text-sm text-gray-600">{note.clone()}</div>
+                                            }
+                                        >
+                                            <ActionForm action=review class="space-y-2">
+                                                <CsrfField/>
+                                                <input type="hidden" name="id" value=registration.id/>
+                                                <input type="text" name="note" placeholder="Note to applicant" class="w-full px-2 py-1 bg-gray-100 text-lexodus-800 rounded border border-lexodus-200"/>
+                                                <div class="flex gap-4">
+                                                    <button type="submit" name="decision" value=registration_status::APPROVED class="text-lexodus-500 underline">"Approve"</button>
+                                                    <button type="submit" name="decision" value=registration_status::DENIED class="text-red-600 underline">"Deny"</button>
+                                                </div>
+                                            </ActionForm>
+                                        </Show>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="7" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn AttorneyRegistrations() -> impl IntoView {
+    view! {
+        <Meta property="og:title" content="Attorney Registrations | Lexodus"/>
+        <Title text="Attorney Registrations | Lexodus"/>
+        <Meta name="description" content="Review attorney requests for Lexodus accounts."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Attorney Registrations"</h2>
+                    <a href="/users" class="text-lexodus-500 underline">"User Management"</a>
+                </div>
+                <div class="space-y-6">
+                    <RegistrationQueue/>
+                </div>
+            </div>
+        </DefaultLayout>
+    }
+}
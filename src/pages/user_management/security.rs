@@ -0,0 +1,257 @@
+use crate::domain::models::activity_log::ImpersonationActivity;
+use crate::domain::models::auth_log::{AuthEvent, LockedAccount};
+use crate::domain::models::permission::{Permission, RequiresPermission};
+use crate::layouts::default::*;
+use crate::providers::csrf::CsrfField;
+use cfg_if::cfg_if;
+use leptos::*;
+use leptos_meta::Meta;
+use leptos_meta::Title;
+use leptos_router::ActionForm;
+
+cfg_if! {
+    if #[cfg(feature = "ssr")] {
+        use crate::domain::models::auth_log::{event_type, status};
+        use crate::domain::models::user::User;
+        use crate::functions::auth::{record_auth_event, ClientInfo};
+        use crate::functions::authz::authorize;
+        use crate::functions::csrf::verify_csrf;
+        use crate::functions::con;
+        use leptos_spin::RequestParts;
+
+        /// Number of events shown on the security page
+        const RECENT_EVENT_LIMIT: i64 = 100;
+    }
+}
+
+#[server(GetAuthEvents, "/api")]
+pub async fn get_auth_events() -> Result<Vec<AuthEvent>, ServerFnError> {
+    authorize::<GetAuthEvents>().await?;
+    let conn = con()?;
+    Ok(AuthEvent::recent(RECENT_EVENT_LIMIT, &conn).await?)
+}
+
+impl RequiresPermission for GetAuthEvents {
+    const PERMISSION: Permission = Permission::AUDIT_READ;
+}
+
+#[server(GetImpersonationActivity, "/api")]
+pub async fn get_impersonation_activity() -> Result<Vec<ImpersonationActivity>, ServerFnError> {
+    authorize::<GetImpersonationActivity>().await?;
+    let conn = con()?;
+    Ok(ImpersonationActivity::recent(RECENT_EVENT_LIMIT, &conn).await?)
+}
+
+impl RequiresPermission for GetImpersonationActivity {
+    const PERMISSION: Permission = Permission::AUDIT_READ;
+}
+
+#[server(GetLockedAccounts, "/api")]
+pub async fn get_locked_accounts() -> Result<Vec<LockedAccount>, ServerFnError> {
+    authorize::<GetLockedAccounts>().await?;
+    let conn = con()?;
+    Ok(LockedAccount::list(&conn).await?)
+}
+
+impl RequiresPermission for GetLockedAccounts {
+    const PERMISSION: Permission = Permission::USER_READ;
+}
+
+#[server(UnlockAccount, "/api")]
+pub async fn unlock_account(csrf_token: String, user_id: i64) -> Result<(), ServerFnError> {
+    verify_csrf(&csrf_token).await?;
+    let admin = authorize::<UnlockAccount>().await?;
+    let conn = con()?;
+    User::unlock(user_id, &conn).await?;
+
+    let client = use_context::<RequestParts>()
+        .map(|req| ClientInfo::from_request(&req))
+        .unwrap_or_default();
+    let reason = format!("unlocked by user {}", admin.id);
+    record_auth_event(
+        client.event(Some(user_id), event_type::UNLOCK, status::SUCCESS, Some(&reason)),
+        &conn,
+    )
+    .await;
+    Ok(())
+}
+
+impl RequiresPermission for UnlockAccount {
+    const PERMISSION: Permission = Permission::USER_UPDATE;
+}
+
+#[component]
+pub fn LockedAccountList() -> impl IntoView {
+    let unlock = create_server_action::<UnlockAccount>();
+    let accounts = create_resource(move || unlock.version().get(), |_| get_locked_accounts());
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Locked Accounts"</h2>
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Username"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Failed Attempts"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Locked Until"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium"></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="4" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || accounts.get().map(|result| match result {
+                        Ok(accounts) if accounts.is_empty() => view! {
+                            <tr><td colspan="4" class="text-center text-lexodus-700 border-b py-4">"No accounts are locked."</td></tr>
+                        }.into_view(),
+                        Ok(accounts) => accounts.into_iter().map(|account| {
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{account.username}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{account.failed_login_attempts}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{account.locked_until_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">
+                                        <ActionForm action=unlock>
+                                            <CsrfField/>
+                                            <input type="hidden" name="user_id" value=account.user_id/>
+                                            <button type="submit" class="text-lexodus-500 underline">"Unlock"</button>
+                                        </ActionForm>
+                                    </td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="4" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn AuthEventList() -> impl IntoView {
+    let events = create_resource(|| (), |_| get_auth_events());
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Recent Authentication Events"</h2>
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Time"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"User"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Event"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"IP Address"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"User Agent"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Reason"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="7" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || events.get().map(|result| match result {
+                        Ok(events) => events.into_iter().map(|event| {
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.created_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.username.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.event_type}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.status}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.ip_address.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.user_agent.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{event.failure_reason.unwrap_or_default()}</td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="7" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn ImpersonationActivityList() -> impl IntoView {
+    let activity = create_resource(|| (), |_| get_impersonation_activity());
+
+    view! {
+        <section class="bg-white p-6 rounded-lg shadow-lg border border-lexodus-200 mt-8 relative">
+            <h2 class="text-xl font-semibold text-lexodus-800 mb-6">"Impersonation Activity"</h2>
+
+            <table class="min-w-full bg-white">
+                <thead>
+                    <tr>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Time"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Administrator"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Acting As"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Event"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Detail"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"Status"</th>
+                        <th class="py-2 px-4 border-b text-left text-lexodus-700 font-medium">"IP Address"</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <Suspense fallback=move || view! { <tr><td colspan="7" class="text-center py-4">"Loading..."</td></tr> }>
+                    {move || activity.get().map(|result| match result {
+                        Ok(entries) if entries.is_empty() => view! {
+                            <tr><td colspan="7" class="text-center text-lexodus-700 border-b py-4">"No one has acted as another user."</td></tr>
+                        }.into_view(),
+                        Ok(entries) => entries.into_iter().map(|entry| {
+                            view! {
+                                <tr class="hover:bg-lexodus-50">
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.created_at_pretty}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.actor.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.acting_as.unwrap_or_default()}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.action_type}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.description}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.status}</td>
+                                    <td class="py-2 px-4 border-b text-lexodus-800">{entry.ip_address.unwrap_or_default()}</td>
+                                </tr>
+                            }
+                        }).collect_view(),
+                        Err(e) => view! {
+                            <tr>
+                                <td colspan="7" class="text-center text-red-500 border-b py-4">{e.to_string()}</td>
+                            </tr>
+                        }.into_view(),
+                    })}
+                    </Suspense>
+                </tbody>
+            </table>
+        </section>
+    }
+}
+
+#[component]
+pub fn SecurityAudit() -> impl IntoView {
+    view! {
+        <Meta property="og:title" content="Security Audit | Lexodus"/>
+        <Title text="Security Audit | Lexodus"/>
+        <Meta name="description" content="Recent authentication events, locked accounts and impersonation activity."/>
+        <DefaultLayout>
+            <div class="w-full p-8 bg-lexodus-50">
+                <div class="flex justify-between items-center mb-8">
+                    <h2 class="text-2xl font-semibold text-lexodus-800">"Security Audit"</h2>
+                    <a href="/users" class="text-lexodus-500 underline">"User Management"</a>
+                </div>
+                <div class="space-y-6">
+                    <LockedAccountList/>
+                    <AuthEventList/>
+                    <ImpersonationActivityList/>
+                </div>
+            </div>
+        </DefaultLayout>
+    }
+}
@@ -3,7 +3,8 @@ use cfg_if::cfg_if;
 cfg_if! {
 if #[cfg(feature = "ssr")] {
 use async_session::{Session, Result, chrono::Utc, SessionStore};
-use spin_sdk::pg::{Connection, ParameterValue};
+use spin_sdk::pg::{Connection, DbValue, ParameterValue};
+use crate::session::UserSessions;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tracing::info;
@@ -14,13 +15,6 @@ pub struct PostgresStore {
     table_name: String,
 }
 
-#[derive(Debug, Clone)]
-pub struct SessionRow {
-    id: String,
-    expiry: Option<i64>,
-    session: String,
-}
-
 impl PostgresStore {
     pub fn from_connection(postgres_connection: Arc<Connection>) -> Self {
         Self {
@@ -29,30 +23,6 @@ impl PostgresStore {
         }
     }
 
-    pub fn from_connection_with_table_name(postgres_connection: Arc<Connection>, table_name: impl AsRef<str>) -> Self {
-        Self {
-            connection: postgres_connection,
-            table_name: table_name.as_ref().to_string(),
-        }
-    }
-
-    pub fn with_table_name(mut self, table_name: impl AsRef<str>) -> Self {
-        let table_name = table_name.as_ref();
-        if table_name.is_empty()
-            || !table_name
-                .chars()
-                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
-        {
-            panic!(
-                "table name must be [a-zA-Z0-9_-]+, but {} was not",
-                table_name
-            );
-        }
-
-        self.table_name = table_name.to_owned();
-        self
-    }
-
     pub async fn migrate(&self) -> Result<()> {
         info!("migrating sessions on `{}`", self.table_name);
 
@@ -67,10 +37,12 @@ impl PostgresStore {
             self.table_name
         );
 
-        self.connection.execute(&query, &[]).await?;
+        self.connection.execute(&query, &[])?;
         Ok(())
     }
 
+    /// Performs a one-time cleanup task that clears out stale
+    /// (expired) sessions. You may want to call this from cron.
     pub async fn cleanup(&self) -> Result<()> {
         let query = format!(
             r#"
@@ -80,15 +52,38 @@ impl PostgresStore {
             self.table_name
         );
 
-        self.connection.execute(&query, &[&ParameterValue::Int8(Utc::now().timestamp())]).await?;
+        self.connection.execute(&query, &[ParameterValue::Int64(Utc::now().timestamp())])?;
         Ok(())
     }
 
-    pub async fn count(&self) -> Result<i64> {
-        let query = format!("SELECT COUNT(*) FROM {}", self.table_name);
-        let result = self.connection.query(&query, &[]).await?;
-        let count: i64 = result[0].get("count");
-        Ok(count)
+    fn sessions_from_rows(rows: &[Vec<DbValue>]) -> Result<Vec<Session>> {
+        let sessions = rows
+            .iter()
+            .filter_map(|row| match &row[0] {
+                DbValue::Str(s) => Some(serde_json::from_str(s)),
+                _ => None,
+            })
+            .collect::<std::result::Result<Vec<Session>, _>>()?;
+        Ok(sessions)
+    }
+}
+
+#[async_trait]
+impl UserSessions for PostgresStore {
+    async fn sessions_for_user(&self, user_id: i64) -> Result<Vec<Session>> {
+        let query = format!(
+            r#"
+            SELECT session FROM {}
+            WHERE (session::jsonb -> 'data' ->> 'user_id') = $1
+              AND (expiry IS NULL OR expiry > $2)
+            "#,
+            self.table_name
+        );
+        let rowset = self.connection.query(&query, &[
+            ParameterValue::Str(user_id.to_string()),
+            ParameterValue::Int64(Utc::now().timestamp()),
+        ])?;
+        Self::sessions_from_rows(&rowset.rows)
     }
 }
 
@@ -98,28 +93,18 @@ impl SessionStore for PostgresStore {
         let id = Session::id_from_cookie_value(&cookie_value)?;
         let query = format!(
             r#"
-            SELECT * FROM {}
+            SELECT session FROM {}
             WHERE id = $1 AND (expiry IS NULL OR expiry > $2)
             "#,
             self.table_name
         );
 
-        let result = self.connection.query(&query, &[
-            &ParameterValue::Text(id.to_string()),
-            &ParameterValue::Int8(Utc::now().timestamp())
-        ]).await?;
-
-        let session_row = result.get(0).map(|row| SessionRow {
-            id: row.get("id"),
-            expiry: row.get("expiry"),
-            session: row.get("session"),
-        });
-
-        let session: Option<String> = session_row.map(|s| s.session);
+        let rowset = self.connection.query(&query, &[
+            ParameterValue::Str(id.to_string()),
+            ParameterValue::Int64(Utc::now().timestamp()),
+        ])?;
 
-        Ok(session
-            .map(|session| serde_json::from_str(&session))
-            .transpose()?)
+        Ok(Self::sessions_from_rows(&rowset.rows)?.into_iter().next())
     }
 
     async fn store_session(&self, session: Session) -> Result<Option<String>> {
@@ -139,10 +124,10 @@ impl SessionStore for PostgresStore {
         );
 
         self.connection.execute(&query, &[
-            &ParameterValue::Text(id.to_string()),
-            &ParameterValue::Text(string),
-            &expiry.map_or(ParameterValue::Null, |e| ParameterValue::Int8(e)),
-        ]).await?;
+            ParameterValue::Str(id.to_string()),
+            ParameterValue::Str(string),
+            expiry.map_or(ParameterValue::DbNull, ParameterValue::Int64),
+        ])?;
 
         Ok(session.into_cookie_value())
     }
@@ -156,13 +141,13 @@ impl SessionStore for PostgresStore {
             self.table_name
         );
 
-        self.connection.execute(&query, &[&ParameterValue::Text(id.to_string())]).await?;
+        self.connection.execute(&query, &[ParameterValue::Str(id.to_string())])?;
         Ok(())
     }
 
     async fn clear_store(&self) -> Result {
         let query = format!("DELETE FROM {}", self.table_name);
-        self.connection.execute(&query, &[]).await?;
+        self.connection.execute(&query, &[])?;
         Ok(())
     }
 }
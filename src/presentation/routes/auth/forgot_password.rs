@@ -0,0 +1,46 @@
+use crate::functions::account_recovery::RequestPasswordReset;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Ask for a password reset link by email
+#[component]
+pub fn ForgotPassword() -> impl IntoView {
+    let request_reset = create_server_action::<RequestPasswordReset>();
+    let response = request_reset.value();
+
+    view! {
+      <Meta property="og:title" content="Forgot Password"/>
+      <Title text="Forgot Password"/>
+      <Meta name="description" content="Reset your Lexodus password"/>
+      <Meta property="og:description" content="Reset your Lexodus password"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Forgot Password"</h2>
+          <ActionForm action=request_reset class="space-y-6">
+              <CsrfField/>
+                <div class="mb-4">
+                    <label for="email" class="block text-lexodus-800 text-sm font-bold mb-2">"Email"</label>
+                    <input type="email" id="email" name="email" autocomplete="email" class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" required/>
+                </div>
+                <button type="submit" class="bg-lexodus-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline">
+                    "Send reset link"
+                </button>
+          </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(_) => view! { <p class="mt-4 text-green-600">"If that address belongs to an account, a reset link is on its way."</p> }.into_view(),
+                Err(e) => view! { <p class="mt-4 text-red-500">{e.to_string()}</p> }.into_view(),
+            })}
+            <a rel="external" class="text-lexodus-500 underline" href="/login">
+              "Back to login"
+            </a>
+        </div>
+    </div>
+    }
+}
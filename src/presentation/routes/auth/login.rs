@@ -1,5 +1,7 @@
+use crate::functions::oidc::{get_sso_provider, BeginSsoLogin};
 use crate::functions::{self, auth::Login};
 use crate::providers::auth::AuthContext;
+use crate::providers::csrf::CsrfField;
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
@@ -24,6 +26,7 @@ pub fn Login(action: Action<functions::auth::Login, Result<(), ServerFnError>>)
         <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
             <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Login"</h2>
           <ActionForm action=action class="space-y-6">
+              <CsrfField/>
                 <div class="mb-4">
                     <label for="username" class="block text-lexodus-800 text-sm font-bold mb-2">"Username"</label>
                     <input type="username" id="username" name="username" class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline"  aria-describedby="username-error" required/>
@@ -59,12 +62,15 @@ pub fn Login(action: Action<functions::auth::Login, Result<(), ServerFnError>>)
               <div class="text-center text-sm text-gray-500">
                 "Don't have an account?"
               </div>
-                <a rel="external" class="text-lexodus-500 underline" href="/signup">
-                  "Sign up"
+                <a rel="external" class="text-lexodus-500 underline" href="/register/attorney">
+                  "Attorney? Request access"
                 </a>
                 <a rel="external" class="text-lexodus-500 underline" href="/cases">
                   "Already logged in?"
                 </a>
+                <a rel="external" class="text-lexodus-500 underline" href="/forgot-password">
+                  "Forgot password?"
+                </a>
 
 
               <Transition fallback=move || ()>
@@ -91,9 +97,33 @@ pub fn Login(action: Action<functions::auth::Login, Result<(), ServerFnError>>)
               </Transition>
             </div>
              </ActionForm>
-
+            <SsoLoginButton/>
 
         </div>
     </div>
     }
 }
+
+/// "Sign in with ..." for the configured identity provider. Renders nothing when SSO is off.
+#[component]
+pub fn SsoLoginButton() -> impl IntoView {
+    let begin = create_server_action::<BeginSsoLogin>();
+    let provider = create_resource(|| (), |_| get_sso_provider());
+    let response = begin.value();
+
+    view! {
+        <Transition fallback=move || ()>
+            {move || provider.get().and_then(Result::ok).flatten().map(|name| view! {
+                <div class="mt-6 pt-6 border-t border-lexodus-200">
+                    <ActionForm action=begin>
+                        <CsrfField/>
+                        <button type="submit" class="w-full bg-white border border-lexodus-500 text-lexodus-800 font-bold py-2 px-4 rounded hover:bg-lexodus-50 focus:outline-none focus:shadow-outline">
+                            {format!("Sign in with {name}")}
+                        </button>
+                    </ActionForm>
+                    {move || response.get().and_then(Result::err).map(|e| view! { <p class="mt-4 text-red-500">{e.to_string()}</p> })}
+                </div>
+            })}
+        </Transition>
+    }
+}
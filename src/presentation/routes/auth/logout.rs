@@ -1,11 +1,27 @@
 use crate::functions;
+use crate::providers::csrf::CsrfContext;
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
 
 #[component]
 pub fn Logout(action: Action<functions::auth::Logout, Result<(), ServerFnError>>) -> impl IntoView {
-    _ = &action.dispatch(functions::auth::Logout {});
+    let csrf = expect_context::<CsrfContext>();
+    // Logging out is a state change too, so wait for the token before dispatching
+    create_effect(move |dispatched: Option<bool>| {
+        if dispatched == Some(true) {
+            return true;
+        }
+        match csrf.token.get() {
+            Some(Ok(csrf_token)) => {
+                action.dispatch(functions::auth::Logout { csrf_token });
+                true
+            }
+            _ => false,
+        }
+    });
+
+    let logged_out = move || action.version().get() > 0;
 
     view! {
       <Meta property="og:title" content="Logout"/>
@@ -13,7 +29,8 @@ pub fn Logout(action: Action<functions::auth::Logout, Result<(), ServerFnError>>
       <Meta name="description" content="Logout"/>
       <Meta property="og:description" content="Logout"/>
 
-      />
-      <Redirect path="/"/>
+      <Show when=logged_out>
+        <Redirect path="/"/>
+      </Show>
     }
 }
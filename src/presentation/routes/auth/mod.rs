@@ -4,3 +4,9 @@ pub mod logout;
 // pub use logout::*;
 pub mod signup;
 // pub use signup::*;
+pub mod two_factor;
+pub mod forgot_password;
+pub mod reset_password;
+pub mod verify_email;
+pub mod sso_callback;
+pub mod register_attorney;
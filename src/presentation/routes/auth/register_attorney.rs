@@ -0,0 +1,62 @@
+use crate::functions::registration::RequestAttorneyRegistration;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Attorneys ask for an account here; the clerk's office approves or denies it
+#[component]
+pub fn AttorneyRegistrationForm() -> impl IntoView {
+    let request = create_server_action::<RequestAttorneyRegistration>();
+    let response = request.value();
+    let submitted = move || response.with(|r| matches!(r, Some(Ok(_))));
+    let field = |id: &'static str, label: &'static str, kind: &'static str, autocomplete: &'static str, required: bool| view! {
+        <div class="mb-4">
+            <label for=id class="block text-lexodus-800 text-sm font-bold mb-2">{label}</label>
+            <input id=id name=format!("form[{id}]") type=kind autocomplete=autocomplete required=required class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline"/>
+        </div>
+    };
+
+    view! {
+      <Meta property="og:title" content="Attorney Registration"/>
+      <Title text="Attorney Registration"/>
+      <Meta name="description" content="Request a Lexodus attorney account"/>
+      <Meta property="og:description" content="Request a Lexodus attorney account"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-md">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Attorney Registration"</h2>
+            <Show
+                when=submitted
+                fallback=move || view! {
+                    <p class="mb-6 text-gray-600">"The clerk's office reviews every request. You will be emailed once yours has been decided."</p>
+                    <ActionForm action=request class="space-y-2">
+                        <CsrfField/>
+                        {field("full_name", "Full Name", "text", "name", true)}
+                        {field("email", "Email", "email", "email", true)}
+                        {field("username", "Username", "text", "username", true)}
+                        {field("bar_number", "Bar Number", "text", "off", true)}
+                        {field("admitting_jurisdiction", "Admitting Jurisdiction", "text", "off", true)}
+                        {field("firm_name", "Firm (optional)", "text", "organization", false)}
+                        {field("password", "Password", "password", "new-password", true)}
+                        {field("password_confirmation", "Confirm Password", "password", "new-password", true)}
+                        <button type="submit" class="bg-lexodus-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline">
+                            "Request access"
+                        </button>
+                    </ActionForm>
+                    {move || response.get().and_then(Result::err).map(|e| view! { <p class="mt-4 text-red-500">{e.to_string()}</p> })}
+                }
+            >
+                <p class="text-green-600">"Your request has been submitted. Check your email for a confirmation."</p>
+            </Show>
+            <a rel="external" class="text-lexodus-500 underline" href="/login">
+              "Back to login"
+            </a>
+        </div>
+    </div>
+    }
+}
@@ -0,0 +1,57 @@
+use crate::functions::account_recovery::ResetPassword;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Choose a new password with the token from a reset email
+#[component]
+pub fn ResetPasswordForm() -> impl IntoView {
+    let reset = create_server_action::<ResetPassword>();
+    let response = reset.value();
+    let query = use_query_map();
+    let token = move || query.with(|q| q.get("token").cloned().unwrap_or_default());
+    let expired = move || query.with(|q| q.get("expired").is_some());
+
+    view! {
+      <Meta property="og:title" content="Reset Password"/>
+      <Title text="Reset Password"/>
+      <Meta name="description" content="Choose a new Lexodus password"/>
+      <Meta property="og:description" content="Choose a new Lexodus password"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Reset Password"</h2>
+            <Show when=expired>
+                <p class="mb-4 text-lexodus-700">"Your password has expired. Choose a new one to continue."</p>
+            </Show>
+          <ActionForm action=reset class="space-y-6">
+              <CsrfField/>
+                <input type="hidden" name="token" value=token/>
+                <div class="mb-4">
+                    <label for="password" class="block text-lexodus-800 text-sm font-bold mb-2">"New password"</label>
+                    <input type="password" id="password" name="password" autocomplete="new-password" class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" required/>
+                </div>
+                <div class="mb-4">
+                    <label for="password_confirmation" class="block text-lexodus-800 text-sm font-bold mb-2">"Confirm new password"</label>
+                    <input type="password" id="password_confirmation" name="password_confirmation" autocomplete="new-password" class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" required/>
+                </div>
+                <button type="submit" class="bg-lexodus-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline">
+                    "Reset password"
+                </button>
+          </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(_) => view! { <Redirect path="/login"/> }.into_view(),
+                Err(e) => view! { <p class="mt-4 text-red-500">{e.to_string()}</p> }.into_view(),
+            })}
+            <a rel="external" class="text-lexodus-500 underline" href="/forgot-password">
+              "Request a new link"
+            </a>
+        </div>
+    </div>
+    }
+}
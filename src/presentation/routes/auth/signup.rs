@@ -1,9 +1,22 @@
-use crate::functions::auth::Signup;
+use crate::functions::auth::{get_invitation, Signup};
+use crate::providers::csrf::CsrfField;
 use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
+/// Accounts are created from a staff invitation link; without one the page
+/// points attorneys at the registration request form instead.
 #[component]
 pub fn Signup(action: Action<Signup, Result<(), ServerFnError>>) -> impl IntoView {
+    let query = use_query_map();
+    let invitation = move || query.with(|q| q.get("invitation").cloned().unwrap_or_default());
+    let grant = create_resource(invitation, |token| async move {
+        if token.is_empty() {
+            Ok(None)
+        } else {
+            get_invitation(token).await
+        }
+    });
+    let response = action.value();
     view! {
         <Meta property="og:title" content="Sign Up"/>
         <Title text="Sign Up"/>
@@ -20,7 +33,15 @@ pub fn Signup(action: Action<Signup, Result<(), ServerFnError>>) -> impl IntoVie
             </div>
             <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
                 <h2 class="text-2xl font-bold mb-6 text-center text-white">"Sign Up"</h2>
+                <Suspense fallback=move || view! { <p class="text-lexodus-700">"Checking invitation..."</p> }>
+                {move || grant.get().map(|result| match result {
+                    Ok(Some(grant)) => view! {
+                <p class="mb-4 text-lexodus-800">
+                    "You have been invited as " <strong>{grant.role}</strong> " with the address " <strong>{grant.email}</strong> "."
+                </p>
                 <ActionForm action=action class="space-y-6">
+                    <CsrfField/>
+                    <input type="hidden" name="invitation" value=invitation/>
                     <div class="mb-4">
                         <label
                             for="username"
@@ -42,19 +63,19 @@ pub fn Signup(action: Action<Signup, Result<(), ServerFnError>>) -> impl IntoVie
                     </div>
                     <div class="mb-4">
                         <label
-                            for="display_name"
+                            for="full_name"
                             class="block text-lexodus-800  text-sm font-bold mb-2"
                         >
-                            "Displayed Name"
+                            "Full Name"
                         </label>
                         <div class="mt-1">
                             <input
-                                id="display_name"
+                                id="full_name"
                                 required
-                                name="display_name"
+                                name="full_name"
                                 type="text"
-                                autoComplete="display_name"
-                                aria-describedby="display_name-error"
+                                autoComplete="name"
+                                aria-describedby="full_name-error"
                                 class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline"
                             />
                         </div>
@@ -110,6 +131,17 @@ pub fn Signup(action: Action<Signup, Result<(), ServerFnError>>) -> impl IntoVie
                         </a>
                     </div>
                 </ActionForm>
+                {move || response.get().and_then(Result::err).map(|e| view! { <p class="mt-4 text-red-500">{e.to_string()}</p> })}
+                    }.into_view(),
+                    Ok(None) => view! {
+                        <p class="mb-4 text-lexodus-800">
+                            "Lexodus accounts are by invitation. Court staff receive a signup link from an administrator; if yours has expired, ask for a new one."
+                        </p>
+                        <a rel="external" class="text-lexodus-500 underline" href="/register/attorney">"Attorneys can request access here."</a>
+                    }.into_view(),
+                    Err(e) => view! { <p class="text-red-500">{e.to_string()}</p> }.into_view(),
+                })}
+                </Suspense>
             </div>
         </div>
     }
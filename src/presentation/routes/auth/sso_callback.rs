@@ -0,0 +1,70 @@
+use crate::functions;
+use crate::providers::csrf::CsrfContext;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Where the identity provider sends the browser back. The code is redeemed as
+/// soon as the CSRF token has loaded; the server redirects on success.
+#[component]
+pub fn SsoCallback(action: Action<functions::oidc::CompleteSsoLogin, Result<(), ServerFnError>>) -> impl IntoView {
+    let csrf = expect_context::<CsrfContext>();
+    let query = use_query_map();
+    let param = move |name: &str| query.with_untracked(|q| q.get(name).cloned());
+    let provider_error = param("error_description").or_else(|| param("error"));
+    let code = param("code");
+    let state = param("state").unwrap_or_default();
+
+    create_effect(move |dispatched: Option<bool>| {
+        if dispatched == Some(true) {
+            return true;
+        }
+        let Some(code) = code.clone() else {
+            return true;
+        };
+        match csrf.token.get() {
+            Some(Ok(csrf_token)) => {
+                action.dispatch(functions::oidc::CompleteSsoLogin {
+                    csrf_token,
+                    code,
+                    state: state.clone(),
+                });
+                true
+            }
+            _ => false,
+        }
+    });
+
+    let response = action.value();
+    let missing_code = param("code").is_none() && provider_error.is_none();
+
+    view! {
+      <Meta property="og:title" content="Signing in"/>
+      <Title text="Signing in"/>
+      <Meta name="description" content="Completing single sign-on"/>
+      <Meta property="og:description" content="Completing single sign-on"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Single Sign-On"</h2>
+            {move || {
+                let failure = provider_error
+                    .clone()
+                    .or_else(|| missing_code.then(|| "The identity provider did not return a sign-in code.".to_string()))
+                    .or_else(|| response.get().and_then(Result::err).map(|e| e.to_string()));
+                match failure {
+                    Some(message) => view! {
+                        <p class="text-red-500 mb-4">{message}</p>
+                        <a rel="external" class="text-lexodus-500 underline" href="/login">"Back to login"</a>
+                    }.into_view(),
+                    None => view! { <p class="text-lexodus-700">"Signing you in..."</p> }.into_view(),
+                }
+            }}
+        </div>
+    </div>
+    }
+}
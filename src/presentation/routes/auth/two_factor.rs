@@ -0,0 +1,47 @@
+use crate::functions;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Second login step for accounts with two-factor authentication enabled
+#[component]
+pub fn TwoFactorLogin(
+    action: Action<functions::two_factor::VerifyTwoFactor, Result<(), ServerFnError>>,
+) -> impl IntoView {
+    let response = action.value();
+
+    view! {
+      <Meta property="og:title" content="Two-Factor Authentication"/>
+      <Title text="Two-Factor Authentication"/>
+      <Meta name="description" content="Enter your authentication code"/>
+      <Meta property="og:description" content="Enter your authentication code"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Two-Factor Authentication"</h2>
+          <ActionForm action=action class="space-y-6">
+              <CsrfField/>
+                <div class="mb-4">
+                    <label for="code" class="block text-lexodus-800 text-sm font-bold mb-2">"Authentication or recovery code"</label>
+                    <input type="text" id="code" name="code" inputmode="numeric" autocomplete="one-time-code" class="shadow appearance-none border rounded w-full py-2 px-3 text-gray-700 leading-tight focus:outline-none focus:shadow-outline" required/>
+                </div>
+                <button type="submit" class="bg-lexodus-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline">
+                    "Verify"
+                </button>
+          </ActionForm>
+            {move || response.get().map(|result| match result {
+                Ok(_) => view! { <Redirect path="/cases"/> }.into_view(),
+                Err(_) => view! { <p class="mt-4 text-red-500">"That code was not accepted."</p> }.into_view(),
+            })}
+            <a rel="external" class="text-lexodus-500 underline" href="/login">
+              "Back to login"
+            </a>
+        </div>
+    </div>
+    }
+}
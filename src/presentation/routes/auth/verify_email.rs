@@ -0,0 +1,48 @@
+use crate::functions::account_recovery::VerifyEmail;
+use crate::providers::csrf::CsrfField;
+use leptos::*;
+use leptos_meta::*;
+use leptos_router::*;
+
+/// Landing page for verification links. Confirming takes a click so that link
+/// scanners in mail clients cannot use up the token.
+#[component]
+pub fn VerifyEmailPage() -> impl IntoView {
+    let verify = create_server_action::<VerifyEmail>();
+    let response = verify.value();
+    let query = use_query_map();
+    let token = move || query.with(|q| q.get("token").cloned().unwrap_or_default());
+
+    view! {
+      <Meta property="og:title" content="Verify Email"/>
+      <Title text="Verify Email"/>
+      <Meta name="description" content="Confirm your Lexodus email address"/>
+      <Meta property="og:description" content="Confirm your Lexodus email address"/>
+
+        <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="text-center mb-8">
+            <h1 class="text-4xl font-extrabold text-lexodus-800 mb-2">"Lexodus"</h1>
+            <p class="text-lg text-gray-600">"Enhancing judicial efficiency through technology"</p>
+        </div>
+        <div class="bg-white bg-lexodus-300 backdrop-filter backdrop-blur-lg p-8 rounded-lg shadow-lg w-full max-w-sm">
+            <h2 class="text-2xl font-bold mb-6 text-center text-lexodus-800">"Verify Email"</h2>
+            {move || match response.get() {
+                Some(Ok(_)) => view! {
+                    <p class="text-green-600 mb-4">"Your email address is verified."</p>
+                    <a rel="external" class="text-lexodus-500 underline" href="/cases">"Continue"</a>
+                }.into_view(),
+                result => view! {
+                    <ActionForm action=verify class="space-y-6">
+                        <CsrfField/>
+                        <input type="hidden" name="token" value=token/>
+                        <button type="submit" class="bg-lexodus-500 hover:bg-blue-700 text-white font-bold py-2 px-4 rounded focus:outline-none focus:shadow-outline">
+                            "Confirm my email address"
+                        </button>
+                    </ActionForm>
+                    {result.and_then(Result::err).map(|e| view! { <p class="mt-4 text-red-500">{e.to_string()}</p> })}
+                }.into_view(),
+            }}
+        </div>
+    </div>
+    }
+}
@@ -1,20 +1,30 @@
 use crate::domain::models::user::SafeUser;
 use crate::functions::auth::{Login, Logout, Signup};
+use crate::functions::oidc::CompleteSsoLogin;
+use crate::functions::two_factor::VerifyTwoFactor;
 use crate::functions::user::get_safe_user;
 use leptos::*;
 
+/// Versions of the login, signup, logout, 2FA and SSO actions. Resources keyed
+/// on it refetch whenever the signed-in user may have changed.
+pub type AuthVersions = (usize, usize, usize, usize, usize);
+
 #[derive(Clone)]
 pub struct AuthContext {
     pub login: Action<Login, Result<(), ServerFnError>>,
     pub logout: Action<Logout, Result<(), ServerFnError>>,
     pub signup: Action<Signup, Result<(), ServerFnError>>,
-    pub user: Resource<(usize, usize, usize), Result<Option<SafeUser>, ServerFnError>>,
+    pub verify_two_factor: Action<VerifyTwoFactor, Result<(), ServerFnError>>,
+    pub sso_login: Action<CompleteSsoLogin, Result<(), ServerFnError>>,
+    pub user: Resource<AuthVersions, Result<Option<SafeUser>, ServerFnError>>,
 }
 /// Get the current user and place it in Context
 pub fn provide_auth() {
     let login = create_server_action::<Login>();
     let logout = create_server_action::<Logout>();
     let signup = create_server_action::<Signup>();
+    let verify_two_factor = create_server_action::<VerifyTwoFactor>();
+    let sso_login = create_server_action::<CompleteSsoLogin>();
 
     let user = create_resource(
         move || {
@@ -22,6 +32,8 @@ pub fn provide_auth() {
                 login.version().get(),
                 signup.version().get(),
                 logout.version().get(),
+                verify_two_factor.version().get(),
+                sso_login.version().get(),
             )
         },
         move |_| get_safe_user(),
@@ -32,5 +44,7 @@ pub fn provide_auth() {
         login,
         logout,
         signup,
+        verify_two_factor,
+        sso_login,
     })
 }
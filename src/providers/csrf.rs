@@ -0,0 +1,52 @@
+use crate::functions::csrf::get_csrf_token;
+use crate::providers::auth::{AuthContext, AuthVersions};
+use leptos::*;
+
+#[derive(Clone, Copy)]
+pub struct CsrfContext {
+    pub token: Resource<AuthVersions, Result<String, ServerFnError>>,
+}
+
+impl CsrfContext {
+    /// The loaded token, or an empty string while it is loading
+    pub fn value(&self) -> String {
+        self.token
+            .get()
+            .and_then(Result::ok)
+            .unwrap_or_default()
+    }
+}
+
+/// Fetch the session's CSRF token and place it in Context. Logging in or out
+/// issues a new session, so the token is refetched whenever that happens.
+/// Blocking so the cookie for a fresh anonymous session is set before the
+/// response headers go out.
+pub fn provide_csrf() {
+    let auth_context = expect_context::<AuthContext>();
+    let token = create_blocking_resource(
+        move || {
+            (
+                auth_context.login.version().get(),
+                auth_context.signup.version().get(),
+                auth_context.logout.version().get(),
+                auth_context.verify_two_factor.version().get(),
+                auth_context.sso_login.version().get(),
+            )
+        },
+        move |_| get_csrf_token(),
+    );
+
+    provide_context(CsrfContext { token })
+}
+
+/// Hidden input carrying the CSRF token. Place inside every form that posts to
+/// a mutating server function.
+#[component]
+pub fn CsrfField() -> impl IntoView {
+    let csrf = expect_context::<CsrfContext>();
+    view! {
+        <Suspense fallback=|| ()>
+            <input type="hidden" name="csrf_token" value=move || csrf.value()/>
+        </Suspense>
+    }
+}
@@ -2,30 +2,38 @@ use leptos::*;
 use leptos_meta::*;
 use leptos_router::*;
 
-use crate::pages::cases::*;
 use crate::pages::user_management::*;
+use crate::pages::account::{AccountApiTokens, AccountSecurity, AccountSessions};
 
 use crate::pages::cases::{
     create::CaseManagement,
-    create::CreateCaseForm,
     edit_case::EditCase,
-    list_case::ListCases,
-    // search_case::SearchCases,
+    conflicts::RecusalConflicts,
+    search_case::SearchCases,
     view_case::ViewCase,
 };
 
 // auth
 use crate::pages::home::Home;
+use crate::pages::recusals::Recusals;
 use crate::presentation::routes::login::Login;
 use crate::presentation::routes::logout::Logout;
 use crate::presentation::routes::signup::Signup;
+use crate::presentation::routes::register_attorney::AttorneyRegistrationForm;
+use crate::presentation::routes::two_factor::TwoFactorLogin;
+use crate::presentation::routes::forgot_password::ForgotPassword;
+use crate::presentation::routes::reset_password::ResetPasswordForm;
+use crate::presentation::routes::verify_email::VerifyEmailPage;
+use crate::presentation::routes::sso_callback::SsoCallback;
 use crate::providers::auth::{provide_auth, AuthContext};
+use crate::providers::csrf::provide_csrf;
 
 #[component]
 pub fn App() -> impl IntoView {
     // Provides context that manages stylesheets, titles, meta tags, etc.
     provide_meta_context();
     provide_auth();
+    provide_csrf();
     let auth_context = use_context::<AuthContext>().expect("Failed to get AuthContext");
 
     view! {
@@ -46,15 +54,40 @@ pub fn App() -> impl IntoView {
                 view! { <Login action=auth_context.login/> }
             }
           />
-          <Route path="cases" view=CaseManagement>
-              <Route path="" view=ListCases/>
+          <Route
+            path="/login/2fa"
+            view=move || {
+                view! { <TwoFactorLogin action=auth_context.verify_two_factor/> }
+            }
+          />
+          <Route
+            path="/login/sso/callback"
+            view=move || {
+                view! { <SsoCallback action=auth_context.sso_login/> }
+            }
+          />
+          <Route path="/forgot-password" view=ForgotPassword/>
+          <Route path="/reset-password" view=ResetPasswordForm/>
+          <Route path="/verify-email" view=VerifyEmailPage/>
+          <Route path="/register/attorney" view=AttorneyRegistrationForm/>
+          <Route path="cases" view=|| view! { <Outlet/> }>
+              <Route path="" view=CaseManagement/>
               // <Route path="new" view=CreateCaseForm user=user/>
+              // A case id or a case number, e.g. /cases/1:24-cv-00042
               <Route path=":id" view=ViewCase/>
               <Route path=":id/edit" view=EditCase/>
-              // <Route path="search" view=SearchCases/>
+              <Route path="search" view=SearchCases/>
+              <Route path="conflicts" view=RecusalConflicts/>
           </Route>
             // <Route path="/case-management/activity" view=Activity/>
+            <Route path="/recusals" view=Recusals/>
             <Route path="/users" view=UserManagement/>
+            <Route path="/users/security" view=SecurityAudit/>
+            <Route path="/users/registrations" view=AttorneyRegistrations/>
+            <Route path="/account/security" view=AccountSecurity/>
+            <Route path="/account/sessions" view=AccountSessions/>
+            <Route path="/account/api-tokens" view=AccountApiTokens/>
+            <Route path="/csrf-error" view=CsrfError/>
             <Route path="/*any" view=NotFound/>
             <Route
               path="signup"
@@ -102,3 +135,26 @@ fn NotFound() -> impl IntoView {
 
     view! { <h1>"Not Found"</h1> }
 }
+
+/// Where a form submission with a missing or stale CSRF token is sent
+#[component]
+fn CsrfError() -> impl IntoView {
+    #[cfg(feature = "ssr")]
+    {
+        let resp = expect_context::<leptos_spin::ResponseOptions>();
+        resp.set_status(403);
+    }
+
+    view! {
+      <Title text="Request rejected"/>
+      <div class="bg-lexodus-100 flex items-center justify-center min-h-screen flex-col">
+        <div class="bg-white p-8 rounded-lg shadow-lg w-full max-w-md text-center">
+          <h2 class="text-2xl font-bold mb-4 text-lexodus-800">"Request rejected"</h2>
+          <p class="text-gray-600 mb-6">
+            "The form you submitted has expired or did not come from this site. Go back, reload the page and try again."
+          </p>
+          <a rel="external" class="text-lexodus-500 underline" href="/">"Return home"</a>
+        </div>
+      </div>
+    }
+}
@@ -1,15 +1,34 @@
+use crate::domain::models::permission::RequiresPermission;
+use crate::kv_session::KvStore;
+use crate::pg_session::PostgresStore;
 use crate::session::SqliteStore;
+use crate::session_backend::SessionBackend;
 use leptos::provide_context;
+use leptos::server_fn::ServerFn;
 use leptos_spin::{
     render_best_match_to_stream_with_context, server_fn::register_explicit, RouteTable,
 };
-use spin_sdk::http::{IncomingRequest, ResponseOutparam};
+use leptos_spin::{request::SpinRequest, response::SpinResponse};
+use futures_util::SinkExt;
+use spin_sdk::http::{Headers, IncomingRequest, Method, OutgoingResponse, ResponseOutparam};
+use spin_sdk::key_value::Store as KeyValueStore;
 use spin_sdk::pg::{self};
 use spin_sdk::{http_component, sqlite::Connection as SqliteConnection, variables};
 use std::sync::Arc;
 
 #[http_component]
 async fn handle_lexodus(req: IncomingRequest, resp_out: ResponseOutparam) {
+    // Server functions are the only state-changing endpoints. Refuse cross-site
+    // posts before they reach one; the per-form CSRF token is checked inside.
+    let is_server_fn = req
+        .path_with_query()
+        .is_some_and(|path| path.starts_with("/api"));
+    if matches!(req.method(), Method::Post) && is_server_fn && !crate::functions::csrf::same_origin(&req.headers().entries()) {
+        tracing::warn!("Rejected cross-origin POST to {:?}", req.path_with_query());
+        forbidden(resp_out).await;
+        return;
+    }
+
     let mut conf = leptos::get_configuration(None).await.unwrap();
     conf.leptos_options.output_name = "lexodus".to_owned();
     // let token = variables::get("token").unwrap();
@@ -69,27 +88,113 @@ async fn handle_lexodus(req: IncomingRequest, resp_out: ResponseOutparam) {
     // Setup up Store for user sessions
     // let store = SqliteStore::from_connection(con.clone());
     // store.migrate().await.expect("Failed to migrate sessions!");
-    let store = SqliteStore::from_connection(sqlite_connection.clone());
+    let store = session_backend(&sqlite_connection, &postgres_connection);
     store.migrate().await.expect("Failed to migrate sessions!");
+    // There is no scheduler, so sweep expired sessions on a small share of requests
+    if rand::random::<u8>() == 0 {
+        if let Err(e) = store.cleanup().await {
+            tracing::warn!("Session cleanup failed: {e}");
+        }
+    }
+    // Requests made while acting as another user are audited under the real user
+    if let Some(path) = req.path_with_query() {
+        let req_parts = leptos_spin::RequestParts::new_from_req(&req);
+        crate::functions::impersonation::audit_request(&req_parts, &path, &store, &postgres_connection).await;
+    }
+    let session_config = crate::functions::sessions::SessionConfig::from_variables();
+    let mailer = crate::mail::Mailer::from_variables();
+    let password_policy = crate::functions::password_policy::PasswordPolicy::from_variables();
+    let oidc_config = crate::functions::oidc::OidcConfig::from_variables();
     // Register server functions
     // Case Management
-    register_explicit::<crate::pages::cases::CreateCase>();
-    register_explicit::<crate::pages::cases::GetCases>();
-    register_explicit::<crate::pages::cases::UpdateCaseStatus>();
-    register_explicit::<crate::pages::cases::GetJudges>();
-    register_explicit::<crate::pages::cases::GetCourts>();
+    register_guarded::<crate::pages::cases::CreateCase>();
+    register_guarded::<crate::pages::cases::LogFailedCaseCreation>();
+    register_guarded::<crate::pages::cases::GetCases>();
+    register_guarded::<crate::pages::cases::case_status::UpdateCaseStatus>();
+    register_guarded::<crate::pages::cases::case_status::GetCaseStatusHistory>();
+    register_guarded::<crate::pages::cases::assign_judge::GetJudgeAssignments>();
+    register_guarded::<crate::pages::cases::assign_judge::GetReassignmentPool>();
+    register_guarded::<crate::pages::cases::assign_judge::ReassignJudge>();
+    register_guarded::<crate::pages::cases::case_links::GetCaseLinks>();
+    register_guarded::<crate::pages::cases::case_links::LinkCase>();
+    register_guarded::<crate::pages::cases::case_links::UnlinkCase>();
+    register_guarded::<crate::pages::cases::sealing::GetSealingOrders>();
+    register_guarded::<crate::pages::cases::sealing::UpdateCaseSealing>();
+    register_guarded::<crate::pages::cases::transfer::GetCaseTransfers>();
+    register_guarded::<crate::pages::cases::transfer::GetTransferDestinations>();
+    register_guarded::<crate::pages::cases::transfer::TransferCase>();
+    register_guarded::<crate::pages::cases::conflicts::GetCaseConflicts>();
+    register_guarded::<crate::pages::cases::conflicts::ListRecusalConflicts>();
+    register_guarded::<crate::pages::cases::conflicts::ClearRecusalConflict>();
+    register_guarded::<crate::pages::cases::GetJudges>();
+    register_guarded::<crate::pages::cases::GetCourts>();
+    register_guarded::<crate::pages::cases::GetDivisions>();
+    register_guarded::<crate::pages::cases::GetCaseDetails>();
+    register_guarded::<crate::pages::cases::GetCaseParties>();
+    register_guarded::<crate::pages::cases::GetCaseDocket>();
+    register_guarded::<crate::pages::cases::GetCaseDeadlines>();
+    register_guarded::<crate::pages::cases::GetCaseMotions>();
+    register_guarded::<crate::pages::cases::GetCaseDocuments>();
+    register_guarded::<crate::pages::cases::GetCaseForEdit>();
+    register_guarded::<crate::pages::cases::UpdateCase>();
+    register_guarded::<crate::pages::cases::SearchCasesAction>();
     // User Role Management
-    register_explicit::<crate::pages::user_management::CreateUser>();
-    register_explicit::<crate::pages::user_management::GetUsers>();
-    // Auth
+    register_guarded::<crate::pages::user_management::CreateUser>();
+    register_guarded::<crate::pages::user_management::GetUsers>();
+    register_guarded::<crate::functions::two_factor::ResetTwoFactor>();
+    register_guarded::<crate::pages::user_management::GetAuthEvents>();
+    register_guarded::<crate::pages::user_management::GetLockedAccounts>();
+    register_guarded::<crate::pages::user_management::UnlockAccount>();
+    register_guarded::<crate::pages::user_management::CreateInvitation>();
+    register_guarded::<crate::pages::user_management::ListInvitations>();
+    register_guarded::<crate::pages::user_management::RevokeInvitation>();
+    register_guarded::<crate::pages::user_management::ListAttorneyRegistrations>();
+    register_guarded::<crate::pages::user_management::ReviewAttorneyRegistration>();
+    register_guarded::<crate::pages::user_management::GetImpersonationActivity>();
+    register_guarded::<crate::functions::impersonation::StartImpersonation>();
+    // Auth (public)
     register_explicit::<crate::functions::auth::Login>();
     register_explicit::<crate::functions::auth::Logout>();
+    register_explicit::<crate::functions::auth::GetInvitation>();
     register_explicit::<crate::functions::auth::Signup>();
+    register_explicit::<crate::functions::registration::RequestAttorneyRegistration>();
     register_explicit::<crate::functions::user::GetUser>();
     register_explicit::<crate::functions::user::GetSafeUser>();
+    register_explicit::<crate::functions::csrf::GetCsrfToken>();
+    // Account recovery (public, token checked inside)
+    register_explicit::<crate::functions::account_recovery::RequestPasswordReset>();
+    register_explicit::<crate::functions::account_recovery::ResetPassword>();
+    register_explicit::<crate::functions::account_recovery::SendVerificationEmail>();
+    register_explicit::<crate::functions::account_recovery::VerifyEmail>();
+    register_explicit::<crate::functions::password_policy::ChangePassword>();
+    // Two-factor (checks the session itself)
+    register_explicit::<crate::functions::two_factor::BeginTwoFactorEnrollment>();
+    register_explicit::<crate::functions::two_factor::ConfirmTwoFactorEnrollment>();
+    register_explicit::<crate::functions::two_factor::VerifyTwoFactor>();
+    // Sessions (the caller's own)
+    register_explicit::<crate::functions::sessions::ListSessions>();
+    register_explicit::<crate::functions::sessions::RevokeSession>();
+    register_explicit::<crate::functions::sessions::RevokeAllSessions>();
+    // API tokens (the caller's own)
+    register_explicit::<crate::functions::api_tokens::ListApiTokenScopes>();
+    register_explicit::<crate::functions::api_tokens::ListApiTokens>();
+    register_explicit::<crate::functions::api_tokens::CreateApiToken>();
+    register_explicit::<crate::functions::api_tokens::RevokeApiToken>();
+    // Single sign-on (public, state checked inside)
+    register_explicit::<crate::functions::oidc::GetSsoProvider>();
+    register_explicit::<crate::functions::oidc::BeginSsoLogin>();
+    register_explicit::<crate::functions::oidc::CompleteSsoLogin>();
+    // Impersonation (the caller's own session)
+    register_explicit::<crate::functions::impersonation::GetImpersonation>();
+    register_explicit::<crate::functions::impersonation::StopImpersonation>();
     // Parties
-    register_explicit::<crate::pages::parties::AddParty>();
-    register_explicit::<crate::pages::parties::GetParties>();
+    register_guarded::<crate::pages::parties::AddParty>();
+    register_guarded::<crate::pages::parties::GetParties>();
+    // Recusal lists
+    register_guarded::<crate::pages::recusals::GetChambersJudges>();
+    register_guarded::<crate::pages::recusals::GetRecusals>();
+    register_guarded::<crate::pages::recusals::AddRecusal>();
+    register_guarded::<crate::pages::recusals::SetRecusalActive>();
 
     render_best_match_to_stream_with_context(
         req,
@@ -100,8 +205,64 @@ async fn handle_lexodus(req: IncomingRequest, resp_out: ResponseOutparam) {
             provide_context(sqlite_connection.clone());
             provide_context(postgres_connection.clone());
             provide_context(store.clone());
+            provide_context(session_config.clone());
+            provide_context(mailer.clone());
+            provide_context(password_policy.clone());
+            if let Some(oidc_config) = &oidc_config {
+                provide_context(oidc_config.clone());
+            }
         },
         &conf.leptos_options,
     )
     .await
 }
+
+/// 403 for requests refused before routing
+async fn forbidden(resp_out: ResponseOutparam) {
+    let headers = Headers::from_list(&[("Content-Type".to_string(), b"text/html".to_vec())])
+        .expect("Failed to construct response headers");
+    let og = OutgoingResponse::new(headers);
+    og.set_status_code(403).expect("Failed to set status");
+    let mut body = og.take_body();
+    resp_out.set(og);
+    let page = "<h1>Forbidden</h1><p>Cross-site requests are not accepted. Return to the site and try again.</p>";
+    if let Err(e) = body.send(page.as_bytes().to_vec()).await {
+        tracing::warn!("Failed to send 403 body: {e}");
+    }
+}
+
+/// Pick the session store named by the `session_backend` variable:
+/// `sqlite` (the default), `postgres` or `kv`. An unknown name is logged and
+/// falls back to SQLite rather than taking every request down.
+fn session_backend(
+    sqlite_connection: &Arc<SqliteConnection>,
+    postgres_connection: &Arc<pg::Connection>,
+) -> SessionBackend {
+    match variables::get("session_backend").as_deref() {
+        Ok("postgres") => SessionBackend::Postgres(PostgresStore::from_connection(
+            postgres_connection.clone(),
+        )),
+        Ok("kv") => SessionBackend::Kv(KvStore::from_store(Arc::new(
+            KeyValueStore::open_default().expect("Failed to open key-value store"),
+        ))),
+        Ok("sqlite") | Err(_) => {
+            SessionBackend::Sqlite(SqliteStore::from_connection(sqlite_connection.clone()))
+        }
+        Ok(other) => {
+            tracing::error!("Unknown session_backend `{other}`, expected sqlite, postgres or kv; using sqlite");
+            SessionBackend::Sqlite(SqliteStore::from_connection(sqlite_connection.clone()))
+        }
+    }
+}
+
+/// Register a server function that requires a permission. Only functions that
+/// declare one through `RequiresPermission` can be registered this way, so the
+/// registration list doubles as the record of which endpoints are guarded.
+fn register_guarded<T>()
+where
+    T: ServerFn<ServerRequest = SpinRequest, ServerResponse = SpinResponse>
+        + RequiresPermission
+        + 'static,
+{
+    register_explicit::<T>();
+}
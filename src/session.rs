@@ -95,6 +95,33 @@ impl SqliteStore {
         Ok(count)
     }
 }
+/// Lookups by owner, behind the active sessions page and "log out everywhere".
+/// Sessions are matched on the `user_id` value stored in their data.
+#[async_trait]
+pub trait UserSessions {
+    /// Unexpired sessions belonging to the user
+    async fn sessions_for_user(&self, user_id: i64) -> Result<Vec<Session>>;
+}
+
+#[async_trait]
+impl UserSessions for SqliteStore {
+    async fn sessions_for_user(&self, user_id: i64) -> Result<Vec<Session>> {
+        let rowset = self.connection.execute(&self.substitute_table_name(
+            r#"
+            SELECT session FROM %%TABLE_NAME%%
+              WHERE json_extract(session, '$.data.user_id') = ?
+                AND (expiry IS NULL OR expiry > ?)
+            "#,
+        ), &[Text(user_id.to_string()), Integer(Utc::now().timestamp())])?;
+        let sessions = rowset
+            .rows()
+            .filter_map(|row| row.get::<&str>("session"))
+            .map(serde_json::from_str)
+            .collect::<std::result::Result<Vec<Session>, _>>()?;
+        Ok(sessions)
+    }
+}
+
 #[async_trait]
 impl SessionStore for SqliteStore{
  async fn load_session(&self, cookie_value: String) -> Result<Option<Session>> {
@@ -142,7 +169,6 @@ impl SessionStore for SqliteStore{
 
     async fn destroy_session(&self, session: Session) -> Result {
         let id = session.id();
-        println!("ID: {id}");
         let _ = &self.connection.execute(&self.substitute_table_name(
             r#"
             DELETE FROM %%TABLE_NAME%% WHERE id = ?
@@ -0,0 +1,218 @@
+use cfg_if::cfg_if;
+
+cfg_if! {
+if #[cfg(feature = "ssr")] {
+use crate::kv_session::KvStore;
+use crate::pg_session::PostgresStore;
+use crate::session::{SqliteStore, UserSessions};
+use async_session::{Result, Session, SessionStore};
+use async_trait::async_trait;
+
+/// The session store chosen for this deployment through the `session_backend`
+/// Spin variable. Server functions only ever see this type in context.
+#[derive(Debug, Clone)]
+pub enum SessionBackend {
+    Sqlite(SqliteStore),
+    Postgres(PostgresStore),
+    Kv(KvStore),
+}
+
+impl SessionBackend {
+    /// Create the session table if the backend has one
+    pub async fn migrate(&self) -> Result<()> {
+        match self {
+            SessionBackend::Sqlite(store) => store.migrate().await,
+            SessionBackend::Postgres(store) => store.migrate().await,
+            SessionBackend::Kv(_) => Ok(()),
+        }
+    }
+
+    /// Remove expired sessions
+    pub async fn cleanup(&self) -> Result<()> {
+        match self {
+            SessionBackend::Sqlite(store) => store.cleanup().await,
+            SessionBackend::Postgres(store) => store.cleanup().await,
+            SessionBackend::Kv(store) => store.cleanup().await,
+        }
+    }
+}
+
+#[async_trait]
+impl UserSessions for SessionBackend {
+    async fn sessions_for_user(&self, user_id: i64) -> Result<Vec<Session>> {
+        match self {
+            SessionBackend::Sqlite(store) => store.sessions_for_user(user_id).await,
+            SessionBackend::Postgres(store) => store.sessions_for_user(user_id).await,
+            SessionBackend::Kv(store) => store.sessions_for_user(user_id).await,
+        }
+    }
+}
+
+#[async_trait]
+impl SessionStore for SessionBackend {
+    async fn load_session(&self, cookie_value: String) -> Result<Option<Session>> {
+        match self {
+            SessionBackend::Sqlite(store) => store.load_session(cookie_value).await,
+            SessionBackend::Postgres(store) => store.load_session(cookie_value).await,
+            SessionBackend::Kv(store) => store.load_session(cookie_value).await,
+        }
+    }
+
+    async fn store_session(&self, session: Session) -> Result<Option<String>> {
+        match self {
+            SessionBackend::Sqlite(store) => store.store_session(session).await,
+            SessionBackend::Postgres(store) => store.store_session(session).await,
+            SessionBackend::Kv(store) => store.store_session(session).await,
+        }
+    }
+
+    async fn destroy_session(&self, session: Session) -> Result {
+        match self {
+            SessionBackend::Sqlite(store) => store.destroy_session(session).await,
+            SessionBackend::Postgres(store) => store.destroy_session(session).await,
+            SessionBackend::Kv(store) => store.destroy_session(session).await,
+        }
+    }
+
+    async fn clear_store(&self) -> Result {
+        match self {
+            SessionBackend::Sqlite(store) => store.clear_store().await,
+            SessionBackend::Postgres(store) => store.clear_store().await,
+            SessionBackend::Kv(store) => store.clear_store().await,
+        }
+    }
+}
+}}
+
+/// Scenarios every session store must pass, run through `SessionBackend` so
+/// each backend is exercised the way server functions use it
+#[cfg(all(test, feature = "ssr"))]
+pub(crate) mod tests {
+    use super::*;
+    use crate::kv_session::memory::MemoryKv;
+    use async_session::chrono::{Duration, Utc};
+    use std::future::Future;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Waker};
+
+    /// Drive a future that never waits on I/O, as the in-memory stores do
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn user_session(user_id: i64) -> Session {
+        let mut session = Session::new();
+        session.insert("user_id", user_id).unwrap();
+        session.expire_in(std::time::Duration::from_secs(600));
+        session
+    }
+
+    fn store_and_load(store: &SessionBackend) {
+        let mut session = user_session(1);
+        session.insert("role", "clerk").unwrap();
+        let id = session.id().to_string();
+        let cookie = block_on(store.store_session(session)).unwrap().expect("a new session yields a cookie");
+
+        let loaded = block_on(store.load_session(cookie)).unwrap().expect("stored session loads");
+        assert_eq!(loaded.id(), id);
+        assert_eq!(loaded.get::<String>("role").as_deref(), Some("clerk"));
+        assert_eq!(loaded.get::<i64>("user_id"), Some(1));
+    }
+
+    fn unknown_cookie_loads_nothing(store: &SessionBackend) {
+        let cookie = Session::new().into_cookie_value().unwrap();
+        assert!(block_on(store.load_session(cookie)).unwrap().is_none());
+    }
+
+    fn restore_replaces(store: &SessionBackend) {
+        let session = user_session(2);
+        let cookie = block_on(store.store_session(session)).unwrap().unwrap();
+        let mut loaded = block_on(store.load_session(cookie.clone())).unwrap().unwrap();
+        loaded.insert("renewed", true).unwrap();
+        block_on(store.store_session(loaded)).unwrap();
+
+        let reloaded = block_on(store.load_session(cookie)).unwrap().unwrap();
+        assert_eq!(reloaded.get::<bool>("renewed"), Some(true));
+        assert_eq!(block_on(store.sessions_for_user(2)).unwrap().len(), 1);
+    }
+
+    fn destroy(store: &SessionBackend) {
+        let session = user_session(3);
+        let cookie = block_on(store.store_session(session)).unwrap().unwrap();
+        let loaded = block_on(store.load_session(cookie.clone())).unwrap().unwrap();
+        block_on(store.destroy_session(loaded)).unwrap();
+
+        assert!(block_on(store.load_session(cookie)).unwrap().is_none());
+        assert!(block_on(store.sessions_for_user(3)).unwrap().is_empty());
+    }
+
+    fn expired_and_cleanup(store: &SessionBackend) {
+        let mut stale = user_session(4);
+        stale.set_expiry(Utc::now() - Duration::seconds(60));
+        let stale_cookie = block_on(store.store_session(stale)).unwrap().unwrap();
+        let live_cookie = block_on(store.store_session(user_session(4))).unwrap().unwrap();
+
+        assert!(block_on(store.load_session(stale_cookie.clone())).unwrap().is_none());
+        block_on(store.cleanup()).unwrap();
+        assert!(block_on(store.load_session(stale_cookie)).unwrap().is_none());
+        assert!(block_on(store.load_session(live_cookie)).unwrap().is_some());
+        assert_eq!(block_on(store.sessions_for_user(4)).unwrap().len(), 1);
+    }
+
+    fn sessions_are_listed_per_user(store: &SessionBackend) {
+        block_on(store.store_session(user_session(5))).unwrap();
+        block_on(store.store_session(user_session(5))).unwrap();
+        block_on(store.store_session(user_session(6))).unwrap();
+
+        let fives = block_on(store.sessions_for_user(5)).unwrap();
+        assert_eq!(fives.len(), 2);
+        assert!(fives.iter().all(|s| s.get::<i64>("user_id") == Some(5)));
+        assert!(block_on(store.sessions_for_user(99)).unwrap().is_empty());
+    }
+
+    fn clear(store: &SessionBackend) {
+        let cookie = block_on(store.store_session(user_session(7))).unwrap().unwrap();
+        block_on(store.clear_store()).unwrap();
+        assert!(block_on(store.load_session(cookie)).unwrap().is_none());
+        assert!(block_on(store.sessions_for_user(7)).unwrap().is_empty());
+    }
+
+    /// Run every scenario against a freshly migrated, empty store
+    pub(crate) fn conformance(store: SessionBackend) {
+        block_on(store.migrate()).unwrap();
+        block_on(store.clear_store()).unwrap();
+        store_and_load(&store);
+        unknown_cookie_loads_nothing(&store);
+        restore_replaces(&store);
+        destroy(&store);
+        expired_and_cleanup(&store);
+        sessions_are_listed_per_user(&store);
+        clear(&store);
+    }
+
+    #[test]
+    fn kv_store_conforms() {
+        conformance(SessionBackend::Kv(KvStore::from_store(Arc::new(MemoryKv::default()))));
+    }
+
+    #[test]
+    #[ignore = "needs the Spin SQLite host"]
+    fn sqlite_store_conforms() {
+        let connection = spin_sdk::sqlite::Connection::open("default").unwrap();
+        conformance(SessionBackend::Sqlite(SqliteStore::from_connection(Arc::new(connection))));
+    }
+
+    #[test]
+    #[ignore = "needs the Spin Postgres host and `LEXODUS_TEST_DB_URL`"]
+    fn postgres_store_conforms() {
+        let url = std::env::var("LEXODUS_TEST_DB_URL").unwrap();
+        let connection = spin_sdk::pg::Connection::open(&url).unwrap();
+        conformance(SessionBackend::Postgres(PostgresStore::from_connection(Arc::new(connection))));
+    }
+}